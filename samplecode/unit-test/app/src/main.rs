@@ -30,88 +30,89 @@ extern crate sgx_types;
 extern crate sgx_urts;
 extern crate dirs;
 use sgx_types::*;
-use sgx_urts::SgxEnclave;
+use sgx_urts::{SgxEnclave, SgxEnclaveBuilder};
+use sgx_urts::switchless::SwitchlessConfig;
+use sgx_urts::kvstore;
 
-use std::io::{Read, Write};
+use std::io::Write;
 use std::fs;
-use std::path;
+use std::env;
+use std::process;
+use std::ffi::CString;
+use std::sync::Arc;
+use std::os::raw::c_char;
 
 static ENCLAVE_FILE: &'static str = "enclave.signed.so";
 static ENCLAVE_TOKEN: &'static str = "enclave.token";
 
+const REPORT_LEN: usize = 64 * 1024;
+
 extern {
-    fn test_main_entrance(eid: sgx_enclave_id_t, retval: *mut sgx_status_t) -> sgx_status_t;
+    fn test_main_entrance(eid: sgx_enclave_id_t,
+                          retval: *mut sgx_status_t,
+                          filter: *const c_char,
+                          format: u32,
+                          report: *mut u8,
+                          report_len: usize,
+                          report_size: *mut usize,
+                          failed: *mut u32) -> sgx_status_t;
 }
 
 fn init_enclave() -> SgxResult<SgxEnclave> {
 
-    let mut launch_token: sgx_launch_token_t = [0; 1024];
-    let mut launch_token_updated: i32 = 0;
-    // Step 1: try to retrieve the launch token saved by last transaction
-    //         if there is no token, then create a new one.
-    //
-    // try to get the token saved in $HOME */
-    let mut home_dir = path::PathBuf::new();
-    let use_token = match dirs::home_dir() {
+    // Debug Support: set the debug flag to 1
+    let mut builder = SgxEnclaveBuilder::new(ENCLAVE_FILE);
+    builder.debug(1);
+
+    // The launch token is kept in $HOME between runs. The builder reads it
+    // back, and saves it again if it was updated.
+    match dirs::home_dir() {
         Some(path) => {
             println!("[+] Home dir is {}", path.display());
-            home_dir = path;
-            true
+            builder.launch_token_file(path.join(ENCLAVE_TOKEN));
         },
-        None => {
-            println!("[-] Cannot get home dir");
-            false
-        }
-    };
-
-    let token_file: path::PathBuf = home_dir.join(ENCLAVE_TOKEN);;
-    if use_token == true {
-        match fs::File::open(&token_file) {
-            Err(_) => {
-                println!("[-] Open token file {} error! Will create one.", token_file.as_path().to_str().unwrap());
-            },
-            Ok(mut f) => {
-                println!("[+] Open token file success! ");
-                match f.read(&mut launch_token) {
-                    Ok(1024) => {
-                        println!("[+] Token file valid!");
-                    },
-                    _ => println!("[+] Token file invalid, will create new token file"),
-                }
-            }
-        }
+        None => println!("[-] Cannot get home dir"),
     }
 
-    // Step 2: call sgx_create_enclave to initialize an enclave instance
-    // Debug Support: set 2nd parameter to 1
-    let debug = 1;
-    let mut misc_attr = sgx_misc_attribute_t {secs_attr: sgx_attributes_t { flags:0, xfrm:0}, misc_select:0};
-    let enclave = try!(SgxEnclave::create(ENCLAVE_FILE,
-                                          debug,
-                                          &mut launch_token,
-                                          &mut launch_token_updated,
-                                          &mut misc_attr));
-
-    // Step 3: save the launch token if it is updated
-    if use_token == true && launch_token_updated != 0 {
-        // reopen the file with write capablity
-        match fs::File::create(&token_file) {
-            Ok(mut f) => {
-                match f.write_all(&launch_token) {
-                    Ok(()) => println!("[+] Saved updated launch token!"),
-                    Err(_) => println!("[-] Failed to save updated launch token!"),
+    // The file and network OCALLs of sgx_net_switchless.edl go through two
+    // untrusted workers.
+    builder.switchless(SwitchlessConfig::new().untrusted_workers(2));
+
+    builder.create()
+}
+
+fn usage() -> ! {
+    println!("usage: app [--format json|tap|junit] [--output FILE] [FILTER]");
+    process::exit(2);
+}
+
+fn main() {
+
+    // Only tests whose name contains FILTER are run. The report is printed,
+    // or written to FILE, in the selected format.
+    let mut format = None;
+    let mut output = None;
+    let mut filter = String::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_ref().map(|s| s.as_str()) {
+                    Some("json") => Some(0),
+                    Some("tap") => Some(1),
+                    Some("junit") => Some(2),
+                    _ => usage(),
                 }
             },
-            Err(_) => {
-                println!("[-] Failed to save updated enclave token, but doesn't matter");
-            },
+            "--output" => output = Some(args.next().unwrap_or_else(|| usage())),
+            _ if arg.starts_with("-") => usage(),
+            _ => filter = arg,
         }
     }
+    let filter = CString::new(filter).unwrap_or_else(|_| usage());
 
-    Ok(enclave)
-}
-
-fn main() {
+    // Backs the KvStorage tests.
+    kvstore::set_store(Arc::new(kvstore::MemoryKvStore::new()));
 
     let enclave = match init_enclave() {
         Ok(r) => {
@@ -120,26 +121,61 @@ fn main() {
         },
         Err(x) => {
             println!("[-] Init Enclave Failed {}!", x.as_str());
-            return;
+            process::exit(1);
         },
     };
 
     let mut retval = sgx_status_t::SGX_SUCCESS;
+    let mut report = vec![0u8; REPORT_LEN];
+    let mut report_size: usize = 0;
+    let mut failed: u32 = 0;
 
     let result = unsafe {
         test_main_entrance(enclave.geteid(),
-                           &mut retval)
+                           &mut retval,
+                           filter.as_ptr(),
+                           format.unwrap_or(0),
+                           report.as_mut_ptr(),
+                           report.len(),
+                           &mut report_size,
+                           &mut failed)
     };
 
     match result {
         sgx_status_t::SGX_SUCCESS => {},
         _ => {
             println!("[-] ECALL Enclave Failed {}!", result.as_str());
-            return;
+            process::exit(1);
         }
     }
 
     println!("[+] unit_test ended!");
 
+    match retval {
+        sgx_status_t::SGX_SUCCESS => {
+            report.truncate(report_size);
+            if format.is_some() {
+                let written = match output {
+                    Some(ref path) => fs::write(path, &report),
+                    None => std::io::stdout().write_all(&report),
+                };
+                if written.is_err() {
+                    println!("[-] Failed to write the test report!");
+                }
+            }
+        },
+        sgx_status_t::SGX_ERROR_OUT_OF_MEMORY => {
+            println!("[-] Test report needs {} bytes, more than {}!", report_size, REPORT_LEN);
+        },
+        _ => {
+            println!("[-] Running tests failed {}!", retval.as_str());
+            process::exit(1);
+        }
+    }
+
     enclave.destroy();
+
+    if failed != 0 {
+        process::exit(1);
+    }
 }
@@ -31,6 +31,7 @@
 
 #![cfg_attr(not(target_env = "sgx"), no_std)]
 #![cfg_attr(target_env = "sgx", feature(rustc_private))]
+#![feature(asm)]
 
 extern crate sgx_types;
 #[cfg(not(target_env = "sgx"))]
@@ -42,6 +43,14 @@ extern crate sgx_tunittest;
 extern crate sgx_trts;
 extern crate sgx_rand;
 extern crate sgx_tseal;
+extern crate sgx_tprotected_fs;
+extern crate sgx_tservice;
+extern crate sgx_tse;
+extern crate sgx_ra_tls;
+extern crate sgx_tdh_session;
+extern crate sgx_ra_sp;
+extern crate rustls;
+extern crate webpki;
 
 extern crate sgx_serialize;
 pub use sgx_serialize::*;
@@ -55,6 +64,9 @@ use sgx_tunittest::*;
 
 use std::vec::Vec;
 use std::string::String;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
 
 mod utils;
 
@@ -79,24 +91,75 @@ use test_serialize::*;
 mod test_file;
 use test_file::*;
 
+mod test_pfs;
+use test_pfs::*;
+
+mod test_net;
+use test_net::*;
+
 mod test_time;
 use test_time::*;
 
+mod test_quote;
+use test_quote::*;
+
+mod test_tservice;
+use test_tservice::*;
+
+mod test_thread;
+use test_thread::*;
+
+mod test_ra_tls;
+use test_ra_tls::*;
+
+mod test_dh_session;
+use test_dh_session::*;
+
+mod test_ra_sp;
+use test_ra_sp::*;
+
+const REPORT_JSON: u32 = 0;
+const REPORT_TAP: u32 = 1;
+const REPORT_JUNIT: u32 = 2;
+
 #[no_mangle]
 pub extern "C"
-fn test_main_entrance() -> sgx_status_t {
-    rsgx_unit_tests!(
+fn test_main_entrance(filter: *const c_char,
+                      format: u32,
+                      report: *mut u8,
+                      report_len: usize,
+                      report_size: *mut usize,
+                      failed: *mut u32) -> sgx_status_t {
+
+    let filter = unsafe { CStr::from_ptr(filter) };
+    let filter = match filter.to_str() {
+        Ok("") => None,
+        Ok(s) => Some(s),
+        Err(_) => return sgx_status_t::SGX_ERROR_INVALID_PARAMETER,
+    };
+
+    let result = rsgx_test_suite!(filter;
                      // tcrypto
                      test_rsgx_sha256_slice,
                      test_rsgx_sha256_handle,
+                     test_aes_gcm_aead,
+                     test_aes_gcm_handle,
+                     test_aes_ctr_cmac_aead,
+                     test_hmac_sha256,
+                     test_hkdf_sha256,
+                     test_ecc256_key_encoding,
+                     test_ecc256_signature_der,
+                     test_rsa3072_key_encoding,
                      // assert
                      foo_panic,
                      foo_should,
                      foo_assert,
+                     test_panic_hook,
                      // rts::veh
                      test_register_first_exception_handler,
                      test_register_last_exception_handler,
                      test_register_multiple_exception_handler,
+                     test_register_closure_exception_handler,
                      // rts::trts
                      test_read_rand,
                      test_data_is_within_enclave,
@@ -122,6 +185,11 @@ fn test_main_entrance() -> sgx_status_t {
                      test_seal_unseal,
                      test_number_sealing,        // Thanks to @silvanegli
                      test_array_sealing,         // Thanks to @silvanegli
+                     test_sealed_data_bytes,
+                     test_reseal_data,
+                     test_seal_stream,
+                     test_versioned_sealed_state,
+                     test_versioned_sealed_state_two_sealers,
                      test_mac_aadata_slice,
                      test_mac_aadata_number,
                      // rand
@@ -136,13 +204,69 @@ fn test_main_entrance() -> sgx_status_t {
                      test_serialize_enum,
                      // std::sgxfs
                      test_sgxfs,
+                     test_sgxfs_set_len,
+                     test_sgxfs_rename_rekey,
+                     test_sgxfs_storage,
+                     // tprotected_fs::pfs
+                     test_pfs_compat,
+                     test_pfs_memory,
+                     test_pfs_crash_recovery,
+                     test_pfs_rename_meta,
                      // std::fs
                      test_fs,
+                     test_fs_read_dir,
                      // std::fs untrusted mode
                      test_fs_untrusted_fs_feature_enabled,
+                     // std::net
+                     test_net_tcp,
+                     test_net_lookup_host,
+                     // os::unix::asyncio
+                     test_asyncio_poll,
+                     test_asyncio_epoll,
                      // std::time
-                     test_std_time
+                     test_std_time,
+                     // types::quote
+                     test_quote_parse,
+                     test_attestation_policy,
+                     // tservice
+                     test_tservice_time_provider,
+                     test_tservice_counter_provider,
+                     // thread
+                     test_thread_spawn_join,
+                     test_thread_join_panic,
+                     test_thread_out_of_tcs,
+                     // ra_tls
+                     test_ra_tls_attested_cert,
+                     test_ra_tls_tampered_evidence,
+                     test_ra_tls_unbound_key,
+                     // tdh_session
+                     test_dh_session_handshake,
+                     test_dh_session_policy,
+                     test_dh_session_sequence,
+                     // ra_sp
+                     test_ra_sp_kdf,
+                     test_ra_sp_key_exchange,
+                     test_ra_sp_bad_msg3,
+                     test_ra_sp_quote_status
                      );
+
+    let output = match format {
+        REPORT_JSON => result.to_json(),
+        REPORT_TAP => result.to_tap(),
+        REPORT_JUNIT => result.to_junit_xml("unit-test"),
+        _ => return sgx_status_t::SGX_ERROR_INVALID_PARAMETER,
+    };
+
+    unsafe {
+        *report_size = output.len();
+        *failed = result.failed() as u32;
+    }
+    if output.len() > report_len {
+        return sgx_status_t::SGX_ERROR_OUT_OF_MEMORY;
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(output.as_ptr(), report, output.len());
+    }
     sgx_status_t::SGX_SUCCESS
 }
 
@@ -26,7 +26,9 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
-use std::panic;
+use std::panic::{self, PanicInfo};
+use std::string::{String, ToString};
+use std::sync::{Arc, SgxMutex};
 use std::vec::Vec;
 
 pub fn foo_panic () {
@@ -47,3 +49,30 @@ pub fn foo_assert() {
     should_panic!(assert!(false));
     should_panic!(panic!());
 }
+
+pub fn test_panic_hook() {
+    let seen: Arc<SgxMutex<Option<(String, String, u32)>>> = Arc::new(SgxMutex::new(None));
+    let previous = panic::take_hook();
+
+    let record = seen.clone();
+    panic::set_hook(Box::new(move |info: &PanicInfo| {
+        let msg = info.payload().downcast_ref::<&'static str>().map(|s| s.to_string());
+        let location = info.location().unwrap();
+        *record.lock().unwrap() = Some((msg.unwrap_or_default(), location.file().to_string(), location.line()));
+    }));
+    let line = line!() + 1;
+    assert!(panic::catch_unwind(|| panic!("hooked panic")).is_err());
+    assert_eq!(*seen.lock().unwrap(), Some(("hooked panic".to_string(), file!().to_string(), line)));
+
+    // take_hook puts the default hook back, and hands over the custom one
+    let hook = panic::take_hook();
+    *seen.lock().unwrap() = None;
+    assert!(panic::catch_unwind(|| panic!("default hook")).is_err());
+    assert!(seen.lock().unwrap().is_none());
+    panic::set_hook(hook);
+    assert!(panic::catch_unwind(|| panic!("hooked again")).is_err());
+    assert_eq!(seen.lock().unwrap().as_ref().map(|s| &s.0[..]), Some("hooked again"));
+
+    drop(panic::take_hook());
+    panic::set_hook(previous);
+}
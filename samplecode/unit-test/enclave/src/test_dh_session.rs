@@ -0,0 +1,112 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tdh_session::*;
+use utils::*;
+
+// Runs the exchange between two sessions of this enclave.
+fn handshake<P: SessionPolicy>(policy: &P) -> SgxResult<(LocalSession, LocalSession)> {
+    let (responder, msg1) = LocalSessionResponder::new()?;
+    let mut initiator = LocalSessionInitiator::new();
+    let msg2 = initiator.proc_msg1(&msg1)?;
+    let (msg3, responder_session) = responder.proc_msg2(&msg2, policy)?;
+    let msg3 = rsgx_dh_msg3_from_bytes(&rsgx_dh_msg3_to_bytes(&msg3)?)?;
+    let initiator_session = initiator.proc_msg3(&msg3, policy)?;
+    Ok((initiator_session, responder_session))
+}
+
+fn self_policy() -> IdentityPolicy {
+    let body = self_report_body();
+    IdentityPolicy::new().mr_enclave(&body.mr_enclave)
+                         .mr_signer(&body.mr_signer)
+                         .min_isv_svn(body.isv_svn)
+                         .allow_debug(true)
+}
+
+pub fn test_dh_session_handshake() {
+    let (mut initiator, mut responder) = handshake(&self_policy()).unwrap();
+    assert_eq!(initiator.role(), SessionRole::Initiator);
+    assert_eq!(responder.role(), SessionRole::Responder);
+    let peer_mr_enclave = initiator.peer_identity().mr_enclave;
+    assert_eq!(peer_mr_enclave.m, self_report_body().mr_enclave.m);
+
+    let msg = initiator.seal_message(b"ping").unwrap();
+    assert_eq!(msg.len(), 4 + SESSION_MSG_OVERHEAD);
+    assert_eq!(responder.open_message(&msg).unwrap(), b"ping");
+    let msg = responder.seal_message(b"pong").unwrap();
+    assert_eq!(initiator.open_message(&msg).unwrap(), b"pong");
+
+    // A message does not open on the side which sealed it.
+    let msg = initiator.seal_message(b"ping").unwrap();
+    assert!(initiator.open_message(&msg).is_err());
+    let mut tampered = msg.clone();
+    tampered[SESSION_MSG_SEQ_SIZE] ^= 1;
+    assert_eq!(responder.open_message(&tampered).err(), Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+    assert_eq!(responder.open_message(&msg).unwrap(), b"ping");
+}
+
+pub fn test_dh_session_policy() {
+    let body = self_report_body();
+
+    let mut mr_signer = body.mr_signer;
+    mr_signer.m[0] ^= 1;
+    let policy = IdentityPolicy::new().mr_signer(&mr_signer).allow_debug(true);
+    assert_eq!(handshake(&policy).err(), Some(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE));
+
+    let policy = self_policy().min_isv_svn(body.isv_svn + 1);
+    assert_eq!(handshake(&policy).err(), Some(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE));
+
+    let policy = |_: &sgx_dh_session_enclave_identity_t| false;
+    assert_eq!(handshake(&policy).err(), Some(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE));
+
+    assert!(handshake(&self_policy()).is_ok());
+}
+
+pub fn test_dh_session_sequence() {
+    let (mut initiator, mut responder) = handshake(&self_policy()).unwrap();
+
+    let msg0 = initiator.seal_message(b"0").unwrap();
+    let msg1 = initiator.seal_message(b"1").unwrap();
+    let msg2 = initiator.seal_message(b"2").unwrap();
+
+    assert_eq!(responder.open_message(&msg0).unwrap(), b"0");
+    // replayed
+    assert_eq!(responder.open_message(&msg0).err(), Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER));
+    // reordered
+    assert_eq!(responder.open_message(&msg2).err(), Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER));
+    assert_eq!(responder.open_message(&msg1).unwrap(), b"1");
+    assert_eq!(responder.open_message(&msg2).unwrap(), b"2");
+
+    // A sequence number rewritten to the expected one fails the tag.
+    let msg3 = initiator.seal_message(b"3").unwrap();
+    let mut msg4 = initiator.seal_message(b"4").unwrap();
+    msg4[0] = 3;
+    assert_eq!(responder.open_message(&msg4).err(), Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+    assert_eq!(responder.open_message(&msg3).unwrap(), b"3");
+}
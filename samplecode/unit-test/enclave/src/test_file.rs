@@ -27,11 +27,12 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use sgx_rand::{Rng, StdRng};
-use std::sgxfs::{self, SgxFile};
+use std::sgxfs::{self, SgxFile, OpenOptions, ProtectedStorage, HostStorage, MemoryStorage, KvStorage};
 use std::untrusted::fs::File;
 use std::untrusted::fs::remove_file;
-use std::io::{Read, Write};
+use std::io::{Read, Write, Seek, SeekFrom};
 use std::string::*;
+use std::vec::Vec;
 
 pub fn test_sgxfs() {
     
@@ -96,6 +97,126 @@ pub fn test_sgxfs() {
     }
 }
 
+pub fn test_sgxfs_set_len() {
+    let mut write_data: [u8; 5000] = [0; 5000];
+    {
+        let mut rand = StdRng::new().unwrap();
+        rand.fill_bytes(&mut write_data);
+
+        let mut file = SgxFile::create("sgx_file_len").unwrap();
+        assert!(file.write_all(&write_data).is_ok());
+        assert!(file.seek(SeekFrom::Start(10)).is_ok());
+        assert_eq!(file.metadata().unwrap().len(), 5000);
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 10);
+    }
+    assert_eq!(sgxfs::metadata("sgx_file_len").unwrap().len(), 5000);
+
+    // Extend with zeros.
+    assert!(sgxfs::set_len("sgx_file_len", 6000).is_ok());
+    {
+        let mut read_data = Vec::new();
+        assert!(SgxFile::open("sgx_file_len").unwrap().read_to_end(&mut read_data).is_ok());
+        assert_eq!(read_data.len(), 6000);
+        assert_eq!(&read_data[..5000], &write_data[..]);
+        assert!(read_data[5000..].iter().all(|b| *b == 0));
+    }
+
+    // Shrink.
+    assert!(sgxfs::set_len("sgx_file_len", 100).is_ok());
+    assert_eq!(sgxfs::read("sgx_file_len").unwrap(), &write_data[..100]);
+    assert!(sgxfs::metadata("sgx_file_len.sgxtmp").is_err());
+
+    let result = sgxfs::remove("sgx_file_len");
+    assert_eq!(result.is_ok(), true);
+    assert!(sgxfs::metadata("sgx_file_len").is_err());
+}
+
+pub fn test_sgxfs_rename_rekey() {
+    let key1: [u8; 16] = [1; 16];
+    let key2: [u8; 16] = [2; 16];
+    {
+        let mut file = SgxFile::create_ex("sgx_file_from", &key1).unwrap();
+        assert!(file.write_all(b"Hello, world!").is_ok());
+    }
+
+    assert!(sgxfs::rename_ex("sgx_file_from", "sgx_file_to", &key1).is_ok());
+    assert!(SgxFile::open_ex("sgx_file_from", &key1).is_err());
+    {
+        let mut s = String::new();
+        assert!(SgxFile::open_ex("sgx_file_to", &key1).unwrap().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "Hello, world!");
+    }
+
+    // Another path to the same file leaves it alone.
+    assert!(sgxfs::rename_ex("sgx_file_to", "./sgx_file_to", &key1).is_ok());
+    assert_eq!(sgxfs::metadata_ex("sgx_file_to", &key1).unwrap().len(), 13);
+
+    // An existing file is replaced.
+    {
+        let mut file = SgxFile::create_ex("sgx_file_from", &key1).unwrap();
+        assert!(file.write_all(b"replaced").is_ok());
+    }
+    assert!(sgxfs::rename_ex("sgx_file_to", "sgx_file_from", &key1).is_ok());
+    assert!(sgxfs::rename_ex("sgx_file_from", "sgx_file_to", &key1).is_ok());
+    assert!(SgxFile::open_ex("sgx_file_from", &key1).is_err());
+
+    assert!(sgxfs::rekey("sgx_file_to", &key1, &key2).is_ok());
+    assert!(SgxFile::open_ex("sgx_file_to", &key1).is_err());
+    assert_eq!(sgxfs::metadata_ex("sgx_file_to", &key2).unwrap().len(), 13);
+    {
+        let mut s = String::new();
+        assert!(SgxFile::open_ex("sgx_file_to", &key2).unwrap().read_to_string(&mut s).is_ok());
+        assert_eq!(s, "Hello, world!");
+    }
+    assert!(sgxfs::metadata_ex("sgx_file_to.sgxtmp", &key2).is_err());
+
+    let result = sgxfs::remove("sgx_file_to");
+    assert_eq!(result.is_ok(), true);
+}
+
+fn sgxfs_storage_roundtrip<S: ProtectedStorage + Clone + 'static>(storage: S) {
+    let mut data = vec![0_u8; 20000];
+    StdRng::new().unwrap().fill_bytes(&mut data);
+    {
+        let mut file = OpenOptions::new().write(true).open_in(storage.clone(), "sgx_file_in").unwrap();
+        assert!(file.write_all(&data).is_ok());
+        assert_eq!(file.metadata().unwrap().len(), 20000);
+    }
+    {
+        let mut file = OpenOptions::new().read(true).update(true).open_in(storage.clone(), "sgx_file_in").unwrap();
+        assert_eq!(file.seek(SeekFrom::Start(5000)).unwrap(), 5000);
+        assert!(file.write_all(b"patch").is_ok());
+    }
+    data[5000..5005].copy_from_slice(b"patch");
+    {
+        let mut file = OpenOptions::new().read(true).open_in(storage.clone(), "sgx_file_in").unwrap();
+        let mut read = Vec::new();
+        assert!(file.read_to_end(&mut read).is_ok());
+        assert!(read == data);
+    }
+    let key = [7_u8; 16];
+    assert!(OpenOptions::new().read(true).open_in_ex(storage.clone(), "sgx_file_in", &key).is_err());
+    assert!(sgxfs::remove_in(&storage, "sgx_file_in").is_ok());
+    assert!(OpenOptions::new().read(true).open_in(storage.clone(), "sgx_file_in").is_err());
+}
+
+pub fn test_sgxfs_storage() {
+    sgxfs_storage_roundtrip(HostStorage);
+    sgxfs_storage_roundtrip(MemoryStorage::new());
+    // The app serves sgx_kvstore.edl from a MemoryKvStore.
+    sgxfs_storage_roundtrip(KvStorage::new());
+
+    // HostStorage files are the files of SgxFile::open.
+    {
+        let mut file = OpenOptions::new().write(true).open_in(HostStorage, "sgx_file_host").unwrap();
+        assert!(file.write_all(b"Hello, world!").is_ok());
+    }
+    let mut s = String::new();
+    assert!(SgxFile::open("sgx_file_host").unwrap().read_to_string(&mut s).is_ok());
+    assert_eq!(s, "Hello, world!");
+    assert!(sgxfs::remove("sgx_file_host").is_ok());
+}
+
 pub fn test_fs () {
     {
         let f = File::create("foo.txt");
@@ -117,6 +238,47 @@ pub fn test_fs () {
     }
 }
 
+pub fn test_fs_read_dir() {
+    use std::untrusted::fs;
+    use std::untrusted::path::PathEx;
+    use std::path::Path;
+    use std::io::ErrorKind;
+
+    let dir = Path::new("sgx_read_dir");
+    let _ = fs::remove_dir_all(dir);
+    assert!(fs::create_dir(dir).is_ok());
+    assert_eq!(fs::create_dir(dir).unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    // the longest name a directory entry can hold, and a short one
+    let long_name = "n".repeat(255);
+    for name in &["a", "file_b.txt", &long_name[..]] {
+        assert!(File::create(dir.join(name)).is_ok());
+    }
+    assert!(fs::create_dir(dir.join("sub")).is_ok());
+
+    let mut entries: Vec<(String, bool)> = fs::read_dir(dir).unwrap().map(|entry| {
+        let entry = entry.unwrap();
+        (entry.file_name().into_string().unwrap(), entry.file_type().unwrap().is_dir())
+    }).collect();
+    entries.sort();
+    let mut expected = vec![("a".to_string(), false),
+                            ("file_b.txt".to_string(), false),
+                            (long_name.clone(), false),
+                            ("sub".to_string(), true)];
+    expected.sort();
+    assert_eq!(entries, expected);
+
+    // only empty directories are removed
+    assert!(fs::remove_dir(dir).is_err());
+    assert!(fs::remove_dir(dir.join("sub")).is_ok());
+    for name in &["a", "file_b.txt", &long_name[..]] {
+        assert!(remove_file(dir.join(name)).is_ok());
+    }
+    assert!(fs::read_dir(dir).unwrap().next().is_none());
+    assert!(fs::remove_dir(dir).is_ok());
+    assert!(!dir.exists());
+}
+
 pub fn test_fs_untrusted_fs_feature_enabled() {
     {
         use std::fs;
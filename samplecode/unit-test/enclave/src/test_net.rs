@@ -0,0 +1,124 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+use std::io::{Read, Write, ErrorKind};
+use std::net::{TcpListener, TcpStream, SocketAddr, ToSocketAddrs};
+use std::untrusted::net::lookup_host;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::asyncio::{self, Epoll, pollfd, epoll_event};
+use sgx_trts::libc::{POLLIN, EPOLLIN};
+use std::time::Duration;
+use std::vec::Vec;
+
+pub fn test_net_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    assert!(addr.ip().is_loopback());
+    assert!(addr.port() != 0);
+
+    // the connection is queued by the kernel, so one thread can do both ends
+    let mut client = TcpStream::connect(addr).unwrap();
+    let (mut server, peer) = listener.accept().unwrap();
+    assert_eq!(peer, client.local_addr().unwrap());
+    assert_eq!(server.peer_addr().unwrap(), client.local_addr().unwrap());
+
+    assert!(client.write_all(b"ping").is_ok());
+    let mut buf = [0_u8; 4];
+    assert!(server.read_exact(&mut buf).is_ok());
+    assert_eq!(&buf, b"ping");
+    assert!(server.write_all(b"pong").is_ok());
+    assert!(client.read_exact(&mut buf).is_ok());
+    assert_eq!(&buf, b"pong");
+
+    // a closed peer reads as end of stream
+    drop(server);
+    assert_eq!(client.read(&mut buf).unwrap(), 0);
+
+    // nothing answers on a non-routable address
+    let unroutable: SocketAddr = "10.255.255.1:80".parse().unwrap();
+    let err = TcpStream::connect_timeout(&unroutable, Duration::from_millis(200)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+pub fn test_net_lookup_host() {
+    let addrs: Vec<SocketAddr> = lookup_host("localhost").unwrap().collect();
+    assert!(!addrs.is_empty());
+    assert!(addrs.iter().all(|addr| addr.ip().is_loopback() && addr.port() == 0));
+
+    let addrs: Vec<SocketAddr> = "localhost:8080".to_socket_addrs().unwrap().collect();
+    assert!(!addrs.is_empty());
+    assert!(addrs.iter().all(|addr| addr.ip().is_loopback() && addr.port() == 8080));
+    let addrs: Vec<SocketAddr> = ("localhost", 8081).to_socket_addrs().unwrap().collect();
+    assert!(addrs.iter().all(|addr| addr.ip().is_loopback() && addr.port() == 8081));
+
+    // .invalid names never resolve
+    assert!(lookup_host("no-such-host.invalid").is_err());
+    assert!("no-such-host.invalid:80".to_socket_addrs().is_err());
+    assert!("localhost".to_socket_addrs().is_err());
+}
+
+// A connected pair of loopback sockets.
+fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+pub fn test_asyncio_poll() {
+    let (mut client, server) = tcp_pair();
+    let mut fds = [pollfd { fd: server.as_raw_fd(), events: POLLIN, revents: 0 }];
+
+    // nothing to read yet, so the call times out
+    assert_eq!(asyncio::poll(&mut fds, Some(Duration::from_millis(10))).unwrap(), 0);
+    assert_eq!(fds[0].revents, 0);
+
+    assert!(client.write_all(b"x").is_ok());
+    assert_eq!(asyncio::poll(&mut fds, Some(Duration::from_secs(5))).unwrap(), 1);
+    assert!(fds[0].revents & POLLIN != 0);
+}
+
+pub fn test_asyncio_epoll() {
+    let (mut client, server) = tcp_pair();
+    let epoll = Epoll::new().unwrap();
+    assert!(epoll.add(server.as_raw_fd(), EPOLLIN as u32, 7).is_ok());
+    let mut events = [epoll_event { events: 0, u64: 0 }; 4];
+
+    // nothing to read yet, so the call times out
+    assert_eq!(epoll.wait(&mut events, Some(Duration::from_millis(10))).unwrap(), 0);
+
+    assert!(client.write_all(b"x").is_ok());
+    assert_eq!(epoll.wait(&mut events, Some(Duration::from_secs(5))).unwrap(), 1);
+    // epoll_event is packed, its fields are copied out before use
+    let (flags, token) = (events[0].events, events[0].u64);
+    assert!(flags & EPOLLIN as u32 != 0);
+    assert_eq!(token, 7);
+
+    // the data is still unread, but the socket is no longer registered
+    assert!(epoll.delete(server.as_raw_fd()).is_ok());
+    assert_eq!(epoll.wait(&mut events, Some(Duration::from_millis(10))).unwrap(), 0);
+}
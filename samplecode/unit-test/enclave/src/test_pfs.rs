@@ -0,0 +1,189 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_rand::{Rng, StdRng};
+use sgx_tprotected_fs::SgxFileStream;
+use sgx_tprotected_fs::pfs::{self, ProtectedFile, MemoryStorage};
+use sgx_types::*;
+use std::ffi::CString;
+use std::vec::Vec;
+
+const KEY: sgx_key_128bit_t = [7; 16];
+
+fn read_all(storage: &MemoryStorage, name: &CString, key: &sgx_key_128bit_t) -> Result<Vec<u8>, i32> {
+    let mut file = ProtectedFile::open_in(storage.clone(), name, &CString::new("r").unwrap(), key)?;
+    let mut data = Vec::new();
+    let mut buf = [0_u8; 1000];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    Ok(data)
+}
+
+pub fn test_pfs_compat() {
+    let name = CString::new("pfs_compat_file").unwrap();
+    let mut data = vec![0_u8; 500000];
+    StdRng::new().unwrap().fill_bytes(&mut data);
+
+    // written in Rust, read by the Intel library
+    {
+        let mut file = ProtectedFile::open(&name, &CString::new("w").unwrap(), &KEY).unwrap();
+        assert_eq!(file.write(&data).unwrap(), data.len());
+        assert!(file.close().is_ok());
+    }
+    {
+        let stream = SgxFileStream::open(&name, &CString::new("r+").unwrap(), &KEY).unwrap();
+        let mut read = vec![0_u8; data.len()];
+        assert_eq!(stream.read(&mut read).unwrap(), data.len());
+        assert_eq!(read, data);
+        assert!(stream.seek(100000, sgx_tprotected_fs::SeekFrom::Start).is_ok());
+        assert!(stream.write(b"written by sgx_fwrite").is_ok());
+    }
+    // and back
+    data[100000..100021].copy_from_slice(b"written by sgx_fwrite");
+    {
+        let mut file = ProtectedFile::open(&name, &CString::new("r").unwrap(), &KEY).unwrap();
+        let mut read = vec![0_u8; data.len() + 1];
+        assert_eq!(file.read(&mut read).unwrap(), data.len());
+        assert_eq!(&read[..data.len()], &data[..]);
+        assert!(file.is_eof());
+    }
+    assert!(pfs::remove(&name).is_ok());
+}
+
+pub fn test_pfs_memory() {
+    let storage = MemoryStorage::new();
+    let name = CString::new("pfs_memory_file").unwrap();
+    let mut data = vec![0_u8; 800000];
+    let mut rng = StdRng::new().unwrap();
+    rng.fill_bytes(&mut data);
+
+    {
+        let mut file = ProtectedFile::open_in(storage.clone(), &name, &CString::new("w+").unwrap(), &KEY).unwrap();
+        for chunk in data.chunks(7777) {
+            assert_eq!(file.write(chunk).unwrap(), chunk.len());
+        }
+        for _ in 0..100 {
+            let offset = rng.gen_range(0, data.len());
+            let len = rng.gen_range(1, 10000);
+            let mut buf = vec![0_u8; len];
+            assert!(file.seek(offset as i64, sgx_tprotected_fs::SeekFrom::Start).is_ok());
+            let n = file.read(&mut buf).unwrap();
+            assert_eq!(&buf[..n], &data[offset..offset + n]);
+        }
+    }
+    assert_eq!(read_all(&storage, &name, &KEY).unwrap(), data);
+    assert!(read_all(&storage, &name, &[8; 16]).is_err());
+
+    // flip one bit of a data node
+    let mut raw = storage.contents(&name).unwrap();
+    raw[pfs::NODE_SIZE * 150 + 1] ^= 1;
+    storage.set_contents(&name, raw);
+    assert_eq!(read_all(&storage, &name, &KEY).err(), Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH as i32));
+}
+
+pub fn test_pfs_crash_recovery() {
+    let name = CString::new("pfs_crash_file").unwrap();
+    let mut rng = StdRng::new().unwrap();
+    let mut old = vec![0_u8; 300000];
+    rng.fill_bytes(&mut old);
+    let mut update = vec![0_u8; 20000];
+    rng.fill_bytes(&mut update);
+    let mut new = old.clone();
+    new[150000..170000].copy_from_slice(&update);
+    new.extend_from_slice(&update);
+
+    let initial = MemoryStorage::new();
+    {
+        let mut file = ProtectedFile::open_in(initial.clone(), &name, &CString::new("w").unwrap(), &KEY).unwrap();
+        assert!(file.write(&old).is_ok());
+    }
+    let snapshot = initial.contents(&name).unwrap();
+
+    let apply = |storage: &MemoryStorage| -> Result<(), i32> {
+        let mut file = ProtectedFile::open_in(storage.clone(), &name, &CString::new("r+").unwrap(), &KEY)?;
+        file.seek(150000, sgx_tprotected_fs::SeekFrom::Start)?;
+        file.write(&update)?;
+        file.seek(0, sgx_tprotected_fs::SeekFrom::End)?;
+        file.write(&update)?;
+        file.close()
+    };
+
+    let storage = MemoryStorage::new();
+    storage.set_contents(&name, snapshot.clone());
+    let writes = storage.writes();
+    assert!(apply(&storage).is_ok());
+    let needed = storage.writes() - writes;
+
+    // crash after every possible number of writes: the file is either untouched or fully updated
+    for count in 0..needed {
+        let storage = MemoryStorage::new();
+        storage.set_contents(&name, snapshot.clone());
+        storage.fail_after(count);
+        let _ = apply(&storage);
+        storage.clear_failures();
+        let data = read_all(&storage, &name, &KEY).unwrap();
+        assert!(data == old || data == new);
+    }
+}
+
+pub fn test_pfs_rename_meta() {
+    let storage = MemoryStorage::new();
+    let old_name = CString::new("dir/pfs_old_name").unwrap();
+    let new_name = CString::new("other\\pfs_new_name").unwrap();
+    let mut data = vec![0_u8; 300000];
+    StdRng::new().unwrap().fill_bytes(&mut data);
+    {
+        let mut file = ProtectedFile::open_in(storage.clone(), &old_name, &CString::new("w").unwrap(), &KEY).unwrap();
+        assert_eq!(file.write(&data).unwrap(), data.len());
+    }
+    let before = storage.contents(&old_name).unwrap();
+
+    assert!(pfs::rename_meta_in(storage.clone(), &old_name, &new_name, Some(&KEY)).is_ok());
+    let after = storage.contents(&old_name).unwrap();
+    // only the meta data node changed
+    assert_eq!(after.len(), before.len());
+    assert!(after[..pfs::NODE_SIZE] != before[..pfs::NODE_SIZE]);
+    assert!(after[pfs::NODE_SIZE..] == before[pfs::NODE_SIZE..]);
+    assert_eq!(read_all(&storage, &old_name, &KEY).err(),
+               Some(sgx_status_t::SGX_ERROR_FILE_NAME_MISMATCH as i32));
+
+    // an interrupted rename is resumed, another name is refused
+    assert!(pfs::rename_meta_in(storage.clone(), &old_name, &new_name, Some(&KEY)).is_ok());
+    assert!(pfs::rename_meta_in(storage.clone(), &old_name, &CString::new("pfs_third_name").unwrap(), Some(&KEY)).is_err());
+
+    // '\' separates directories too, so the file opens from any directory
+    let moved_name = CString::new("pfs_new_name").unwrap();
+    storage.set_contents(&moved_name, after);
+    assert!(pfs::remove_in(&storage, &old_name).is_ok());
+    assert_eq!(read_all(&storage, &moved_name, &KEY).unwrap(), data);
+}
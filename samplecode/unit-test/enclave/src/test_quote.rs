@@ -0,0 +1,114 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use std::vec::Vec;
+
+fn sample_quote(signature_len: usize) -> Vec<u8> {
+    let mut quote = vec![0_u8; SGX_QUOTE_HEADER_SIZE + signature_len];
+    quote[0] = 2;                                   // version
+    quote[2] = 1;                                   // sign_type
+    quote[4..8].copy_from_slice(&[0xaa, 0x0a, 0, 0]);   // epid_group_id
+    quote[8] = 7;                                   // qe_svn
+    // report_body starts at 48
+    quote[48 + 48..48 + 56].copy_from_slice(&[0x05, 0, 0, 0, 0, 0, 0, 0]);  // flags
+    quote[48 + 56..48 + 64].copy_from_slice(&[0x07, 0, 0, 0, 0, 0, 0, 0]);  // xfrm
+    for b in quote[48 + 64..48 + 96].iter_mut() { *b = 0x11; }              // mr_enclave
+    for b in quote[48 + 128..48 + 160].iter_mut() { *b = 0x22; }            // mr_signer
+    quote[48 + 256] = 3;                            // isv_prod_id
+    quote[48 + 258] = 4;                            // isv_svn
+    quote[48 + 320] = 0x99;                         // report_data
+    quote[432] = signature_len as u8;
+    for (i, b) in quote[SGX_QUOTE_HEADER_SIZE..].iter_mut().enumerate() { *b = i as u8; }
+    quote
+}
+
+pub fn test_quote_parse() {
+    let bytes = sample_quote(10);
+    let quote = Quote::parse(&bytes).unwrap();
+    assert_eq!(quote.version(), 2);
+    assert_eq!(quote.sign_type(), sgx_quote_sign_type_t::SGX_LINKABLE_SIGNATURE as u16);
+    assert_eq!(quote.epid_group_id(), [0xaa, 0x0a, 0, 0]);
+    assert_eq!(quote.qe_svn(), 7);
+    assert_eq!(quote.flags(), SGX_FLAGS_INITTED | SGX_FLAGS_MODE64BIT);
+    assert_eq!(quote.xfrm(), 7);
+    assert!(!quote.is_debug());
+    assert_eq!(quote.mr_enclave().m, [0x11; SGX_HASH_SIZE]);
+    assert_eq!(quote.mr_signer().m, [0x22; SGX_HASH_SIZE]);
+    assert_eq!(quote.isv_prod_id(), 3);
+    assert_eq!(quote.isv_svn(), 4);
+    assert_eq!(quote.report_data().d[0], 0x99);
+    assert_eq!(quote.signature(), &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    // The length has to match signature_len exactly.
+    assert!(Quote::parse(&bytes[..bytes.len() - 1]).is_err());
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert!(Quote::parse(&longer).is_err());
+    assert!(Quote::parse(&bytes[..SGX_QUOTE_HEADER_SIZE - 1]).is_err());
+
+    let body = Quote::parse_body(&bytes[..SGX_QUOTE_BODY_SIZE]).unwrap();
+    assert_eq!(body.isv_svn(), 4);
+    assert_eq!(body.mr_signer().m, [0x22; SGX_HASH_SIZE]);
+    assert!(body.signature().is_empty());
+    assert!(Quote::parse_body(&bytes[..SGX_QUOTE_HEADER_SIZE]).is_err());
+}
+
+pub fn test_attestation_policy() {
+    let bytes = sample_quote(0);
+    let quote = Quote::parse(&bytes).unwrap();
+
+    let enclaves = [sgx_measurement_t { m: [0x33; SGX_HASH_SIZE] }, sgx_measurement_t { m: [0x11; SGX_HASH_SIZE] }];
+    let signers = [sgx_measurement_t { m: [0x22; SGX_HASH_SIZE] }];
+    let others = [sgx_measurement_t { m: [0x44; SGX_HASH_SIZE] }];
+
+    assert_eq!(AttestationPolicy::new().check_quote(&quote), Ok(()));
+    let policy = AttestationPolicy::new()
+        .mr_enclaves(&enclaves)
+        .mr_signers(&signers)
+        .isv_prod_id(3)
+        .min_isv_svn(4)
+        .xfrm(SGX_XFRM_LEGACY, SGX_XFRM_LEGACY);
+    assert_eq!(policy.check_quote(&quote), Ok(()));
+
+    assert_eq!(policy.mr_enclaves(&others).check_quote(&quote),
+               Err(PolicyRejection::MrEnclaveNotAllowed([0x11; SGX_HASH_SIZE])));
+    assert_eq!(policy.mr_signers(&others).check_quote(&quote),
+               Err(PolicyRejection::MrSignerNotAllowed([0x22; SGX_HASH_SIZE])));
+    assert_eq!(policy.isv_prod_id(2).check_quote(&quote),
+               Err(PolicyRejection::IsvProdIdMismatch { expected: 2, found: 3 }));
+    assert_eq!(policy.min_isv_svn(5).check_quote(&quote),
+               Err(PolicyRejection::IsvSvnTooLow { min: 5, found: 4 }));
+    assert_eq!(policy.xfrm(SGX_XFRM_AVX512, SGX_XFRM_AVX512).check_quote(&quote),
+               Err(PolicyRejection::XfrmMismatch { mask: SGX_XFRM_AVX512, expected: SGX_XFRM_AVX512, found: 7 }));
+
+    let mut body = *quote.report_body();
+    body.attributes.flags |= SGX_FLAGS_DEBUG;
+    assert_eq!(policy.check(&body), Err(PolicyRejection::DebugNotAllowed));
+    assert_eq!(policy.allow_debug(true).check(&body), Ok(()));
+}
@@ -0,0 +1,160 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tcrypto::*;
+use sgx_ra_sp::*;
+use std::vec::Vec;
+
+const GID: sgx_epid_group_id_t = [0x0a, 0x0b, 0x0c, 0x0d];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Msg3Fault {
+    None,
+    Mac,
+    ReportData,
+    GroupId,
+}
+
+fn public_bytes(key: &sgx_ec256_public_t) -> Vec<u8> {
+    let mut bytes = key.gx.to_vec();
+    bytes.extend_from_slice(&key.gy);
+    bytes
+}
+
+// Plays the enclave side of the exchange by hand, up to a msg3 with the given fault, and returns
+// what the service provider made of it.
+fn attest(status: QuoteStatus, fault: Msg3Fault) -> SgxResult<RaResult> {
+    let ecc_handle = SgxEccHandle::new();
+    ecc_handle.open().unwrap();
+    let (sp_private, sp_public) = ecc_handle.create_key_pair().unwrap();
+    let mut service = StubAttestationService::new();
+    service.sig_rl = vec![1, 2, 3];
+    service.quote_status = status;
+    let mut spid = sgx_spid_t::default();
+    spid.id = [0x55; 16];
+    let sp = ServiceProvider::new(spid, sp_private, service);
+
+    // msg1
+    let (a, g_a) = ecc_handle.create_key_pair().unwrap();
+    let mut msg1 = public_bytes(&g_a);
+    msg1.extend_from_slice(&GID);
+    let (session, msg2) = sp.proc_msg1(&msg1).unwrap();
+
+    // msg2: g_b, spid, quote type, KDF id, signature of g_b || g_a, MAC, SigRL
+    assert_eq!(msg2.len(), RA_MSG2_HEADER_SIZE + 3);
+    let mut g_b = sgx_ec256_public_t::default();
+    g_b.gx.copy_from_slice(&msg2[..32]);
+    g_b.gy.copy_from_slice(&msg2[32..64]);
+    assert_eq!(&msg2[64..80], &spid.id);
+    assert_eq!(&msg2[168..], &[1, 2, 3]);
+
+    let mut signature = sgx_ec256_signature_t::default();
+    for i in 0..16 {
+        let word = &msg2[84 + i * 4..88 + i * 4];
+        let word = (word[0] as u32) | (word[1] as u32) << 8 | (word[2] as u32) << 16 | (word[3] as u32) << 24;
+        if i < 8 { signature.x[i] = word; } else { signature.y[i - 8] = word; }
+    }
+    let mut gb_ga = public_bytes(&g_b);
+    gb_ga.extend_from_slice(&msg1[..64]);
+    assert!(ecc_handle.ecdsa_verify_slice(&gb_ga, &sp_public, &signature).unwrap());
+
+    let shared = ecc_handle.compute_shared_dhkey(&a, &g_b).unwrap();
+    let keys = RaKeys::derive(&shared).unwrap();
+    assert_eq!(rsgx_rijndael128_cmac_slice(&keys.smk, &msg2[..148]).unwrap(), &msg2[148..164]);
+
+    // msg3: MAC, g_a, security properties, quote
+    let mut quote = vec![0_u8; SGX_QUOTE_HEADER_SIZE + 10];
+    quote[0] = 2;
+    quote[2] = sgx_quote_sign_type_t::SGX_LINKABLE_SIGNATURE as u8;
+    quote[4..8].copy_from_slice(&GID);
+    quote[432] = 10;
+    let mut binding = public_bytes(&g_a);
+    binding.extend_from_slice(&public_bytes(&g_b));
+    binding.extend_from_slice(&keys.vk);
+    quote[48 + 320..48 + 352].copy_from_slice(&rsgx_sha256_slice(&binding).unwrap());
+    match fault {
+        Msg3Fault::ReportData => quote[48 + 320] ^= 1,
+        Msg3Fault::GroupId => quote[4] ^= 1,
+        _ => (),
+    }
+
+    let mut msg3 = vec![0_u8; SGX_MAC_SIZE];
+    msg3.extend_from_slice(&public_bytes(&g_a));
+    msg3.extend_from_slice(&[0_u8; 256]);
+    msg3.extend_from_slice(&quote);
+    let mac = rsgx_rijndael128_cmac_slice(&keys.smk, &msg3[SGX_MAC_SIZE..]).unwrap();
+    msg3[..SGX_MAC_SIZE].copy_from_slice(&mac);
+    if fault == Msg3Fault::Mac {
+        msg3[0] ^= 1;
+    }
+
+    let result = sp.proc_msg3(session, &msg3)?;
+    assert_eq!(result.key(sgx_ra_key_type_t::SGX_RA_KEY_SK), keys.sk);
+    assert_eq!(result.key(sgx_ra_key_type_t::SGX_RA_KEY_MK), keys.mk);
+    assert_eq!(&result.report_body().report_data.d[..], &quote[48 + 320..48 + 384]);
+    assert!(result.ps_sec_prop().is_none());
+    Ok(result)
+}
+
+// Keys from two fixed shared secrets, computed with an independent AES-CMAC implementation.
+pub fn test_ra_sp_kdf() {
+    let mut shared = sgx_ec256_dh_shared_t::default();
+    for (i, b) in shared.s.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let keys = RaKeys::derive(&shared).unwrap();
+    assert_eq!(keys.smk, [0x39, 0x99, 0xa1, 0x76, 0xf6, 0x16, 0x79, 0x10, 0x4b, 0x0b, 0x22, 0x61, 0x87, 0x12, 0x5f, 0x95]);
+    assert_eq!(keys.sk, [0x71, 0x66, 0x75, 0xba, 0x42, 0xc8, 0xe9, 0x07, 0x01, 0x66, 0xea, 0x4c, 0x68, 0x40, 0xa3, 0x3f]);
+    assert_eq!(keys.mk, [0x06, 0xd7, 0x1c, 0x1b, 0xa8, 0xd1, 0xe3, 0x4a, 0xe4, 0x05, 0xf2, 0x50, 0x5f, 0xff, 0x7a, 0x79]);
+    assert_eq!(keys.vk, [0xf4, 0xc7, 0xae, 0x2f, 0xe8, 0x74, 0xc6, 0xb8, 0x26, 0x93, 0xda, 0xb1, 0x3e, 0xba, 0xe8, 0xde]);
+
+    shared.s = [0xa5; SGX_ECP256_KEY_SIZE];
+    let keys = RaKeys::derive(&shared).unwrap();
+    assert_eq!(keys.smk, [0x45, 0x15, 0x96, 0xdd, 0xf5, 0xaf, 0x87, 0x6a, 0xfb, 0x97, 0x7a, 0xf4, 0x81, 0x49, 0xd0, 0x0c]);
+    assert_eq!(keys.sk, [0x5e, 0x72, 0x10, 0xe8, 0x0b, 0x3a, 0xd6, 0x73, 0xa1, 0x2c, 0x34, 0x7a, 0xab, 0xd9, 0xd9, 0x00]);
+    assert_eq!(keys.mk, [0x85, 0xd4, 0xea, 0xc8, 0x48, 0xda, 0xf6, 0x7e, 0x0f, 0xac, 0x7f, 0x2b, 0x7f, 0xcf, 0x0c, 0x2f]);
+    assert_eq!(keys.vk, [0x16, 0x35, 0xcb, 0x82, 0xee, 0xe0, 0xda, 0xf2, 0x9e, 0x5f, 0x58, 0x7a, 0xee, 0x53, 0x26, 0x58]);
+}
+
+pub fn test_ra_sp_key_exchange() {
+    let result = attest(QuoteStatus::Ok, Msg3Fault::None).unwrap();
+    assert_eq!(result.attestation_report().quote_status, QuoteStatus::Ok);
+}
+
+pub fn test_ra_sp_bad_msg3() {
+    assert_eq!(attest(QuoteStatus::Ok, Msg3Fault::Mac).err(), Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+    assert_eq!(attest(QuoteStatus::Ok, Msg3Fault::ReportData).err(), Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+    assert_eq!(attest(QuoteStatus::Ok, Msg3Fault::GroupId).err(), Some(sgx_status_t::SGX_ERROR_INVALID_PARAMETER));
+}
+
+pub fn test_ra_sp_quote_status() {
+    assert_eq!(attest(QuoteStatus::GroupRevoked, Msg3Fault::None).err(), Some(sgx_status_t::SGX_ERROR_INVALID_SIGNATURE));
+    assert_eq!(attest(QuoteStatus::GroupOutOfDate, Msg3Fault::None).err(), Some(sgx_status_t::SGX_ERROR_UPDATE_NEEDED));
+    assert_eq!(attest(QuoteStatus::Unknown, Msg3Fault::None).err(), Some(sgx_status_t::SGX_ERROR_UNEXPECTED));
+}
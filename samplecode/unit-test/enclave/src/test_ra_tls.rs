@@ -0,0 +1,122 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tcrypto::*;
+use sgx_tcrypto::der::*;
+use sgx_ra_tls::*;
+use std::vec::Vec;
+use rustls;
+use rustls::ServerCertVerifier;
+use webpki;
+use utils::*;
+
+// Replaces the TBSCertificate of `cert` with the edited one, signed again with `key`, so that
+// only the evidence checks can reject the result.
+fn resign<F>(cert: &[u8], key: &[u8], edit: F) -> Vec<u8> where F: FnOnce(&mut Vec<u8>) {
+    let mut outer = DerReader::new(cert);
+    let mut cert = DerReader::new(outer.read(TAG_SEQUENCE).unwrap());
+    let (tbs, _) = cert.read_element(TAG_SEQUENCE).unwrap();
+    let algorithm = cert.read(TAG_SEQUENCE).unwrap();
+
+    let mut tbs = tbs.to_vec();
+    edit(&mut tbs);
+    let (private, _) = rsgx_ecc256_private_key_from_der(key).unwrap();
+    let ecc_handle = SgxEccHandle::new();
+    ecc_handle.open().unwrap();
+    let signature = ecc_handle.ecdsa_sign_slice(tbs.as_slice(), &private).unwrap();
+    ecc_handle.close().unwrap();
+
+    let mut signature_bits = vec![0_u8];
+    signature_bits.extend_from_slice(&rsgx_ecc256_signature_to_der(&signature));
+    let mut resigned = tbs;
+    write_tlv(&mut resigned, TAG_SEQUENCE, algorithm);
+    write_tlv(&mut resigned, TAG_BIT_STRING, &signature_bits);
+    wrap(TAG_SEQUENCE, &resigned)
+}
+
+fn verify_server_cert<F>(cert: &rustls::Certificate, policy: F) -> bool
+    where F: Fn(&sgx_report_body_t, &AttestationEvidence) -> bool + Send + Sync {
+
+    let verifier = AttestedServerCertVerifier::new(policy);
+    let dns_name = webpki::DNSNameRef::try_from_ascii_str("enclave").unwrap();
+    verifier.verify_server_cert(&rustls::RootCertStore::empty(), &[cert.clone()], dns_name, &[]).is_ok()
+}
+
+pub fn test_ra_tls_attested_cert() {
+    let target_info = self_target_info();
+    let attested = rsgx_create_attested_cert("enclave", |report_data| {
+        rsgx_create_report_evidence(&target_info, report_data)
+    }).unwrap();
+
+    let evidence = rsgx_verify_attested_cert(&attested.cert.0).unwrap();
+    assert_eq!(evidence.report_body().unwrap().mr_enclave.m, target_info.mr_enclave.m);
+    assert!(verify_server_cert(&attested.cert, |body, _| body.mr_enclave.m == target_info.mr_enclave.m));
+    assert!(!verify_server_cert(&attested.cert, |_, _| false));
+
+    // Signed again without a change, the certificate is still accepted.
+    let resigned = resign(&attested.cert.0, &attested.key.0, |_| ());
+    assert!(rsgx_verify_attested_cert(&resigned).is_ok());
+
+    // A changed byte breaks the self-signature.
+    let mut tampered = attested.cert.0.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(rsgx_verify_attested_cert(&tampered).is_err());
+
+    assert!(rsgx_create_attested_server_config("enclave", |report_data| {
+        rsgx_create_report_evidence(&target_info, report_data)
+    }).is_ok());
+}
+
+pub fn test_ra_tls_tampered_evidence() {
+    let target_info = self_target_info();
+    let attested = rsgx_create_attested_cert("enclave", |report_data| {
+        rsgx_create_report_evidence(&target_info, report_data)
+    }).unwrap();
+
+    // The report follows the OID and the OCTET STRING header of the extension; isv_svn is at
+    // offset 258 of the report body. The report MAC no longer matches.
+    let tampered = resign(&attested.cert.0, &attested.key.0, |tbs| {
+        let oid = tbs.windows(OID_SGX_REPORT.len()).position(|w| w == OID_SGX_REPORT).unwrap();
+        let report = oid + OID_SGX_REPORT.len() + 4;
+        tbs[report + 258] ^= 1;
+    });
+    assert!(rsgx_verify_attested_cert(&tampered).is_err());
+    assert!(!verify_server_cert(&rustls::Certificate(tampered), |_, _| true));
+}
+
+pub fn test_ra_tls_unbound_key() {
+    let target_info = self_target_info();
+    // The report is valid, but its report data is not the hash of the certificate key.
+    let attested = rsgx_create_attested_cert("enclave", |_| {
+        rsgx_create_report_evidence(&target_info, &sgx_report_data_t::default())
+    }).unwrap();
+    assert!(rsgx_verify_attested_cert(&attested.cert.0).is_err());
+    assert!(!verify_server_cert(&attested.cert, |_, _| true));
+}
@@ -29,6 +29,7 @@
 use sgx_types::*;
 use std::vec::Vec;
 use std::string::String;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use sgx_trts::trts::*;
 use sgx_trts::veh::*;
@@ -81,6 +82,54 @@ pub fn test_register_multiple_exception_handler() {
     }
 }
 
+static CLOSURE_HANDLED: AtomicUsize = AtomicUsize::new(0);
+static FALLBACK_HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+// ud2 raises #UD and is two bytes long.
+fn raise_ud() {
+    unsafe { asm!("ud2" :::: "volatile"); }
+}
+
+extern "C" fn fallback_exception_handler(info: *mut sgx_exception_info_t) -> uint32_t {
+    let info = unsafe { &mut *info };
+    if info.exception_vector != sgx_exception_vector_t::SGX_EXCEPTION_VECTOR_UD {
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+    info.cpu_context.rip += 2;
+    FALLBACK_HANDLED.fetch_add(1, Ordering::SeqCst);
+    EXCEPTION_CONTINUE_EXECUTION
+}
+
+pub fn test_register_closure_exception_handler() {
+    let fallback = rsgx_register_exception_handler(0, fallback_exception_handler);
+    assert!(fallback.is_some());
+
+    let guard = register_exception_handler(true, |info| {
+        if info.vector() != sgx_exception_vector_t::SGX_EXCEPTION_VECTOR_UD {
+            return HandleResult::ContinueSearch;
+        }
+        info.context_mut().rip += 2;
+        CLOSURE_HANDLED.fetch_add(1, Ordering::SeqCst);
+        HandleResult::ContinueExecution
+    });
+    assert!(guard.is_some());
+    // Called first, and passes the exception on.
+    let searching = register_exception_handler(true, |_| HandleResult::ContinueSearch);
+    assert!(searching.is_some());
+
+    raise_ud();
+    assert_eq!(CLOSURE_HANDLED.load(Ordering::SeqCst), 1);
+    assert_eq!(FALLBACK_HANDLED.load(Ordering::SeqCst), 0);
+
+    drop(guard);
+    drop(searching);
+    raise_ud();
+    assert_eq!(CLOSURE_HANDLED.load(Ordering::SeqCst), 1);
+    assert_eq!(FALLBACK_HANDLED.load(Ordering::SeqCst), 1);
+
+    assert_eq!(rsgx_unregister_exception_handler(fallback.unwrap()), true);
+}
+
 // trts
 pub fn test_read_rand(){
     let mut rand_arr = [0; 100];
@@ -92,6 +92,165 @@ pub fn test_array_sealing() {
 	assert_eq!(unsealed_data.get_decrypt_txt(), data);
 }
 
+pub fn test_sealed_data_bytes() {
+    let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let aad: [u8; 4] = [0xa, 0xb, 0xc, 0xd];
+    let sealed_data = SgxSealedData::<[u8]>::seal_data(&aad, &data).expect("error while sealing array");
+
+    let bytes = sealed_data.to_bytes();
+    assert_eq!(bytes.len() as u32, SgxSealedData::<[u8]>::calc_raw_sealed_data_size(4, 10));
+
+    let sealed_data = SgxSealedData::<[u8]>::from_bytes(&bytes).expect("error while parsing bytes");
+    let unsealed_data = sealed_data.unseal_data().expect("error while unsealing array");
+    assert_eq!(unsealed_data.get_decrypt_txt(), data);
+    assert_eq!(unsealed_data.get_additional_txt(), aad);
+
+    // truncated or padded buffers
+    assert!(SgxSealedData::<[u8]>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(SgxSealedData::<[u8]>::from_bytes(&bytes[..100]).is_err());
+    let mut padded = bytes.clone();
+    padded.push(0);
+    assert!(SgxSealedData::<[u8]>::from_bytes(&padded).is_err());
+    // MAC'd data carries no encrypted text
+    assert!(SgxMacAadata::<[u8]>::from_bytes(&bytes).is_err());
+}
+
+pub fn test_reseal_data() {
+    let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let aad: [u8; 4] = [0xa, 0xb, 0xc, 0xd];
+    let attribute_mask = sgx_attributes_t { flags: 0xFF0000000000000B, xfrm: 0 };
+    let sealed_data = SgxSealedData::<[u8]>::seal_data_ex(SGX_KEYPOLICY_MRENCLAVE,
+                                                          attribute_mask,
+                                                          0xF0000000,
+                                                          &aad,
+                                                          &data).expect("error while sealing array");
+    let info = sealed_data.get_seal_info();
+    assert!(info.is_mrenclave_policy());
+    assert!(!info.is_mrsigner_policy());
+    assert_eq!(info.is_outdated().unwrap(), false);
+
+    let resealed_data = sealed_data.reseal_data().expect("error while resealing array");
+    let info = resealed_data.get_seal_info();
+    assert!(info.is_mrsigner_policy());
+    assert_ne!(resealed_data.get_key_request().key_id.id, sealed_data.get_key_request().key_id.id);
+
+    let unsealed_data = resealed_data.unseal_data().expect("error while unsealing array");
+    assert_eq!(unsealed_data.get_decrypt_txt(), data);
+    assert_eq!(unsealed_data.get_additional_txt(), aad);
+}
+
+pub fn test_seal_stream() {
+    use std::io::{Read, Write};
+    use std::vec::Vec;
+
+    let mut data = vec![0_u8; 10000];
+    let mut rand = StdRng::new().unwrap();
+    rand.fill_bytes(&mut data);
+
+    let mut writer = SealWriter::with_chunk_size(Vec::new(), 4096).unwrap();
+    writer.write_all(&data[..5000]).unwrap();
+    writer.write_all(&data[5000..]).unwrap();
+    let sealed = writer.finish().unwrap();
+
+    let mut unsealed = Vec::new();
+    let mut reader = UnsealReader::new(&sealed[..]).unwrap();
+    reader.read_to_end(&mut unsealed).unwrap();
+    assert_eq!(unsealed, data);
+
+    // chunk records are [len u32][text][tag 16]
+    let header_size = sealed.len() - (3 * 4 + 10000 + 3 * 16);
+    let record_size = 4 + 4096 + 16;
+
+    let mut unsealed = Vec::new();
+    let truncated = &sealed[..header_size + 2 * record_size];
+    let mut reader = UnsealReader::new(truncated).unwrap();
+    assert!(reader.read_to_end(&mut unsealed).is_err());
+
+    let mut reordered = sealed.clone();
+    let first = header_size..header_size + record_size;
+    let second = header_size + record_size..header_size + 2 * record_size;
+    let first_record = sealed[first.clone()].to_vec();
+    reordered[first].copy_from_slice(&sealed[second.clone()]);
+    reordered[second].copy_from_slice(&first_record);
+    let mut unsealed = Vec::new();
+    let mut reader = UnsealReader::new(&reordered[..]).unwrap();
+    assert!(reader.read_to_end(&mut unsealed).is_err());
+}
+
+pub fn test_versioned_sealed_state() {
+    use std::untrusted::fs;
+    use std::vec::Vec;
+
+    struct Loopback<'a>(&'a RemoteCounterServer<FileCounterProvider>);
+
+    impl<'a> RemoteCounterService for Loopback<'a> {
+        fn call(&self, request: &[u8]) -> SgxResult<Vec<u8>> {
+            self.0.handle(request)
+        }
+    }
+
+    let dir = "sgx_versioned_counters";
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).unwrap();
+
+    let state = VersionedSealedState::<u64, _>::create(FileCounterProvider::new(dir)).unwrap();
+    let first = state.seal(&1234).unwrap();
+    assert_eq!(state.unseal(&first).unwrap(), 1234);
+    let second = state.seal(&5678).unwrap();
+    assert_eq!(state.version().unwrap(), 2);
+    assert_eq!(state.unseal(&second).unwrap(), 5678);
+    match state.unseal(&first) {
+        Err(VersionedStateError::Stale { sealed: 1, current: 2 }) => {},
+        _ => panic!("a rolled back blob was unsealed"),
+    }
+
+    let other = VersionedSealedState::<u64, _>::create(FileCounterProvider::new(dir)).unwrap();
+    match other.unseal(&second) {
+        Err(VersionedStateError::CounterMismatch) => {},
+        _ => panic!("a blob of another counter was unsealed"),
+    }
+    other.destroy().unwrap();
+
+    let reopened = VersionedSealedState::<u64, _>::open(FileCounterProvider::new(dir), state.get_counter_uuid());
+    assert_eq!(reopened.unseal(&second).unwrap(), 5678);
+    state.destroy().unwrap();
+    assert!(reopened.unseal(&second).is_err());
+
+    let key = [0x5a_u8; 16];
+    let server = RemoteCounterServer::new(FileCounterProvider::new(dir), &key);
+    let remote = VersionedSealedState::<[u8], _>::create(RemoteCounterProvider::new(Loopback(&server), &key)).unwrap();
+    let first = remote.seal(b"first").unwrap();
+    let second = remote.seal(b"second").unwrap();
+    assert_eq!(remote.unseal(&second).unwrap(), b"second".to_vec());
+    assert!(remote.unseal(&first).is_err());
+    remote.destroy().unwrap();
+
+    let wrong_key = RemoteCounterProvider::new(Loopback(&server), &[0_u8; 16]);
+    assert!(VersionedSealedState::<u64, _>::create(wrong_key).is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+pub fn test_versioned_sealed_state_two_sealers() {
+    use sgx_tservice::sgxcounter::MockCounterProvider;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    let state = Arc::new(VersionedSealedState::<u64, _>::create(MockCounterProvider::new()).unwrap());
+    let sealers: Vec<_> = (0..2_u64).map(|i| {
+        let state = state.clone();
+        thread::spawn(move || {
+            (0..50_u64).map(|j| state.seal(&(i * 100 + j)).unwrap()).collect::<Vec<_>>()
+        })
+    }).collect();
+    let blobs: Vec<Vec<u8>> = sealers.into_iter().flat_map(|sealer| sealer.join().unwrap()).collect();
+
+    // every seal took its own version, and only the last one is current
+    assert_eq!(state.version().unwrap(), 100);
+    assert_eq!(blobs.iter().filter(|blob| state.unseal(blob).is_ok()).count(), 1);
+}
+
 pub fn test_mac_aadata_number() {
     use std::boxed::Box;
     let aad_data  : u64 = 123456789;
@@ -0,0 +1,83 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::sgx_status_t;
+use std::thread;
+use std::sync::{Arc, SgxMutex, SgxCondvar};
+use std::string::ToString;
+use std::vec::Vec;
+
+pub fn test_thread_spawn_join() {
+    let handle = thread::Builder::new().name("worker".to_string()).spawn(|| {
+        assert_eq!(thread::current().name(), Some("worker"));
+        6 * 7
+    }).unwrap();
+    assert_eq!(handle.join().unwrap(), 42);
+
+    let handles: Vec<_> = (0..2).map(|i| thread::spawn(move || i + 1)).collect();
+    let sum: i32 = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+    assert_eq!(sum, 3);
+}
+
+pub fn test_thread_join_panic() {
+    let handle = thread::spawn(|| {
+        panic!("worker panicked");
+    });
+    let payload = handle.join().unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"worker panicked"));
+}
+
+pub fn test_thread_out_of_tcs() {
+    let gate = Arc::new((SgxMutex::new(false), SgxCondvar::new()));
+    let mut handles = Vec::new();
+
+    // Every spawned thread holds its TCS until the gate opens, so spawning
+    // fails once all of them are taken.
+    let err = loop {
+        let gate = gate.clone();
+        let spawned = thread::Builder::new().spawn(move || {
+            let mut open = gate.0.lock().unwrap();
+            while !*open {
+                open = gate.1.wait(open).unwrap();
+            }
+        });
+        match spawned {
+            Ok(handle) => handles.push(handle),
+            Err(err) => break err,
+        }
+        assert!(handles.len() < 16, "the TCS were never exhausted");
+    };
+    assert_eq!(err.raw_sgx_error(), Some(sgx_status_t::SGX_ERROR_OUT_OF_TCS));
+    assert!(!handles.is_empty());
+
+    *gate.0.lock().unwrap() = true;
+    gate.1.broadcast();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tcrypto::SgxEccHandle;
+use sgx_tservice::sgxtime::*;
+use sgx_tservice::sgxcounter::*;
+use std::boxed::Box;
+
+struct TestTimeSource {
+    server: TimeServer,
+    secs: sgx_time_t,
+}
+
+impl TimeTokenSource for TestTimeSource {
+    fn get_token(&self, nonce: &[u8; SGX_TIME_TOKEN_NONCE_SIZE]) -> SgxResult<SgxTimeToken> {
+        self.server.issue(self.secs, nonce)
+    }
+}
+
+pub fn test_tservice_time_provider() {
+    let clock: &'static MockTimeProvider = Box::leak(Box::new(MockTimeProvider::new(1000, &[1; 32])));
+    set_time_provider(clock);
+    let start = SgxTime::now().ok().unwrap();
+    clock.advance(30);
+    assert_eq!(start.elapsed().ok().unwrap(), 30);
+    clock.set_source_nonce(&[2; 32]);
+    match start.elapsed() {
+        Err(SgxTimeError::TimeSourceChanged) => {},
+        _ => panic!("time of another source was compared"),
+    }
+
+    let ecc_handle = SgxEccHandle::new();
+    ecc_handle.open().unwrap();
+    let (private_key, public_key) = ecc_handle.create_key_pair().unwrap();
+    let source = TestTimeSource { server: TimeServer::new(&private_key, &[3; 32]), secs: 1234 };
+    let signed: &'static SignedTimeProvider<TestTimeSource> = Box::leak(Box::new(SignedTimeProvider::new(source, &public_key)));
+    set_time_provider(signed);
+    let now = SgxTime::now().ok().unwrap();
+    assert_eq!(now.get_secs(), 1234);
+    assert_eq!(now.get_source_nonce(), [3; 32]);
+
+    let (_, other_key) = ecc_handle.create_key_pair().unwrap();
+    let source = TestTimeSource { server: TimeServer::new(&private_key, &[3; 32]), secs: 1234 };
+    let untrusted = SignedTimeProvider::new(source, &other_key);
+    assert_eq!(untrusted.now().err(), Some(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+
+    clear_time_provider();
+}
+
+pub fn test_tservice_counter_provider() {
+    let provider: &'static MockCounterProvider = Box::leak(Box::new(MockCounterProvider::new()));
+    set_counter_provider(provider);
+
+    let mut value: u32 = 1;
+    let counter = SgxMonotonicCounter::new(&mut value).unwrap();
+    assert_eq!(value, 0);
+    assert_eq!(counter.increment().unwrap(), 1);
+    assert_eq!(counter.increment().unwrap(), 2);
+    assert_eq!(counter.read().unwrap(), 2);
+
+    let other = SgxMonotonicCounter::new_ex(0x1, &sgx_attributes_t::default(), &mut value).unwrap();
+    clear_counter_provider();
+    // the counter stays with the provider which created it
+    assert_eq!(other.increment().unwrap(), 1);
+    counter.destory().unwrap();
+    assert_eq!(counter.read().err(), Some(sgx_status_t::SGX_ERROR_MC_NOT_FOUND));
+
+    let (counter_uuid, _) = provider.create().unwrap();
+    provider.destroy(&counter_uuid).unwrap();
+    let (reused_uuid, _) = provider.create().unwrap();
+    assert_eq!(provider.read(&counter_uuid).err(), Some(sgx_status_t::SGX_ERROR_MC_NOT_FOUND));
+    assert_eq!(provider.read(&reused_uuid).unwrap(), 0);
+    provider.destroy(&reused_uuid).unwrap();
+}
@@ -26,6 +26,8 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use sgx_types::*;
+use sgx_tse::rsgx_create_report;
 use std::vec::Vec;
 
 pub fn hex_to_bytes(hex_string: &str) -> Vec<u8> {
@@ -38,3 +40,17 @@ pub fn hex_to_bytes(hex_string: &str) -> Vec<u8> {
     }).collect()
 }
 
+// The identity of this enclave.
+pub fn self_report_body() -> sgx_report_body_t {
+    rsgx_create_report(&sgx_target_info_t::default(), &sgx_report_data_t::default()).unwrap().body
+}
+
+// The target info of this enclave, so that it can verify the reports it creates.
+pub fn self_target_info() -> sgx_target_info_t {
+    let body = self_report_body();
+    let mut target_info = sgx_target_info_t::default();
+    target_info.mr_enclave = body.mr_enclave;
+    target_info.attributes = body.attributes;
+    target_info.misc_select = body.misc_select;
+    target_info
+}
@@ -0,0 +1,115 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tcrypto::rsgx_rijndael128_cmac_slice;
+use std::vec::Vec;
+use std::ptr;
+
+/// The identifier of the AES-CMAC key derivation function in msg2.
+pub const RA_KDF_ID_AES_CMAC: u16 = 0x0001;
+
+///
+/// The keys derived from the shared key of a remote attestation.
+///
+/// SMK authenticates msg2 and msg3, VK binds the quote to the key exchange, and SK and MK are
+/// left to the application once the enclave is attested. All of them are zeroed on drop.
+///
+#[derive(Default)]
+pub struct RaKeys {
+    pub smk: sgx_ec_key_128bit_t,
+    pub sk: sgx_ec_key_128bit_t,
+    pub mk: sgx_ec_key_128bit_t,
+    pub vk: sgx_ec_key_128bit_t,
+}
+
+impl RaKeys {
+
+    ///
+    /// Derives the keys from the shared key with the AES-CMAC key derivation function, the
+    /// same way the enclave does in `sgx_ra_proc_msg2`.
+    ///
+    /// The key derivation key is the AES-CMAC of the little-endian shared key under an all-zero
+    /// key. Each key is then the AES-CMAC of `0x01 || label || 0x00 || 0x0080` under the key
+    /// derivation key, with the labels `SMK`, `SK`, `MK` and `VK`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The CMAC operation failed.
+    ///
+    pub fn derive(shared: &sgx_ec256_dh_shared_t) -> SgxResult<RaKeys> {
+        let zero_key: sgx_cmac_128bit_key_t = [0; SGX_CMAC_KEY_SIZE];
+        let kdk = KeyDerivationKey(rsgx_rijndael128_cmac_slice(&zero_key, &shared.s[..])?);
+        Ok(RaKeys {
+            smk: kdk.derive(b"SMK")?,
+            sk: kdk.derive(b"SK")?,
+            mk: kdk.derive(b"MK")?,
+            vk: kdk.derive(b"VK")?,
+        })
+    }
+}
+
+impl Drop for RaKeys {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.smk);
+        clear_bytes(&mut self.sk);
+        clear_bytes(&mut self.mk);
+        clear_bytes(&mut self.vk);
+    }
+}
+
+struct KeyDerivationKey(sgx_cmac_128bit_key_t);
+
+impl KeyDerivationKey {
+    fn derive(&self, label: &[u8]) -> SgxResult<sgx_ec_key_128bit_t> {
+        let mut derivation = Vec::with_capacity(label.len() + 4);
+        derivation.push(0x01);
+        derivation.extend_from_slice(label);
+        derivation.extend_from_slice(&[0x00, 0x80, 0x00]);
+        rsgx_rijndael128_cmac_slice(&self.0, &derivation[..])
+    }
+}
+
+impl Drop for KeyDerivationKey {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.0);
+    }
+}
+
+// Plain stores to memory which is dropped next may be removed, volatile ones are not.
+pub(crate) fn clear_bytes(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
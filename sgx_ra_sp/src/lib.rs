@@ -0,0 +1,66 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Remote Attestation Service Provider
+//!
+//! The service provider half of the remote attestation and key exchange protocol, the
+//! counterpart of `sgx_tkey_exchange` in the attesting enclave.
+//!
+//! `ServiceProvider::proc_msg1` takes msg1, fetches the SigRL for the EPID group and returns
+//! msg2 along with an `RaSession`. `ServiceProvider::proc_msg3` checks the MAC of msg3 with SMK
+//! and the binding of the quote to the key exchange, submits the quote for verification, and on
+//! success returns SK and MK together with the verified report body.
+//!
+//! Quote verification is left to an `AttestationService`. An implementation normally talks to
+//! the Intel Attestation Service through the untrusted side; `StubAttestationService` answers
+//! locally with a fixed status, for testing.
+//!
+
+#![cfg_attr(not(target_env = "sgx"), no_std)]
+#![cfg_attr(target_env = "sgx", feature(rustc_private))]
+
+#![allow(non_camel_case_types)]
+
+extern crate sgx_types;
+extern crate sgx_trts;
+extern crate sgx_tcrypto;
+#[cfg(not(target_env = "sgx"))]
+#[macro_use]
+extern crate sgx_tstd as std;
+
+mod kdf;
+pub use self::kdf::*;
+
+mod msg;
+pub use self::msg::*;
+
+mod service;
+pub use self::service::*;
+
+mod sp;
+pub use self::sp::*;
@@ -0,0 +1,172 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use std::vec::Vec;
+
+/// The size of msg1.
+pub const RA_MSG1_SIZE: usize = 68;
+/// The size of msg2 without the SigRL.
+pub const RA_MSG2_HEADER_SIZE: usize = 168;
+/// The size of msg3 without the quote.
+pub const RA_MSG3_HEADER_SIZE: usize = 336;
+
+// The part of msg2 covered by its MAC: g_b, spid, quote_type, kdf_id and sign_gb_ga.
+const RA_MSG2_CMACED_SIZE: usize = 148;
+
+///
+/// Parses msg1 as sent by `sgx_ra_get_msg1`.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The message is not exactly `RA_MSG1_SIZE` bytes.
+///
+pub fn rsgx_ra_msg1_from_bytes(bytes: &[u8]) -> SgxResult<sgx_ra_msg1_t> {
+    if bytes.len() != RA_MSG1_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut msg1 = sgx_ra_msg1_t::default();
+    msg1.g_a = read_ec256_public(&bytes[..64]);
+    msg1.gid.copy_from_slice(&bytes[64..68]);
+    Ok(msg1)
+}
+
+///
+/// The content of msg2. `sgx_ra_msg2_t` ends in a flexible array, so the SigRL is held
+/// separately and put back in place by `to_bytes`.
+///
+#[derive(Clone, Default)]
+pub struct RaMsg2 {
+    pub g_b: sgx_ec256_public_t,
+    pub spid: sgx_spid_t,
+    pub quote_type: u16,
+    pub kdf_id: u16,
+    pub sign_gb_ga: sgx_ec256_signature_t,
+    pub mac: sgx_mac_t,
+    pub sig_rl: Vec<u8>,
+}
+
+impl RaMsg2 {
+
+    ///
+    /// Encodes the message in the layout of `sgx_ra_msg2_t`, as expected by `sgx_ra_proc_msg2`.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.cmaced_bytes();
+        bytes.extend_from_slice(&self.mac);
+        push_u32(&mut bytes, self.sig_rl.len() as u32);
+        bytes.extend_from_slice(&self.sig_rl);
+        bytes
+    }
+
+    pub(crate) fn cmaced_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RA_MSG2_HEADER_SIZE + self.sig_rl.len());
+        push_ec256_public(&mut bytes, &self.g_b);
+        bytes.extend_from_slice(&self.spid.id);
+        push_u16(&mut bytes, self.quote_type);
+        push_u16(&mut bytes, self.kdf_id);
+        for word in self.sign_gb_ga.x.iter().chain(self.sign_gb_ga.y.iter()) {
+            push_u32(&mut bytes, *word);
+        }
+        debug_assert_eq!(bytes.len(), RA_MSG2_CMACED_SIZE);
+        bytes
+    }
+}
+
+///
+/// The content of msg3 as produced by `sgx_ra_get_msg3`.
+///
+#[derive(Clone, Default)]
+pub struct RaMsg3 {
+    pub mac: sgx_mac_t,
+    pub g_a: sgx_ec256_public_t,
+    pub ps_sec_prop: sgx_ps_sec_prop_desc_t,
+    pub quote: Vec<u8>,
+}
+
+impl RaMsg3 {
+
+    ///
+    /// Parses msg3.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The message is shorter than its header, or the quote in it is malformed.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<RaMsg3> {
+        if bytes.len() < RA_MSG3_HEADER_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let mut msg3 = RaMsg3::default();
+        msg3.mac.copy_from_slice(&bytes[..16]);
+        msg3.g_a = read_ec256_public(&bytes[16..80]);
+        msg3.ps_sec_prop.sgx_ps_sec_prop_desc.copy_from_slice(&bytes[80..RA_MSG3_HEADER_SIZE]);
+        msg3.quote = bytes[RA_MSG3_HEADER_SIZE..].to_vec();
+        Quote::parse(&msg3.quote)?;
+        Ok(msg3)
+    }
+
+    ///
+    /// Returns the platform service security properties, or `None` if the enclave did not
+    /// include them and left the field zeroed.
+    ///
+    pub fn ps_sec_prop(&self) -> Option<&sgx_ps_sec_prop_desc_t> {
+        if self.ps_sec_prop.sgx_ps_sec_prop_desc.iter().all(|b| *b == 0) {
+            None
+        } else {
+            Some(&self.ps_sec_prop)
+        }
+    }
+}
+
+pub(crate) fn push_ec256_public(bytes: &mut Vec<u8>, key: &sgx_ec256_public_t) {
+    bytes.extend_from_slice(&key.gx);
+    bytes.extend_from_slice(&key.gy);
+}
+
+fn read_ec256_public(bytes: &[u8]) -> sgx_ec256_public_t {
+    let mut key = sgx_ec256_public_t::default();
+    key.gx.copy_from_slice(&bytes[..SGX_ECP256_KEY_SIZE]);
+    key.gy.copy_from_slice(&bytes[SGX_ECP256_KEY_SIZE..SGX_ECP256_KEY_SIZE * 2]);
+    key
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.push(value as u8);
+    bytes.push((value >> 8) as u8);
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    for i in 0..4 {
+        bytes.push((value >> (i * 8)) as u8);
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+///
+/// The verdict of the attestation service on a quote, named after the `isvEnclaveQuoteStatus`
+/// values of the Intel Attestation Service.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteStatus {
+    Ok,
+    SignatureInvalid,
+    GroupRevoked,
+    SignatureRevoked,
+    KeyRevoked,
+    SigrlVersionMismatch,
+    GroupOutOfDate,
+    ConfigurationNeeded,
+    SwHardeningNeeded,
+    ConfigurationAndSwHardeningNeeded,
+    Unknown,
+}
+
+impl QuoteStatus {
+
+    ///
+    /// Maps an `isvEnclaveQuoteStatus` string to a status. Unrecognized strings map to
+    /// `QuoteStatus::Unknown`.
+    ///
+    pub fn from_ias_str(status: &str) -> QuoteStatus {
+        match status {
+            "OK" => QuoteStatus::Ok,
+            "SIGNATURE_INVALID" => QuoteStatus::SignatureInvalid,
+            "GROUP_REVOKED" => QuoteStatus::GroupRevoked,
+            "SIGNATURE_REVOKED" => QuoteStatus::SignatureRevoked,
+            "KEY_REVOKED" => QuoteStatus::KeyRevoked,
+            "SIGRL_VERSION_MISMATCH" => QuoteStatus::SigrlVersionMismatch,
+            "GROUP_OUT_OF_DATE" => QuoteStatus::GroupOutOfDate,
+            "CONFIGURATION_NEEDED" => QuoteStatus::ConfigurationNeeded,
+            "SW_HARDENING_NEEDED" => QuoteStatus::SwHardeningNeeded,
+            "CONFIGURATION_AND_SW_HARDENING_NEEDED" => QuoteStatus::ConfigurationAndSwHardeningNeeded,
+            _ => QuoteStatus::Unknown,
+        }
+    }
+
+    ///
+    /// Returns the `isvEnclaveQuoteStatus` string of the status.
+    ///
+    pub fn as_ias_str(&self) -> &'static str {
+        match *self {
+            QuoteStatus::Ok => "OK",
+            QuoteStatus::SignatureInvalid => "SIGNATURE_INVALID",
+            QuoteStatus::GroupRevoked => "GROUP_REVOKED",
+            QuoteStatus::SignatureRevoked => "SIGNATURE_REVOKED",
+            QuoteStatus::KeyRevoked => "KEY_REVOKED",
+            QuoteStatus::SigrlVersionMismatch => "SIGRL_VERSION_MISMATCH",
+            QuoteStatus::GroupOutOfDate => "GROUP_OUT_OF_DATE",
+            QuoteStatus::ConfigurationNeeded => "CONFIGURATION_NEEDED",
+            QuoteStatus::SwHardeningNeeded => "SW_HARDENING_NEEDED",
+            QuoteStatus::ConfigurationAndSwHardeningNeeded => "CONFIGURATION_AND_SW_HARDENING_NEEDED",
+            QuoteStatus::Unknown => "UNKNOWN",
+        }
+    }
+
+    ///
+    /// The error `ServiceProvider::proc_msg3` returns when it does not accept the status.
+    ///
+    pub fn to_error(&self) -> sgx_status_t {
+        match *self {
+            QuoteStatus::GroupOutOfDate |
+            QuoteStatus::ConfigurationNeeded |
+            QuoteStatus::SwHardeningNeeded |
+            QuoteStatus::ConfigurationAndSwHardeningNeeded => sgx_status_t::SGX_ERROR_UPDATE_NEEDED,
+            QuoteStatus::SigrlVersionMismatch => sgx_status_t::SGX_ERROR_INVALID_STATE,
+            QuoteStatus::Unknown => sgx_status_t::SGX_ERROR_UNEXPECTED,
+            _ => sgx_status_t::SGX_ERROR_INVALID_SIGNATURE,
+        }
+    }
+}
+
+///
+/// The result of verifying a quote with the attestation service.
+///
+#[derive(Clone, Debug)]
+pub struct AttestationReport {
+    /// The identifier the service assigned to the report.
+    pub id: String,
+    pub quote_status: QuoteStatus,
+    /// The CRL reason code when the group or key was revoked.
+    pub revocation_reason: Option<u32>,
+    /// The platform info blob, for `sgx_report_attestation_status` on the attested platform.
+    pub platform_info_blob: Option<Vec<u8>>,
+}
+
+///
+/// A backend that verifies quotes, normally the Intel Attestation Service reached through the
+/// untrusted side.
+///
+pub trait AttestationService {
+
+    ///
+    /// Returns the signature revocation list of an EPID group, empty if there is none.
+    ///
+    /// `gid` is as found in msg1, in little-endian order.
+    ///
+    fn get_sig_rl(&self, gid: &sgx_epid_group_id_t) -> SgxResult<Vec<u8>>;
+
+    ///
+    /// Verifies a quote, along with the platform service security properties if the enclave
+    /// provided them.
+    ///
+    /// An error means that no verdict could be obtained; a quote found invalid is reported
+    /// through `AttestationReport::quote_status`.
+    ///
+    fn verify_quote(&self, quote: &[u8], ps_sec_prop: Option<&sgx_ps_sec_prop_desc_t>) -> SgxResult<AttestationReport>;
+}
+
+///
+/// An attestation service that answers without verifying anything, for testing the key
+/// exchange locally.
+///
+/// It returns the configured SigRL for every group and the configured status for every quote.
+/// Never use it in production: it accepts any quote.
+///
+#[derive(Clone, Debug)]
+pub struct StubAttestationService {
+    pub sig_rl: Vec<u8>,
+    pub quote_status: QuoteStatus,
+}
+
+impl StubAttestationService {
+
+    ///
+    /// Creates a stub with an empty SigRL that reports every quote as `QuoteStatus::Ok`.
+    ///
+    pub fn new() -> StubAttestationService {
+        StubAttestationService {
+            sig_rl: Vec::new(),
+            quote_status: QuoteStatus::Ok,
+        }
+    }
+}
+
+impl Default for StubAttestationService {
+    fn default() -> StubAttestationService {
+        StubAttestationService::new()
+    }
+}
+
+impl AttestationService for StubAttestationService {
+
+    fn get_sig_rl(&self, _gid: &sgx_epid_group_id_t) -> SgxResult<Vec<u8>> {
+        Ok(self.sig_rl.clone())
+    }
+
+    fn verify_quote(&self, _quote: &[u8], _ps_sec_prop: Option<&sgx_ps_sec_prop_desc_t>) -> SgxResult<AttestationReport> {
+        Ok(AttestationReport {
+            id: "stub".to_string(),
+            quote_status: self.quote_status,
+            revocation_reason: None,
+            platform_info_blob: None,
+        })
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_trts::memeq::ConsttimeMemEq;
+use sgx_tcrypto::*;
+use std::vec::Vec;
+use kdf::*;
+use msg::*;
+use service::*;
+
+///
+/// The service provider side of the remote attestation and key exchange.
+///
+/// It holds the SPID registered with the attestation service, the long-term ECDSA key whose
+/// public half the enclave passes to `rsgx_ra_init`, and the backend used to verify quotes.
+/// The long-term key is zeroed on drop.
+///
+pub struct ServiceProvider<S: AttestationService> {
+    spid: sgx_spid_t,
+    sign_key: sgx_ec256_private_t,
+    quote_type: sgx_quote_sign_type_t,
+    accepted_status: Vec<QuoteStatus>,
+    service: S,
+}
+
+///
+/// The state of an attestation between msg1 and msg3.
+///
+pub struct RaSession {
+    g_a: sgx_ec256_public_t,
+    g_b: sgx_ec256_public_t,
+    gid: sgx_epid_group_id_t,
+    keys: RaKeys,
+}
+
+///
+/// A successful attestation: the verified report body and the keys shared with the enclave.
+///
+pub struct RaResult {
+    report: AttestationReport,
+    report_body: sgx_report_body_t,
+    ps_sec_prop: Option<sgx_ps_sec_prop_desc_t>,
+    keys: RaKeys,
+}
+
+impl<S: AttestationService> ServiceProvider<S> {
+
+    ///
+    /// Creates a service provider that asks for linkable quotes and accepts only
+    /// `QuoteStatus::Ok`.
+    ///
+    pub fn new(spid: sgx_spid_t, sign_key: sgx_ec256_private_t, service: S) -> ServiceProvider<S> {
+        ServiceProvider {
+            spid: spid,
+            sign_key: sign_key,
+            quote_type: sgx_quote_sign_type_t::SGX_LINKABLE_SIGNATURE,
+            accepted_status: vec![QuoteStatus::Ok],
+            service: service,
+        }
+    }
+
+    ///
+    /// Sets the quote signature type requested in msg2. It has to match the type the SPID was
+    /// registered for.
+    ///
+    pub fn quote_type(mut self, quote_type: sgx_quote_sign_type_t) -> ServiceProvider<S> {
+        self.quote_type = quote_type;
+        self
+    }
+
+    ///
+    /// Also accepts quotes the attestation service reports with `status`, such as
+    /// `QuoteStatus::GroupOutOfDate` for platforms that are behind on updates.
+    ///
+    pub fn accept_quote_status(mut self, status: QuoteStatus) -> ServiceProvider<S> {
+        if !self.accepted_status.contains(&status) {
+            self.accepted_status.push(status);
+        }
+        self
+    }
+
+    ///
+    /// Returns the attestation service backend.
+    ///
+    pub fn service(&self) -> &S {
+        &self.service
+    }
+
+    ///
+    /// Processes msg1 and generates msg2.
+    ///
+    /// A new key pair g_b is generated for the session and combined with the enclave's g_a to
+    /// derive the session keys. msg2 carries g_b and the SigRL of the enclave's EPID group,
+    /// with g_b || g_a signed by the long-term key and the whole authenticated with SMK.
+    ///
+    /// # Parameters
+    ///
+    /// **msg1**
+    ///
+    /// msg1 as returned by `sgx_ra_get_msg1`.
+    ///
+    /// # Return value
+    ///
+    /// The session to pass to `proc_msg3`, and msg2 to send to the enclave.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// msg1 is malformed or g_a is not a point on the curve.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// A cryptographic operation failed.
+    ///
+    /// Errors from `AttestationService::get_sig_rl` are returned as is.
+    ///
+    pub fn proc_msg1(&self, msg1: &[u8]) -> SgxResult<(RaSession, Vec<u8>)> {
+        let msg1 = rsgx_ra_msg1_from_bytes(msg1)?;
+
+        let ecc_handle = SgxEccHandle::new();
+        ecc_handle.open()?;
+        if !ecc_handle.check_point(&msg1.g_a)? {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let sig_rl = self.service.get_sig_rl(&msg1.gid)?;
+
+        let (mut b, g_b) = ecc_handle.create_key_pair()?;
+        let shared = ecc_handle.compute_shared_dhkey(&b, &msg1.g_a);
+        clear_bytes(&mut b.r);
+        let mut shared = shared?;
+        let keys = RaKeys::derive(&shared);
+        clear_bytes(&mut shared.s);
+        let keys = keys?;
+
+        let mut gb_ga = Vec::with_capacity(SGX_ECP256_KEY_SIZE * 4);
+        push_ec256_public(&mut gb_ga, &g_b);
+        push_ec256_public(&mut gb_ga, &msg1.g_a);
+
+        let mut msg2 = RaMsg2::default();
+        msg2.g_b = g_b;
+        msg2.spid = self.spid;
+        msg2.quote_type = self.quote_type as u16;
+        msg2.kdf_id = RA_KDF_ID_AES_CMAC;
+        msg2.sign_gb_ga = ecc_handle.ecdsa_sign_slice(&gb_ga[..], &self.sign_key)?;
+        msg2.mac = rsgx_rijndael128_cmac_slice(&keys.smk, &msg2.cmaced_bytes()[..])?;
+        msg2.sig_rl = sig_rl;
+
+        let session = RaSession {
+            g_a: msg1.g_a,
+            g_b: g_b,
+            gid: msg1.gid,
+            keys: keys,
+        };
+        Ok((session, msg2.to_bytes()))
+    }
+
+    ///
+    /// Processes msg3 and verifies the quote in it.
+    ///
+    /// The checks are, in order: g_a matches msg1, the MAC of msg3 verifies with SMK, the quote
+    /// has the requested signature type and the EPID group of msg1, the first 32 bytes of its
+    /// report data are SHA256(g_a || g_b || VK) and the other 32 are zero, and finally the
+    /// attestation service returns a status this service provider accepts.
+    ///
+    /// # Parameters
+    ///
+    /// **session**
+    ///
+    /// The session returned by `proc_msg1` along with the msg2 the enclave answered.
+    ///
+    /// **msg3**
+    ///
+    /// msg3 as returned by `sgx_ra_get_msg3`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// msg3 is malformed, belongs to another session, or holds a quote of another type or from
+    /// another EPID group.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The MAC of msg3 does not verify, or the quote is not bound to the key exchange.
+    ///
+    /// **SGX_ERROR_INVALID_SIGNATURE**, **SGX_ERROR_UPDATE_NEEDED**, **SGX_ERROR_INVALID_STATE**,
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// The attestation service returned a status that is not accepted; see
+    /// `QuoteStatus::to_error`.
+    ///
+    /// Errors from `AttestationService::verify_quote` are returned as is.
+    ///
+    pub fn proc_msg3(&self, session: RaSession, msg3: &[u8]) -> SgxResult<RaResult> {
+        let parsed = RaMsg3::from_bytes(msg3)?;
+
+        if !parsed.g_a.gx.consttime_memeq(&session.g_a.gx) || !parsed.g_a.gy.consttime_memeq(&session.g_a.gy) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mac = rsgx_rijndael128_cmac_slice(&session.keys.smk, &msg3[SGX_MAC_SIZE..])?;
+        if !mac.consttime_memeq(&parsed.mac) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+
+        let quote = Quote::parse(&parsed.quote)?;
+        if quote.sign_type() != self.quote_type as u16 || quote.epid_group_id() != session.gid {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut binding = Vec::with_capacity(SGX_ECP256_KEY_SIZE * 4 + SGX_CMAC_KEY_SIZE);
+        push_ec256_public(&mut binding, &session.g_a);
+        push_ec256_public(&mut binding, &session.g_b);
+        binding.extend_from_slice(&session.keys.vk);
+        let mut expected = [0_u8; SGX_REPORT_DATA_SIZE];
+        expected[..SGX_SHA256_HASH_SIZE].copy_from_slice(&rsgx_sha256_slice(&binding[..])?);
+        let report_body = *quote.report_body();
+        if !report_body.report_data.d.consttime_memeq(&expected) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+
+        let report = self.service.verify_quote(&parsed.quote, parsed.ps_sec_prop())?;
+        if !self.accepted_status.contains(&report.quote_status) {
+            return Err(report.quote_status.to_error());
+        }
+
+        Ok(RaResult {
+            report: report,
+            report_body: report_body,
+            ps_sec_prop: parsed.ps_sec_prop().cloned(),
+            keys: session.keys,
+        })
+    }
+}
+
+impl<S: AttestationService> Drop for ServiceProvider<S> {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.sign_key.r);
+    }
+}
+
+impl RaSession {
+
+    ///
+    /// Returns the enclave's public key from msg1.
+    ///
+    pub fn g_a(&self) -> &sgx_ec256_public_t {
+        &self.g_a
+    }
+
+    ///
+    /// Returns the service provider's public key sent in msg2.
+    ///
+    pub fn g_b(&self) -> &sgx_ec256_public_t {
+        &self.g_b
+    }
+}
+
+impl RaResult {
+
+    ///
+    /// Returns the report of the attestation service.
+    ///
+    pub fn attestation_report(&self) -> &AttestationReport {
+        &self.report
+    }
+
+    ///
+    /// Returns the body of the report in the quote. Its identity fields are verified, but
+    /// whether to trust that enclave is for the caller to decide.
+    ///
+    pub fn report_body(&self) -> &sgx_report_body_t {
+        &self.report_body
+    }
+
+    ///
+    /// Returns the platform service security properties from msg3, if any.
+    ///
+    pub fn ps_sec_prop(&self) -> Option<&sgx_ps_sec_prop_desc_t> {
+        self.ps_sec_prop.as_ref()
+    }
+
+    ///
+    /// Returns the key the enclave gets from `rsgx_ra_get_keys` for `key_type`.
+    ///
+    pub fn key(&self, key_type: sgx_ra_key_type_t) -> sgx_ra_key_128_t {
+        match key_type {
+            sgx_ra_key_type_t::SGX_RA_KEY_SK => self.keys.sk,
+            sgx_ra_key_type_t::SGX_RA_KEY_MK => self.keys.mk,
+        }
+    }
+}
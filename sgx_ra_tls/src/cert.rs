@@ -0,0 +1,308 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_tcrypto::*;
+use sgx_tcrypto::der::*;
+use sgx_tse::rsgx_create_report;
+use std::vec::Vec;
+use std::sync::Arc;
+use std::mem;
+use std::ptr;
+use rustls;
+
+// 1.2.840.10045.4.3.2
+pub(crate) const OID_ECDSA_WITH_SHA256: &'static [u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+// 2.5.4.3
+const OID_COMMON_NAME: &'static [u8] = &[0x55, 0x04, 0x03];
+// 1.2.840.113741.1337.6, as used by Intel's RA-TLS for a raw quote.
+pub const OID_SGX_QUOTE: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF8, 0x4D, 0x8A, 0x39, 0x06];
+// 1.2.840.113741.1337.7, a raw sgx_report_t.
+pub const OID_SGX_REPORT: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF8, 0x4D, 0x8A, 0x39, 0x07];
+
+// The certificate carries no trusted time; it is valid for as long as UTCTime reaches.
+const NOT_BEFORE: &'static [u8] = b"180101000000Z";
+const NOT_AFTER: &'static [u8] = b"491231235959Z";
+
+const SERIAL_NUMBER_SIZE: usize = 16;
+
+///
+/// Attestation evidence carried in a certificate extension.
+///
+#[derive(Clone)]
+pub enum AttestationEvidence {
+    /// A report for local attestation. Only the enclave it targets can verify it.
+    Report(sgx_report_t),
+    /// A quote for remote attestation, as returned by `sgx_get_quote`.
+    Quote(Vec<u8>),
+}
+
+impl AttestationEvidence {
+
+    ///
+    /// Returns the report body the evidence attests to.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The quote is malformed.
+    ///
+    pub fn report_body(&self) -> SgxResult<sgx_report_body_t> {
+        match *self {
+            AttestationEvidence::Report(ref report) => Ok(report.body),
+            AttestationEvidence::Quote(ref quote) => Quote::parse(quote).map(|quote| *quote.report_body()),
+        }
+    }
+
+    pub(crate) fn oid(&self) -> &'static [u8] {
+        match *self {
+            AttestationEvidence::Report(_) => OID_SGX_REPORT,
+            AttestationEvidence::Quote(_) => OID_SGX_QUOTE,
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            AttestationEvidence::Report(ref report) => {
+                let size = mem::size_of::<sgx_report_t>();
+                let mut bytes = vec![0_u8; size];
+                unsafe {
+                    ptr::copy_nonoverlapping(report as * const sgx_report_t as * const u8,
+                                             bytes.as_mut_ptr(),
+                                             size);
+                }
+                bytes
+            },
+            AttestationEvidence::Quote(ref quote) => quote.clone(),
+        }
+    }
+
+    pub(crate) fn from_bytes(oid: &[u8], bytes: &[u8]) -> Option<Self> {
+        if oid == OID_SGX_REPORT {
+            if bytes.len() != mem::size_of::<sgx_report_t>() {
+                return None;
+            }
+            let report = unsafe { ptr::read_unaligned(bytes.as_ptr() as * const sgx_report_t) };
+            Some(AttestationEvidence::Report(report))
+        } else if oid == OID_SGX_QUOTE {
+            Some(AttestationEvidence::Quote(bytes.to_vec()))
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// A self-signed certificate and its PKCS#8 private key, both generated inside the enclave.
+///
+/// The private key is cleared when the `AttestedCert` is dropped, unless it was handed over
+/// to rustls by `into_server_config`.
+///
+pub struct AttestedCert {
+    pub cert: rustls::Certificate,
+    pub key: rustls::PrivateKey,
+}
+
+impl AttestedCert {
+
+    ///
+    /// Builds a `rustls::ServerConfig` presenting this certificate, without client authentication.
+    ///
+    /// The private key is moved into the configuration, not copied. From then on it is owned by
+    /// rustls, which does not clear it when it is dropped, also when the key is rejected.
+    ///
+    pub fn into_server_config(mut self) -> Result<rustls::ServerConfig, rustls::TLSError> {
+        let cert = mem::replace(&mut self.cert, rustls::Certificate(Vec::new()));
+        let key = mem::replace(&mut self.key, rustls::PrivateKey(Vec::new()));
+        let mut config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+        config.set_single_cert(vec![cert], key)?;
+        Ok(config)
+    }
+}
+
+impl Drop for AttestedCert {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.key.0);
+    }
+}
+
+///
+/// Returns the report data that binds evidence to a public key: the SHA256 hash of its
+/// DER SubjectPublicKeyInfo, followed by zeros.
+///
+pub fn rsgx_ra_tls_report_data(public: &sgx_ec256_public_t) -> SgxResult<sgx_report_data_t> {
+    spki_report_data(&rsgx_ecc256_public_key_to_der(public))
+}
+
+pub(crate) fn spki_report_data(spki: &[u8]) -> SgxResult<sgx_report_data_t> {
+    let hash = rsgx_sha256_slice(spki)?;
+    let mut report_data = sgx_report_data_t::default();
+    report_data.d[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+    Ok(report_data)
+}
+
+///
+/// Creates report evidence for `target_info`, the enclave that will verify it.
+/// Meant to be called from the `attest` callback of `rsgx_create_attested_cert`.
+///
+pub fn rsgx_create_report_evidence(target_info: &sgx_target_info_t,
+                                   report_data: &sgx_report_data_t) -> SgxResult<AttestationEvidence> {
+    rsgx_create_report(target_info, report_data).map(AttestationEvidence::Report)
+}
+
+///
+/// The rsgx_create_attested_cert function generates an ECDSA P-256 key pair and a self-signed
+/// certificate for it. The certificate carries the evidence returned by `attest`, whose report
+/// must bind the key through the report data it is given.
+///
+/// # Parameters
+///
+/// **common_name**
+///
+/// The subject and issuer common name of the certificate.
+///
+/// **attest**
+///
+/// Turns the report data into evidence: a report via `rsgx_create_report_evidence`, or a quote
+/// obtained by the untrusted side from such a report.
+///
+/// # Errors
+///
+/// Any error returned by `attest` or by the cryptography library.
+///
+pub fn rsgx_create_attested_cert<F>(common_name: &str, attest: F) -> SgxResult<AttestedCert>
+    where F: FnOnce(&sgx_report_data_t) -> SgxResult<AttestationEvidence> {
+
+    let ecc_handle = SgxEccHandle::new();
+    ecc_handle.open()?;
+    let (mut private, public) = ecc_handle.create_key_pair()?;
+    let cert = attested_cert(&ecc_handle, &private, &public, common_name, attest);
+    clear_bytes(&mut private.r);
+    ecc_handle.close()?;
+    cert
+}
+
+fn attested_cert<F>(ecc_handle: &SgxEccHandle,
+                    private: &sgx_ec256_private_t,
+                    public: &sgx_ec256_public_t,
+                    common_name: &str,
+                    attest: F) -> SgxResult<AttestedCert>
+    where F: FnOnce(&sgx_report_data_t) -> SgxResult<AttestationEvidence> {
+
+    let spki = rsgx_ecc256_public_key_to_der(public);
+    let evidence = attest(&spki_report_data(&spki)?)?;
+
+    let mut serial = [0_u8; SERIAL_NUMBER_SIZE];
+    rsgx_read_rand(&mut serial)?;
+    serial[0] &= 0x7F;
+    serial[0] |= 0x40;
+
+    let tbs = tbs_certificate(&serial, common_name, &spki, &evidence);
+    let signature = ecc_handle.ecdsa_sign_slice(tbs.as_slice(), private)?;
+
+    let mut signature_bits = vec![0_u8];
+    signature_bits.extend_from_slice(&rsgx_ecc256_signature_to_der(&signature));
+    let mut cert = tbs;
+    write_tlv(&mut cert, TAG_SEQUENCE, &signature_algorithm());
+    write_tlv(&mut cert, TAG_BIT_STRING, &signature_bits);
+
+    Ok(AttestedCert {
+        cert: rustls::Certificate(wrap(TAG_SEQUENCE, &cert)),
+        key: rustls::PrivateKey(rsgx_ecc256_private_key_to_der(private, public)),
+    })
+}
+
+///
+/// The rsgx_create_attested_server_config function generates an attested certificate with
+/// `rsgx_create_attested_cert` and returns a `rustls::ServerConfig` that presents it.
+///
+/// # Errors
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// rustls rejected the generated key.
+///
+/// Otherwise, any error of `rsgx_create_attested_cert`.
+///
+pub fn rsgx_create_attested_server_config<F>(common_name: &str, attest: F) -> SgxResult<Arc<rustls::ServerConfig>>
+    where F: FnOnce(&sgx_report_data_t) -> SgxResult<AttestationEvidence> {
+
+    rsgx_create_attested_cert(common_name, attest)?
+        .into_server_config()
+        .map(Arc::new)
+        .map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)
+}
+
+fn signature_algorithm() -> Vec<u8> {
+    wrap(TAG_OID, OID_ECDSA_WITH_SHA256)
+}
+
+fn name(common_name: &str) -> Vec<u8> {
+    let mut attribute = Vec::new();
+    write_tlv(&mut attribute, TAG_OID, OID_COMMON_NAME);
+    write_tlv(&mut attribute, TAG_UTF8_STRING, common_name.as_bytes());
+    let rdn = wrap(TAG_SET, &wrap(TAG_SEQUENCE, &attribute));
+    wrap(TAG_SEQUENCE, &rdn)
+}
+
+// TBSCertificate ([RFC 5280]) with the evidence as a non-critical extension.
+fn tbs_certificate(serial: &[u8],
+                   common_name: &str,
+                   spki: &[u8],
+                   evidence: &AttestationEvidence) -> Vec<u8> {
+
+    let mut validity = Vec::new();
+    write_tlv(&mut validity, TAG_UTC_TIME, NOT_BEFORE);
+    write_tlv(&mut validity, TAG_UTC_TIME, NOT_AFTER);
+
+    let mut extension = Vec::new();
+    write_tlv(&mut extension, TAG_OID, evidence.oid());
+    write_tlv(&mut extension, TAG_OCTET_STRING, &evidence.to_bytes());
+    let extensions = wrap(TAG_SEQUENCE, &wrap(TAG_SEQUENCE, &extension));
+
+    let name = name(common_name);
+    let mut tbs = Vec::new();
+    write_tlv(&mut tbs, TAG_CONTEXT_0, &[TAG_INTEGER, 1, 2]);
+    write_uint(&mut tbs, serial);
+    write_tlv(&mut tbs, TAG_SEQUENCE, &signature_algorithm());
+    tbs.extend_from_slice(&name);
+    write_tlv(&mut tbs, TAG_SEQUENCE, &validity);
+    tbs.extend_from_slice(&name);
+    tbs.extend_from_slice(spki);
+    write_tlv(&mut tbs, TAG_CONTEXT_3, &extensions);
+    wrap(TAG_SEQUENCE, &tbs)
+}
+
+// Volatile writes, so that clearing a buffer which is about to be freed is not optimized out.
+fn clear_bytes(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
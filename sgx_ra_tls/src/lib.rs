@@ -0,0 +1,67 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Attested TLS
+//!
+//! Self-signed X.509 certificates whose key never leaves the enclave, carrying attestation
+//! evidence for that key, and the rustls configuration to serve and verify them.
+//!
+//! The key is an ECDSA P-256 key generated with `SgxEccHandle`. The first 32 bytes of the
+//! report data are the SHA256 hash of the DER SubjectPublicKeyInfo of the key; the report, or a
+//! quote made from it, goes into a non-critical certificate extension:
+//!
+//! * `1.2.840.113741.1337.6` holds a quote, as in Intel's RA-TLS.
+//! * `1.2.840.113741.1337.7` holds an `sgx_report_t` for local attestation.
+//!
+//! On the server side, `rsgx_create_attested_server_config` returns a `rustls::ServerConfig`.
+//! On the client side, `AttestedServerCertVerifier` checks the certificate with webpki and the
+//! evidence against the key, then leaves the decision on the report body to a policy callback.
+//!
+
+#![cfg_attr(not(target_env = "sgx"), no_std)]
+#![cfg_attr(target_env = "sgx", feature(rustc_private))]
+
+#![allow(non_camel_case_types)]
+
+extern crate sgx_types;
+extern crate sgx_trts;
+extern crate sgx_tcrypto;
+extern crate sgx_tse;
+#[cfg(not(target_env = "sgx"))]
+#[macro_use]
+extern crate sgx_tstd as std;
+
+extern crate untrusted;
+extern crate rustls;
+extern crate webpki;
+
+mod cert;
+pub use self::cert::*;
+
+mod verify;
+pub use self::verify::*;
@@ -0,0 +1,202 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tse::rsgx_verify_report;
+use sgx_tcrypto::der::*;
+use std::string::ToString;
+use std::vec::Vec;
+use std::sync::Arc;
+use rustls;
+use webpki;
+use untrusted;
+use cert::*;
+
+///
+/// The rsgx_verify_attested_cert function checks a certificate made by `rsgx_create_attested_cert`
+/// and returns the evidence it carries.
+///
+/// The certificate must be signed by its own ECDSA P-256 key, and the report data of the evidence
+/// must be the hash of that key. Report evidence is verified with `rsgx_verify_report`, so it must
+/// target the calling enclave. Quote evidence is not verified here; that is up to the caller,
+/// for instance through the Intel Attestation Service.
+///
+/// # Errors
+///
+/// **TLSError::WebPKIError**
+///
+/// The certificate is malformed, not self-signed, or carries no evidence.
+///
+/// **TLSError::General**
+///
+/// The evidence does not bind the key, or the report failed verification.
+///
+pub fn rsgx_verify_attested_cert(cert_der: &[u8]) -> Result<AttestationEvidence, rustls::TLSError> {
+
+    let (spki, evidence) = read_attested_cert(cert_der).map_err(rustls::TLSError::WebPKIError)?;
+
+    let report_data = spki_report_data(spki)
+        .map_err(|_| rustls::TLSError::General("cannot hash the public key".to_string()))?;
+    let body = evidence.report_body()
+        .map_err(|_| rustls::TLSError::General("malformed attestation evidence".to_string()))?;
+    if body.report_data.d[..] != report_data.d[..] {
+        return Err(rustls::TLSError::General("attestation evidence does not bind the key".to_string()));
+    }
+
+    if let AttestationEvidence::Report(ref report) = evidence {
+        rsgx_verify_report(report)
+            .map_err(|_| rustls::TLSError::General("report verification failed".to_string()))?;
+    }
+    Ok(evidence)
+}
+
+// Checks the self-signature; returns the SubjectPublicKeyInfo element and the evidence.
+fn read_attested_cert(cert_der: &[u8]) -> Result<(&[u8], AttestationEvidence), webpki::Error> {
+
+    let (tbs, tbs_value, algorithm, signature) = split_certificate(cert_der).map_err(|_| webpki::Error::BadDER)?;
+    if algorithm != wrap(TAG_OID, OID_ECDSA_WITH_SHA256).as_slice() {
+        return Err(webpki::Error::UnsupportedSignatureAlgorithm);
+    }
+    let signature = match signature.split_first() {
+        Some((&0, signature)) => signature,
+        _ => return Err(webpki::Error::BadDER),
+    };
+
+    let end_entity = webpki::EndEntityCert::from(untrusted::Input::from(cert_der))?;
+    end_entity.verify_signature(&webpki::ECDSA_P256_SHA256,
+                                untrusted::Input::from(tbs),
+                                untrusted::Input::from(signature))?;
+
+    let (spki, mut evidence) = read_tbs_certificate(tbs_value).map_err(|_| webpki::Error::BadDER)?;
+    if evidence.len() != 1 {
+        return Err(webpki::Error::ExtensionValueInvalid);
+    }
+    Ok((spki, evidence.remove(0)))
+}
+
+// Returns the TBSCertificate element and its contents, the signature algorithm and the
+// signature bits.
+fn split_certificate(cert_der: &[u8]) -> SgxResult<(&[u8], &[u8], &[u8], &[u8])> {
+
+    let mut outer = DerReader::new(cert_der);
+    let mut cert = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+    let (tbs, tbs_value) = cert.read_element(TAG_SEQUENCE)?;
+    let algorithm = cert.read(TAG_SEQUENCE)?;
+    let signature = cert.read(TAG_BIT_STRING)?;
+    cert.finish()?;
+    Ok((tbs, tbs_value, algorithm, signature))
+}
+
+// Returns the SubjectPublicKeyInfo element and the evidence extensions found.
+fn read_tbs_certificate(tbs: &[u8]) -> SgxResult<(&[u8], Vec<AttestationEvidence>)> {
+
+    let mut tbs = DerReader::new(tbs);
+    tbs.read(TAG_CONTEXT_0)?;
+    for _ in 0..5 {
+        // serialNumber, signature, issuer, validity, subject
+        tbs.skip()?;
+    }
+    let (spki, _) = tbs.read_element(TAG_SEQUENCE)?;
+
+    let mut evidence = Vec::new();
+    while let Some(tag) = tbs.peek_tag() {
+        if tag != TAG_CONTEXT_3 {
+            tbs.skip()?;
+            continue;
+        }
+        let mut outer = DerReader::new(tbs.read(TAG_CONTEXT_3)?);
+        let mut extensions = DerReader::new(outer.read(TAG_SEQUENCE)?);
+        outer.finish()?;
+        while extensions.peek_tag().is_some() {
+            let mut extension = DerReader::new(extensions.read(TAG_SEQUENCE)?);
+            let oid = extension.read(TAG_OID)?;
+            if extension.peek_tag() == Some(TAG_BOOLEAN) {
+                extension.skip()?;
+            }
+            let value = extension.read(TAG_OCTET_STRING)?;
+            extension.finish()?;
+            if let Some(found) = AttestationEvidence::from_bytes(oid, value) {
+                evidence.push(found);
+            }
+        }
+    }
+    Ok((spki, evidence))
+}
+
+///
+/// A `rustls::ServerCertVerifier` that accepts a server by its attestation evidence instead of
+/// a certificate chain. The DNS name is not checked.
+///
+/// After `rsgx_verify_attested_cert` succeeds, `policy` decides on the report body, for instance
+/// by comparing `mr_enclave` or `mr_signer`. For quote evidence the policy must also verify
+/// the quote itself.
+///
+pub struct AttestedServerCertVerifier<F> {
+    policy: F,
+}
+
+impl<F> AttestedServerCertVerifier<F>
+    where F: Fn(&sgx_report_body_t, &AttestationEvidence) -> bool + Send + Sync {
+
+    pub fn new(policy: F) -> Self {
+        AttestedServerCertVerifier { policy: policy }
+    }
+}
+
+impl<F> rustls::ServerCertVerifier for AttestedServerCertVerifier<F>
+    where F: Fn(&sgx_report_body_t, &AttestationEvidence) -> bool + Send + Sync {
+
+    fn verify_server_cert(&self,
+                          _roots: &rustls::RootCertStore,
+                          presented_certs: &[rustls::Certificate],
+                          _dns_name: webpki::DNSNameRef,
+                          _ocsp_response: &[u8]) -> Result<rustls::ServerCertVerified, rustls::TLSError> {
+
+        let cert = presented_certs.first().ok_or(rustls::TLSError::NoCertificatesPresented)?;
+        let evidence = rsgx_verify_attested_cert(&cert.0)?;
+        let body = evidence.report_body()
+            .map_err(|_| rustls::TLSError::General("malformed attestation evidence".to_string()))?;
+        if (self.policy)(&body, &evidence) {
+            Ok(rustls::ServerCertVerified::assertion())
+        } else {
+            Err(rustls::TLSError::General("attestation rejected by policy".to_string()))
+        }
+    }
+}
+
+///
+/// Builds a `rustls::ClientConfig` that verifies the server with an `AttestedServerCertVerifier`.
+///
+pub fn rsgx_attested_client_config<F>(policy: F) -> Arc<rustls::ClientConfig>
+    where F: Fn(&sgx_report_body_t, &AttestationEvidence) -> bool + Send + Sync + 'static {
+
+    let mut config = rustls::ClientConfig::new();
+    config.dangerous().set_certificate_verifier(Arc::new(AttestedServerCertVerifier::new(policy)));
+    Arc::new(config)
+}
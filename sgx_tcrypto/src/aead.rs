@@ -0,0 +1,912 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//!
+//! Authenticated Encryption with Associated Data
+//!
+//! The `Aead` trait gives the ciphers of this library a common interface, so protocol code can
+//! switch between them without changing its call sites. Two ciphers implement it:
+//!
+//! * `SgxAesGcm`, AES-GCM as specified in [NIST SP 800-38D], with any IV length and a tag of
+//!   4, 8 or 12 to 16 bytes.
+//! * `SgxAesCtrCmac`, AES-CTR encryption followed by an AES-CMAC over the nonce, the additional
+//!   data and the ciphertext.
+//!
+//! `SgxNonceSequence` hands out distinct nonces for a key, and `SgxAesGcmHandle` performs
+//! AES-GCM over multiple datasets.
+//!
+use sgx_types::*;
+use sgx_trts::memeq::ConsttimeMemEq;
+use crypto::SgxCmacHandle;
+use core::ops::Drop;
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::ptr;
+
+/// The nonce size of `SgxAesCtrCmac` and `SgxNonceSequence`, and the default nonce size of `SgxAesGcm`.
+pub const SGX_AEAD_NONCE_SIZE: usize = 12;
+
+const BLOCK_SIZE: usize = 16;
+const CTR_CHUNK_SIZE: usize = 256;
+const CTR_INC_BITS: u32 = 32;
+/// The counter is 32 bits wide, so at most 2^32 - 2 blocks are encrypted under one IV.
+const GCM_MAX_TEXT_LEN: u64 = ((1_u64 << 32) - 2) * (BLOCK_SIZE as u64);
+const GHASH_R: u128 = 0xE1 << 120;
+
+fn block_to_u128(block: &[u8]) -> u128 {
+    block[..BLOCK_SIZE].iter().fold(0, |v, &b| (v << 8) | b as u128)
+}
+
+fn u128_to_block(v: u128) -> [u8; BLOCK_SIZE] {
+    let mut block = [0_u8; BLOCK_SIZE];
+    for i in 0..BLOCK_SIZE {
+        block[i] = (v >> (120 - i * 8)) as u8;
+    }
+    block
+}
+
+fn inc32(counter: &mut [u8; BLOCK_SIZE], n: u32) {
+    let c = counter[12..].iter().fold(0_u32, |v, &b| (v << 8) | b as u32).wrapping_add(n);
+    for i in 0..4 {
+        counter[12 + i] = (c >> (24 - i * 8)) as u8;
+    }
+}
+
+// The writes are volatile: the buffers cleared here are usually dead right after.
+fn clear_bytes(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
+
+// Multiplication in GF(2^128) as defined for GHASH, without data-dependent branches.
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    let mut z: u128 = 0;
+    let mut v = y;
+    for i in 0..128 {
+        z ^= v & 0_u128.wrapping_sub((x >> (127 - i)) & 1);
+        v = (v >> 1) ^ (GHASH_R & 0_u128.wrapping_sub(v & 1));
+    }
+    z
+}
+
+fn aes_ctr(key: &sgx_aes_ctr_128bit_key_t, counter: &[u8; BLOCK_SIZE], src: &[u8], dst: &mut [u8]) -> SgxError {
+
+    // sgx_aes_ctr_encrypt advances the counter it is given.
+    let mut ctr = *counter;
+    let ret = unsafe {
+        sgx_aes_ctr_encrypt(key as * const sgx_aes_ctr_128bit_key_t,
+                            src.as_ptr(),
+                            src.len() as u32,
+                            ctr.as_mut_ptr() as * const u8,
+                            CTR_INC_BITS,
+                            dst.as_mut_ptr())
+    };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(ret),
+    }
+}
+
+fn aes_block(key: &sgx_aes_ctr_128bit_key_t, block: &[u8; BLOCK_SIZE]) -> SgxResult<[u8; BLOCK_SIZE]> {
+    let mut out = [0_u8; BLOCK_SIZE];
+    aes_ctr(key, block, &[0_u8; BLOCK_SIZE], &mut out)?;
+    Ok(out)
+}
+
+#[derive(Clone, Copy, Default)]
+struct Ghash {
+    h: u128,
+    y: u128,
+    buf: [u8; BLOCK_SIZE],
+    buf_len: usize,
+}
+
+impl Ghash {
+
+    fn new(h: u128) -> Self {
+        Ghash { h: h, ..Ghash::default() }
+    }
+
+    fn block(&mut self, block: &[u8]) {
+        self.y = gf128_mul(self.y ^ block_to_u128(block), self.h);
+    }
+
+    fn update(&mut self, data: &[u8]) {
+
+        let mut data = data;
+        if self.buf_len > 0 {
+            let n = cmp::min(BLOCK_SIZE - self.buf_len, data.len());
+            self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+            if self.buf_len < BLOCK_SIZE {
+                return;
+            }
+            let block = self.buf;
+            self.block(&block);
+            self.buf_len = 0;
+        }
+        while data.len() >= BLOCK_SIZE {
+            self.block(&data[..BLOCK_SIZE]);
+            data = &data[BLOCK_SIZE..];
+        }
+        if data.len() > 0 {
+            self.buf[..data.len()].copy_from_slice(data);
+            self.buf_len = data.len();
+        }
+    }
+
+    // Zero-pads the pending data to a full block.
+    fn pad(&mut self) {
+        if self.buf_len > 0 {
+            let mut block = [0_u8; BLOCK_SIZE];
+            block[..self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
+            self.block(&block);
+            self.buf_len = 0;
+        }
+    }
+
+    fn finish(&mut self, aad_len: u64, text_len: u64) -> u128 {
+        self.pad();
+        let lens = ((aad_len.wrapping_mul(8) as u128) << 64) | (text_len.wrapping_mul(8) as u128);
+        self.block(&u128_to_block(lens));
+        self.y
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct CtrStream {
+    key: sgx_aes_ctr_128bit_key_t,
+    counter: [u8; BLOCK_SIZE],
+    keystream: [u8; BLOCK_SIZE],
+    used: usize,
+}
+
+impl CtrStream {
+
+    fn new(key: &sgx_aes_ctr_128bit_key_t, counter: &[u8; BLOCK_SIZE]) -> Self {
+        CtrStream {
+            key: *key,
+            counter: *counter,
+            keystream: [0_u8; BLOCK_SIZE],
+            used: BLOCK_SIZE,
+        }
+    }
+
+    fn apply(&mut self, data: &mut [u8]) -> SgxError {
+
+        let len = data.len();
+        let mut pos = 0;
+        while pos < len && self.used < BLOCK_SIZE {
+            data[pos] ^= self.keystream[self.used];
+            self.used += 1;
+            pos += 1;
+        }
+
+        // The library is not given overlapping source and destination buffers.
+        let mut chunk = [0_u8; CTR_CHUNK_SIZE];
+        while len - pos >= BLOCK_SIZE {
+            let n = cmp::min(CTR_CHUNK_SIZE, (len - pos) / BLOCK_SIZE * BLOCK_SIZE);
+            chunk[..n].copy_from_slice(&data[pos..pos + n]);
+            let ret = aes_ctr(&self.key, &self.counter, &chunk[..n], &mut data[pos..pos + n]);
+            if ret.is_err() {
+                clear_bytes(&mut chunk);
+                return ret;
+            }
+            inc32(&mut self.counter, (n / BLOCK_SIZE) as u32);
+            pos += n;
+        }
+        clear_bytes(&mut chunk);
+
+        if pos < len {
+            self.keystream = aes_block(&self.key, &self.counter)?;
+            inc32(&mut self.counter, 1);
+            self.used = 0;
+            while pos < len {
+                data[pos] ^= self.keystream[self.used];
+                self.used += 1;
+                pos += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        *self = CtrStream::default();
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct GcmState {
+    ghash: Ghash,
+    ctr: CtrStream,
+    tag_mask: [u8; BLOCK_SIZE],
+    aad_len: u64,
+    text_len: u64,
+    text_started: bool,
+}
+
+impl GcmState {
+
+    fn new(key: &sgx_aes_gcm_128bit_key_t, iv: &[u8]) -> SgxResult<Self> {
+
+        if iv.len() == 0 || iv.len() > u32::max_value() as usize {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let h = block_to_u128(&aes_block(key, &[0_u8; BLOCK_SIZE])?);
+        let mut j0 = [0_u8; BLOCK_SIZE];
+        if iv.len() == SGX_AESGCM_IV_SIZE {
+            j0[..SGX_AESGCM_IV_SIZE].copy_from_slice(iv);
+            j0[BLOCK_SIZE - 1] = 1;
+        } else {
+            let mut ghash = Ghash::new(h);
+            ghash.update(iv);
+            j0 = u128_to_block(ghash.finish(0, iv.len() as u64));
+        }
+
+        let tag_mask = aes_block(key, &j0)?;
+        let mut counter = j0;
+        inc32(&mut counter, 1);
+
+        Ok(GcmState {
+            ghash: Ghash::new(h),
+            ctr: CtrStream::new(key, &counter),
+            tag_mask: tag_mask,
+            aad_len: 0,
+            text_len: 0,
+            text_started: false,
+        })
+    }
+
+    fn update_aad(&mut self, aad: &[u8]) -> SgxError {
+        if self.text_started {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.aad_len = self.aad_len.checked_add(aad.len() as u64).ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+        self.ghash.update(aad);
+        Ok(())
+    }
+
+    // Adds ciphertext to the tag.
+    fn absorb_text(&mut self, text: &[u8]) -> SgxError {
+        let text_len = self.text_len + text.len() as u64;
+        if text_len > GCM_MAX_TEXT_LEN {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if !self.text_started {
+            self.ghash.pad();
+            self.text_started = true;
+        }
+        self.text_len = text_len;
+        self.ghash.update(text);
+        Ok(())
+    }
+
+    fn encrypt(&mut self, in_out: &mut [u8]) -> SgxError {
+        if self.text_len + in_out.len() as u64 > GCM_MAX_TEXT_LEN {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.ctr.apply(in_out)?;
+        self.absorb_text(in_out)
+    }
+
+    fn decrypt(&mut self, in_out: &mut [u8]) -> SgxError {
+        self.absorb_text(in_out)?;
+        self.ctr.apply(in_out)
+    }
+
+    fn tag(&mut self) -> [u8; BLOCK_SIZE] {
+        let s = self.ghash.finish(self.aad_len, self.text_len);
+        let mut tag = u128_to_block(s);
+        for i in 0..BLOCK_SIZE {
+            tag[i] ^= self.tag_mask[i];
+        }
+        tag
+    }
+
+    fn clear(&mut self) {
+        self.ctr.clear();
+        *self = GcmState::default();
+    }
+}
+
+///
+/// A cipher providing authenticated encryption with associated data.
+///
+/// Each call uses a nonce of `nonce_len` bytes and a tag of `tag_len` bytes. A nonce must never
+/// be used twice with the same key; `SgxNonceSequence` hands out nonces that are never repeated.
+///
+pub trait Aead {
+
+    ///
+    /// The size of the nonce in bytes.
+    ///
+    fn nonce_len(&self) -> usize;
+
+    ///
+    /// The size of the tag in bytes.
+    ///
+    fn tag_len(&self) -> usize;
+
+    ///
+    /// Encrypts `in_out` in place and authenticates it together with `aad`.
+    ///
+    /// # Parameters
+    ///
+    /// **nonce**
+    ///
+    /// The nonce, `nonce_len` bytes.
+    ///
+    /// **aad**
+    ///
+    /// The additional data, which is authenticated but not encrypted.
+    ///
+    /// **in_out**
+    ///
+    /// The plain text on input, the cipher text on output.
+    ///
+    /// **tag**
+    ///
+    /// The buffer receiving the tag, `tag_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The size of the nonce or the tag is not valid, or the data is too large.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    fn seal_in_place(&self, nonce: &[u8], aad: &[u8], in_out: &mut [u8], tag: &mut [u8]) -> SgxError;
+
+    ///
+    /// Verifies the tag over `aad` and `in_out`, and decrypts `in_out` in place.
+    ///
+    /// `in_out` is left unchanged if the tag does not match.
+    ///
+    /// # Parameters
+    ///
+    /// **nonce**
+    ///
+    /// The nonce the data was sealed with.
+    ///
+    /// **aad**
+    ///
+    /// The additional data the data was sealed with.
+    ///
+    /// **in_out**
+    ///
+    /// The cipher text on input, the plain text on output.
+    ///
+    /// **tag**
+    ///
+    /// The tag produced by `seal_in_place`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The size of the nonce or the tag is not valid, or the data is too large.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The tag does not match.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    fn open_in_place(&self, nonce: &[u8], aad: &[u8], in_out: &mut [u8], tag: &[u8]) -> SgxError;
+}
+
+///
+/// AES-GCM with a 128-bit key.
+///
+/// Unlike rsgx_rijndael128GCM_encrypt, the IV may have any non-zero length and the tag may be
+/// truncated. A 12-byte IV and a 16-byte tag are recommended.
+///
+pub struct SgxAesGcm {
+    key: sgx_aes_gcm_128bit_key_t,
+    nonce_len: usize,
+    tag_len: usize,
+}
+
+impl SgxAesGcm {
+
+    ///
+    /// Constructs an SgxAesGcm with a 12-byte nonce and a 16-byte tag.
+    ///
+    pub fn new(key: &sgx_aes_gcm_128bit_key_t) -> Self {
+        SgxAesGcm {
+            key: *key,
+            nonce_len: SGX_AESGCM_IV_SIZE,
+            tag_len: SGX_AESGCM_MAC_SIZE,
+        }
+    }
+
+    ///
+    /// Constructs an SgxAesGcm with the given nonce and tag sizes.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The nonce size is zero, or the tag size is not 4, 8 or 12 to 16 bytes.
+    ///
+    pub fn with_params(key: &sgx_aes_gcm_128bit_key_t, nonce_len: usize, tag_len: usize) -> SgxResult<Self> {
+
+        if nonce_len == 0 || nonce_len > u32::max_value() as usize {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        match tag_len {
+            4 | 8 | 12..=16 => {},
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+        Ok(SgxAesGcm {
+            key: *key,
+            nonce_len: nonce_len,
+            tag_len: tag_len,
+        })
+    }
+}
+
+impl Aead for SgxAesGcm {
+
+    fn nonce_len(&self) -> usize {
+        self.nonce_len
+    }
+
+    fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    fn seal_in_place(&self, nonce: &[u8], aad: &[u8], in_out: &mut [u8], tag: &mut [u8]) -> SgxError {
+
+        if nonce.len() != self.nonce_len || tag.len() != self.tag_len {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut state = GcmState::new(&self.key, nonce)?;
+        let mut ret = state.update_aad(aad);
+        if ret.is_ok() {
+            ret = state.encrypt(in_out);
+        }
+        if ret.is_ok() {
+            let full_tag = state.tag();
+            tag.copy_from_slice(&full_tag[..self.tag_len]);
+        }
+        state.clear();
+        ret
+    }
+
+    fn open_in_place(&self, nonce: &[u8], aad: &[u8], in_out: &mut [u8], tag: &[u8]) -> SgxError {
+
+        if nonce.len() != self.nonce_len || tag.len() != self.tag_len {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut state = GcmState::new(&self.key, nonce)?;
+        let mut ret = state.update_aad(aad);
+        if ret.is_ok() {
+            ret = state.absorb_text(in_out);
+        }
+        if ret.is_ok() {
+            let full_tag = state.tag();
+            if !full_tag[..self.tag_len].consttime_memeq(tag) {
+                ret = Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+            }
+        }
+        if ret.is_ok() {
+            ret = state.ctr.apply(in_out);
+        }
+        state.clear();
+        ret
+    }
+}
+
+impl Drop for SgxAesGcm {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.key);
+    }
+}
+
+///
+/// AES-CTR encryption with a 128-bit key, authenticated by AES-CMAC with a separate 128-bit key.
+///
+/// The initial counter block is the 12-byte nonce followed by a 32-bit zero counter, so at most
+/// 2^32 blocks are encrypted under one nonce. The 16-byte tag is the CMAC over the nonce, the
+/// size of the additional data as a 64-bit big-endian number, the additional data and the cipher
+/// text.
+///
+pub struct SgxAesCtrCmac {
+    enc_key: sgx_aes_ctr_128bit_key_t,
+    mac_key: sgx_cmac_128bit_key_t,
+}
+
+impl SgxAesCtrCmac {
+
+    ///
+    /// Constructs an SgxAesCtrCmac. The two keys must be independent.
+    ///
+    pub fn new(enc_key: &sgx_aes_ctr_128bit_key_t, mac_key: &sgx_cmac_128bit_key_t) -> Self {
+        SgxAesCtrCmac {
+            enc_key: *enc_key,
+            mac_key: *mac_key,
+        }
+    }
+
+    fn mac(&self, nonce: &[u8], aad: &[u8], text: &[u8]) -> SgxResult<sgx_cmac_128bit_tag_t> {
+
+        let aad_len = aad.len() as u64;
+        let mut len_block = [0_u8; 8];
+        for i in 0..8 {
+            len_block[i] = (aad_len >> (56 - i * 8)) as u8;
+        }
+
+        let handle = SgxCmacHandle::new();
+        handle.init(&self.mac_key)?;
+        handle.update_slice(nonce)?;
+        handle.update_slice(&len_block)?;
+        if aad.len() > 0 {
+            handle.update_slice(aad)?;
+        }
+        if text.len() > 0 {
+            handle.update_slice(text)?;
+        }
+        handle.get_hash()
+    }
+
+    fn check_params(&self, nonce: &[u8], in_out: &[u8], tag_len: usize) -> SgxError {
+        if nonce.len() != SGX_AEAD_NONCE_SIZE || tag_len != SGX_CMAC_MAC_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if in_out.len() as u64 > (1_u64 << 32) * (BLOCK_SIZE as u64) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(())
+    }
+
+    fn counter(nonce: &[u8]) -> [u8; BLOCK_SIZE] {
+        let mut counter = [0_u8; BLOCK_SIZE];
+        counter[..SGX_AEAD_NONCE_SIZE].copy_from_slice(nonce);
+        counter
+    }
+}
+
+impl Aead for SgxAesCtrCmac {
+
+    fn nonce_len(&self) -> usize {
+        SGX_AEAD_NONCE_SIZE
+    }
+
+    fn tag_len(&self) -> usize {
+        SGX_CMAC_MAC_SIZE
+    }
+
+    fn seal_in_place(&self, nonce: &[u8], aad: &[u8], in_out: &mut [u8], tag: &mut [u8]) -> SgxError {
+
+        self.check_params(nonce, in_out, tag.len())?;
+
+        let mut ctr = CtrStream::new(&self.enc_key, &Self::counter(nonce));
+        let ret = ctr.apply(in_out);
+        ctr.clear();
+        ret?;
+
+        let mac = self.mac(nonce, aad, in_out)?;
+        tag.copy_from_slice(&mac);
+        Ok(())
+    }
+
+    fn open_in_place(&self, nonce: &[u8], aad: &[u8], in_out: &mut [u8], tag: &[u8]) -> SgxError {
+
+        self.check_params(nonce, in_out, tag.len())?;
+
+        let mac = self.mac(nonce, aad, in_out)?;
+        if !mac[..].consttime_memeq(tag) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+
+        let mut ctr = CtrStream::new(&self.enc_key, &Self::counter(nonce));
+        let ret = ctr.apply(in_out);
+        ctr.clear();
+        ret
+    }
+}
+
+impl Drop for SgxAesCtrCmac {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.enc_key);
+        clear_bytes(&mut self.mac_key);
+    }
+}
+
+///
+/// A sequence of distinct 12-byte nonces for one key.
+///
+/// Each nonce is the 4-byte fixed field given at construction followed by a 64-bit big-endian
+/// counter, as described in [NIST SP 800-38D] 8.2.1. The sequence is not Clone, so a nonce
+/// cannot be handed out twice. Different sequences for the same key need different fixed
+/// fields.
+///
+pub struct SgxNonceSequence {
+    fixed: [u8; 4],
+    counter: u64,
+    exhausted: bool,
+}
+
+impl SgxNonceSequence {
+
+    ///
+    /// Constructs an SgxNonceSequence starting at counter zero.
+    ///
+    pub fn new(fixed: &[u8; 4]) -> Self {
+        SgxNonceSequence {
+            fixed: *fixed,
+            counter: 0,
+            exhausted: false,
+        }
+    }
+
+    ///
+    /// Returns the next nonce.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// All 2^64 nonces have been handed out.
+    ///
+    pub fn advance(&mut self) -> SgxResult<[u8; SGX_AEAD_NONCE_SIZE]> {
+
+        if self.exhausted {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+
+        let mut nonce = [0_u8; SGX_AEAD_NONCE_SIZE];
+        nonce[..4].copy_from_slice(&self.fixed);
+        for i in 0..8 {
+            nonce[4 + i] = (self.counter >> (56 - i * 8)) as u8;
+        }
+
+        match self.counter.checked_add(1) {
+            Some(counter) => self.counter = counter,
+            None => self.exhausted = true,
+        }
+        Ok(nonce)
+    }
+}
+
+/// AES-GCM algorithm context state.
+/// This is a handle to the context state used to perform an AES-GCM encryption or decryption
+/// over multiple datasets.
+pub struct SgxAesGcmHandle {
+    state: RefCell<GcmState>,
+    initflag: Cell<bool>,
+}
+
+impl SgxAesGcmHandle {
+
+    ///
+    /// Constructs a new, empty SgxAesGcmHandle.
+    ///
+    pub fn new() -> Self {
+        SgxAesGcmHandle {
+            state: RefCell::new(GcmState::default()),
+            initflag: Cell::new(false),
+        }
+    }
+
+    ///
+    /// init starts an AES-GCM encryption or decryption with the key and the IV.
+    ///
+    /// This should be part of the Init, Update … Update, Final process when the data is not
+    /// available at once. Otherwise the recommended call is `SgxAesGcm::seal_in_place` or
+    /// `SgxAesGcm::open_in_place`.
+    ///
+    /// # Description
+    ///
+    /// After init, the additional data is passed to update_aad, then the text to
+    /// encrypt_update or decrypt_update. An encryption is completed by get_mac, and a
+    /// decryption by verify_mac. Calling init again discards the current operation.
+    ///
+    /// # Parameters
+    ///
+    /// **key**
+    ///
+    /// A pointer to key to be used in the AES-GCM operation. The size must be 128 bits.
+    ///
+    /// **iv**
+    ///
+    /// The IV, of any non-zero size. A 12-byte IV is recommended.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The IV is empty.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn init(&self, key: &sgx_aes_gcm_128bit_key_t, iv: &[u8]) -> SgxError {
+
+        self.close()?;
+        let state = GcmState::new(key, iv)?;
+        *self.state.borrow_mut() = state;
+        self.initflag.set(true);
+        Ok(())
+    }
+
+    ///
+    /// update_aad adds additional data, which is authenticated but not encrypted.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The state is not initialized, or text has already been processed.
+    ///
+    pub fn update_aad(&self, aad: &[u8]) -> SgxError {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.state.borrow_mut().update_aad(aad)
+    }
+
+    ///
+    /// encrypt_update encrypts the next part of the plain text in place.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The state is not initialized.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// More text than AES-GCM allows under one IV.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn encrypt_update(&self, in_out: &mut [u8]) -> SgxError {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.state.borrow_mut().encrypt(in_out)
+    }
+
+    ///
+    /// decrypt_update decrypts the next part of the cipher text in place.
+    ///
+    /// The plain text is not authenticated until verify_mac succeeds, and must not be used before.
+    ///
+    /// # Errors
+    ///
+    /// The same as encrypt_update.
+    ///
+    pub fn decrypt_update(&self, in_out: &mut [u8]) -> SgxError {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        self.state.borrow_mut().decrypt(in_out)
+    }
+
+    ///
+    /// get_mac completes an encryption and returns the 16-byte tag.
+    ///
+    /// The state is closed afterwards.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The state is not initialized.
+    ///
+    pub fn get_mac(&self) -> SgxResult<sgx_aes_gcm_128bit_tag_t> {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let tag = self.state.borrow_mut().tag();
+        self.close()?;
+        Ok(tag)
+    }
+
+    ///
+    /// verify_mac completes a decryption and checks the tag, which may be truncated to 4, 8 or
+    /// 12 to 16 bytes.
+    ///
+    /// The state is closed afterwards.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The state is not initialized.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The tag size is not valid.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The tag does not match.
+    ///
+    pub fn verify_mac(&self, tag: &[u8]) -> SgxError {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        match tag.len() {
+            4 | 8 | 12..=16 => {},
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+        let full_tag = self.state.borrow_mut().tag();
+        self.close()?;
+        if full_tag[..tag.len()].consttime_memeq(tag) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH)
+        }
+    }
+
+    ///
+    /// close clears the AES-GCM state.
+    ///
+    pub fn close(&self) -> SgxError {
+
+        if self.initflag.get() == false {
+            return Ok(());
+        }
+        self.state.borrow_mut().clear();
+        self.initflag.set(false);
+        Ok(())
+    }
+}
+
+impl Default for SgxAesGcmHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SgxAesGcmHandle {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//!
+//! A minimal DER encoder and decoder
+//!
+//! Just enough of ASN.1 DER ([X.690]) to write and read the keys, signatures
+//! and certificates of the SGX crates: definite lengths up to four bytes and
+//! single-byte tags. Decoding errors are reported as
+//! `SGX_ERROR_INVALID_PARAMETER`.
+//!
+use sgx_types::*;
+use alloc::vec::Vec;
+use core::mem;
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0C;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+pub const TAG_CONTEXT_0: u8 = 0xA0;
+pub const TAG_CONTEXT_1: u8 = 0xA1;
+pub const TAG_CONTEXT_3: u8 = 0xA3;
+
+fn write_len(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = (0..mem::size_of::<usize>()).rev()
+            .map(|i| (len >> (i * 8)) as u8)
+            .skip_while(|&b| b == 0)
+            .collect::<Vec<u8>>();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+}
+
+///
+/// Appends an element with the given tag and contents to `out`.
+///
+pub fn write_tlv(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    write_len(out, value.len());
+    out.extend_from_slice(value);
+}
+
+///
+/// Returns an element with the given tag and contents.
+///
+pub fn wrap(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(value.len() + 6);
+    write_tlv(&mut out, tag, value);
+    out
+}
+
+///
+/// Appends a non-negative INTEGER, given as a big-endian magnitude, to `out`.
+///
+pub fn write_uint(out: &mut Vec<u8>, be: &[u8]) {
+    let start = be.iter().position(|&b| b != 0).unwrap_or(be.len());
+    let be = &be[start..];
+    let pad = be.is_empty() || be[0] & 0x80 != 0;
+    out.push(TAG_INTEGER);
+    write_len(out, be.len() + pad as usize);
+    if pad {
+        out.push(0);
+    }
+    out.extend_from_slice(be);
+}
+
+///
+/// Reads the elements of a DER encoding one after the other.
+///
+pub struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+
+    pub fn new(data: &'a [u8]) -> Self {
+        DerReader { data: data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    ///
+    /// Returns the tag of the next element, if any.
+    ///
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().cloned()
+    }
+
+    ///
+    /// Checks that all of the input was read.
+    ///
+    pub fn finish(&self) -> SgxError {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+
+    ///
+    /// Reads the next element, which must have the given tag, and returns the
+    /// whole element and its contents.
+    ///
+    pub fn read_element(&mut self, tag: u8) -> SgxResult<(&'a [u8], &'a [u8])> {
+
+        let data = self.data;
+        if data.len() < 2 || data[0] != tag {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let (len, header) = match data[1] {
+            len @ 0..=0x7F => (len as usize, 2),
+            0x81..=0x84 => {
+                let count = (data[1] & 0x7F) as usize;
+                if data.len() < 2 + count || data[2] == 0 {
+                    return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+                }
+                let len = data[2..2 + count].iter().fold(0_usize, |acc, &b| acc << 8 | b as usize);
+                if len < 0x80 {
+                    return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+                }
+                (len, 2 + count)
+            },
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        };
+
+        if data.len() - header < len {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        self.data = &data[header + len..];
+        Ok((&data[..header + len], &data[header..header + len]))
+    }
+
+    ///
+    /// Reads the next element, which must have the given tag, and returns its
+    /// contents.
+    ///
+    pub fn read(&mut self, tag: u8) -> SgxResult<&'a [u8]> {
+        self.read_element(tag).map(|(_, value)| value)
+    }
+
+    ///
+    /// Reads a non-negative INTEGER and returns its big-endian magnitude.
+    ///
+    pub fn read_uint(&mut self) -> SgxResult<&'a [u8]> {
+        let value = self.read(TAG_INTEGER)?;
+        match value.split_first() {
+            Some((&0, rest)) if rest.is_empty() => Ok(rest),
+            Some((&0, rest)) if rest[0] & 0x80 != 0 => Ok(rest),
+            Some((&first, _)) if first & 0x80 == 0 && first != 0 => Ok(value),
+            _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+
+    ///
+    /// Skips the next element, whatever its tag.
+    ///
+    pub fn skip(&mut self) -> SgxError {
+        match self.peek_tag() {
+            Some(tag) => self.read(tag).map(|_| ()),
+            None => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+}
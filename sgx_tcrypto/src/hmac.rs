@@ -0,0 +1,443 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//!
+//! HMAC-SHA256 and HKDF-SHA256
+//!
+//! HMAC as specified in [RFC 2104] and HKDF as specified in [RFC 5869], both built on the
+//! SHA256 functions of this library.
+//!
+use sgx_types::*;
+use sgx_types::marker::ContiguousMemory;
+use sgx_trts::memeq::ConsttimeMemEq;
+use crypto::{SgxShaHandle, rsgx_sha256_slice};
+use core::ops::Drop;
+use core::cell::{Cell, RefCell};
+use core::mem;
+use core::ptr;
+
+const SHA256_BLOCK_SIZE: usize = 64;
+const HKDF_MAX_OUTPUT_SIZE: usize = 255 * SGX_SHA256_HASH_SIZE;
+
+/// HMAC-SHA256 algorithm context state.
+/// This is a handle to the context state used to perform an iterative HMAC-SHA256 over
+/// multiple datasets. It holds a SHA256 state of the library.
+pub struct SgxHmacHandle {
+    inner: SgxShaHandle,
+    outer_key: RefCell<[u8; SHA256_BLOCK_SIZE]>,
+    initflag: Cell<bool>,
+}
+
+impl SgxHmacHandle {
+
+    ///
+    /// Constructs a new, empty SgxHmacHandle.
+    ///
+    pub fn new() -> Self {
+        SgxHmacHandle {
+            inner: SgxShaHandle::new(),
+            outer_key: RefCell::new([0_u8; SHA256_BLOCK_SIZE]),
+            initflag: Cell::new(false),
+        }
+    }
+
+    ///
+    /// init starts an HMAC-SHA256 with the key.
+    ///
+    /// This should be part of the Init, Update … Update, Final process when the HMAC is to be
+    /// performed over multiple datasets. If a complete dataset is available, the recommended call
+    /// is rsgx_hmac_sha256_slice to perform the HMAC in a single call.
+    ///
+    /// # Parameters
+    ///
+    /// **key**
+    ///
+    /// The key, of any size. Keys longer than 64 bytes are hashed first, as RFC 2104 specifies.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The key is too large.
+    ///
+    /// **SGX_ERROR_OUT_OF_MEMORY**
+    ///
+    /// Not enough memory is available to complete this operation.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn init(&self, key: &[u8]) -> SgxError {
+
+        if self.initflag.get() == true {
+            return Ok(());
+        }
+
+        let mut block = [0_u8; SHA256_BLOCK_SIZE];
+        if key.len() > SHA256_BLOCK_SIZE {
+            let hash = rsgx_sha256_slice(key)?;
+            block[..SGX_SHA256_HASH_SIZE].copy_from_slice(&hash);
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner_key = [0_u8; SHA256_BLOCK_SIZE];
+        {
+            let mut outer_key = self.outer_key.borrow_mut();
+            for i in 0..SHA256_BLOCK_SIZE {
+                inner_key[i] = block[i] ^ 0x36;
+                outer_key[i] = block[i] ^ 0x5c;
+            }
+        }
+        clear_bytes(&mut block);
+
+        let mut ret = self.inner.init();
+        if ret.is_ok() {
+            ret = self.inner.update_slice(&inner_key);
+        }
+        clear_bytes(&mut inner_key);
+        if ret.is_err() {
+            let _ = self.inner.close();
+            clear_bytes(&mut *self.outer_key.borrow_mut());
+            return ret;
+        }
+
+        self.initflag.set(true);
+        Ok(())
+    }
+
+    ///
+    /// update_msg performs an HMAC-SHA256 over the input dataset provided.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The HMAC state is not initialized.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The input is too large.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn update_msg<T>(&self, src: &T) -> SgxError
+        where T: Copy + ContiguousMemory {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        if mem::size_of::<T>() == 0 {
+            return Ok(());
+        }
+        self.inner.update_msg(src)
+    }
+
+    ///
+    /// update_slice performs an HMAC-SHA256 over the input dataset provided.
+    ///
+    pub fn update_slice<T>(&self, src: &[T]) -> SgxError
+        where T: Copy + ContiguousMemory {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        if mem::size_of_val(src) == 0 {
+            return Ok(());
+        }
+        self.inner.update_slice(src)
+    }
+
+    ///
+    /// get_hash obtains the HMAC-SHA256 over the datasets provided so far.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The HMAC state is not initialized.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn get_hash(&self) -> SgxResult<sgx_hmac_256bit_tag_t> {
+
+        if self.initflag.get() == false {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+
+        let inner_hash = self.inner.get_hash()?;
+        let outer = SgxShaHandle::new();
+        outer.init()?;
+        outer.update_slice(&*self.outer_key.borrow())?;
+        outer.update_slice(&inner_hash)?;
+        let hash = outer.get_hash();
+        outer.close()?;
+        hash
+    }
+
+    ///
+    /// verify_hash compares the HMAC-SHA256 over the datasets provided so far with a tag, in
+    /// constant time.
+    ///
+    /// # Return value
+    ///
+    /// **true**
+    ///
+    /// The tag matches.
+    ///
+    /// **false**
+    ///
+    /// The tag does not match.
+    ///
+    /// # Errors
+    ///
+    /// The same as get_hash.
+    ///
+    pub fn verify_hash(&self, tag: &[u8]) -> SgxResult<bool> {
+        let mut hash = self.get_hash()?;
+        let result = hash[..].consttime_memeq(tag);
+        clear_bytes(&mut hash);
+        Ok(result)
+    }
+
+    ///
+    /// close cleans up and deallocates the HMAC-SHA256 state.
+    ///
+    pub fn close(&self) -> SgxError {
+
+        if self.initflag.get() == false {
+            return Ok(());
+        }
+
+        clear_bytes(&mut *self.outer_key.borrow_mut());
+        self.initflag.set(false);
+        self.inner.close()
+    }
+}
+
+impl Default for SgxHmacHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SgxHmacHandle {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+// Volatile, or clearing a key on drop would be a dead store.
+fn clear_bytes(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
+
+///
+/// rsgx_hmac_sha256_slice performs an HMAC-SHA256 over the input data buffer.
+///
+/// # Parameters
+///
+/// **key**
+///
+/// The key, of any size.
+///
+/// **src**
+///
+/// The input data.
+///
+/// # Return value
+///
+/// The 256-bit HMAC.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The key or the input is too large.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hmac_sha256_slice<T>(key: &[u8], src: &[T]) -> SgxResult<sgx_hmac_256bit_tag_t>
+    where T: Copy + ContiguousMemory {
+
+    let handle = SgxHmacHandle::new();
+    handle.init(key)?;
+    handle.update_slice(src)?;
+    let hash = handle.get_hash();
+    handle.close()?;
+    hash
+}
+
+///
+/// rsgx_hmac_sha256_verify_slice checks an HMAC-SHA256 tag over the input data buffer in
+/// constant time.
+///
+/// # Return value
+///
+/// **true**
+///
+/// The tag matches.
+///
+/// **false**
+///
+/// The tag does not match.
+///
+/// # Errors
+///
+/// The same as rsgx_hmac_sha256_slice.
+///
+pub fn rsgx_hmac_sha256_verify_slice<T>(key: &[u8], src: &[T], tag: &[u8]) -> SgxResult<bool>
+    where T: Copy + ContiguousMemory {
+
+    let handle = SgxHmacHandle::new();
+    handle.init(key)?;
+    handle.update_slice(src)?;
+    let result = handle.verify_hash(tag);
+    handle.close()?;
+    result
+}
+
+///
+/// rsgx_hkdf_extract performs the HKDF-Extract step with SHA256.
+///
+/// # Description
+///
+/// The function extracts a pseudorandom key from the input keying material, as specified in
+/// RFC 5869 section 2.2. The result is the key to pass to rsgx_hkdf_expand.
+///
+/// # Parameters
+///
+/// **salt**
+///
+/// The optional salt. An empty salt is the same as a salt of 32 zero bytes.
+///
+/// **ikm**
+///
+/// The input keying material.
+///
+/// # Return value
+///
+/// The 256-bit pseudorandom key.
+///
+/// # Errors
+///
+/// The same as rsgx_hmac_sha256_slice.
+///
+pub fn rsgx_hkdf_extract(salt: &[u8], ikm: &[u8]) -> SgxResult<sgx_hmac_256bit_key_t> {
+    rsgx_hmac_sha256_slice(salt, ikm)
+}
+
+///
+/// rsgx_hkdf_expand performs the HKDF-Expand step with SHA256.
+///
+/// # Description
+///
+/// The function fills okm with output keying material derived from the pseudorandom key and
+/// the context information, as specified in RFC 5869 section 2.3.
+///
+/// # Parameters
+///
+/// **prk**
+///
+/// The pseudorandom key, at least 32 bytes, usually the output of rsgx_hkdf_extract.
+///
+/// **info**
+///
+/// The optional context and application specific information.
+///
+/// **okm**
+///
+/// The buffer receiving the output keying material, up to 8160 bytes.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The pseudorandom key is shorter than 32 bytes, or the output buffer is larger than 8160 bytes.
+///
+/// **SGX_ERROR_OUT_OF_MEMORY**
+///
+/// Not enough memory is available to complete this operation.
+///
+/// **SGX_ERROR_UNEXPECTED**
+///
+/// An internal cryptography library failure occurred.
+///
+pub fn rsgx_hkdf_expand(prk: &[u8], info: &[u8], okm: &mut [u8]) -> SgxError {
+
+    if prk.len() < SGX_SHA256_HASH_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if okm.len() > HKDF_MAX_OUTPUT_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let mut t = sgx_hmac_256bit_tag_t::default();
+    let mut ret = Ok(());
+    for (i, chunk) in okm.chunks_mut(SGX_SHA256_HASH_SIZE).enumerate() {
+        let counter = [(i + 1) as u8];
+        let handle = SgxHmacHandle::new();
+        ret = handle.init(prk);
+        if ret.is_ok() && i > 0 {
+            ret = handle.update_slice(&t);
+        }
+        if ret.is_ok() {
+            ret = handle.update_slice(info);
+        }
+        if ret.is_ok() {
+            ret = handle.update_slice(&counter);
+        }
+        if ret.is_ok() {
+            match handle.get_hash() {
+                Ok(hash) => t = hash,
+                Err(e) => ret = Err(e),
+            }
+        }
+        let _ = handle.close();
+        if ret.is_err() {
+            break;
+        }
+        let n = chunk.len();
+        chunk.copy_from_slice(&t[..n]);
+    }
+    clear_bytes(&mut t);
+    ret
+}
@@ -0,0 +1,990 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//!
+//! Key and signature encodings
+//!
+//! Import and export of the key types of this library in the formats other
+//! platforms expect:
+//!
+//! * SubjectPublicKeyInfo ([RFC 5280]) and PKCS#8 PrivateKeyInfo ([RFC 5208]), as DER or PEM.
+//! * ASN.1 DER ECDSA signatures ([RFC 3279]), `SEQUENCE { r INTEGER, s INTEGER }`.
+//! * JSON Web Key ([RFC 7517]).
+//!
+//! The SGX key structures store their integers little-endian; all of the
+//! external formats are big-endian, the conversion is done here. Only the
+//! encoding is checked on import. Use `SgxEccHandle::check_point` to verify that
+//! an imported public key lies on the curve.
+//!
+use sgx_types::*;
+use alloc::vec::Vec;
+use alloc::string::String;
+use core::str;
+use core::ptr;
+use der::*;
+
+// 1.2.840.10045.2.1
+const OID_EC_PUBLIC_KEY: &'static [u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+// 1.2.840.10045.3.1.7
+const OID_PRIME256V1: &'static [u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+// 1.2.840.113549.1.1.1
+const OID_RSA_ENCRYPTION: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+
+const PEM_PUBLIC_KEY: &'static str = "PUBLIC KEY";
+const PEM_PRIVATE_KEY: &'static str = "PRIVATE KEY";
+
+///
+/// The rsgx_ecc256_public_key_to_der function encodes an ECC public key as a
+/// DER SubjectPublicKeyInfo with the id-ecPublicKey algorithm and the prime256v1 curve.
+///
+pub fn rsgx_ecc256_public_key_to_der(public: &sgx_ec256_public_t) -> Vec<u8> {
+
+    let mut spki = Vec::new();
+    write_tlv(&mut spki, TAG_SEQUENCE, &ecc256_algorithm_id());
+    write_tlv(&mut spki, TAG_BIT_STRING, &ecc256_bit_string(public));
+    wrap(TAG_SEQUENCE, &spki)
+}
+
+///
+/// The rsgx_ecc256_public_key_from_der function decodes an ECC public key from a
+/// DER SubjectPublicKeyInfo.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not a prime256v1 SubjectPublicKeyInfo with an uncompressed point.
+///
+pub fn rsgx_ecc256_public_key_from_der(der: &[u8]) -> SgxResult<sgx_ec256_public_t> {
+
+    let mut outer = DerReader::new(der);
+    let mut spki = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    ecc256_read_algorithm_id(&mut spki)?;
+    let point = read_bit_string(&mut spki)?;
+    spki.finish()?;
+    ecc256_public_from_point(point)
+}
+
+///
+/// The rsgx_ecc256_public_key_to_pem function encodes an ECC public key as a
+/// `PUBLIC KEY` PEM block.
+///
+pub fn rsgx_ecc256_public_key_to_pem(public: &sgx_ec256_public_t) -> String {
+    pem_encode(PEM_PUBLIC_KEY, &rsgx_ecc256_public_key_to_der(public))
+}
+
+///
+/// The rsgx_ecc256_public_key_from_pem function decodes an ECC public key from
+/// the first `PUBLIC KEY` PEM block of the input.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// No `PUBLIC KEY` block was found, or it does not hold a prime256v1 public key.
+///
+pub fn rsgx_ecc256_public_key_from_pem(pem: &str) -> SgxResult<sgx_ec256_public_t> {
+    rsgx_ecc256_public_key_from_der(&pem_decode(pem, PEM_PUBLIC_KEY)?)
+}
+
+///
+/// The rsgx_ecc256_private_key_to_der function encodes an ECC key pair as a DER
+/// PKCS#8 PrivateKeyInfo holding an ECPrivateKey ([RFC 5915]) with the public key.
+///
+/// The output holds the private key; the caller should clear it after use.
+///
+pub fn rsgx_ecc256_private_key_to_der(private: &sgx_ec256_private_t,
+                                      public: &sgx_ec256_public_t) -> Vec<u8> {
+
+    let mut d = private.r;
+    d.reverse();
+
+    let mut ec_key = Vec::new();
+    write_tlv(&mut ec_key, TAG_INTEGER, &[1]);
+    write_tlv(&mut ec_key, TAG_OCTET_STRING, &d);
+    let bit_string = wrap(TAG_BIT_STRING, &ecc256_bit_string(public));
+    write_tlv(&mut ec_key, TAG_CONTEXT_1, &bit_string);
+    clear_bytes(&mut d);
+
+    let mut ec_key = wrap(TAG_SEQUENCE, &ec_key);
+    let mut info = Vec::new();
+    write_tlv(&mut info, TAG_INTEGER, &[0]);
+    write_tlv(&mut info, TAG_SEQUENCE, &ecc256_algorithm_id());
+    write_tlv(&mut info, TAG_OCTET_STRING, &ec_key);
+    clear_bytes(&mut ec_key);
+
+    let der = wrap(TAG_SEQUENCE, &info);
+    clear_bytes(&mut info);
+    der
+}
+
+///
+/// The rsgx_ecc256_private_key_from_der function decodes an ECC private key from
+/// a DER PKCS#8 PrivateKeyInfo.
+///
+/// # Return value
+///
+/// The private key, and the public key if the ECPrivateKey carries one.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not a PKCS#8 prime256v1 private key.
+///
+pub fn rsgx_ecc256_private_key_from_der(der: &[u8])
+    -> SgxResult<(sgx_ec256_private_t, Option<sgx_ec256_public_t>)> {
+
+    let mut outer = DerReader::new(der);
+    let mut info = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    read_version(&mut info, 0)?;
+    ecc256_read_algorithm_id(&mut info)?;
+    let mut outer = DerReader::new(info.read(TAG_OCTET_STRING)?);
+    let mut ec_key = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    read_version(&mut ec_key, 1)?;
+    let d = ec_key.read(TAG_OCTET_STRING)?;
+    if d.len() != SGX_ECP256_KEY_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut private = sgx_ec256_private_t::default();
+    be_to_le(d, &mut private.r)?;
+
+    if ec_key.peek_tag() == Some(TAG_CONTEXT_0) {
+        let mut params = DerReader::new(ec_key.read(TAG_CONTEXT_0)?);
+        if params.read(TAG_OID)? != OID_PRIME256V1 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        params.finish()?;
+    }
+
+    let mut public = None;
+    if ec_key.peek_tag() == Some(TAG_CONTEXT_1) {
+        let mut key = DerReader::new(ec_key.read(TAG_CONTEXT_1)?);
+        public = Some(ecc256_public_from_point(read_bit_string(&mut key)?)?);
+        key.finish()?;
+    }
+    ec_key.finish()?;
+
+    Ok((private, public))
+}
+
+///
+/// The rsgx_ecc256_private_key_to_pem function encodes an ECC key pair as a
+/// `PRIVATE KEY` PEM block.
+///
+pub fn rsgx_ecc256_private_key_to_pem(private: &sgx_ec256_private_t,
+                                      public: &sgx_ec256_public_t) -> String {
+
+    let mut der = rsgx_ecc256_private_key_to_der(private, public);
+    let pem = pem_encode(PEM_PRIVATE_KEY, &der);
+    clear_bytes(&mut der);
+    pem
+}
+
+///
+/// The rsgx_ecc256_private_key_from_pem function decodes an ECC private key from
+/// the first `PRIVATE KEY` PEM block of the input.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// No `PRIVATE KEY` block was found, or it does not hold a prime256v1 private key.
+///
+pub fn rsgx_ecc256_private_key_from_pem(pem: &str)
+    -> SgxResult<(sgx_ec256_private_t, Option<sgx_ec256_public_t>)> {
+
+    let mut der = pem_decode(pem, PEM_PRIVATE_KEY)?;
+    let result = rsgx_ecc256_private_key_from_der(&der);
+    clear_bytes(&mut der);
+    result
+}
+
+///
+/// The rsgx_ecc256_signature_to_der function encodes an ECDSA signature as
+/// `SEQUENCE { r INTEGER, s INTEGER }`.
+///
+pub fn rsgx_ecc256_signature_to_der(signature: &sgx_ec256_signature_t) -> Vec<u8> {
+
+    let mut r = [0_u8; SGX_ECP256_KEY_SIZE];
+    let mut s = [0_u8; SGX_ECP256_KEY_SIZE];
+    words_to_be(&signature.x, &mut r);
+    words_to_be(&signature.y, &mut s);
+
+    let mut seq = Vec::new();
+    write_uint(&mut seq, &r);
+    write_uint(&mut seq, &s);
+    wrap(TAG_SEQUENCE, &seq)
+}
+
+///
+/// The rsgx_ecc256_signature_from_der function decodes an ECDSA signature from
+/// `SEQUENCE { r INTEGER, s INTEGER }`.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is malformed, or r or s is negative or does not fit in 256 bits.
+///
+pub fn rsgx_ecc256_signature_from_der(der: &[u8]) -> SgxResult<sgx_ec256_signature_t> {
+
+    let mut outer = DerReader::new(der);
+    let mut seq = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    let mut r = [0_u8; SGX_ECP256_KEY_SIZE];
+    let mut s = [0_u8; SGX_ECP256_KEY_SIZE];
+    be_to_le(seq.read_uint()?, &mut r)?;
+    be_to_le(seq.read_uint()?, &mut s)?;
+    seq.finish()?;
+
+    let mut signature = sgx_ec256_signature_t::default();
+    le_to_words(&r, &mut signature.x);
+    le_to_words(&s, &mut signature.y);
+    Ok(signature)
+}
+
+///
+/// The rsgx_ecc256_public_key_to_jwk function encodes an ECC public key as a
+/// JSON Web Key with `"kty":"EC"` and `"crv":"P-256"`.
+///
+pub fn rsgx_ecc256_public_key_to_jwk(public: &sgx_ec256_public_t) -> String {
+    ecc256_jwk(public, None)
+}
+
+///
+/// The rsgx_ecc256_public_key_from_jwk function decodes an ECC public key from a
+/// JSON Web Key. Members other than `kty`, `crv`, `x` and `y` are ignored.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not a P-256 `EC` JSON Web Key.
+///
+pub fn rsgx_ecc256_public_key_from_jwk(jwk: &str) -> SgxResult<sgx_ec256_public_t> {
+
+    let members = jwk_parse(jwk)?;
+    ecc256_public_from_jwk(&members)
+}
+
+///
+/// The rsgx_ecc256_private_key_to_jwk function encodes an ECC key pair as a
+/// JSON Web Key with the `d` member.
+///
+/// The output holds the private key; the caller should clear it after use.
+///
+pub fn rsgx_ecc256_private_key_to_jwk(private: &sgx_ec256_private_t,
+                                      public: &sgx_ec256_public_t) -> String {
+    ecc256_jwk(public, Some(private))
+}
+
+///
+/// The rsgx_ecc256_private_key_from_jwk function decodes an ECC key pair from a
+/// JSON Web Key with the `d` member.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not a P-256 `EC` JSON Web Key, or it has no `d` member.
+///
+pub fn rsgx_ecc256_private_key_from_jwk(jwk: &str)
+    -> SgxResult<(sgx_ec256_private_t, sgx_ec256_public_t)> {
+
+    let members = jwk_parse(jwk)?;
+    let public = ecc256_public_from_jwk(&members)?;
+
+    let mut d = b64_decode(jwk_member(&members, "d")?.as_bytes(), true)?;
+    let mut private = sgx_ec256_private_t::default();
+    let result = if d.len() == SGX_ECP256_KEY_SIZE {
+        be_to_le(&d, &mut private.r)
+    } else {
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    };
+    clear_bytes(&mut d);
+    result.map(|_| (private, public))
+}
+
+///
+/// The rsgx_rsa3072_public_key_to_der function encodes an RSA public key as a
+/// DER SubjectPublicKeyInfo with the rsaEncryption algorithm.
+///
+pub fn rsgx_rsa3072_public_key_to_der(public: &sgx_rsa3072_public_key_t) -> Vec<u8> {
+
+    let mut n = public.modulus;
+    let mut e = public.exponent;
+    n.reverse();
+    e.reverse();
+
+    let mut rsa_key = Vec::new();
+    write_uint(&mut rsa_key, &n);
+    write_uint(&mut rsa_key, &e);
+    let rsa_key = wrap(TAG_SEQUENCE, &rsa_key);
+
+    let mut bit_string = Vec::with_capacity(rsa_key.len() + 1);
+    bit_string.push(0);
+    bit_string.extend_from_slice(&rsa_key);
+
+    let mut spki = Vec::new();
+    write_tlv(&mut spki, TAG_SEQUENCE, &rsa_algorithm_id());
+    write_tlv(&mut spki, TAG_BIT_STRING, &bit_string);
+    wrap(TAG_SEQUENCE, &spki)
+}
+
+///
+/// The rsgx_rsa3072_public_key_from_der function decodes an RSA public key from
+/// a DER SubjectPublicKeyInfo.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not an rsaEncryption SubjectPublicKeyInfo, the modulus is not
+/// 3072 bits, or the public exponent does not fit in 32 bits.
+///
+pub fn rsgx_rsa3072_public_key_from_der(der: &[u8]) -> SgxResult<sgx_rsa3072_public_key_t> {
+
+    let mut outer = DerReader::new(der);
+    let mut spki = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    rsa_read_algorithm_id(&mut spki)?;
+    let mut outer = DerReader::new(read_bit_string(&mut spki)?);
+    spki.finish()?;
+    let mut rsa_key = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    let mut public = sgx_rsa3072_public_key_t::default();
+    read_modulus(&mut rsa_key, &mut public.modulus)?;
+    be_to_le(rsa_key.read_uint()?, &mut public.exponent)?;
+    rsa_key.finish()?;
+    Ok(public)
+}
+
+///
+/// The rsgx_rsa3072_public_key_to_pem function encodes an RSA public key as a
+/// `PUBLIC KEY` PEM block.
+///
+pub fn rsgx_rsa3072_public_key_to_pem(public: &sgx_rsa3072_public_key_t) -> String {
+    pem_encode(PEM_PUBLIC_KEY, &rsgx_rsa3072_public_key_to_der(public))
+}
+
+///
+/// The rsgx_rsa3072_public_key_from_pem function decodes an RSA public key from
+/// the first `PUBLIC KEY` PEM block of the input.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// No `PUBLIC KEY` block was found, or it does not hold an RSA 3072 public key.
+///
+pub fn rsgx_rsa3072_public_key_from_pem(pem: &str) -> SgxResult<sgx_rsa3072_public_key_t> {
+    rsgx_rsa3072_public_key_from_der(&pem_decode(pem, PEM_PUBLIC_KEY)?)
+}
+
+///
+/// The rsgx_rsa3072_public_key_to_jwk function encodes an RSA public key as a
+/// JSON Web Key with `"kty":"RSA"`.
+///
+pub fn rsgx_rsa3072_public_key_to_jwk(public: &sgx_rsa3072_public_key_t) -> String {
+
+    let mut n = public.modulus;
+    let mut e = public.exponent;
+    n.reverse();
+    e.reverse();
+
+    let mut jwk = String::from("{\"kty\":\"RSA\",\"n\":\"");
+    b64_encode_into(&mut jwk, strip_zeros(&n), true);
+    jwk.push_str("\",\"e\":\"");
+    b64_encode_into(&mut jwk, strip_zeros(&e), true);
+    jwk.push_str("\"}");
+    jwk
+}
+
+///
+/// The rsgx_rsa3072_public_key_from_jwk function decodes an RSA public key from a
+/// JSON Web Key. Members other than `kty`, `n` and `e` are ignored.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not an `RSA` JSON Web Key with a 3072 bit modulus.
+///
+pub fn rsgx_rsa3072_public_key_from_jwk(jwk: &str) -> SgxResult<sgx_rsa3072_public_key_t> {
+
+    let members = jwk_parse(jwk)?;
+    if jwk_member(&members, "kty")? != "RSA" {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let n = b64_decode(jwk_member(&members, "n")?.as_bytes(), true)?;
+    let e = b64_decode(jwk_member(&members, "e")?.as_bytes(), true)?;
+    let mut public = sgx_rsa3072_public_key_t::default();
+    if strip_zeros(&n).len() != SGX_RSA3072_KEY_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    be_to_le(&n, &mut public.modulus)?;
+    be_to_le(&e, &mut public.exponent)?;
+    Ok(public)
+}
+
+///
+/// The parts of an RSA private key, as `rsgx_create_rsa_key_pair` outputs them.
+///
+/// All values are little-endian. The PKCS#1 structure needs the CRT values, which
+/// `sgx_rsa3072_key_t` does not keep.
+///
+#[derive(Copy, Clone)]
+pub struct SgxRsaPrivateKeyParts<'a> {
+    /// The modulus.
+    pub n: &'a [u8],
+    /// The public exponent.
+    pub e: &'a [u8],
+    /// The private exponent.
+    pub d: &'a [u8],
+    /// The first prime factor.
+    pub p: &'a [u8],
+    /// The second prime factor.
+    pub q: &'a [u8],
+    /// The CRT exponent d mod (p - 1).
+    pub dmp1: &'a [u8],
+    /// The CRT exponent d mod (q - 1).
+    pub dmq1: &'a [u8],
+    /// The CRT coefficient q^-1 mod p.
+    pub iqmp: &'a [u8],
+}
+
+///
+/// The rsgx_rsa3072_private_key_to_der function encodes an RSA private key as a
+/// DER PKCS#8 PrivateKeyInfo holding an RSAPrivateKey ([RFC 8017]).
+///
+/// The output holds the private key; the caller should clear it after use.
+///
+pub fn rsgx_rsa3072_private_key_to_der(key: &SgxRsaPrivateKeyParts) -> Vec<u8> {
+
+    let mut rsa_key = Vec::new();
+    write_tlv(&mut rsa_key, TAG_INTEGER, &[0]);
+    for value in [key.n, key.e, key.d, key.p, key.q, key.dmp1, key.dmq1, key.iqmp].iter() {
+        let mut be = value.to_vec();
+        be.reverse();
+        write_uint(&mut rsa_key, &be);
+        clear_bytes(&mut be);
+    }
+
+    let mut rsa_key_seq = wrap(TAG_SEQUENCE, &rsa_key);
+    clear_bytes(&mut rsa_key);
+
+    let mut info = Vec::new();
+    write_tlv(&mut info, TAG_INTEGER, &[0]);
+    write_tlv(&mut info, TAG_SEQUENCE, &rsa_algorithm_id());
+    write_tlv(&mut info, TAG_OCTET_STRING, &rsa_key_seq);
+    clear_bytes(&mut rsa_key_seq);
+
+    let der = wrap(TAG_SEQUENCE, &info);
+    clear_bytes(&mut info);
+    der
+}
+
+///
+/// The rsgx_rsa3072_private_key_to_pem function encodes an RSA private key as a
+/// `PRIVATE KEY` PEM block.
+///
+pub fn rsgx_rsa3072_private_key_to_pem(key: &SgxRsaPrivateKeyParts) -> String {
+
+    let mut der = rsgx_rsa3072_private_key_to_der(key);
+    let pem = pem_encode(PEM_PRIVATE_KEY, &der);
+    clear_bytes(&mut der);
+    pem
+}
+
+///
+/// The rsgx_rsa3072_private_key_from_der function decodes an RSA private key from
+/// a DER PKCS#8 PrivateKeyInfo. Only the modulus and the two exponents are kept.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The input is not a PKCS#8 rsaEncryption private key with a 3072 bit modulus.
+///
+pub fn rsgx_rsa3072_private_key_from_der(der: &[u8]) -> SgxResult<sgx_rsa3072_key_t> {
+
+    let mut outer = DerReader::new(der);
+    let mut info = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    read_version(&mut info, 0)?;
+    rsa_read_algorithm_id(&mut info)?;
+    let mut outer = DerReader::new(info.read(TAG_OCTET_STRING)?);
+    let mut rsa_key = DerReader::new(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+
+    read_version(&mut rsa_key, 0)?;
+    let mut key = sgx_rsa3072_key_t::default();
+    read_modulus(&mut rsa_key, &mut key.modulus)?;
+    be_to_le(rsa_key.read_uint()?, &mut key.e)?;
+    be_to_le(rsa_key.read_uint()?, &mut key.d)?;
+    for _ in 0..5 {
+        rsa_key.read_uint()?;
+    }
+    rsa_key.finish()?;
+    Ok(key)
+}
+
+///
+/// The rsgx_rsa3072_private_key_from_pem function decodes an RSA private key from
+/// the first `PRIVATE KEY` PEM block of the input.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// No `PRIVATE KEY` block was found, or it does not hold an RSA 3072 private key.
+///
+pub fn rsgx_rsa3072_private_key_from_pem(pem: &str) -> SgxResult<sgx_rsa3072_key_t> {
+
+    let mut der = pem_decode(pem, PEM_PRIVATE_KEY)?;
+    let result = rsgx_rsa3072_private_key_from_der(&der);
+    clear_bytes(&mut der);
+    result
+}
+
+fn ecc256_algorithm_id() -> Vec<u8> {
+    let mut alg = Vec::new();
+    write_tlv(&mut alg, TAG_OID, OID_EC_PUBLIC_KEY);
+    write_tlv(&mut alg, TAG_OID, OID_PRIME256V1);
+    alg
+}
+
+fn ecc256_read_algorithm_id(reader: &mut DerReader) -> SgxError {
+    let mut alg = DerReader::new(reader.read(TAG_SEQUENCE)?);
+    if alg.read(TAG_OID)? != OID_EC_PUBLIC_KEY || alg.read(TAG_OID)? != OID_PRIME256V1 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    alg.finish()
+}
+
+fn rsa_algorithm_id() -> Vec<u8> {
+    let mut alg = Vec::new();
+    write_tlv(&mut alg, TAG_OID, OID_RSA_ENCRYPTION);
+    write_tlv(&mut alg, TAG_NULL, &[]);
+    alg
+}
+
+fn rsa_read_algorithm_id(reader: &mut DerReader) -> SgxError {
+    let mut alg = DerReader::new(reader.read(TAG_SEQUENCE)?);
+    if alg.read(TAG_OID)? != OID_RSA_ENCRYPTION {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if !alg.is_empty() && alg.read(TAG_NULL)?.len() != 0 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    alg.finish()
+}
+
+// The contents of the subjectPublicKey BIT STRING: no unused bits, then the
+// uncompressed point 04 || X || Y.
+fn ecc256_bit_string(public: &sgx_ec256_public_t) -> Vec<u8> {
+    let mut point = Vec::with_capacity(2 + 2 * SGX_ECP256_KEY_SIZE);
+    point.push(0);
+    point.push(4);
+    point.extend(public.gx.iter().rev());
+    point.extend(public.gy.iter().rev());
+    point
+}
+
+fn ecc256_public_from_point(point: &[u8]) -> SgxResult<sgx_ec256_public_t> {
+    if point.len() != 1 + 2 * SGX_ECP256_KEY_SIZE || point[0] != 4 {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut public = sgx_ec256_public_t::default();
+    be_to_le(&point[1..1 + SGX_ECP256_KEY_SIZE], &mut public.gx)?;
+    be_to_le(&point[1 + SGX_ECP256_KEY_SIZE..], &mut public.gy)?;
+    Ok(public)
+}
+
+fn ecc256_jwk(public: &sgx_ec256_public_t, private: Option<&sgx_ec256_private_t>) -> String {
+
+    let mut x = public.gx;
+    let mut y = public.gy;
+    x.reverse();
+    y.reverse();
+
+    let mut jwk = String::from("{\"kty\":\"EC\",\"crv\":\"P-256\",\"x\":\"");
+    b64_encode_into(&mut jwk, &x, true);
+    jwk.push_str("\",\"y\":\"");
+    b64_encode_into(&mut jwk, &y, true);
+    if let Some(private) = private {
+        let mut d = private.r;
+        d.reverse();
+        jwk.push_str("\",\"d\":\"");
+        b64_encode_into(&mut jwk, &d, true);
+        clear_bytes(&mut d);
+    }
+    jwk.push_str("\"}");
+    jwk
+}
+
+fn ecc256_public_from_jwk(members: &[(&str, &str)]) -> SgxResult<sgx_ec256_public_t> {
+
+    if jwk_member(members, "kty")? != "EC" || jwk_member(members, "crv")? != "P-256" {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let x = b64_decode(jwk_member(members, "x")?.as_bytes(), true)?;
+    let y = b64_decode(jwk_member(members, "y")?.as_bytes(), true)?;
+    if x.len() != SGX_ECP256_KEY_SIZE || y.len() != SGX_ECP256_KEY_SIZE {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let mut public = sgx_ec256_public_t::default();
+    be_to_le(&x, &mut public.gx)?;
+    be_to_le(&y, &mut public.gy)?;
+    Ok(public)
+}
+
+fn read_version(reader: &mut DerReader, version: u8) -> SgxError {
+    let value = reader.read(TAG_INTEGER)?;
+    if value.len() == 1 && value[0] == version {
+        Ok(())
+    } else {
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    }
+}
+
+fn read_bit_string<'a>(reader: &mut DerReader<'a>) -> SgxResult<&'a [u8]> {
+    match reader.read(TAG_BIT_STRING)?.split_first() {
+        Some((&0, bits)) => Ok(bits),
+        _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    }
+}
+
+fn read_modulus(reader: &mut DerReader, modulus: &mut [u8]) -> SgxError {
+    let n = reader.read_uint()?;
+    if n.len() != modulus.len() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    be_to_le(n, modulus)
+}
+
+fn strip_zeros(be: &[u8]) -> &[u8] {
+    let start = be.iter().position(|&b| b != 0).unwrap_or(be.len());
+    &be[start..]
+}
+
+// Writes a big-endian magnitude into a little-endian field, zero extended.
+fn be_to_le(be: &[u8], le: &mut [u8]) -> SgxError {
+    let be = strip_zeros(be);
+    if be.len() > le.len() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    for (i, byte) in le.iter_mut().enumerate() {
+        *byte = if i < be.len() { be[be.len() - 1 - i] } else { 0 };
+    }
+    Ok(())
+}
+
+fn words_to_be(words: &[u32], be: &mut [u8]) {
+    let len = be.len();
+    for (i, word) in words.iter().enumerate() {
+        for j in 0..4 {
+            be[len - 1 - (i * 4 + j)] = (*word >> (j * 8)) as u8;
+        }
+    }
+}
+
+fn le_to_words(le: &[u8], words: &mut [u32]) {
+    for (word, bytes) in words.iter_mut().zip(le.chunks(4)) {
+        *word = (bytes[0] as u32) | (bytes[1] as u32) << 8 |
+                (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24;
+    }
+}
+
+// Private key bytes are cleared with volatile writes, which the compiler keeps.
+fn clear_bytes(buf: &mut [u8]) {
+    for b in buf.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+}
+
+const B64_STANDARD: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const B64_URL_SAFE: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Standard alphabet with padding for PEM, URL-safe without padding for JWK.
+fn b64_encode_into(out: &mut String, data: &[u8], url_safe: bool) {
+    let table = if url_safe { B64_URL_SAFE } else { B64_STANDARD };
+    for chunk in data.chunks(3) {
+        let b = [chunk[0],
+                 if chunk.len() > 1 { chunk[1] } else { 0 },
+                 if chunk.len() > 2 { chunk[2] } else { 0 }];
+        let symbols = [b[0] >> 2,
+                       (b[0] & 0x03) << 4 | b[1] >> 4,
+                       (b[1] & 0x0F) << 2 | b[2] >> 6,
+                       b[2] & 0x3F];
+        for (i, &s) in symbols.iter().enumerate() {
+            if i <= chunk.len() {
+                out.push(table[s as usize] as char);
+            } else if !url_safe {
+                out.push('=');
+            }
+        }
+    }
+}
+
+fn b64_decode(input: &[u8], url_safe: bool) -> SgxResult<Vec<u8>> {
+
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc = 0_u32;
+    let mut bits = 0;
+    let mut padding = 0;
+    for &c in input {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' if !url_safe => 62,
+            b'/' if !url_safe => 63,
+            b'-' if url_safe => 62,
+            b'_' if url_safe => 63,
+            b'=' if !url_safe => {
+                padding += 1;
+                continue;
+            },
+            b' ' | b'\t' | b'\r' | b'\n' if !url_safe => continue,
+            _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        };
+        if padding > 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        acc = acc << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    // A final group of two or three symbols leaves four or two unused bits,
+    // which must be zero and, with the standard alphabet, padded with '='.
+    let expected_padding = match bits {
+        0 => 0,
+        4 => 2,
+        2 => 1,
+        _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    };
+    if acc & ((1 << bits) - 1) != 0 || (!url_safe && padding != expected_padding) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(out)
+}
+
+fn pem_encode(label: &str, der: &[u8]) -> String {
+    let mut pem = String::with_capacity(der.len() * 4 / 3 + 64);
+    pem.push_str("-----BEGIN ");
+    pem.push_str(label);
+    pem.push_str("-----\n");
+    for line in der.chunks(48) {
+        b64_encode_into(&mut pem, line, false);
+        pem.push('\n');
+    }
+    pem.push_str("-----END ");
+    pem.push_str(label);
+    pem.push_str("-----\n");
+    pem
+}
+
+fn pem_decode(pem: &str, label: &str) -> SgxResult<Vec<u8>> {
+
+    let mut begin = String::from("-----BEGIN ");
+    begin.push_str(label);
+    begin.push_str("-----");
+    let mut end = String::from("-----END ");
+    end.push_str(label);
+    end.push_str("-----");
+
+    let start = pem.find(begin.as_str())
+        .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)? + begin.len();
+    let len = pem[start..].find(end.as_str())
+        .ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)?;
+    b64_decode(pem[start..start + len].as_bytes(), false)
+}
+
+// Collects the string members of a flat JSON object. Members with other
+// values are skipped; escapes are not decoded since JWK values are base64url.
+fn jwk_parse(jwk: &str) -> SgxResult<Vec<(&str, &str)>> {
+
+    let mut parser = JsonParser { data: jwk.as_bytes(), pos: 0 };
+    let mut members = Vec::new();
+    parser.expect(b'{')?;
+    if parser.peek() == Some(b'}') {
+        parser.pos += 1;
+    } else {
+        loop {
+            let name = parser.string()?;
+            parser.expect(b':')?;
+            if parser.peek() == Some(b'"') {
+                let value = parser.string()?;
+                members.push((str_from(name)?, str_from(value)?));
+            } else {
+                parser.skip_value(0)?;
+            }
+            match parser.next() {
+                Some(b',') => continue,
+                Some(b'}') => break,
+                _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+            }
+        }
+    }
+    if parser.peek().is_some() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    Ok(members)
+}
+
+fn jwk_member<'a>(members: &[(&str, &'a str)], name: &str) -> SgxResult<&'a str> {
+    let mut found = members.iter().filter(|m| m.0 == name);
+    match (found.next(), found.next()) {
+        (Some(m), None) => Ok(m.1),
+        _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    }
+}
+
+fn str_from(bytes: &[u8]) -> SgxResult<&str> {
+    str::from_utf8(bytes).map_err(|_| sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+}
+
+const JSON_MAX_DEPTH: usize = 16;
+
+struct JsonParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b' ' | b'\t' | b'\r' | b'\n' => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.data.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn expect(&mut self, c: u8) -> SgxError {
+        if self.next() == Some(c) {
+            Ok(())
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+        }
+    }
+
+    // Returns the raw contents of a string, escapes left in place.
+    fn string(&mut self) -> SgxResult<&'a [u8]> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(&self.data[start..self.pos - 1]);
+                },
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+        Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+    }
+
+    fn skip_value(&mut self, depth: usize) -> SgxError {
+        if depth > JSON_MAX_DEPTH {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        match self.peek() {
+            Some(b'"') => self.string().map(|_| ()),
+            Some(open @ b'[') | Some(open @ b'{') => {
+                let close = if open == b'[' { b']' } else { b'}' };
+                self.pos += 1;
+                if self.peek() == Some(close) {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                loop {
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+                    self.skip_value(depth + 1)?;
+                    match self.next() {
+                        Some(b',') => continue,
+                        Some(c) if c == close => return Ok(()),
+                        _ => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+                    }
+                }
+            },
+            Some(_) => {
+                let start = self.pos;
+                while self.pos < self.data.len() {
+                    match self.data[self.pos] {
+                        b'-' | b'+' | b'.' | b'0'..=b'9' | b'a'..=b'z' | b'E' => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                if self.pos == start {
+                    Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+                } else {
+                    Ok(())
+                }
+            },
+            None => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+}
@@ -33,11 +33,25 @@
 //!
 
 #![no_std]
+#![feature(alloc)]
 
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
 extern crate sgx_types;
+extern crate sgx_trts;
+extern crate alloc;
 
 mod crypto;
-pub use self::crypto::*;
\ No newline at end of file
+pub use self::crypto::*;
+
+mod aead;
+pub use self::aead::*;
+
+mod hmac;
+pub use self::hmac::*;
+
+pub mod der;
+
+mod keyfmt;
+pub use self::keyfmt::*;
@@ -0,0 +1,63 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Local Attestation Sessions
+//!
+//! Secure sessions between two enclaves on the same platform, built on the DH key exchange of
+//! `sgx_tdh`.
+//!
+//! * `LocalSessionInitiator` and `LocalSessionResponder` run the three-message exchange and check
+//!   the peer enclave against a `SessionPolicy`, such as `IdentityPolicy` or a closure.
+//! * `LocalSession` frames messages with AES-GCM under the derived AEK. Each message carries a
+//!   sequence number; a message that is replayed, dropped or reordered is rejected.
+//! * `SessionTable` keeps the sessions of an enclave by id, so the ECALLs carrying the exchange
+//!   only pass a session id and the messages.
+//!
+//! How the messages travel between the enclaves is left to the application.
+//!
+
+#![cfg_attr(not(target_env = "sgx"), no_std)]
+#![cfg_attr(target_env = "sgx", feature(rustc_private))]
+
+#![allow(non_camel_case_types)]
+
+extern crate sgx_types;
+extern crate sgx_tcrypto;
+extern crate sgx_tdh;
+#[cfg(not(target_env = "sgx"))]
+#[macro_use]
+extern crate sgx_tstd as std;
+
+mod policy;
+pub use self::policy::*;
+
+mod session;
+pub use self::session::*;
+
+mod table;
+pub use self::table::*;
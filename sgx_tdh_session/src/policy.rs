@@ -0,0 +1,142 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+
+///
+/// Decides whether a peer enclave may take part in a session.
+///
+pub trait SessionPolicy {
+    ///
+    /// Returns true to accept the peer described by `peer`.
+    ///
+    fn verify(&self, peer: &sgx_dh_session_enclave_identity_t) -> bool;
+}
+
+impl<F> SessionPolicy for F where F: Fn(&sgx_dh_session_enclave_identity_t) -> bool {
+    fn verify(&self, peer: &sgx_dh_session_enclave_identity_t) -> bool {
+        self(peer)
+    }
+}
+
+///
+/// A policy on the identity of the peer enclave.
+///
+/// By default, any production enclave is accepted. Each setter adds a requirement,
+/// and debug enclaves are rejected unless `allow_debug` is set.
+///
+#[derive(Clone, Copy, Default)]
+pub struct IdentityPolicy {
+    mr_signer: Option<sgx_measurement_t>,
+    mr_enclave: Option<sgx_measurement_t>,
+    isv_prod_id: Option<sgx_prod_id_t>,
+    min_isv_svn: sgx_isv_svn_t,
+    allow_debug: bool,
+}
+
+impl IdentityPolicy {
+
+    ///
+    /// Constructs a policy that accepts any production enclave.
+    ///
+    pub fn new() -> Self {
+        IdentityPolicy::default()
+    }
+
+    ///
+    /// Requires the peer to be signed by the key whose hash is `mr_signer`.
+    ///
+    pub fn mr_signer(mut self, mr_signer: &sgx_measurement_t) -> Self {
+        self.mr_signer = Some(*mr_signer);
+        self
+    }
+
+    ///
+    /// Requires the peer to have the measurement `mr_enclave`.
+    ///
+    pub fn mr_enclave(mut self, mr_enclave: &sgx_measurement_t) -> Self {
+        self.mr_enclave = Some(*mr_enclave);
+        self
+    }
+
+    ///
+    /// Requires the peer to have the product ID `isv_prod_id`.
+    ///
+    pub fn isv_prod_id(mut self, isv_prod_id: sgx_prod_id_t) -> Self {
+        self.isv_prod_id = Some(isv_prod_id);
+        self
+    }
+
+    ///
+    /// Requires the security version of the peer to be at least `min_isv_svn`.
+    ///
+    pub fn min_isv_svn(mut self, min_isv_svn: sgx_isv_svn_t) -> Self {
+        self.min_isv_svn = min_isv_svn;
+        self
+    }
+
+    ///
+    /// Accepts debug enclaves as well, whose secrets are open to the debugger.
+    ///
+    pub fn allow_debug(mut self, allow_debug: bool) -> Self {
+        self.allow_debug = allow_debug;
+        self
+    }
+}
+
+impl SessionPolicy for IdentityPolicy {
+    fn verify(&self, peer: &sgx_dh_session_enclave_identity_t) -> bool {
+
+        // The identity is packed; copy the fields out before comparing them.
+        let attributes = peer.attributes;
+        let peer_mr_signer = peer.mr_signer;
+        let peer_mr_enclave = peer.mr_enclave;
+        let peer_isv_prod_id = peer.isv_prod_id;
+        let peer_isv_svn = peer.isv_svn;
+
+        if !self.allow_debug && (attributes.flags & SGX_FLAGS_DEBUG) != 0 {
+            return false;
+        }
+        if let Some(ref mr_signer) = self.mr_signer {
+            if peer_mr_signer.m != mr_signer.m {
+                return false;
+            }
+        }
+        if let Some(ref mr_enclave) = self.mr_enclave {
+            if peer_mr_enclave.m != mr_enclave.m {
+                return false;
+            }
+        }
+        if let Some(isv_prod_id) = self.isv_prod_id {
+            if peer_isv_prod_id != isv_prod_id {
+                return false;
+            }
+        }
+        peer_isv_svn >= self.min_isv_svn
+    }
+}
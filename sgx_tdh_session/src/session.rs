@@ -0,0 +1,372 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tcrypto::{Aead, SgxAesGcm};
+use sgx_tdh::{SgxDhMsg1, SgxDhMsg2, SgxDhMsg3, SgxDhInitiator, SgxDhResponder};
+use std::vec::Vec;
+use std::mem;
+use std::ptr;
+use policy::SessionPolicy;
+
+/// The size of the sequence number at the start of each message.
+pub const SESSION_MSG_SEQ_SIZE: usize = 8;
+/// The size of the tag at the end of each message.
+pub const SESSION_MSG_TAG_SIZE: usize = SGX_AESGCM_MAC_SIZE;
+/// The overhead of a message over its plain text.
+pub const SESSION_MSG_OVERHEAD: usize = SESSION_MSG_SEQ_SIZE + SESSION_MSG_TAG_SIZE;
+
+/// The side of the DH exchange a session was established on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionRole {
+    Initiator,
+    Responder,
+}
+
+impl SessionRole {
+    // Both directions share the AEK; the nonce starts with the role of the sender.
+    fn nonce_prefix(&self) -> [u8; 4] {
+        match *self {
+            SessionRole::Initiator => [0, 0, 0, 1],
+            SessionRole::Responder => [0, 0, 0, 2],
+        }
+    }
+
+    fn peer(&self) -> SessionRole {
+        match *self {
+            SessionRole::Initiator => SessionRole::Responder,
+            SessionRole::Responder => SessionRole::Initiator,
+        }
+    }
+}
+
+///
+/// The initiator of a local attestation session.
+///
+/// It handles msg1 from the responder, answers with msg2, and completes the session with msg3.
+///
+pub struct LocalSessionInitiator {
+    initiator: SgxDhInitiator,
+}
+
+impl LocalSessionInitiator {
+
+    ///
+    /// Starts a session as the initiator.
+    ///
+    pub fn new() -> Self {
+        LocalSessionInitiator { initiator: SgxDhInitiator::init_session() }
+    }
+
+    ///
+    /// Handles msg1 from the responder and returns msg2 for it.
+    ///
+    /// # Errors
+    ///
+    /// Any error of `SgxDhInitiator::proc_msg1`.
+    ///
+    pub fn proc_msg1(&mut self, msg1: &SgxDhMsg1) -> SgxResult<SgxDhMsg2> {
+        let mut msg2 = SgxDhMsg2::default();
+        self.initiator.proc_msg1(msg1, &mut msg2)?;
+        Ok(msg2)
+    }
+
+    ///
+    /// Handles msg3 from the responder, checks the responder against `policy` and returns the
+    /// established session.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// The policy rejected the responder.
+    ///
+    /// Otherwise, any error of `SgxDhInitiator::proc_msg3`.
+    ///
+    pub fn proc_msg3<P: SessionPolicy + ?Sized>(mut self, msg3: &SgxDhMsg3, policy: &P) -> SgxResult<LocalSession> {
+        let mut aek = sgx_key_128bit_t::default();
+        let mut identity = sgx_dh_session_enclave_identity_t::default();
+        self.initiator.proc_msg3(msg3, &mut aek, &mut identity)?;
+        LocalSession::establish(SessionRole::Initiator, aek, identity, policy)
+    }
+}
+
+///
+/// The responder of a local attestation session.
+///
+/// It starts the exchange with msg1, handles msg2 from the initiator, and answers with msg3.
+///
+pub struct LocalSessionResponder {
+    responder: SgxDhResponder,
+}
+
+impl LocalSessionResponder {
+
+    ///
+    /// Starts a session and returns msg1 for the initiator.
+    ///
+    /// # Errors
+    ///
+    /// Any error of `SgxDhResponder::gen_msg1`.
+    ///
+    pub fn new() -> SgxResult<(Self, SgxDhMsg1)> {
+        let mut responder = LocalSessionResponder { responder: SgxDhResponder::init_session() };
+        let mut msg1 = SgxDhMsg1::default();
+        responder.responder.gen_msg1(&mut msg1)?;
+        Ok((responder, msg1))
+    }
+
+    ///
+    /// Handles msg2 from the initiator, checks the initiator against `policy`, and returns msg3
+    /// for the initiator together with the established session.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// The policy rejected the initiator.
+    ///
+    /// Otherwise, any error of `SgxDhResponder::proc_msg2`.
+    ///
+    pub fn proc_msg2<P: SessionPolicy + ?Sized>(mut self, msg2: &SgxDhMsg2, policy: &P) -> SgxResult<(SgxDhMsg3, LocalSession)> {
+        let mut msg3 = SgxDhMsg3::new();
+        let mut aek = sgx_key_128bit_t::default();
+        let mut identity = sgx_dh_session_enclave_identity_t::default();
+        self.responder.proc_msg2(msg2, &mut msg3, &mut aek, &mut identity)?;
+        let session = LocalSession::establish(SessionRole::Responder, aek, identity, policy)?;
+        Ok((msg3, session))
+    }
+}
+
+///
+/// An established local attestation session.
+///
+/// Messages are sealed with AES-GCM under the AEK. A message is the sequence number
+/// (8 bytes, little-endian), the cipher text, and a 16-byte tag; the sequence number is
+/// authenticated as additional data. Each side numbers the messages it sends from zero, and
+/// only accepts the next number from its peer, so a message cannot be replayed, dropped or
+/// reordered without `open_message` failing.
+///
+pub struct LocalSession {
+    role: SessionRole,
+    aek: sgx_key_128bit_t,
+    peer_identity: sgx_dh_session_enclave_identity_t,
+    send_seq: u64,
+    recv_seq: u64,
+}
+
+impl LocalSession {
+
+    fn establish<P: SessionPolicy + ?Sized>(role: SessionRole,
+                                            aek: sgx_key_128bit_t,
+                                            peer_identity: sgx_dh_session_enclave_identity_t,
+                                            policy: &P) -> SgxResult<LocalSession> {
+        // Constructed first so the AEK is cleared on rejection too.
+        let session = LocalSession {
+            role: role,
+            aek: aek,
+            peer_identity: peer_identity,
+            send_seq: 0,
+            recv_seq: 0,
+        };
+        if policy.verify(&session.peer_identity) {
+            Ok(session)
+        } else {
+            Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)
+        }
+    }
+
+    ///
+    /// The side of the exchange this session was established on.
+    ///
+    pub fn role(&self) -> SessionRole {
+        self.role
+    }
+
+    ///
+    /// The identity of the peer enclave, as attested during the exchange.
+    ///
+    pub fn peer_identity(&self) -> &sgx_dh_session_enclave_identity_t {
+        &self.peer_identity
+    }
+
+    ///
+    /// Seals `plaintext` as the next message to the peer.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_STATE**
+    ///
+    /// The session has sent as many messages as the sequence number allows.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The message is too large.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn seal_message(&mut self, plaintext: &[u8]) -> SgxResult<Vec<u8>> {
+
+        if self.send_seq == u64::max_value() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_STATE);
+        }
+        let seq = self.send_seq;
+        let mut msg = vec![0_u8; plaintext.len() + SESSION_MSG_OVERHEAD];
+        write_seq(&mut msg[..SESSION_MSG_SEQ_SIZE], seq);
+
+        {
+            let (header, body) = msg.split_at_mut(SESSION_MSG_SEQ_SIZE);
+            let (data, tag) = body.split_at_mut(plaintext.len());
+            data.copy_from_slice(plaintext);
+            let nonce = message_nonce(self.role, seq);
+            SgxAesGcm::new(&self.aek).seal_in_place(&nonce, header, data, tag)?;
+        }
+
+        self.send_seq += 1;
+        Ok(msg)
+    }
+
+    ///
+    /// Opens the next message from the peer and returns its plain text.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The message is too short, or it is not the next message from the peer.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The message was not sealed by the peer of this session, or it was modified.
+    ///
+    /// **SGX_ERROR_UNEXPECTED**
+    ///
+    /// An internal cryptography library failure occurred.
+    ///
+    pub fn open_message(&mut self, msg: &[u8]) -> SgxResult<Vec<u8>> {
+
+        if msg.len() < SESSION_MSG_OVERHEAD {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let (header, body) = msg.split_at(SESSION_MSG_SEQ_SIZE);
+        let (data, tag) = body.split_at(body.len() - SESSION_MSG_TAG_SIZE);
+        let seq = read_seq(header);
+        if seq != self.recv_seq {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let mut plaintext = data.to_vec();
+        let nonce = message_nonce(self.role.peer(), seq);
+        SgxAesGcm::new(&self.aek).open_in_place(&nonce, header, &mut plaintext, tag)?;
+
+        self.recv_seq += 1;
+        Ok(plaintext)
+    }
+}
+
+impl Drop for LocalSession {
+    fn drop(&mut self) {
+        // A plain assignment would be a dead store, and could be optimized out.
+        for b in self.aek.iter_mut() {
+            unsafe { ptr::write_volatile(b, 0) };
+        }
+    }
+}
+
+fn message_nonce(sender: SessionRole, seq: u64) -> [u8; SGX_AESGCM_IV_SIZE] {
+    let mut nonce = [0_u8; SGX_AESGCM_IV_SIZE];
+    nonce[..4].copy_from_slice(&sender.nonce_prefix());
+    for i in 0..8 {
+        nonce[4 + i] = (seq >> (56 - i * 8)) as u8;
+    }
+    nonce
+}
+
+fn write_seq(buf: &mut [u8], seq: u64) {
+    for i in 0..SESSION_MSG_SEQ_SIZE {
+        buf[i] = (seq >> (i * 8)) as u8;
+    }
+}
+
+fn read_seq(buf: &[u8]) -> u64 {
+    buf.iter().take(SESSION_MSG_SEQ_SIZE).rev().fold(0_u64, |acc, &b| acc << 8 | b as u64)
+}
+
+///
+/// Converts msg3 to the byte layout of `sgx_dh_msg3_t`, followed by its additional properties,
+/// to hand it to the initiator.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The additional properties of msg3 are too large.
+///
+pub fn rsgx_dh_msg3_to_bytes(msg3: &SgxDhMsg3) -> SgxResult<Vec<u8>> {
+
+    let size = msg3.calc_raw_sealed_data_size();
+    if size == u32::max_value() {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let mut bytes = vec![0_u8; size as usize];
+    let raw = unsafe { msg3.to_raw_dh_msg3_t(bytes.as_mut_ptr() as * mut sgx_dh_msg3_t, size) };
+    match raw {
+        Some(_) => Ok(bytes),
+        None => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+    }
+}
+
+///
+/// Converts bytes produced by `rsgx_dh_msg3_to_bytes` back to msg3.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// The length of `bytes` does not match the additional properties it declares.
+///
+pub fn rsgx_dh_msg3_from_bytes(bytes: &[u8]) -> SgxResult<SgxDhMsg3> {
+
+    let header_size = mem::size_of::<sgx_dh_msg3_t>();
+    if bytes.len() < header_size || bytes.len() > u32::max_value() as usize {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    let header = unsafe { ptr::read_unaligned(bytes.as_ptr() as * const sgx_dh_msg3_t) };
+    if header.msg3_body.additional_prop_length as usize != bytes.len() - header_size {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    // from_raw_dh_msg3_t takes a mutable pointer but only reads through it.
+    let mut raw = bytes.to_vec();
+    let msg3 = unsafe {
+        SgxDhMsg3::from_raw_dh_msg3_t(raw.as_mut_ptr() as * mut sgx_dh_msg3_t, raw.len() as u32)
+    };
+    msg3.ok_or(sgx_status_t::SGX_ERROR_INVALID_PARAMETER)
+}
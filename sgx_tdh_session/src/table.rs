@@ -0,0 +1,259 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_tdh::{SgxDhMsg1, SgxDhMsg2, SgxDhMsg3};
+use std::collections::HashMap;
+use std::sync::{SgxMutex, SgxMutexGuard};
+use std::vec::Vec;
+use policy::SessionPolicy;
+use session::*;
+
+/// The number of sessions a `SessionTable` holds unless told otherwise.
+pub const SESSION_TABLE_DEFAULT_CAPACITY: usize = 16;
+
+enum SessionEntry {
+    Pending(LocalSessionResponder),
+    Active(LocalSession),
+}
+
+struct SessionTableInner {
+    next_id: u32,
+    sessions: HashMap<u32, SessionEntry>,
+}
+
+///
+/// The sessions of an enclave, by session id.
+///
+/// On the responder side, `session_request`, `exchange_report` and `end_session` map onto the
+/// ECALLs of the exchange. Sessions established as initiator can be added with `insert`.
+/// Every peer is checked against the policy of the table.
+///
+pub struct SessionTable<P> {
+    policy: P,
+    capacity: usize,
+    inner: SgxMutex<SessionTableInner>,
+}
+
+impl<P: SessionPolicy> SessionTable<P> {
+
+    ///
+    /// Constructs a table of up to `SESSION_TABLE_DEFAULT_CAPACITY` sessions.
+    ///
+    pub fn new(policy: P) -> Self {
+        SessionTable::with_capacity(policy, SESSION_TABLE_DEFAULT_CAPACITY)
+    }
+
+    ///
+    /// Constructs a table of up to `capacity` sessions, pending ones included.
+    ///
+    pub fn with_capacity(policy: P, capacity: usize) -> Self {
+        SessionTable {
+            policy: policy,
+            capacity: capacity,
+            inner: SgxMutex::new(SessionTableInner {
+                next_id: 1,
+                sessions: HashMap::new(),
+            }),
+        }
+    }
+
+    ///
+    /// The policy peers are checked against.
+    ///
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    ///
+    /// Starts a session as the responder and returns its id with msg1 for the initiator.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_BUSY**
+    ///
+    /// The table is full.
+    ///
+    /// Otherwise, any error of `LocalSessionResponder::new`.
+    ///
+    pub fn session_request(&self) -> SgxResult<(u32, SgxDhMsg1)> {
+        let (responder, msg1) = LocalSessionResponder::new()?;
+        let session_id = self.add(SessionEntry::Pending(responder))?;
+        Ok((session_id, msg1))
+    }
+
+    ///
+    /// Handles msg2 from the initiator of a pending session and returns msg3 for it.
+    /// The session is removed if the exchange fails or the policy rejects the initiator.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// There is no pending session with this id.
+    ///
+    /// Otherwise, any error of `LocalSessionResponder::proc_msg2`.
+    ///
+    pub fn exchange_report(&self, session_id: u32, msg2: &SgxDhMsg2) -> SgxResult<SgxDhMsg3> {
+
+        let mut inner = self.lock()?;
+        let responder = match inner.sessions.remove(&session_id) {
+            Some(SessionEntry::Pending(responder)) => responder,
+            Some(active) => {
+                inner.sessions.insert(session_id, active);
+                return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+            },
+            None => return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        };
+
+        let (msg3, session) = responder.proc_msg2(msg2, &self.policy)?;
+        inner.sessions.insert(session_id, SessionEntry::Active(session));
+        Ok(msg3)
+    }
+
+    ///
+    /// Adds a session established as initiator, after checking the responder against the
+    /// policy of the table, and returns its id.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_ENCLAVE**
+    ///
+    /// The policy rejected the responder.
+    ///
+    /// **SGX_ERROR_BUSY**
+    ///
+    /// The table is full.
+    ///
+    pub fn insert(&self, session: LocalSession) -> SgxResult<u32> {
+        if !self.policy.verify(session.peer_identity()) {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_ENCLAVE);
+        }
+        self.add(SessionEntry::Active(session))
+    }
+
+    ///
+    /// Removes a session, pending or established.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// There is no session with this id.
+    ///
+    pub fn end_session(&self, session_id: u32) -> SgxError {
+        match self.lock()?.sessions.remove(&session_id) {
+            Some(_) => Ok(()),
+            None => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+
+    ///
+    /// Returns the identity of the peer of an established session.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// There is no established session with this id.
+    ///
+    pub fn peer_identity(&self, session_id: u32) -> SgxResult<sgx_dh_session_enclave_identity_t> {
+        self.with_session(session_id, |session| Ok(*session.peer_identity()))
+    }
+
+    ///
+    /// Seals `plaintext` as the next message of an established session.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// There is no established session with this id.
+    ///
+    /// Otherwise, any error of `LocalSession::seal_message`.
+    ///
+    pub fn seal_message(&self, session_id: u32, plaintext: &[u8]) -> SgxResult<Vec<u8>> {
+        self.with_session(session_id, |session| session.seal_message(plaintext))
+    }
+
+    ///
+    /// Opens the next message of an established session.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// There is no established session with this id.
+    ///
+    /// Otherwise, any error of `LocalSession::open_message`.
+    ///
+    pub fn open_message(&self, session_id: u32, msg: &[u8]) -> SgxResult<Vec<u8>> {
+        self.with_session(session_id, |session| session.open_message(msg))
+    }
+
+    ///
+    /// Runs `f` on an established session while holding the table lock.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// There is no established session with this id.
+    ///
+    /// Otherwise, any error returned by `f`.
+    ///
+    pub fn with_session<T, F>(&self, session_id: u32, f: F) -> SgxResult<T>
+        where F: FnOnce(&mut LocalSession) -> SgxResult<T> {
+
+        match self.lock()?.sessions.get_mut(&session_id) {
+            Some(&mut SessionEntry::Active(ref mut session)) => f(session),
+            _ => Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
+        }
+    }
+
+    fn add(&self, entry: SessionEntry) -> SgxResult<u32> {
+
+        let mut inner = self.lock()?;
+        if inner.sessions.len() >= self.capacity {
+            return Err(sgx_status_t::SGX_ERROR_BUSY);
+        }
+
+        // Ids are never 0 and skip those still in use.
+        let mut session_id = inner.next_id;
+        while session_id == 0 || inner.sessions.contains_key(&session_id) {
+            session_id = session_id.wrapping_add(1);
+        }
+        inner.next_id = session_id.wrapping_add(1);
+        inner.sessions.insert(session_id, entry);
+        Ok(session_id)
+    }
+
+    fn lock(&self) -> SgxResult<SgxMutexGuard<SessionTableInner>> {
+        self.inner.lock().map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)
+    }
+}
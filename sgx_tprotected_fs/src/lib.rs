@@ -31,11 +31,20 @@
 //! Intel Protected File System Library provides protected files API for Intel SGX enclaves.
 //! With this API, the files are encrypted and saved on the untrusted disk during a write operation,
 //! and they are verified for confidentiality and integrity during a read operation.
+//!
+//! `SgxFileStream` wraps Intel's library; the `pfs` module implements the same file
+//! format in Rust, over a pluggable storage backend.
 
 #![no_std]
+#![feature(alloc)]
 
 extern crate sgx_types;
 extern crate sgx_trts;
+extern crate sgx_tcrypto;
+extern crate sgx_tse;
+extern crate alloc;
 
 mod fs;
 pub use self::fs::*;
+
+pub mod pfs;
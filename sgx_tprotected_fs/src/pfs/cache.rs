@@ -0,0 +1,118 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! A least-recently-used cache of decrypted nodes, keyed by physical node number.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+pub struct LruCache<V> {
+    entries: BTreeMap<u64, (V, u64)>,
+    order: BTreeMap<u64, u64>,
+    tick: u64,
+}
+
+impl<V> LruCache<V> {
+    pub fn new() -> LruCache<V> {
+        LruCache {
+            entries: BTreeMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Inserts `value` as the most recently used entry.
+    pub fn insert(&mut self, key: u64, value: V) {
+        let tick = self.next_tick();
+        if let Some((_, old)) = self.entries.insert(key, (value, tick)) {
+            self.order.remove(&old);
+        }
+        self.order.insert(tick, key);
+    }
+
+    /// Returns the entry and marks it as the most recently used.
+    pub fn get(&mut self, key: u64) -> Option<&mut V> {
+        let tick = self.next_tick();
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                self.order.remove(&entry.1);
+                self.order.insert(tick, key);
+                entry.1 = tick;
+                Some(&mut entry.0)
+            },
+            None => None,
+        }
+    }
+
+    /// Returns the entry without touching the LRU order.
+    pub fn peek(&self, key: u64) -> Option<&V> {
+        self.entries.get(&key).map(|entry| &entry.0)
+    }
+
+    pub fn peek_mut(&mut self, key: u64) -> Option<&mut V> {
+        self.entries.get_mut(&key).map(|entry| &mut entry.0)
+    }
+
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        match self.entries.remove(&key) {
+            Some((value, tick)) => {
+                self.order.remove(&tick);
+                Some(value)
+            },
+            None => None,
+        }
+    }
+
+    /// The key of the least recently used entry.
+    pub fn lru_key(&self) -> Option<u64> {
+        self.order.values().next().map(|key| *key)
+    }
+
+    pub fn keys(&self) -> Vec<u64> {
+        self.entries.keys().map(|key| *key).collect()
+    }
+
+    pub fn retain<F: FnMut(&V) -> bool>(&mut self, mut f: F) {
+        let keys: Vec<u64> = self.entries.iter()
+            .filter(|&(_, entry)| !f(&entry.0))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+}
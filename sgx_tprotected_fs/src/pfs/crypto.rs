@@ -0,0 +1,138 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Node encryption and key derivation.
+//!
+//! Every node is encrypted with AES-128-GCM under its own random key, with an
+//! all-zero IV and no AAD; the key and tag are kept in the parent node. The key
+//! of the meta data node is derived either from the user supplied key
+//! derivation key, or from the enclave's MRSIGNER seal key.
+
+use sgx_types::*;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_tcrypto::*;
+use sgx_tse::*;
+use pfs::node::*;
+
+const EMPTY_KEY: sgx_aes_gcm_128bit_key_t = [0_u8; SGX_AESGCM_KEY_SIZE];
+const ZERO_IV: [u8; SGX_AESGCM_IV_SIZE] = [0_u8; SGX_AESGCM_IV_SIZE];
+
+const MAX_LABEL_LEN: usize = 64;
+const METADATA_KEY_NAME: &'static [u8] = b"SGX-PROTECTED-FS-METADATA-KEY";
+
+/* same masks as the sgx_tseal defaults */
+const FLAGS_NON_SECURITY_BITS: uint64_t = 0x00FF_FFFF_FFFF_FFC0 | SGX_FLAGS_MODE64BIT | SGX_FLAGS_PROVISION_KEY | SGX_FLAGS_EINITTOKEN_KEY;
+const TSEAL_DEFAULT_FLAGSMASK: uint64_t = !FLAGS_NON_SECURITY_BITS;
+const MISC_NON_SECURITY_BITS: uint32_t = 0x0FFF_FFFF;
+const TSEAL_DEFAULT_MISCMASK: uint32_t = !MISC_NON_SECURITY_BITS;
+
+/// `kdf_input_t`, laid out with its natural C alignment (120 bytes).
+fn kdf_input(label: &[u8], node_number: u64, nonce: &sgx_key_id_t) -> [u8; 120] {
+    let mut buf = [0_u8; 120];
+    put_u32(&mut buf[0..], 1);
+    buf[4..4 + label.len()].copy_from_slice(label);
+    put_u64(&mut buf[4 + MAX_LABEL_LEN + 4..], node_number);
+    buf[80..112].copy_from_slice(&nonce.id);
+    put_u32(&mut buf[112..], 0x80);
+    buf
+}
+
+pub fn to_errno(status: sgx_status_t) -> sys_error_t {
+    status as sys_error_t
+}
+
+/// Returns a fresh random node key.
+pub fn random_key() -> SysResult<sgx_aes_gcm_128bit_key_t> {
+    let mut key = EMPTY_KEY;
+    while key == EMPTY_KEY {
+        try!(rsgx_read_rand(&mut key).map_err(to_errno));
+    }
+    Ok(key)
+}
+
+pub fn encrypt_node(key: &sgx_aes_gcm_128bit_key_t, src: &[u8], dst: &mut [u8]) -> SysResult<sgx_aes_gcm_128bit_tag_t> {
+    let mut gmac = sgx_aes_gcm_128bit_tag_t::default();
+    try!(rsgx_rijndael128GCM_encrypt(key, src, &ZERO_IV, &[], dst, &mut gmac).map_err(to_errno));
+    Ok(gmac)
+}
+
+pub fn decrypt_node(key: &sgx_aes_gcm_128bit_key_t,
+                    src: &[u8],
+                    gmac: &sgx_aes_gcm_128bit_tag_t,
+                    dst: &mut [u8]) -> SysError {
+    rsgx_rijndael128GCM_decrypt(key, src, &ZERO_IV, &[], gmac, dst).map_err(to_errno)
+}
+
+fn derive_user_key(kdk: &sgx_key_128bit_t, key_id: &sgx_key_id_t) -> SysResult<sgx_aes_gcm_128bit_key_t> {
+    let input = kdf_input(METADATA_KEY_NAME, 0, key_id);
+    rsgx_rijndael128_cmac_slice(kdk, &input[..]).map_err(to_errno)
+}
+
+fn seal_key_request(plain: &MetadataPlain) -> sgx_key_request_t {
+    let mut key_request = sgx_key_request_t::default();
+    key_request.key_name = SGX_KEYSELECT_SEAL;
+    key_request.key_policy = SGX_KEYPOLICY_MRSIGNER;
+    key_request.isv_svn = plain.isv_svn;
+    key_request.cpu_svn = plain.cpu_svn;
+    key_request.attribute_mask.flags = TSEAL_DEFAULT_FLAGSMASK;
+    key_request.attribute_mask.xfrm = 0;
+    key_request.key_id = plain.key_id;
+    key_request.misc_mask = TSEAL_DEFAULT_MISCMASK;
+    key_request
+}
+
+/// Re-derives the key the meta data node was last encrypted with.
+pub fn restore_metadata_key(plain: &MetadataPlain,
+                            user_key: Option<&sgx_key_128bit_t>) -> SysResult<sgx_aes_gcm_128bit_key_t> {
+    if plain.key_id.id == [0_u8; SGX_KEYID_SIZE] {
+        return Err(to_errno(sgx_status_t::SGX_ERROR_FILE_NO_KEY_ID));
+    }
+    match user_key {
+        Some(kdk) => derive_user_key(kdk, &plain.key_id),
+        None => rsgx_get_key(&seal_key_request(plain)).map_err(to_errno),
+    }
+}
+
+/// Picks a new key ID, records it (and for auto-key files, the current CPU
+/// and ISV SVN) in `plain`, and derives the matching meta data key.
+pub fn new_metadata_key(plain: &mut MetadataPlain,
+                        user_key: Option<&sgx_key_128bit_t>) -> SysResult<sgx_aes_gcm_128bit_key_t> {
+    try!(rsgx_read_rand(&mut plain.key_id.id).map_err(to_errno));
+    match user_key {
+        Some(kdk) => derive_user_key(kdk, &plain.key_id),
+        None => {
+            let report = try!(rsgx_create_report(&sgx_target_info_t::default(),
+                                                 &sgx_report_data_t::default()).map_err(to_errno));
+            plain.cpu_svn = report.body.cpu_svn;
+            plain.isv_svn = report.body.isv_svn;
+            plain.attribute_mask.flags = TSEAL_DEFAULT_FLAGSMASK;
+            plain.attribute_mask.xfrm = 0;
+            rsgx_get_key(&seal_key_request(plain)).map_err(to_errno)
+        }
+    }
+}
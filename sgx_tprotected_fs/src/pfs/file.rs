@@ -0,0 +1,987 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use sgx_trts::libc;
+use sgx_trts::c_str::{CStr, CString};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp;
+use pfs::node::*;
+use pfs::crypto::*;
+use pfs::cache::LruCache;
+use pfs::storage::*;
+use SeekFrom;
+
+const MAX_PAGES_IN_CACHE: usize = 48;
+const FULLNAME_MAX_LEN: usize = 4096;
+const RECOVERY_FILE_SUFFIX: &'static [u8] = b"_recovery";
+const ROOT_MHT_PHYSICAL_NUMBER: u64 = 1;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum FileStatus {
+    Ok,
+    FlushError,
+    WriteToDiskFailed,
+    CryptoError,
+    Corrupted,
+    Closed,
+}
+
+#[derive(Copy, Clone, Default)]
+struct OpenMode {
+    read: bool,
+    write: bool,
+    append: bool,
+    update: bool,
+}
+
+impl OpenMode {
+    /// Parses an `fopen` mode string: one of ‘r’, ‘w’ or ‘a’, then optionally ‘+’ and ‘b’.
+    fn parse(mode: &CStr) -> SysResult<OpenMode> {
+        let mode = mode.to_bytes();
+        if mode.is_empty() || mode.len() > 3 {
+            return Err(libc::EINVAL);
+        }
+        let mut open_mode = OpenMode::default();
+        match mode[0] {
+            b'r' => open_mode.read = true,
+            b'w' => open_mode.write = true,
+            b'a' => open_mode.append = true,
+            _ => return Err(libc::EINVAL),
+        }
+        let mut binary = false;
+        for &c in &mode[1..] {
+            match c {
+                b'+' if !open_mode.update => open_mode.update = true,
+                b'b' if !binary => binary = true,
+                _ => return Err(libc::EINVAL),
+            }
+        }
+        Ok(open_mode)
+    }
+
+    fn read_only(&self) -> bool {
+        self.read && !self.update
+    }
+
+    fn can_read(&self) -> bool {
+        self.read || self.update
+    }
+
+    fn can_write(&self) -> bool {
+        self.write || self.append || self.update
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum NodeKind {
+    Data,
+    Mht,
+}
+
+struct FileNode {
+    kind: NodeKind,
+    number: u64,
+    physical: u64,
+    plain: Box<NodeBuf>,
+    /// The ciphertext as currently stored, `None` if the node was never written.
+    on_disk: Option<Box<NodeBuf>>,
+    dirty: bool,
+}
+
+impl FileNode {
+    fn new(kind: NodeKind, number: u64, physical: u64) -> FileNode {
+        FileNode {
+            kind: kind,
+            number: number,
+            physical: physical,
+            plain: new_node_buf(),
+            on_disk: None,
+            dirty: false,
+        }
+    }
+}
+
+impl Drop for FileNode {
+    fn drop(&mut self) {
+        for b in self.plain.iter_mut() {
+            *b = 0;
+        }
+    }
+}
+
+/// A protected file, implemented in Rust on top of a `ProtectedStorage`.
+///
+/// Files are byte-compatible with the ones of `SgxFileStream` (Intel's
+/// `sgx_tprotected_fs` library): same node layout, same key derivation, same
+/// recovery journal. The API follows `SgxFileStream`.
+///
+/// Flushes are atomic. Once a file has more than one node, every flush first
+/// saves the nodes it is about to overwrite to `<filename>_recovery` and
+/// raises the update flag of the meta data node; the next open of a file found
+/// with the flag raised puts those nodes back.
+pub struct ProtectedFile<S: ProtectedStorage = HostStorage> {
+    storage: S,
+    file: Option<S::File>,
+    name: CString,
+    recovery_name: CString,
+    mode: OpenMode,
+    user_key: Option<sgx_key_128bit_t>,
+    metadata_key: sgx_aes_gcm_128bit_key_t,
+    plain: MetadataPlain,
+    encrypted: MetadataEncrypted,
+    metadata_on_disk: Option<Box<NodeBuf>>,
+    metadata_dirty: bool,
+    root: FileNode,
+    cache: LruCache<FileNode>,
+    offset: u64,
+    eof: bool,
+    status: FileStatus,
+    last_error: sys_error_t,
+    journal_written: bool,
+}
+
+impl ProtectedFile<HostStorage> {
+
+    ///
+    /// Creates or opens a protected file whose keys are derived from `key`.
+    ///
+    /// # Parameters
+    ///
+    /// **filename**
+    ///
+    /// The name of the file to be created or opened.
+    ///
+    /// **mode**
+    ///
+    /// The file open mode string. Allowed values are any combination of ‘r’, ‘w’ or ‘a’, with possible ‘+’
+    /// and possible ‘b’.
+    ///
+    /// **key**
+    ///
+    /// The key derivation key of the file. It has to be provided every time the file is opened.
+    ///
+    /// # Errors
+    ///
+    /// **EINVAL**
+    ///
+    /// The mode is invalid, or the file was created with an automatic key.
+    ///
+    /// **ENOENT**
+    ///
+    /// The mode is ‘r’ and the file does not exist.
+    ///
+    /// **SGX_ERROR_FILE_NOT_SGX_FILE**
+    ///
+    /// The file is not a protected file.
+    ///
+    /// **SGX_ERROR_FILE_NAME_MISMATCH**
+    ///
+    /// The file was created under another name.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The key is wrong or the meta data was tampered with.
+    ///
+    /// **SGX_ERROR_FILE_RECOVERY_NEEDED**
+    ///
+    /// The file was left in the middle of a flush and could not be recovered.
+    ///
+    pub fn open(filename: &CStr, mode: &CStr, key: &sgx_key_128bit_t) -> SysResult<ProtectedFile> {
+        ProtectedFile::open_internal(HostStorage, filename, mode, Some(key), None, None)
+    }
+
+    ///
+    /// Creates or opens a protected file whose keys are derived from the enclave's
+    /// MRSIGNER seal key.
+    ///
+    /// See `open` for the parameters and errors.
+    ///
+    pub fn open_auto_key(filename: &CStr, mode: &CStr) -> SysResult<ProtectedFile> {
+        ProtectedFile::open_internal(HostStorage, filename, mode, None, None, None)
+    }
+}
+
+impl<S: ProtectedStorage> ProtectedFile<S> {
+
+    ///
+    /// Same as `open`, with the encrypted file kept in `storage` instead of
+    /// the host file system.
+    ///
+    pub fn open_in(storage: S, filename: &CStr, mode: &CStr, key: &sgx_key_128bit_t) -> SysResult<ProtectedFile<S>> {
+        ProtectedFile::open_internal(storage, filename, mode, Some(key), None, None)
+    }
+
+    ///
+    /// Same as `open_auto_key`, with the encrypted file kept in `storage`
+    /// instead of the host file system.
+    ///
+    pub fn open_auto_key_in(storage: S, filename: &CStr, mode: &CStr) -> SysResult<ProtectedFile<S>> {
+        ProtectedFile::open_internal(storage, filename, mode, None, None, None)
+    }
+
+    // `new_name`, if any, is the clean name the file is being renamed to: the file may already
+    // carry it, and carries it after the next flush.
+    fn open_internal(storage: S,
+                     filename: &CStr,
+                     mode: &CStr,
+                     user_key: Option<&sgx_key_128bit_t>,
+                     import_key: Option<&sgx_key_128bit_t>,
+                     new_name: Option<&[u8]>) -> SysResult<ProtectedFile<S>> {
+
+        let mode = try!(OpenMode::parse(mode));
+        let name = filename.to_bytes();
+        let clean_name = try!(clean_name(name));
+        let mut recovery_name = name.to_vec();
+        recovery_name.extend_from_slice(RECOVERY_FILE_SUFFIX);
+        let recovery_name = try!(CString::new(recovery_name).map_err(|_| libc::EINVAL));
+
+        let mut exists = try!(storage.exists(filename));
+        if mode.read && !exists {
+            return Err(libc::ENOENT);
+        }
+        if mode.write && exists {
+            try!(storage.remove(filename));
+            exists = false;
+        }
+
+        let (file, size) = try!(storage.open(filename, mode.read_only()));
+        if size % NODE_SIZE as u64 != 0 {
+            return Err(to_errno(sgx_status_t::SGX_ERROR_FILE_NOT_SGX_FILE));
+        }
+
+        let mut pfile = ProtectedFile {
+            storage: storage,
+            file: Some(file),
+            name: filename.to_owned(),
+            recovery_name: recovery_name,
+            mode: mode,
+            user_key: user_key.map(|key| *key),
+            metadata_key: sgx_aes_gcm_128bit_key_t::default(),
+            plain: MetadataPlain::new(user_key.is_some()),
+            encrypted: MetadataEncrypted::new(),
+            metadata_on_disk: None,
+            metadata_dirty: false,
+            root: FileNode::new(NodeKind::Mht, 0, ROOT_MHT_PHYSICAL_NUMBER),
+            cache: LruCache::new(),
+            offset: 0,
+            eof: false,
+            status: FileStatus::Ok,
+            last_error: 0,
+            journal_written: false,
+        };
+
+        if exists && size > 0 {
+            try!(pfile.init_existing_file(clean_name, import_key, new_name));
+        } else {
+            pfile.set_clean_name(new_name.unwrap_or(clean_name));
+        }
+        if mode.append {
+            pfile.offset = pfile.size();
+        }
+        Ok(pfile)
+    }
+
+    fn init_existing_file(&mut self,
+                          clean_name: &[u8],
+                          import_key: Option<&sgx_key_128bit_t>,
+                          new_name: Option<&[u8]>) -> SysError {
+
+        let mut node = new_node_buf();
+        try!(self.file_mut().and_then(|f| f.read_node(0, &mut node[..])));
+        let mut plain = MetadataPlain::parse(&node);
+        if plain.file_id != SGX_FILE_ID || plain.major_version != SGX_FILE_MAJOR_VERSION {
+            return Err(to_errno(sgx_status_t::SGX_ERROR_FILE_NOT_SGX_FILE));
+        }
+
+        if plain.update_flag != 0 {
+            // a flush was interrupted, put back the nodes it had overwritten
+            self.file = None;
+            try!(self.storage.recover(&self.name, &self.recovery_name)
+                .map_err(|_| to_errno(sgx_status_t::SGX_ERROR_FILE_RECOVERY_NEEDED)));
+            let (file, _) = try!(self.storage.open(&self.name, self.mode.read_only()));
+            self.file = Some(file);
+            try!(self.file_mut().and_then(|f| f.read_node(0, &mut node[..])));
+            plain = MetadataPlain::parse(&node);
+            if plain.update_flag != 0 {
+                return Err(to_errno(sgx_status_t::SGX_ERROR_FILE_RECOVERY_NEEDED));
+            }
+        }
+
+        if (plain.use_user_kdk_key != 0) != self.user_key.is_some() {
+            return Err(libc::EINVAL);
+        }
+        let key = match import_key {
+            Some(key) => *key,
+            None => try!(restore_metadata_key(&plain, self.user_key.as_ref())),
+        };
+
+        let mut clear = new_node_buf();
+        let result = decrypt_node(&key, encrypted_part(&node), &plain.gmac, &mut clear[..MD_ENCRYPTED_SIZE]);
+        if result.is_ok() {
+            self.encrypted = MetadataEncrypted::parse(&clear[..MD_ENCRYPTED_SIZE]);
+        }
+        for b in clear.iter_mut() {
+            *b = 0;
+        }
+        try!(result);
+
+        let renamed = new_name.map_or(false, |name| self.encrypted.name_matches(name));
+        if !renamed && !self.encrypted.name_matches(clean_name) {
+            return Err(to_errno(sgx_status_t::SGX_ERROR_FILE_NAME_MISMATCH));
+        }
+        if self.encrypted.size < 0 {
+            return Err(to_errno(sgx_status_t::SGX_ERROR_FILE_NOT_SGX_FILE));
+        }
+
+        if self.size() > MD_USER_DATA_SIZE as u64 {
+            let mut cipher = new_node_buf();
+            try!(self.file_mut().and_then(|f| f.read_node(ROOT_MHT_PHYSICAL_NUMBER, &mut cipher[..])));
+            try!(decrypt_node(&self.encrypted.mht_key, &cipher[..], &self.encrypted.mht_gmac, &mut self.root.plain[..]));
+            self.root.on_disk = Some(cipher);
+        }
+
+        self.plain = plain;
+        self.metadata_key = key;
+        self.metadata_on_disk = Some(node);
+        // an imported file is re-encrypted under this enclave's key on the next flush
+        self.metadata_dirty = import_key.is_some();
+        if let Some(name) = new_name {
+            if !renamed {
+                self.set_clean_name(name);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_clean_name(&mut self, name: &[u8]) {
+        self.encrypted.clean_filename = [0_u8; FILENAME_MAX_LEN];
+        self.encrypted.clean_filename[..name.len()].copy_from_slice(name);
+        self.metadata_dirty = true;
+    }
+
+    fn file_mut(&mut self) -> SysResult<&mut S::File> {
+        self.file.as_mut().ok_or(libc::EBADF)
+    }
+
+    fn size(&self) -> u64 {
+        self.encrypted.size as u64
+    }
+
+    fn fail<T>(&mut self, error: sys_error_t) -> SysResult<T> {
+        self.last_error = error;
+        Err(error)
+    }
+
+    fn check_status(&mut self) -> SysError {
+        if self.status == FileStatus::Ok {
+            Ok(())
+        } else {
+            self.fail(to_errno(sgx_status_t::SGX_ERROR_FILE_BAD_STATUS))
+        }
+    }
+
+    fn node_mut(&mut self, physical: u64) -> SysResult<&mut FileNode> {
+        if physical == ROOT_MHT_PHYSICAL_NUMBER {
+            Ok(&mut self.root)
+        } else {
+            self.cache.peek_mut(physical).ok_or(to_errno(sgx_status_t::SGX_ERROR_UNEXPECTED))
+        }
+    }
+
+    /// Reads and authenticates a node, with the key and tag kept by its parent.
+    fn load_node(&mut self, node: &mut FileNode, parent: u64, index: usize) -> SysError {
+        let entry = mht_entry(&try!(self.node_mut(parent)).plain, index);
+        let mut cipher = new_node_buf();
+        try!(self.file_mut().and_then(|f| f.read_node(node.physical, &mut cipher[..])));
+        if let Err(error) = decrypt_node(&entry.key, &cipher[..], &entry.gmac, &mut node.plain[..]) {
+            self.status = FileStatus::Corrupted;
+            return Err(error);
+        }
+        node.on_disk = Some(cipher);
+        Ok(())
+    }
+
+    /// Brings an MHT node, and all of its ancestors, into the cache.
+    fn get_mht_node(&mut self, number: u64) -> SysResult<u64> {
+        if number == 0 {
+            return Ok(ROOT_MHT_PHYSICAL_NUMBER);
+        }
+        let physical = mht_node_physical_number(number);
+        if self.cache.get(physical).is_some() {
+            return Ok(physical);
+        }
+        let (parent_number, index) = mht_node_parent(number);
+        let parent = try!(self.get_mht_node(parent_number));
+        let mut node = FileNode::new(NodeKind::Mht, number, physical);
+        if mht_node_offset(number) < self.size() {
+            try!(self.load_node(&mut node, parent, index));
+        }
+        self.cache.insert(physical, node);
+        Ok(physical)
+    }
+
+    /// Brings a data node, and the MHT nodes above it, into the cache.
+    fn get_data_node(&mut self, number: u64) -> SysResult<u64> {
+        let physical = data_node_physical_number(number);
+        let (parent_number, index) = data_node_parent(number);
+        if self.cache.get(physical).is_none() {
+            let parent = try!(self.get_mht_node(parent_number));
+            let mut node = FileNode::new(NodeKind::Data, number, physical);
+            if data_node_offset(number) < self.size() {
+                try!(self.load_node(&mut node, parent, index));
+            }
+            self.cache.insert(physical, node);
+        }
+        // keep the ancestors more recently used than the node, so they are never evicted first
+        let mut mht = parent_number;
+        while mht != 0 {
+            self.cache.get(mht_node_physical_number(mht));
+            mht = mht_node_parent(mht).0;
+        }
+        Ok(physical)
+    }
+
+    /// Marks a data node and the whole path to the root as modified.
+    fn set_data_node_dirty(&mut self, number: u64) -> SysError {
+        try!(self.node_mut(data_node_physical_number(number))).dirty = true;
+        let mut mht = data_node_parent(number).0;
+        while mht != 0 {
+            try!(self.node_mut(mht_node_physical_number(mht))).dirty = true;
+            mht = mht_node_parent(mht).0;
+        }
+        self.root.dirty = true;
+        Ok(())
+    }
+
+    fn shrink_cache(&mut self) -> SysError {
+        while self.cache.len() > MAX_PAGES_IN_CACHE {
+            let lru = match self.cache.lru_key() {
+                Some(key) => key,
+                None => break,
+            };
+            if self.cache.peek(lru).map_or(false, |node| node.dirty) {
+                try!(self.internal_flush());
+            }
+            self.cache.remove(lru);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Reads data from the file into `buf`, and advances the position by that amount.
+    ///
+    /// # Return value
+    ///
+    /// The number of bytes read. Zero indicates end of file.
+    ///
+    pub fn read(&mut self, buf: &mut [u8]) -> SysResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        try!(self.check_status());
+        if !self.mode.can_read() {
+            return self.fail(libc::EACCES);
+        }
+        if self.eof {
+            return Ok(0);
+        }
+
+        let remaining = self.size() - self.offset;
+        if (buf.len() as u64) > remaining {
+            self.eof = true;
+        }
+        let len = cmp::min(buf.len() as u64, remaining) as usize;
+        let mut done = 0;
+
+        if self.offset < MD_USER_DATA_SIZE as u64 {
+            let start = self.offset as usize;
+            let n = cmp::min(len, MD_USER_DATA_SIZE - start);
+            buf[..n].copy_from_slice(&self.encrypted.data[start..start + n]);
+            done += n;
+            self.offset += n as u64;
+        }
+
+        while done < len {
+            let pos = self.offset - MD_USER_DATA_SIZE as u64;
+            let number = pos / NODE_SIZE as u64;
+            let start = (pos % NODE_SIZE as u64) as usize;
+            let n = cmp::min(len - done, NODE_SIZE - start);
+            let physical = match self.get_data_node(number) {
+                Ok(physical) => physical,
+                Err(error) => return self.fail(error),
+            };
+            buf[done..done + n].copy_from_slice(&try!(self.node_mut(physical)).plain[start..start + n]);
+            done += n;
+            self.offset += n as u64;
+            if let Err(error) = self.shrink_cache() {
+                return self.fail(error);
+            }
+        }
+        Ok(done)
+    }
+
+    ///
+    /// Writes `buf` to the file, and advances the position by that amount. In append
+    /// mode, the data always goes to the end of the file.
+    ///
+    /// # Return value
+    ///
+    /// The number of bytes written.
+    ///
+    pub fn write(&mut self, buf: &[u8]) -> SysResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        try!(self.check_status());
+        if !self.mode.can_write() {
+            return self.fail(libc::EACCES);
+        }
+        if self.mode.append {
+            self.offset = self.size();
+        }
+
+        let len = buf.len();
+        let mut done = 0;
+
+        if self.offset < MD_USER_DATA_SIZE as u64 {
+            let start = self.offset as usize;
+            let n = cmp::min(len, MD_USER_DATA_SIZE - start);
+            self.encrypted.data[start..start + n].copy_from_slice(&buf[..n]);
+            done += n;
+            self.advance(n);
+        }
+
+        while done < len {
+            let pos = self.offset - MD_USER_DATA_SIZE as u64;
+            let number = pos / NODE_SIZE as u64;
+            let start = (pos % NODE_SIZE as u64) as usize;
+            let n = cmp::min(len - done, NODE_SIZE - start);
+            let result = self.get_data_node(number).and_then(|physical| {
+                try!(self.node_mut(physical)).plain[start..start + n].copy_from_slice(&buf[done..done + n]);
+                self.set_data_node_dirty(number)
+            });
+            if let Err(error) = result {
+                return self.fail(error);
+            }
+            done += n;
+            self.advance(n);
+            if let Err(error) = self.shrink_cache() {
+                return self.fail(error);
+            }
+        }
+        Ok(done)
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.offset += n as u64;
+        if self.offset > self.size() {
+            self.encrypted.size = self.offset as i64;
+        }
+        self.metadata_dirty = true;
+    }
+
+    ///
+    /// Returns the current position in the file.
+    ///
+    pub fn tell(&mut self) -> SysResult<i64> {
+        try!(self.check_status());
+        Ok(self.offset as i64)
+    }
+
+    ///
+    /// Sets the position in the file. Seeking past the end of the file is not allowed.
+    ///
+    /// # Errors
+    ///
+    /// **EINVAL**
+    ///
+    /// The new position would be negative or past the end of the file.
+    ///
+    pub fn seek(&mut self, offset: i64, origin: SeekFrom) -> SysError {
+        try!(self.check_status());
+        let base = match origin {
+            SeekFrom::Start => 0,
+            SeekFrom::Current => self.offset as i64,
+            SeekFrom::End => self.size() as i64,
+        };
+        match base.checked_add(offset) {
+            Some(pos) if pos >= 0 && pos as u64 <= self.size() => {
+                self.offset = pos as u64;
+                self.eof = false;
+                Ok(())
+            },
+            _ => self.fail(libc::EINVAL),
+        }
+    }
+
+    ///
+    /// Writes all modified nodes to the storage. When it returns successfully, the
+    /// changes are committed.
+    ///
+    pub fn flush(&mut self) -> SysError {
+        try!(self.check_status());
+        match self.internal_flush() {
+            Ok(()) => Ok(()),
+            Err(error) => self.fail(error),
+        }
+    }
+
+    ///
+    /// Returns the latest operation error code. 0 indicates that no errors occurred.
+    ///
+    pub fn error(&self) -> sys_error_t {
+        self.last_error
+    }
+
+    ///
+    /// Tells whether a previous read hit the end of the file.
+    ///
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    ///
+    /// Attempts to repair a bad file status, and clears the end-of-file flag.
+    ///
+    /// A failed flush is retried. Errors from a corrupted file, like decryption
+    /// errors, cannot be repaired.
+    ///
+    pub fn clearerr(&mut self) {
+        match self.status {
+            FileStatus::FlushError | FileStatus::WriteToDiskFailed => {
+                if self.internal_flush().is_ok() {
+                    self.status = FileStatus::Ok;
+                }
+            },
+            _ => {},
+        }
+        if self.status == FileStatus::Ok {
+            self.last_error = 0;
+            self.eof = false;
+        }
+    }
+
+    ///
+    /// Flushes the file, then drops all the decrypted nodes from the cache.
+    ///
+    pub fn clear_cache(&mut self) -> SysError {
+        try!(self.flush());
+        self.cache.retain(|node| node.dirty);
+        Ok(())
+    }
+
+    ///
+    /// Flushes and closes the file.
+    ///
+    pub fn close(mut self) -> SysError {
+        self.close_internal()
+    }
+
+    fn close_internal(&mut self) -> SysError {
+        if self.status == FileStatus::Closed {
+            return Ok(());
+        }
+        let result = if self.status == FileStatus::Ok {
+            self.internal_flush()
+        } else {
+            Err(to_errno(sgx_status_t::SGX_ERROR_FILE_BAD_STATUS))
+        };
+        self.file = None;
+        if result.is_ok() && self.journal_written {
+            let _ = self.storage.remove(&self.recovery_name);
+        }
+        self.status = FileStatus::Closed;
+        result
+    }
+
+    fn internal_flush(&mut self) -> SysError {
+        if !self.metadata_dirty && !self.root.dirty {
+            return Ok(());
+        }
+
+        if self.root.dirty && self.metadata_on_disk.is_some() {
+            if let Err(error) = self.write_recovery_file().and_then(|_| self.set_update_flag()) {
+                self.status = FileStatus::FlushError;
+                return Err(error);
+            }
+        }
+
+        let writes = match self.update_all_nodes() {
+            Ok(writes) => writes,
+            Err(error) => {
+                let _ = self.clear_update_flag();
+                self.status = FileStatus::CryptoError;
+                return Err(error);
+            },
+        };
+
+        if let Err(error) = self.write_all_changes(writes) {
+            self.status = FileStatus::WriteToDiskFailed;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Saves the current content of every node about to be overwritten.
+    fn write_recovery_file(&mut self) -> SysError {
+        let mut journal = try!(self.storage.create_recovery_file(&self.recovery_name));
+        self.journal_written = true;
+
+        let mut record = Vec::with_capacity(RECOVERY_NODE_SIZE);
+        record.resize(RECOVERY_NODE_SIZE, 0);
+
+        let root = &self.root;
+        let nodes = self.cache.keys();
+        let cached = nodes.iter().filter_map(|key| self.cache.peek(*key));
+        for node in cached.chain(Some(root)).filter(|node| node.dirty) {
+            if let Some(ref on_disk) = node.on_disk {
+                put_u64(&mut record[..8], node.physical);
+                record[8..].copy_from_slice(&on_disk[..]);
+                try!(journal.write_record(&record));
+            }
+        }
+        if let Some(ref on_disk) = self.metadata_on_disk {
+            put_u64(&mut record[..8], 0);
+            record[8..].copy_from_slice(&on_disk[..]);
+            try!(journal.write_record(&record));
+        }
+        Ok(())
+    }
+
+    fn set_update_flag(&mut self) -> SysError {
+        let mut node = new_node_buf();
+        node.copy_from_slice(&try!(self.metadata_on_disk.as_ref().ok_or(libc::EINVAL))[..]);
+        set_update_flag(&mut node, true);
+        let file = try!(self.file_mut());
+        try!(file.write_node(0, &node[..]));
+        file.flush()
+    }
+
+    fn clear_update_flag(&mut self) -> SysError {
+        let mut node = new_node_buf();
+        match self.metadata_on_disk {
+            Some(ref on_disk) => node.copy_from_slice(&on_disk[..]),
+            None => return Ok(()),
+        }
+        let file = try!(self.file_mut());
+        try!(file.write_node(0, &node[..]));
+        file.flush()
+    }
+
+    /// Encrypts every modified node under a fresh key, children before parents, and
+    /// the meta data node last. Returns what has to be written, in order.
+    fn update_all_nodes(&mut self) -> SysResult<Vec<(u64, Box<NodeBuf>)>> {
+        let mut writes = Vec::new();
+
+        let mut dirty: Vec<(NodeKind, u64, u64)> = Vec::new();
+        for key in self.cache.keys() {
+            if let Some(node) = self.cache.peek(key) {
+                if node.dirty {
+                    dirty.push((node.kind, node.number, node.physical));
+                }
+            }
+        }
+        // data nodes first, then MHT nodes from the deepest up
+        dirty.sort_by(|a, b| match (a.0, b.0) {
+            (NodeKind::Data, NodeKind::Mht) => cmp::Ordering::Less,
+            (NodeKind::Mht, NodeKind::Data) => cmp::Ordering::Greater,
+            _ => b.1.cmp(&a.1),
+        });
+
+        for &(kind, number, physical) in dirty.iter() {
+            let (parent, index) = match kind {
+                NodeKind::Data => data_node_parent(number),
+                NodeKind::Mht => mht_node_parent(number),
+            };
+            let (entry, cipher) = try!(self.encrypt(physical));
+            set_mht_entry(&mut try!(self.node_mut(mht_node_physical_number(parent))).plain, index, &entry);
+            writes.push((physical, cipher));
+        }
+
+        if self.root.dirty {
+            let (entry, cipher) = try!(self.encrypt(ROOT_MHT_PHYSICAL_NUMBER));
+            self.encrypted.mht_key = entry.key;
+            self.encrypted.mht_gmac = entry.gmac;
+            writes.push((ROOT_MHT_PHYSICAL_NUMBER, cipher));
+        }
+
+        let key = try!(new_metadata_key(&mut self.plain, self.user_key.as_ref()));
+        let mut clear = new_node_buf();
+        self.encrypted.write(&mut clear[..MD_ENCRYPTED_SIZE]);
+        let mut node = new_node_buf();
+        let result = encrypt_node(&key, &clear[..MD_ENCRYPTED_SIZE], encrypted_part_mut(&mut node));
+        for b in clear.iter_mut() {
+            *b = 0;
+        }
+        self.plain.gmac = try!(result);
+        self.plain.update_flag = 0;
+        self.plain.write(&mut node);
+        self.metadata_key = key;
+        writes.push((0, node));
+
+        Ok(writes)
+    }
+
+    fn encrypt(&mut self, physical: u64) -> SysResult<(GcmCryptoData, Box<NodeBuf>)> {
+        let key = try!(random_key());
+        let mut cipher = new_node_buf();
+        let gmac = try!(encrypt_node(&key, &try!(self.node_mut(physical)).plain[..], &mut cipher[..]));
+        Ok((GcmCryptoData { key: key, gmac: gmac }, cipher))
+    }
+
+    fn write_all_changes(&mut self, writes: Vec<(u64, Box<NodeBuf>)>) -> SysError {
+        {
+            let file = try!(self.file_mut());
+            for &(physical, ref cipher) in writes.iter() {
+                try!(file.write_node(physical, &cipher[..]));
+            }
+            try!(file.flush());
+        }
+        for (physical, cipher) in writes {
+            if physical == 0 {
+                self.metadata_on_disk = Some(cipher);
+            } else {
+                let node = try!(self.node_mut(physical));
+                node.on_disk = Some(cipher);
+                node.dirty = false;
+            }
+        }
+        self.metadata_dirty = false;
+        Ok(())
+    }
+}
+
+// The last component of a file name, which is recorded in the meta data node. Both '/' and
+// '\' separate components, as in the Intel library.
+fn clean_name(name: &[u8]) -> SysResult<&[u8]> {
+    if name.is_empty() {
+        return Err(libc::EINVAL);
+    }
+    if name.len() >= FULLNAME_MAX_LEN {
+        return Err(libc::ENAMETOOLONG);
+    }
+    let clean_name = match name.iter().rposition(|&c| c == b'/' || c == b'\\') {
+        Some(pos) => &name[pos + 1..],
+        None => name,
+    };
+    if clean_name.is_empty() {
+        return Err(libc::EINVAL);
+    }
+    if clean_name.len() >= FILENAME_MAX_LEN {
+        return Err(libc::ENAMETOOLONG);
+    }
+    Ok(clean_name)
+}
+
+impl<S: ProtectedStorage> Drop for ProtectedFile<S> {
+    fn drop(&mut self) {
+        // Errors are ignored, call close to get them.
+        let _ = self.close_internal();
+        self.metadata_key = sgx_aes_gcm_128bit_key_t::default();
+    }
+}
+
+///
+/// Deletes a protected file.
+///
+pub fn remove(filename: &CStr) -> SysError {
+    remove_in(&HostStorage, filename)
+}
+
+///
+/// Deletes a protected file from `storage`.
+///
+pub fn remove_in<S: ProtectedStorage>(storage: &S, filename: &CStr) -> SysError {
+    storage.remove(filename)
+}
+
+///
+/// Returns the key the meta data node of an auto-key file is currently
+/// encrypted with, so the file can be imported in another enclave or platform
+/// with `import_auto_key`.
+///
+pub fn export_auto_key(filename: &CStr) -> SysResult<sgx_key_128bit_t> {
+    export_auto_key_in(HostStorage, filename)
+}
+
+///
+/// Same as `export_auto_key`, for a file kept in `storage`.
+///
+pub fn export_auto_key_in<S: ProtectedStorage>(storage: S, filename: &CStr) -> SysResult<sgx_key_128bit_t> {
+    let mode = CStr::from_bytes_with_nul(b"r\0").unwrap();
+    let file = try!(ProtectedFile::open_internal(storage, filename, mode, None, None, None));
+    Ok(file.metadata_key)
+}
+
+///
+/// Imports an auto-key file exported with `export_auto_key`: it is opened with
+/// `key` and re-encrypted under this enclave's seal key.
+///
+pub fn import_auto_key(filename: &CStr, key: &sgx_key_128bit_t) -> SysError {
+    import_auto_key_in(HostStorage, filename, key)
+}
+
+///
+/// Same as `import_auto_key`, for a file kept in `storage`.
+///
+pub fn import_auto_key_in<S: ProtectedStorage>(storage: S, filename: &CStr, key: &sgx_key_128bit_t) -> SysError {
+    let mode = CStr::from_bytes_with_nul(b"r+\0").unwrap();
+    let file = try!(ProtectedFile::open_internal(storage, filename, mode, None, Some(key), None));
+    file.close()
+}
+
+///
+/// Records `new_name` as the name of the protected file `filename`, so that it
+/// opens under `new_name` once the storage has moved it there.
+///
+/// The name of a protected file is part of its encrypted meta data; only the
+/// meta data node is re-encrypted, whatever the size of the file. A file which
+/// already carries `new_name` is left as it is, so a rename interrupted before
+/// the move is completed by running it again.
+///
+/// `key` is the key of the file, `None` for an auto-key file.
+///
+pub fn rename_meta(filename: &CStr, new_name: &CStr, key: Option<&sgx_key_128bit_t>) -> SysError {
+    rename_meta_in(HostStorage, filename, new_name, key)
+}
+
+///
+/// Same as `rename_meta`, for a file kept in `storage`.
+///
+pub fn rename_meta_in<S: ProtectedStorage>(storage: S,
+                                           filename: &CStr,
+                                           new_name: &CStr,
+                                           key: Option<&sgx_key_128bit_t>) -> SysError {
+    let mode = CStr::from_bytes_with_nul(b"r+\0").unwrap();
+    let new_name = try!(clean_name(new_name.to_bytes()));
+    let file = try!(ProtectedFile::open_internal(storage, filename, mode, key, None, Some(new_name)));
+    file.close()
+}
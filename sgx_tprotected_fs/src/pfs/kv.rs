@@ -0,0 +1,292 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Protected files on an untrusted key/value store.
+//!
+//! A file `name` is kept as one object per node, under the key `name`, a NUL
+//! byte and the node number in 8 little-endian bytes, and a header object
+//! under the key `name` itself holding the number of nodes. Recovery journals
+//! use the same layout, with one object per record. The store itself lives
+//! outside the enclave, behind the `sgx_kvstore.edl` OCALLs.
+
+use sgx_types::*;
+use sgx_trts::libc::{self, c_int, size_t};
+use sgx_trts::c_str::CStr;
+use alloc::collections::BTreeSet;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use pfs::node::{NODE_SIZE, RECOVERY_NODE_SIZE, get_u64, put_u64};
+use pfs::crypto::to_errno;
+use pfs::storage::{StorageFile, RecoveryFile, ProtectedStorage};
+
+const HEADER_SIZE: usize = 8;
+
+/// Files on the key/value store served by the untrusted application.
+///
+/// Objects are only ever written whole, and a node is written before the
+/// header which counts it, so a store with atomic puts never shows a torn
+/// node. Opening is exclusive among clones of the same `KvStorage`; the store
+/// does not lock objects, so separate instances, or other enclaves, sharing a
+/// store must not open the same file at the same time.
+#[derive(Clone, Default)]
+pub struct KvStorage {
+    locked: Rc<RefCell<BTreeSet<Vec<u8>>>>,
+}
+
+pub struct KvFile {
+    storage: KvStorage,
+    name: Vec<u8>,
+    nodes: u64,
+    read_only: bool,
+}
+
+pub struct KvRecoveryFile {
+    name: Vec<u8>,
+    records: u64,
+}
+
+fn item_key(name: &[u8], number: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(name.len() + 1 + HEADER_SIZE);
+    key.extend_from_slice(name);
+    key.push(0);
+    key.resize(name.len() + 1 + HEADER_SIZE, 0);
+    put_u64(&mut key[name.len() + 1..], number);
+    key
+}
+
+fn check_ocall(status: sgx_status_t) -> SysError {
+    match status {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(to_errno(status)),
+    }
+}
+
+fn store_error(error: c_int) -> sys_error_t {
+    if error > 0 { error } else { libc::EIO }
+}
+
+// Reads the object `key` into `buf`, which must be its exact size.
+fn get(key: &[u8], buf: &mut [u8]) -> SysResult<bool> {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    let mut value_len: size_t = 0;
+    try!(check_ocall(unsafe {
+        u_kvstore_get_ocall(&mut result, &mut error, key.as_ptr(), key.len(),
+                            buf.as_mut_ptr(), buf.len(), &mut value_len)
+    }));
+    if result != 0 {
+        return if error == libc::ENOENT { Ok(false) } else { Err(store_error(error)) };
+    }
+    if value_len != buf.len() {
+        return Err(libc::EIO);
+    }
+    Ok(true)
+}
+
+fn put(key: &[u8], value: &[u8]) -> SysError {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    try!(check_ocall(unsafe {
+        u_kvstore_put_ocall(&mut result, &mut error, key.as_ptr(), key.len(), value.as_ptr(), value.len())
+    }));
+    if result == 0 { Ok(()) } else { Err(store_error(error)) }
+}
+
+fn delete(key: &[u8]) -> SysError {
+    let mut result: c_int = 0;
+    let mut error: c_int = 0;
+    try!(check_ocall(unsafe { u_kvstore_delete_ocall(&mut result, &mut error, key.as_ptr(), key.len()) }));
+    if result == 0 { Ok(()) } else { Err(store_error(error)) }
+}
+
+fn get_count(name: &[u8]) -> SysResult<Option<u64>> {
+    let mut header = [0_u8; HEADER_SIZE];
+    let found = try!(get(name, &mut header));
+    Ok(if found { Some(get_u64(&header)) } else { None })
+}
+
+fn put_count(name: &[u8], count: u64) -> SysError {
+    let mut header = [0_u8; HEADER_SIZE];
+    put_u64(&mut header, count);
+    put(name, &header)
+}
+
+// Writes node `number` of `name`, which has `nodes` nodes, and returns the new count.
+fn put_node(name: &[u8], nodes: u64, number: u64, buf: &[u8]) -> SysResult<u64> {
+    try!(put(&item_key(name, number), buf));
+    if number < nodes {
+        return Ok(nodes);
+    }
+    try!(put_count(name, number + 1));
+    Ok(number + 1)
+}
+
+// Deletes the header first, so that a crash part way leaves unreachable
+// objects rather than a file with missing nodes.
+fn remove_object(name: &[u8]) -> SysError {
+    let count = try!(try!(get_count(name)).ok_or(libc::ENOENT));
+    try!(delete(name));
+    for number in 0..count {
+        let _ = delete(&item_key(name, number));
+    }
+    Ok(())
+}
+
+impl KvStorage {
+    pub fn new() -> KvStorage {
+        KvStorage::default()
+    }
+}
+
+impl StorageFile for KvFile {
+    fn read_node(&mut self, number: u64, buf: &mut [u8]) -> SysError {
+        if buf.len() != NODE_SIZE {
+            return Err(libc::EINVAL);
+        }
+        if number >= self.nodes {
+            return Err(libc::EIO);
+        }
+        let found = try!(get(&item_key(&self.name, number), buf));
+        if found { Ok(()) } else { Err(libc::EIO) }
+    }
+
+    fn write_node(&mut self, number: u64, buf: &[u8]) -> SysError {
+        if self.read_only {
+            return Err(libc::EBADF);
+        }
+        if buf.len() != NODE_SIZE {
+            return Err(libc::EINVAL);
+        }
+        self.nodes = try!(put_node(&self.name, self.nodes, number, buf));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> SysError {
+        Ok(())
+    }
+}
+
+impl Drop for KvFile {
+    fn drop(&mut self) {
+        self.storage.locked.borrow_mut().remove(&self.name);
+    }
+}
+
+impl RecoveryFile for KvRecoveryFile {
+    fn write_record(&mut self, record: &[u8]) -> SysError {
+        if record.len() != RECOVERY_NODE_SIZE {
+            return Err(libc::EINVAL);
+        }
+        try!(put(&item_key(&self.name, self.records), record));
+        try!(put_count(&self.name, self.records + 1));
+        self.records += 1;
+        Ok(())
+    }
+}
+
+impl ProtectedStorage for KvStorage {
+    type File = KvFile;
+    type Recovery = KvRecoveryFile;
+
+    fn exists(&self, name: &CStr) -> SysResult<bool> {
+        get_count(name.to_bytes()).map(|count| count.is_some())
+    }
+
+    fn open(&self, name: &CStr, read_only: bool) -> SysResult<(KvFile, u64)> {
+        let name = name.to_bytes().to_vec();
+        if self.locked.borrow().contains(&name) {
+            return Err(libc::EBUSY);
+        }
+        let nodes = match try!(get_count(&name)) {
+            Some(nodes) => nodes,
+            None if read_only => return Err(libc::ENOENT),
+            None => {
+                try!(put_count(&name, 0));
+                0
+            },
+        };
+        if nodes > (i64::max_value() as u64) / NODE_SIZE as u64 {
+            return Err(libc::EINVAL);
+        }
+        self.locked.borrow_mut().insert(name.clone());
+        let file = KvFile { storage: self.clone(), name: name, nodes: nodes, read_only: read_only };
+        Ok((file, nodes * NODE_SIZE as u64))
+    }
+
+    fn remove(&self, name: &CStr) -> SysError {
+        remove_object(name.to_bytes())
+    }
+
+    fn create_recovery_file(&self, name: &CStr) -> SysResult<KvRecoveryFile> {
+        let name = name.to_bytes().to_vec();
+        try!(put_count(&name, 0).map_err(|_| to_errno(sgx_status_t::SGX_ERROR_FILE_CANT_OPEN_RECOVERY_FILE)));
+        Ok(KvRecoveryFile { name: name, records: 0 })
+    }
+
+    fn recover(&self, name: &CStr, recovery_name: &CStr) -> SysError {
+        let needed = to_errno(sgx_status_t::SGX_ERROR_FILE_RECOVERY_NEEDED);
+        let name = name.to_bytes();
+        let recovery_name = recovery_name.to_bytes();
+        let records = try!(try!(get_count(recovery_name).map_err(|_| needed)).ok_or(needed));
+        let mut nodes = try!(try!(get_count(name).map_err(|_| needed)).ok_or(needed));
+        let mut record = Vec::with_capacity(RECOVERY_NODE_SIZE);
+        record.resize(RECOVERY_NODE_SIZE, 0);
+        for index in 0..records {
+            let found = try!(get(&item_key(recovery_name, index), &mut record).map_err(|_| needed));
+            if !found {
+                return Err(needed);
+            }
+            let number = get_u64(&record);
+            nodes = try!(put_node(name, nodes, number, &record[8..]).map_err(|_| needed));
+        }
+        remove_object(recovery_name)
+    }
+}
+
+extern "C" {
+    fn u_kvstore_get_ocall(result: * mut c_int,
+                           error: * mut c_int,
+                           key: * const u8,
+                           key_len: size_t,
+                           buf: * mut u8,
+                           buf_len: size_t,
+                           value_len: * mut size_t) -> sgx_status_t;
+
+    fn u_kvstore_put_ocall(result: * mut c_int,
+                           error: * mut c_int,
+                           key: * const u8,
+                           key_len: size_t,
+                           value: * const u8,
+                           value_len: size_t) -> sgx_status_t;
+
+    fn u_kvstore_delete_ocall(result: * mut c_int,
+                              error: * mut c_int,
+                              key: * const u8,
+                              key_len: size_t) -> sgx_status_t;
+}
@@ -0,0 +1,61 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! # Protected files in Rust
+//!
+//! A memory-safe implementation of the Intel Protected File System format.
+//!
+//! Files written here can be opened with `SgxFileStream`, and the other way
+//! round. The file is split in 4 KiB nodes, each encrypted with AES-GCM under
+//! its own random key. The keys and tags of the nodes are kept in their parent
+//! node of a Merkle hash tree, whose root is in the meta data node, encrypted
+//! under a key derived from the user key or the enclave's seal key. Decrypted
+//! nodes are kept in an LRU cache, and flushes go through a recovery journal.
+//!
+//! Where the encrypted nodes live is up to a `ProtectedStorage`: the host file
+//! system through the `sgx_tprotected_fs.edl` OCALLs (`HostStorage`),
+//! enclave memory (`MemoryStorage`), which can also simulate crashes, or, with
+//! the `kvstore` feature, a key/value store of the untrusted application
+//! through the `sgx_kvstore.edl` OCALLs (`KvStorage`).
+
+mod node;
+mod crypto;
+mod cache;
+
+mod storage;
+pub use self::storage::*;
+
+#[cfg(feature = "kvstore")]
+mod kv;
+#[cfg(feature = "kvstore")]
+pub use self::kv::*;
+
+mod file;
+pub use self::file::*;
+
+pub use self::node::{NODE_SIZE, RECOVERY_NODE_SIZE};
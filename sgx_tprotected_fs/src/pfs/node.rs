@@ -0,0 +1,290 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! On-disk layout of a protected file.
+//!
+//! A protected file is a sequence of 4 KiB nodes. Node 0 holds the meta data,
+//! node 1 the root of the Merkle hash tree, and the rest are data nodes
+//! interleaved with the other hash tree nodes: every MHT node is followed by
+//! the 96 data nodes it authenticates. The first 3 KiB of user data live in the
+//! encrypted part of the meta data node itself.
+//!
+//! All multi-byte integers are little-endian and the structures are packed,
+//! matching `protected_fs_nodes.h` of the Intel SGX SDK.
+
+use sgx_types::*;
+use alloc::boxed::Box;
+
+pub const NODE_SIZE: usize = 4096;
+pub const MD_USER_DATA_SIZE: usize = 3072;
+pub const FILENAME_MAX_LEN: usize = 260;
+
+pub const ATTACHED_DATA_NODES_COUNT: u64 = 96;
+pub const CHILD_MHT_NODES_COUNT: u64 = 32;
+
+pub const SGX_FILE_ID: u64 = 0x5347_585F_4649_4C45;
+pub const SGX_FILE_MAJOR_VERSION: u8 = 0x01;
+pub const SGX_FILE_MINOR_VERSION: u8 = 0x00;
+
+const KEY_SIZE: usize = 16;
+const GCM_CRYPTO_DATA_SIZE: usize = 32;
+
+/// Size of `meta_data_plain_t`.
+pub const MD_PLAIN_SIZE: usize = 94;
+/// Size of `meta_data_encrypted_t`.
+pub const MD_ENCRYPTED_SIZE: usize = 3392;
+/// Size of `recovery_node_t`: the physical node number followed by the node.
+pub const RECOVERY_NODE_SIZE: usize = 8 + NODE_SIZE;
+
+const MD_UPDATE_FLAG_OFFSET: usize = MD_PLAIN_SIZE - 1;
+
+pub type NodeBuf = [u8; NODE_SIZE];
+
+pub fn new_node_buf() -> Box<NodeBuf> {
+    Box::new([0_u8; NODE_SIZE])
+}
+
+fn get_u16(buf: &[u8]) -> u16 {
+    (buf[0] as u16) | (buf[1] as u16) << 8
+}
+
+fn get_u32(buf: &[u8]) -> u32 {
+    (0..4).fold(0_u32, |v, i| v | (buf[i] as u32) << (8 * i))
+}
+
+pub fn get_u64(buf: &[u8]) -> u64 {
+    (0..8).fold(0_u64, |v, i| v | (buf[i] as u64) << (8 * i))
+}
+
+pub fn put_u16(buf: &mut [u8], v: u16) {
+    buf[0] = v as u8;
+    buf[1] = (v >> 8) as u8;
+}
+
+pub fn put_u32(buf: &mut [u8], v: u32) {
+    for i in 0..4 {
+        buf[i] = (v >> (8 * i)) as u8;
+    }
+}
+
+pub fn put_u64(buf: &mut [u8], v: u64) {
+    for i in 0..8 {
+        buf[i] = (v >> (8 * i)) as u8;
+    }
+}
+
+/// The key and GMAC of a child node, as stored in its parent (`gcm_crypto_data_t`).
+#[derive(Copy, Clone, Default)]
+pub struct GcmCryptoData {
+    pub key: sgx_aes_gcm_128bit_key_t,
+    pub gmac: sgx_aes_gcm_128bit_tag_t,
+}
+
+/// Position of a data node in the file, and of its entry in the parent MHT node.
+pub fn data_node_physical_number(data_node_number: u64) -> u64 {
+    // meta data node + root MHT node + the MHT nodes in front of it
+    2 + data_node_number + data_node_number / ATTACHED_DATA_NODES_COUNT
+}
+
+pub fn mht_node_physical_number(mht_node_number: u64) -> u64 {
+    1 + mht_node_number * (ATTACHED_DATA_NODES_COUNT + 1)
+}
+
+/// The MHT node that authenticates a data node, and the entry it uses there.
+pub fn data_node_parent(data_node_number: u64) -> (u64, usize) {
+    (data_node_number / ATTACHED_DATA_NODES_COUNT,
+     (data_node_number % ATTACHED_DATA_NODES_COUNT) as usize)
+}
+
+/// The parent of a (non-root) MHT node, and the entry it uses there.
+pub fn mht_node_parent(mht_node_number: u64) -> (u64, usize) {
+    let n = mht_node_number - 1;
+    (n / CHILD_MHT_NODES_COUNT,
+     (ATTACHED_DATA_NODES_COUNT + n % CHILD_MHT_NODES_COUNT) as usize)
+}
+
+/// Offset in the file of the first byte stored in a data node.
+pub fn data_node_offset(data_node_number: u64) -> u64 {
+    MD_USER_DATA_SIZE as u64 + data_node_number * NODE_SIZE as u64
+}
+
+/// Offset in the file of the first byte under an MHT node, i.e. the start of
+/// its first data node. The MHT node exists once the file reaches this size.
+pub fn mht_node_offset(mht_node_number: u64) -> u64 {
+    data_node_offset(mht_node_number * ATTACHED_DATA_NODES_COUNT)
+}
+
+/// Entry `index` of a decrypted MHT node: 96 data node entries, then 32 child
+/// MHT node entries.
+pub fn mht_entry(node: &NodeBuf, index: usize) -> GcmCryptoData {
+    let off = index * GCM_CRYPTO_DATA_SIZE;
+    let mut entry = GcmCryptoData::default();
+    entry.key.copy_from_slice(&node[off..off + KEY_SIZE]);
+    entry.gmac.copy_from_slice(&node[off + KEY_SIZE..off + GCM_CRYPTO_DATA_SIZE]);
+    entry
+}
+
+pub fn set_mht_entry(node: &mut NodeBuf, index: usize, entry: &GcmCryptoData) {
+    let off = index * GCM_CRYPTO_DATA_SIZE;
+    node[off..off + KEY_SIZE].copy_from_slice(&entry.key);
+    node[off + KEY_SIZE..off + GCM_CRYPTO_DATA_SIZE].copy_from_slice(&entry.gmac);
+}
+
+/// The clear-text part of the meta data node (`meta_data_plain_t`).
+#[derive(Copy, Clone, Default)]
+pub struct MetadataPlain {
+    pub file_id: u64,
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub key_id: sgx_key_id_t,
+    pub cpu_svn: sgx_cpu_svn_t,
+    pub isv_svn: sgx_isv_svn_t,
+    pub use_user_kdk_key: u8,
+    pub attribute_mask: sgx_attributes_t,
+    pub gmac: sgx_aes_gcm_128bit_tag_t,
+    pub update_flag: u8,
+}
+
+impl MetadataPlain {
+    pub fn new(use_user_kdk_key: bool) -> MetadataPlain {
+        MetadataPlain {
+            file_id: SGX_FILE_ID,
+            major_version: SGX_FILE_MAJOR_VERSION,
+            minor_version: SGX_FILE_MINOR_VERSION,
+            use_user_kdk_key: use_user_kdk_key as u8,
+            ..Default::default()
+        }
+    }
+
+    pub fn parse(node: &NodeBuf) -> MetadataPlain {
+        let mut plain = MetadataPlain::default();
+        plain.file_id = get_u64(&node[0..]);
+        plain.major_version = node[8];
+        plain.minor_version = node[9];
+        plain.key_id.id.copy_from_slice(&node[10..42]);
+        plain.cpu_svn.svn.copy_from_slice(&node[42..58]);
+        plain.isv_svn = get_u16(&node[58..]);
+        plain.use_user_kdk_key = node[60];
+        plain.attribute_mask.flags = get_u64(&node[61..]);
+        plain.attribute_mask.xfrm = get_u64(&node[69..]);
+        plain.gmac.copy_from_slice(&node[77..93]);
+        plain.update_flag = node[MD_UPDATE_FLAG_OFFSET];
+        plain
+    }
+
+    pub fn write(&self, node: &mut NodeBuf) {
+        put_u64(&mut node[0..], self.file_id);
+        node[8] = self.major_version;
+        node[9] = self.minor_version;
+        node[10..42].copy_from_slice(&self.key_id.id);
+        node[42..58].copy_from_slice(&self.cpu_svn.svn);
+        put_u16(&mut node[58..], self.isv_svn);
+        node[60] = self.use_user_kdk_key;
+        put_u64(&mut node[61..], self.attribute_mask.flags);
+        put_u64(&mut node[69..], self.attribute_mask.xfrm);
+        node[77..93].copy_from_slice(&self.gmac);
+        node[MD_UPDATE_FLAG_OFFSET] = self.update_flag;
+    }
+}
+
+/// Sets the update flag of an encoded meta data node, leaving everything else
+/// as it is on disk.
+pub fn set_update_flag(node: &mut NodeBuf, flag: bool) {
+    node[MD_UPDATE_FLAG_OFFSET] = flag as u8;
+}
+
+/// Returns the encrypted part of an encoded meta data node.
+pub fn encrypted_part(node: &NodeBuf) -> &[u8] {
+    &node[MD_PLAIN_SIZE..MD_PLAIN_SIZE + MD_ENCRYPTED_SIZE]
+}
+
+pub fn encrypted_part_mut(node: &mut NodeBuf) -> &mut [u8] {
+    &mut node[MD_PLAIN_SIZE..MD_PLAIN_SIZE + MD_ENCRYPTED_SIZE]
+}
+
+/// The encrypted part of the meta data node (`meta_data_encrypted_t`), in the clear.
+pub struct MetadataEncrypted {
+    pub clean_filename: [u8; FILENAME_MAX_LEN],
+    pub size: i64,
+    pub mc_uuid: [u8; 16],
+    pub mc_value: u32,
+    pub mht_key: sgx_aes_gcm_128bit_key_t,
+    pub mht_gmac: sgx_aes_gcm_128bit_tag_t,
+    pub data: Box<[u8; MD_USER_DATA_SIZE]>,
+}
+
+impl MetadataEncrypted {
+    pub fn new() -> MetadataEncrypted {
+        MetadataEncrypted {
+            clean_filename: [0_u8; FILENAME_MAX_LEN],
+            size: 0,
+            mc_uuid: [0_u8; 16],
+            mc_value: 0,
+            mht_key: sgx_aes_gcm_128bit_key_t::default(),
+            mht_gmac: sgx_aes_gcm_128bit_tag_t::default(),
+            data: Box::new([0_u8; MD_USER_DATA_SIZE]),
+        }
+    }
+
+    pub fn parse(buf: &[u8]) -> MetadataEncrypted {
+        let mut md = MetadataEncrypted::new();
+        md.clean_filename.copy_from_slice(&buf[0..260]);
+        md.size = get_u64(&buf[260..]) as i64;
+        md.mc_uuid.copy_from_slice(&buf[268..284]);
+        md.mc_value = get_u32(&buf[284..]);
+        md.mht_key.copy_from_slice(&buf[288..304]);
+        md.mht_gmac.copy_from_slice(&buf[304..320]);
+        md.data.copy_from_slice(&buf[320..MD_ENCRYPTED_SIZE]);
+        md
+    }
+
+    pub fn write(&self, buf: &mut [u8]) {
+        buf[0..260].copy_from_slice(&self.clean_filename);
+        put_u64(&mut buf[260..], self.size as u64);
+        buf[268..284].copy_from_slice(&self.mc_uuid);
+        put_u32(&mut buf[284..], self.mc_value);
+        buf[288..304].copy_from_slice(&self.mht_key);
+        buf[304..320].copy_from_slice(&self.mht_gmac);
+        buf[320..MD_ENCRYPTED_SIZE].copy_from_slice(&self.data[..]);
+    }
+
+    /// Whether `name` matches the file name recorded when the file was created.
+    pub fn name_matches(&self, name: &[u8]) -> bool {
+        let len = self.clean_filename.iter().position(|&c| c == 0).unwrap_or(FILENAME_MAX_LEN);
+        &self.clean_filename[..len] == name
+    }
+}
+
+impl Drop for MetadataEncrypted {
+    fn drop(&mut self) {
+        self.mht_key = sgx_aes_gcm_128bit_key_t::default();
+        for b in self.data.iter_mut() {
+            *b = 0;
+        }
+    }
+}
@@ -0,0 +1,445 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Where the encrypted nodes of a protected file are kept.
+
+use sgx_types::*;
+use sgx_trts::libc::{self, c_void, c_char};
+use sgx_trts::c_str::CStr;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ptr;
+use pfs::node::{NODE_SIZE, RECOVERY_NODE_SIZE};
+use pfs::crypto::to_errno;
+
+/// An open file of a `ProtectedStorage`, addressed in whole nodes.
+pub trait StorageFile {
+    /// Reads the node at position `number` into `buf`, which is `NODE_SIZE` bytes long.
+    fn read_node(&mut self, number: u64, buf: &mut [u8]) -> SysError;
+
+    /// Writes `buf` as the node at position `number`, growing the file if needed.
+    fn write_node(&mut self, number: u64, buf: &[u8]) -> SysError;
+
+    /// Makes the written nodes durable.
+    fn flush(&mut self) -> SysError;
+}
+
+/// A recovery journal being written, one `recovery_node_t` record at a time.
+pub trait RecoveryFile {
+    fn write_record(&mut self, record: &[u8]) -> SysError;
+}
+
+/// The untrusted side of a protected file: block storage for the encrypted
+/// nodes, plus the recovery journal used to make flushes atomic.
+///
+/// Nothing handed to a backend is in the clear, and everything read back is
+/// authenticated, so backends need not be trusted.
+pub trait ProtectedStorage {
+    type File: StorageFile;
+    type Recovery: RecoveryFile;
+
+    fn exists(&self, name: &CStr) -> SysResult<bool>;
+
+    /// Opens `name` for exclusive use, creating it unless `read_only` is set.
+    /// Returns the file and its size in bytes.
+    fn open(&self, name: &CStr, read_only: bool) -> SysResult<(Self::File, u64)>;
+
+    fn remove(&self, name: &CStr) -> SysError;
+
+    /// Creates (or truncates) the recovery journal `name`.
+    fn create_recovery_file(&self, name: &CStr) -> SysResult<Self::Recovery>;
+
+    /// Writes every node saved in the journal `recovery_name` back to `name`,
+    /// then deletes the journal.
+    fn recover(&self, name: &CStr, recovery_name: &CStr) -> SysError;
+}
+
+/// Files on the host file system, through the `sgx_tprotected_fs.edl` OCALLs.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HostStorage;
+
+pub struct HostFile(* mut c_void);
+
+pub struct HostRecoveryFile(* mut c_void);
+
+fn check_ocall(status: sgx_status_t) -> SysError {
+    match status {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(to_errno(status)),
+    }
+}
+
+fn host_error(ret: i32) -> sys_error_t {
+    if ret > 0 { ret } else { libc::EIO }
+}
+
+impl StorageFile for HostFile {
+    fn read_node(&mut self, number: u64, buf: &mut [u8]) -> SysError {
+        if buf.len() != NODE_SIZE {
+            return Err(libc::EINVAL);
+        }
+        let mut ret: i32 = 0;
+        try!(check_ocall(unsafe {
+            u_sgxprotectedfs_fread_node(&mut ret, self.0, number, buf.as_mut_ptr(), NODE_SIZE as u32)
+        }));
+        if ret == 0 { Ok(()) } else { Err(host_error(ret)) }
+    }
+
+    fn write_node(&mut self, number: u64, buf: &[u8]) -> SysError {
+        if buf.len() != NODE_SIZE {
+            return Err(libc::EINVAL);
+        }
+        let mut ret: i32 = 0;
+        try!(check_ocall(unsafe {
+            u_sgxprotectedfs_fwrite_node(&mut ret, self.0, number, buf.as_ptr(), NODE_SIZE as u32)
+        }));
+        if ret == 0 { Ok(()) } else { Err(host_error(ret)) }
+    }
+
+    fn flush(&mut self) -> SysError {
+        let mut ret: u8 = 0;
+        try!(check_ocall(unsafe { u_sgxprotectedfs_fflush(&mut ret, self.0) }));
+        if ret == 0 { Ok(()) } else { Err(to_errno(sgx_status_t::SGX_ERROR_FILE_FLUSH_FAILED)) }
+    }
+}
+
+impl Drop for HostFile {
+    fn drop(&mut self) {
+        let mut ret: i32 = 0;
+        let _ = unsafe { u_sgxprotectedfs_fclose(&mut ret, self.0) };
+    }
+}
+
+impl RecoveryFile for HostRecoveryFile {
+    fn write_record(&mut self, record: &[u8]) -> SysError {
+        let mut ret: u8 = 0;
+        try!(check_ocall(unsafe {
+            u_sgxprotectedfs_fwrite_recovery_node(&mut ret, self.0, record.as_ptr(), record.len() as u32)
+        }));
+        if ret == 0 { Ok(()) } else { Err(to_errno(sgx_status_t::SGX_ERROR_FILE_CANT_WRITE_RECOVERY_FILE)) }
+    }
+}
+
+impl Drop for HostRecoveryFile {
+    fn drop(&mut self) {
+        let mut ret: i32 = 0;
+        let _ = unsafe { u_sgxprotectedfs_fclose(&mut ret, self.0) };
+    }
+}
+
+impl ProtectedStorage for HostStorage {
+    type File = HostFile;
+    type Recovery = HostRecoveryFile;
+
+    fn exists(&self, name: &CStr) -> SysResult<bool> {
+        let mut ret: u8 = 0;
+        try!(check_ocall(unsafe { u_sgxprotectedfs_check_if_file_exists(&mut ret, name.as_ptr()) }));
+        Ok(ret == 1)
+    }
+
+    fn open(&self, name: &CStr, read_only: bool) -> SysResult<(HostFile, u64)> {
+        let mut file: * mut c_void = ptr::null_mut();
+        let mut size: i64 = 0;
+        let mut error: i32 = 0;
+        try!(check_ocall(unsafe {
+            u_sgxprotectedfs_exclusive_file_open(&mut file, name.as_ptr(), read_only as u8, &mut size, &mut error)
+        }));
+        if file.is_null() {
+            return Err(if error != 0 { error } else { libc::EACCES });
+        }
+        if size < 0 {
+            drop(HostFile(file));
+            return Err(libc::EINVAL);
+        }
+        Ok((HostFile(file), size as u64))
+    }
+
+    fn remove(&self, name: &CStr) -> SysError {
+        let mut ret: i32 = 0;
+        try!(check_ocall(unsafe { u_sgxprotectedfs_remove(&mut ret, name.as_ptr()) }));
+        if ret == 0 { Ok(()) } else { Err(host_error(ret)) }
+    }
+
+    fn create_recovery_file(&self, name: &CStr) -> SysResult<HostRecoveryFile> {
+        let mut file: * mut c_void = ptr::null_mut();
+        try!(check_ocall(unsafe { u_sgxprotectedfs_recovery_file_open(&mut file, name.as_ptr()) }));
+        if file.is_null() {
+            Err(to_errno(sgx_status_t::SGX_ERROR_FILE_CANT_OPEN_RECOVERY_FILE))
+        } else {
+            Ok(HostRecoveryFile(file))
+        }
+    }
+
+    fn recover(&self, name: &CStr, recovery_name: &CStr) -> SysError {
+        let mut ret: i32 = 0;
+        try!(check_ocall(unsafe {
+            u_sgxprotectedfs_do_file_recovery(&mut ret, name.as_ptr(), recovery_name.as_ptr(), NODE_SIZE as u32)
+        }));
+        if ret == 0 { Ok(()) } else { Err(to_errno(sgx_status_t::SGX_ERROR_FILE_RECOVERY_NEEDED)) }
+    }
+}
+
+/// Files kept in enclave memory.
+///
+/// Clones share the same set of files. Besides tests, this is useful for
+/// scratch data that must not outlive the enclave. `fail_after` simulates a
+/// crash: once the given number of writes went through, every later write is
+/// dropped and reported as `EIO`, which leaves the files exactly as a power
+/// cut at that point would.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    state: Rc<RefCell<MemoryState>>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    files: BTreeMap<Vec<u8>, Vec<u8>>,
+    locked: BTreeSet<Vec<u8>>,
+    writes: u64,
+    write_limit: Option<u64>,
+}
+
+impl MemoryState {
+    fn begin_write(&mut self) -> SysError {
+        if let Some(limit) = self.write_limit {
+            if self.writes >= limit {
+                return Err(libc::EIO);
+            }
+        }
+        self.writes += 1;
+        Ok(())
+    }
+}
+
+pub struct MemoryFile {
+    state: Rc<RefCell<MemoryState>>,
+    name: Vec<u8>,
+    read_only: bool,
+}
+
+pub struct MemoryRecoveryFile {
+    state: Rc<RefCell<MemoryState>>,
+    name: Vec<u8>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    /// The raw (encrypted) contents of a file.
+    pub fn contents(&self, name: &CStr) -> Option<Vec<u8>> {
+        self.state.borrow().files.get(name.to_bytes()).cloned()
+    }
+
+    /// Replaces the raw contents of a file, e.g. to roll it back or tamper with it.
+    pub fn set_contents(&self, name: &CStr, contents: Vec<u8>) {
+        self.state.borrow_mut().files.insert(name.to_bytes().to_vec(), contents);
+    }
+
+    /// Number of writes (node writes, journal records, creations and removals)
+    /// performed so far.
+    pub fn writes(&self) -> u64 {
+        self.state.borrow().writes
+    }
+
+    /// Lets `count` more writes through, and fails all the following ones.
+    pub fn fail_after(&self, count: u64) {
+        let mut state = self.state.borrow_mut();
+        let limit = state.writes + count;
+        state.write_limit = Some(limit);
+    }
+
+    /// Stops failing writes.
+    pub fn clear_failures(&self) {
+        self.state.borrow_mut().write_limit = None;
+    }
+}
+
+impl StorageFile for MemoryFile {
+    fn read_node(&mut self, number: u64, buf: &mut [u8]) -> SysError {
+        let state = self.state.borrow();
+        let data = try!(state.files.get(&self.name).ok_or(libc::ENOENT));
+        let start = number as usize * NODE_SIZE;
+        if buf.len() != NODE_SIZE || start + NODE_SIZE > data.len() {
+            return Err(libc::EIO);
+        }
+        buf.copy_from_slice(&data[start..start + NODE_SIZE]);
+        Ok(())
+    }
+
+    fn write_node(&mut self, number: u64, buf: &[u8]) -> SysError {
+        if self.read_only {
+            return Err(libc::EBADF);
+        }
+        if buf.len() != NODE_SIZE {
+            return Err(libc::EINVAL);
+        }
+        let mut state = self.state.borrow_mut();
+        try!(state.begin_write());
+        let data = try!(state.files.get_mut(&self.name).ok_or(libc::ENOENT));
+        let start = number as usize * NODE_SIZE;
+        if data.len() < start + NODE_SIZE {
+            data.resize(start + NODE_SIZE, 0);
+        }
+        data[start..start + NODE_SIZE].copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> SysError {
+        Ok(())
+    }
+}
+
+impl Drop for MemoryFile {
+    fn drop(&mut self) {
+        self.state.borrow_mut().locked.remove(&self.name);
+    }
+}
+
+impl RecoveryFile for MemoryRecoveryFile {
+    fn write_record(&mut self, record: &[u8]) -> SysError {
+        let mut state = self.state.borrow_mut();
+        try!(state.begin_write());
+        state.files.entry(self.name.clone()).or_insert_with(Vec::new).extend_from_slice(record);
+        Ok(())
+    }
+}
+
+impl ProtectedStorage for MemoryStorage {
+    type File = MemoryFile;
+    type Recovery = MemoryRecoveryFile;
+
+    fn exists(&self, name: &CStr) -> SysResult<bool> {
+        Ok(self.state.borrow().files.contains_key(name.to_bytes()))
+    }
+
+    fn open(&self, name: &CStr, read_only: bool) -> SysResult<(MemoryFile, u64)> {
+        let name = name.to_bytes().to_vec();
+        let mut state = self.state.borrow_mut();
+        if state.locked.contains(&name) {
+            return Err(libc::EBUSY);
+        }
+        let existing = state.files.get(&name).map(|data| data.len() as u64);
+        let size = match existing {
+            Some(size) => size,
+            None if read_only => return Err(libc::ENOENT),
+            None => {
+                try!(state.begin_write());
+                state.files.insert(name.clone(), Vec::new());
+                0
+            },
+        };
+        state.locked.insert(name.clone());
+        Ok((MemoryFile { state: self.state.clone(), name: name, read_only: read_only }, size))
+    }
+
+    fn remove(&self, name: &CStr) -> SysError {
+        let mut state = self.state.borrow_mut();
+        if !state.files.contains_key(name.to_bytes()) {
+            return Err(libc::ENOENT);
+        }
+        try!(state.begin_write());
+        state.files.remove(name.to_bytes());
+        Ok(())
+    }
+
+    fn create_recovery_file(&self, name: &CStr) -> SysResult<MemoryRecoveryFile> {
+        let name = name.to_bytes().to_vec();
+        let mut state = self.state.borrow_mut();
+        try!(state.begin_write());
+        state.files.insert(name.clone(), Vec::new());
+        Ok(MemoryRecoveryFile { state: self.state.clone(), name: name })
+    }
+
+    fn recover(&self, name: &CStr, recovery_name: &CStr) -> SysError {
+        let needed = to_errno(sgx_status_t::SGX_ERROR_FILE_RECOVERY_NEEDED);
+        let mut state = self.state.borrow_mut();
+        let journal = try!(state.files.get(recovery_name.to_bytes()).cloned().ok_or(needed));
+        if journal.len() % RECOVERY_NODE_SIZE != 0 || !state.files.contains_key(name.to_bytes()) {
+            return Err(needed);
+        }
+        for record in journal.chunks(RECOVERY_NODE_SIZE) {
+            try!(state.begin_write());
+            let number = (0..8).fold(0_u64, |v, i| v | (record[i] as u64) << (8 * i));
+            let start = number as usize * NODE_SIZE;
+            let data = state.files.get_mut(name.to_bytes()).unwrap();
+            if data.len() < start + NODE_SIZE {
+                data.resize(start + NODE_SIZE, 0);
+            }
+            data[start..start + NODE_SIZE].copy_from_slice(&record[8..]);
+        }
+        try!(state.begin_write());
+        state.files.remove(recovery_name.to_bytes());
+        Ok(())
+    }
+}
+
+extern "C" {
+    fn u_sgxprotectedfs_exclusive_file_open(result: * mut * mut c_void,
+                                            filename: * const c_char,
+                                            read_only: u8,
+                                            file_size: * mut i64,
+                                            error_code: * mut i32) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_check_if_file_exists(result: * mut u8,
+                                             filename: * const c_char) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_fread_node(result: * mut i32,
+                                   f: * mut c_void,
+                                   node_number: u64,
+                                   buffer: * mut u8,
+                                   node_size: u32) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_fwrite_node(result: * mut i32,
+                                    f: * mut c_void,
+                                    node_number: u64,
+                                    buffer: * const u8,
+                                    node_size: u32) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_fclose(result: * mut i32, f: * mut c_void) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_fflush(result: * mut u8, f: * mut c_void) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_remove(result: * mut i32, filename: * const c_char) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_recovery_file_open(result: * mut * mut c_void,
+                                           filename: * const c_char) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_fwrite_recovery_node(result: * mut u8,
+                                             f: * mut c_void,
+                                             data: * const u8,
+                                             data_length: u32) -> sgx_status_t;
+
+    fn u_sgxprotectedfs_do_file_recovery(result: * mut i32,
+                                         filename: * const c_char,
+                                         recovery_filename: * const c_char,
+                                         node_size: u32) -> sgx_status_t;
+}
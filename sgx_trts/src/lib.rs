@@ -82,6 +82,7 @@
 #![feature(ascii_ctype)]
 #![feature(asm)]
 #![feature(lang_items)]
+#![feature(raw)]
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
 #![allow(overflowing_literals)]
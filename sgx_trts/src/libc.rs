@@ -112,6 +112,9 @@ pub type off_t = u64;
 pub type uid_t = u32;
 pub type gid_t = u32;
 pub type ino64_t = u64;
+pub type nfds_t = c_ulong;
+
+pub enum DIR {}
 
 s! {
 
@@ -298,6 +301,20 @@ s! {
         pub revents: c_short,
     }
 
+    #[cfg_attr(target_arch = "x86_64", repr(packed))]
+    pub struct epoll_event {
+        pub events: u32,
+        pub u64: u64,
+    }
+
+    pub struct dirent64 {
+        pub d_ino: ino64_t,
+        pub d_off: off64_t,
+        pub d_reclen: c_ushort,
+        pub d_type: c_uchar,
+        pub d_name: [c_char; 256],
+    }
+
     pub struct winsize {
         pub ws_row: c_ushort,
         pub ws_col: c_ushort,
@@ -391,6 +408,15 @@ pub const S_IRWXO: mode_t = 7;
 pub const S_IXOTH: mode_t = 1;
 pub const S_IWOTH: mode_t = 2;
 pub const S_IROTH: mode_t = 4;
+
+pub const DT_UNKNOWN: u8 = 0;
+pub const DT_FIFO: u8 = 1;
+pub const DT_CHR: u8 = 2;
+pub const DT_DIR: u8 = 4;
+pub const DT_BLK: u8 = 6;
+pub const DT_REG: u8 = 8;
+pub const DT_LNK: u8 = 10;
+pub const DT_SOCK: u8 = 12;
 pub const F_OK: c_int = 0;
 pub const R_OK: c_int = 4;
 pub const W_OK: c_int = 2;
@@ -741,6 +767,15 @@ pub const PATH_MAX: c_int = 4096;
 
 pub const FD_SETSIZE: usize = 1024;
 
+pub const POLLIN: c_short = 0x1;
+pub const POLLPRI: c_short = 0x2;
+pub const POLLOUT: c_short = 0x4;
+pub const POLLERR: c_short = 0x8;
+pub const POLLHUP: c_short = 0x10;
+pub const POLLNVAL: c_short = 0x20;
+pub const POLLRDNORM: c_short = 0x040;
+pub const POLLRDBAND: c_short = 0x080;
+
 pub const EPOLLIN: c_int = 0x1;
 pub const EPOLLPRI: c_int = 0x2;
 pub const EPOLLOUT: c_int = 0x4;
@@ -753,6 +788,12 @@ pub const EPOLLERR: c_int = 0x8;
 pub const EPOLLHUP: c_int = 0x10;
 pub const EPOLLET: c_int = 0x80000000;
 
+pub const EPOLLEXCLUSIVE: c_int = 0x10000000;
+pub const EPOLLRDHUP: c_int = 0x2000;
+pub const EPOLLONESHOT: c_int = 0x40000000;
+
+pub const EPOLL_CLOEXEC: c_int = 0x80000;
+
 pub const EPOLL_CTL_ADD: c_int = 1;
 pub const EPOLL_CTL_MOD: c_int = 3;
 pub const EPOLL_CTL_DEL: c_int = 2;
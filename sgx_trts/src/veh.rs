@@ -27,6 +27,13 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use sgx_types::*;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::mem;
+use core::ptr;
+use core::raw;
 
 pub type exception_handle = * const c_void;
 
@@ -120,3 +127,265 @@ pub fn rsgx_unregister_exception_handler(handle: exception_handle) -> bool {
     let ret = unsafe { sgx_unregister_exception_handler(handle) };
     if ret == 0 { false } else { true }
 }
+
+/// What an exception handler registered by `register_exception_handler`
+/// did with the exception.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HandleResult {
+    /// The handler did not handle the exception; the next handler is called.
+    ContinueSearch,
+    /// The handler handled the exception; the enclave resumes with the
+    /// register context held in `ExceptionInfo`.
+    ContinueExecution,
+}
+
+/// The exception passed to a handler registered by `register_exception_handler`.
+pub struct ExceptionInfo<'a> {
+    info: &'a mut sgx_exception_info_t,
+}
+
+impl<'a> ExceptionInfo<'a> {
+    /// Returns the exception vector, such as `SGX_EXCEPTION_VECTOR_UD`.
+    pub fn vector(&self) -> sgx_exception_vector_t {
+        self.info.exception_vector
+    }
+
+    /// Returns whether it is a hardware or a software exception.
+    pub fn exception_type(&self) -> sgx_exception_type_t {
+        self.info.exception_type
+    }
+
+    /// Returns the general purpose registers at the time of the exception.
+    pub fn context(&self) -> &sgx_cpu_context_t {
+        &self.info.cpu_context
+    }
+
+    /// Returns the general purpose registers, which the enclave resumes with
+    /// if the handler returns `ContinueExecution`. For example, a handler
+    /// emulating an instruction moves the instruction pointer past it.
+    pub fn context_mut(&mut self) -> &mut sgx_cpu_context_t {
+        &mut self.info.cpu_context
+    }
+}
+
+type Handler = dyn Fn(&mut ExceptionInfo) -> HandleResult + Send + Sync;
+type Handlers = Arc<Vec<(u64, Arc<Handler>)>>;
+
+struct Registry {
+    next_id: u64,
+    // Replaced, never changed in place, so that dispatch can hold on to the
+    // handlers without holding the lock.
+    handlers: Handlers,
+    handle: exception_handle,
+}
+
+static mut REGISTRY_LOCK: sgx_spinlock_t = SGX_SPINLOCK_INITIALIZER;
+static mut REGISTRY: * mut Registry = 0 as * mut Registry;
+
+#[allow(improper_ctypes)]
+extern {
+    fn __rust_maybe_catch_panic(f: fn(*mut u8),
+                                data: *mut u8,
+                                data_ptr: *mut usize,
+                                vtable_ptr: *mut usize) -> u32;
+}
+
+struct Call<'a, 'b: 'a> {
+    handler: &'a Handler,
+    info: &'a mut ExceptionInfo<'b>,
+    result: HandleResult,
+}
+
+fn do_call(data: *mut u8) {
+    unsafe {
+        let call = &mut *(data as *mut Call);
+        call.result = (call.handler)(call.info);
+    }
+}
+
+// Unwinding into the exception dispatcher of the tRTS is undefined behavior,
+// so a handler which panics is taken as not handling the exception.
+fn call_handler(handler: &Handler, info: &mut ExceptionInfo) -> HandleResult {
+
+    let mut call = Call {
+        handler: handler,
+        info: info,
+        result: HandleResult::ContinueSearch,
+    };
+    let mut any_data = 0_usize;
+    let mut any_vtable = 0_usize;
+    let r = unsafe {
+        __rust_maybe_catch_panic(do_call,
+                                 &mut call as *mut Call as *mut u8,
+                                 &mut any_data,
+                                 &mut any_vtable)
+    };
+    if r != 0 {
+        let payload: Box<Any + Send> = unsafe {
+            mem::transmute(raw::TraitObject {
+                data: any_data as *mut (),
+                vtable: any_vtable as *mut (),
+            })
+        };
+        drop(payload);
+        return HandleResult::ContinueSearch;
+    }
+    call.result
+}
+
+// The single entry registered with the tRTS. It takes a snapshot of the
+// handlers under the lock and calls them after releasing it, so that no
+// closure runs with the lock held: a handler which faults again would
+// deadlock on it, and other threads would spin while the handlers run.
+extern "C" fn dispatch(info: * mut sgx_exception_info_t) -> uint32_t {
+
+    if info.is_null() {
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+    let handlers = unsafe {
+        sgx_spin_lock(&mut REGISTRY_LOCK);
+        let handlers = if REGISTRY.is_null() { None } else { Some((*REGISTRY).handlers.clone()) };
+        sgx_spin_unlock(&mut REGISTRY_LOCK);
+        handlers
+    };
+    let handlers = match handlers {
+        Some(handlers) => handlers,
+        None => return EXCEPTION_CONTINUE_SEARCH,
+    };
+
+    let mut info = ExceptionInfo { info: unsafe { &mut *info } };
+    for &(_, ref handler) in handlers.iter() {
+        if call_handler(&**handler, &mut info) == HandleResult::ContinueExecution {
+            return EXCEPTION_CONTINUE_EXECUTION;
+        }
+    }
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+///
+/// register_exception_handler registers a Rust closure as an exception handler.
+///
+/// # Description
+///
+/// The closure receives the exception as an `ExceptionInfo` and returns a `HandleResult`. Unlike
+/// rsgx_register_exception_handler, no C-ABI callback or unsafe code is needed. The handler stays registered as long as
+/// the returned `ExceptionGuard` is alive.
+///
+/// The closures registered by this function form a single group in the handler chain of the tRTS. The group is added
+/// to the chain, at its beginning if is_first_handler is true and at its end otherwise, when its first closure is
+/// registered, and removed once its last closure is unregistered. Within the group, is_first_handler selects whether
+/// the closure is called before or after the closures already registered.
+///
+/// The restrictions of rsgx_register_exception_handler apply: OCALLs are not allowed in the handler. In addition, the
+/// handler must not register or unregister exception handlers. A handler which panics is taken as returning
+/// `HandleResult::ContinueSearch`.
+///
+/// # Parameters
+///
+/// **is_first_handler**
+///
+/// Specify whether the handler should be called before (true) or after (false) the other handlers.
+///
+/// **handler**
+///
+/// The closure to be called.
+///
+/// # Requirements
+///
+/// Library: libsgx_trts.a
+///
+/// # Return value
+///
+/// **Some(ExceptionGuard)**
+///
+/// Indicates the exception handler is registered successfully. Dropping the guard unregisters it.
+///
+/// **None**
+///
+/// The exception handler was not registered.
+///
+pub fn register_exception_handler<F>(is_first_handler: bool, handler: F) -> Option<ExceptionGuard>
+    where F: Fn(&mut ExceptionInfo) -> HandleResult + Send + Sync + 'static {
+
+    let handler: Arc<Handler> = Arc::new(handler);
+    unsafe {
+        sgx_spin_lock(&mut REGISTRY_LOCK);
+        if REGISTRY.is_null() {
+            REGISTRY = Box::into_raw(Box::new(Registry {
+                next_id: 1,
+                handlers: Arc::new(Vec::new()),
+                handle: ptr::null(),
+            }));
+        }
+        let registry = &mut *REGISTRY;
+
+        if registry.handle.is_null() {
+            let first = if is_first_handler { 1 } else { 0 };
+            match rsgx_register_exception_handler(first, dispatch) {
+                Some(handle) => registry.handle = handle,
+                None => {
+                    sgx_spin_unlock(&mut REGISTRY_LOCK);
+                    return None;
+                }
+            }
+        }
+
+        let id = registry.next_id;
+        registry.next_id += 1;
+        let mut handlers = Vec::with_capacity(registry.handlers.len() + 1);
+        if is_first_handler {
+            handlers.push((id, handler));
+            handlers.extend(registry.handlers.iter().cloned());
+        } else {
+            handlers.extend(registry.handlers.iter().cloned());
+            handlers.push((id, handler));
+        }
+        let old = mem::replace(&mut registry.handlers, Arc::new(handlers));
+        sgx_spin_unlock(&mut REGISTRY_LOCK);
+        drop(old);
+        Some(ExceptionGuard { id: id })
+    }
+}
+
+/// An exception handler registered by `register_exception_handler`.
+///
+/// The handler is unregistered when the guard is dropped.
+#[must_use]
+#[derive(Debug)]
+pub struct ExceptionGuard {
+    id: u64,
+}
+
+impl ExceptionGuard {
+    /// Keeps the handler registered for the lifetime of the enclave.
+    pub fn leak(self) {
+        ::core::mem::forget(self);
+    }
+}
+
+impl Drop for ExceptionGuard {
+    fn drop(&mut self) {
+        unsafe {
+            sgx_spin_lock(&mut REGISTRY_LOCK);
+            let mut removed = None;
+            if !REGISTRY.is_null() {
+                let registry = &mut *REGISTRY;
+                if registry.handlers.iter().any(|&(id, _)| id == self.id) {
+                    let handlers = registry.handlers.iter()
+                                                    .filter(|&&(id, _)| id != self.id)
+                                                    .cloned()
+                                                    .collect::<Vec<_>>();
+                    removed = Some(mem::replace(&mut registry.handlers, Arc::new(handlers)));
+                }
+                if registry.handlers.is_empty() && !registry.handle.is_null() {
+                    rsgx_unregister_exception_handler(registry.handle);
+                    registry.handle = ptr::null();
+                }
+            }
+            sgx_spin_unlock(&mut REGISTRY_LOCK);
+            // The closure may run arbitrary code when dropped, so drop it
+            // after releasing the lock.
+            drop(removed);
+        }
+    }
+}
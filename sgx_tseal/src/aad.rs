@@ -36,6 +36,7 @@ use core::mem;
 use core::marker::PhantomData;
 use alloc::slice;
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 /// The structure about sealed data, for authenticate and verify.
 pub struct SgxMacAadata<'a, T: 'a + ?Sized> {
@@ -430,6 +431,48 @@ impl<'a, T: 'a + ?Sized> SgxMacAadata<'a, T> {
         SgxMacAadata::default()
     }
 
+    ///
+    /// Serialize SgxMacAadata into an owned buffer in the layout of sgx_sealed_data_t.
+    ///
+    /// The buffer holds the sgx_sealed_data_t header followed by the payload, and can be stored or sent
+    /// as is. Use `from_bytes` to restore it.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    ///
+    /// Deserialize SgxMacAadata from a buffer in the layout of sgx_sealed_data_t.
+    ///
+    /// The payload is copied, so the returned SgxMacAadata does not borrow from `bytes`.
+    ///
+    /// # Parameters
+    ///
+    /// **bytes**
+    ///
+    /// The buffer produced by `to_bytes` or holding a raw sgx_sealed_data_t.
+    ///
+    /// # Return value
+    ///
+    /// The SgxMacAadata restored from the buffer.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The buffer is shorter than the sgx_sealed_data_t header, the length fields of the header are not
+    /// consistent or the buffer holds encrypted text, or the buffer size does not match the size the header describes.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<Self> {
+        let inner = try!(SgxInternalSealedData::from_bytes(bytes));
+        if inner.get_encrypt_txt_len() != 0 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(SgxMacAadata {
+            inner: inner,
+            marker: PhantomData})
+    }
+
     ///
     /// Get the size of payload in SgxMacAadata.
     ///
@@ -472,3 +515,18 @@ impl<'a, T: 'a + ?Sized> SgxMacAadata<'a, T> {
         self.inner.get_add_mac_txt_len()
     }
 }
+
+#[cfg(feature = "serialize")]
+impl<'a, T: 'a + ?Sized> ::sgx_serialize::Serializable for SgxMacAadata<'a, T> {
+    fn encode<S: ::sgx_serialize::Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        ::sgx_serialize::Serializable::encode(&self.to_bytes(), s)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<'a, T: 'a + ?Sized> ::sgx_serialize::DeSerializable for SgxMacAadata<'a, T> {
+    fn decode<D: ::sgx_serialize::Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        let bytes: Vec<u8> = try!(::sgx_serialize::DeSerializable::decode(d));
+        SgxMacAadata::from_bytes(&bytes).map_err(|_| d.error("invalid sealed data"))
+    }
+}
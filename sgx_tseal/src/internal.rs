@@ -32,6 +32,7 @@ use sgx_tcrypto::*;
 use sgx_tse::*;
 use core::mem;
 use core::ptr;
+use core::slice;
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
@@ -46,10 +47,42 @@ use alloc::vec::Vec;
  *    SGX_FLAGS_PROVISION_KEY
  *    SGX_FLAGS_EINITTOKEN_KEY */
 const FLAGS_NON_SECURITY_BITS: uint64_t = (0x00FFFFFFFFFFFFC0 | SGX_FLAGS_MODE64BIT | SGX_FLAGS_PROVISION_KEY| SGX_FLAGS_EINITTOKEN_KEY);
-const TSEAL_DEFAULT_FLAGSMASK: uint64_t = (!FLAGS_NON_SECURITY_BITS);
+pub const TSEAL_DEFAULT_FLAGSMASK: uint64_t = (!FLAGS_NON_SECURITY_BITS);
 
 const MISC_NON_SECURITY_BITS: uint32_t =  0x0FFFFFFF;  /* bit[27:0]: have no security implications */
-const TSEAL_DEFAULT_MISCMASK: uint32_t =  (!MISC_NON_SECURITY_BITS);
+pub const TSEAL_DEFAULT_MISCMASK: uint32_t =  (!MISC_NON_SECURITY_BITS);
+
+/// Build a seal key request with a fresh random key ID for the calling enclave.
+pub fn seal_key_request(key_policy: u16,
+                        attribute_mask: sgx_attributes_t,
+                        misc_mask: sgx_misc_select_t) -> SgxResult<sgx_key_request_t> {
+
+    if (key_policy & (!(SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER)) != 0) ||
+       ((key_policy &  (SGX_KEYPOLICY_MRENCLAVE | SGX_KEYPOLICY_MRSIGNER)) == 0) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+    if ((attribute_mask.flags & SGX_FLAGS_INITTED) == 0) ||
+       ((attribute_mask.flags & SGX_FLAGS_DEBUG) == 0) {
+        return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+    }
+
+    let target_info = sgx_target_info_t::default();
+    let report_data = sgx_report_data_t::default();
+    let mut key_id = sgx_key_id_t::default();
+
+    let report = try!(rsgx_create_report(&target_info, &report_data));
+    try!(rsgx_read_rand(&mut key_id.id));
+
+    Ok(sgx_key_request_t{key_name: SGX_KEYSELECT_SEAL,
+                         key_policy: key_policy,
+                         isv_svn: report.body.isv_svn,
+                         reserved1: 0_u16,
+                         cpu_svn: report.body.cpu_svn,
+                         attribute_mask: attribute_mask,
+                         key_id: key_id,
+                         misc_mask: misc_mask,
+                         reserved2: [0_u8; SGX_KEY_REQUEST_RESERVED2_BYTES]})
+}
 
 #[derive(Clone, Default)]
 pub struct SgxInternalUnsealedData {
@@ -268,6 +301,62 @@ impl SgxInternalSealedData {
         Some(sealed_data)
     }
 
+    pub fn to_bytes(&self) -> Vec<u8> {
+
+        let header_size = mem::size_of::<sgx_sealed_data_t>();
+        let encrypt_len = self.payload_data.encrypt.len();
+        let additional_len = self.payload_data.additional.len();
+
+        let mut header = sgx_sealed_data_t::default();
+        header.key_request = self.key_request;
+        header.plain_text_offset = encrypt_len as u32;
+        header.aes_data.payload_size = self.payload_data.payload_size;
+        header.aes_data.payload_tag = self.payload_data.payload_tag;
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(header_size + encrypt_len + additional_len);
+        bytes.extend_from_slice(unsafe {
+            slice::from_raw_parts(&header as * const sgx_sealed_data_t as * const u8, header_size)
+        });
+        bytes.extend_from_slice(&self.payload_data.encrypt);
+        bytes.extend_from_slice(&self.payload_data.additional);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<Self> {
+
+        let header_size = mem::size_of::<sgx_sealed_data_t>();
+        if bytes.len() < header_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let header = unsafe { ptr::read_unaligned(bytes.as_ptr() as * const sgx_sealed_data_t) };
+        let payload_size = header.aes_data.payload_size;
+        let encrypt_len = header.plain_text_offset;
+        if encrypt_len > payload_size {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let additional_len = payload_size - encrypt_len;
+
+        let sealed_data_size = Self::calc_raw_sealed_data_size(additional_len, encrypt_len);
+        if sealed_data_size == u32::max_value() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        if sealed_data_size as usize != bytes.len() {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+
+        let (encrypt, additional) = bytes[header_size..].split_at(encrypt_len as usize);
+
+        let mut sealed_data = Self::default();
+        sealed_data.key_request = header.key_request;
+        sealed_data.payload_data.payload_size = payload_size;
+        sealed_data.payload_data.payload_tag = header.aes_data.payload_tag;
+        sealed_data.payload_data.encrypt = encrypt.to_vec().into_boxed_slice();
+        sealed_data.payload_data.additional = additional.to_vec().into_boxed_slice();
+
+        Ok(sealed_data)
+    }
+
     pub fn seal_data(additional_text: &[u8], encrypt_text: &[u8]) -> SgxResult<Self> {
 
         //let attribute_mask = sgx_attributes_t{flags: SGX_FLAGS_RESERVED | SGX_FLAGS_INITTED | SGX_FLAGS_DEBUG, xfrm: 0};
@@ -387,6 +476,24 @@ impl SgxInternalSealedData {
         self.unseal_data_helper()
     }
 
+    pub fn reseal_data_ex(&self,
+                          key_policy: u16,
+                          attribute_mask: sgx_attributes_t,
+                          misc_mask: sgx_misc_select_t) -> SgxResult<Self> {
+
+        let mut unsealed_data = try!(self.unseal_data());
+        let result = Self::seal_data_ex(key_policy,
+                                        attribute_mask,
+                                        misc_mask,
+                                        unsealed_data.get_additional_txt(),
+                                        unsealed_data.get_decrypt_txt());
+
+        for b in unsealed_data.decrypt.iter_mut() {
+            *b = 0;
+        }
+        result
+    }
+
     pub fn mac_aadata(additional_text: &[u8]) -> SgxResult<Self> {
 
         let attribute_mask = sgx_attributes_t{flags: TSEAL_DEFAULT_FLAGSMASK, xfrm: 0};
@@ -33,6 +33,8 @@
 //! * Exposes APIs to create sealed data which is both confidentiality andintegrity protected.
 //! * Exposes an API to unseal sealed data inside the enclave.
 //! * Provides APIs to authenticate and verify the input data with AES-GMAC.
+//! * Provides a streaming API to seal and unseal data larger than enclave memory (feature `stream`).
+//! * Provides sealed state bound to a monotonic counter, which cannot be rolled back (feature `versioned`).
 //!
 //! The library also provides APIs to help calculate the sealed data size, encrypt text length, and Message Authentication Code (MAC) text length.
 //!
@@ -95,11 +97,28 @@ extern crate sgx_types;
 extern crate sgx_trts;
 extern crate sgx_tcrypto;
 extern crate sgx_tse;
+#[cfg(feature = "serialize")]
+extern crate sgx_serialize;
+#[cfg(any(feature = "stream", feature = "versioned"))]
+extern crate sgx_tstd;
+#[cfg(feature = "versioned")]
+extern crate sgx_tservice;
 
 mod seal;
-pub use self::seal::{SgxSealedData, SgxUnsealedData};
+pub use self::seal::{SgxSealedData, SgxUnsealedData, SgxSealInfo};
 
 mod aad;
 pub use self::aad::{SgxMacAadata};
 
+#[cfg(feature = "stream")]
+mod stream;
+#[cfg(feature = "stream")]
+pub use self::stream::{SealWriter, UnsealReader, SEAL_STREAM_DEFAULT_CHUNK_SIZE, SEAL_STREAM_MAX_CHUNK_SIZE};
+
+#[cfg(feature = "versioned")]
+mod versioned;
+#[cfg(feature = "versioned")]
+pub use self::versioned::{VersionedSealedState, VersionedStateError, FileCounterProvider,
+                          RemoteCounterService, RemoteCounterProvider, RemoteCounterServer};
+
 mod internal;
\ No newline at end of file
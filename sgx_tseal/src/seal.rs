@@ -40,11 +40,67 @@
 //!
 use sgx_types::*;
 use sgx_types::marker::ContiguousMemory;
+use sgx_tse::rsgx_create_report;
 use internal::*;
 use core::mem;
 use core::marker::PhantomData;
 use alloc::slice;
 use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// The key derivation parameters a sealed data blob was sealed with.
+#[derive(Copy, Clone, Default)]
+pub struct SgxSealInfo {
+    pub key_policy: u16,
+    pub isv_svn: sgx_isv_svn_t,
+    pub cpu_svn: sgx_cpu_svn_t,
+    pub attribute_mask: sgx_attributes_t,
+    pub misc_mask: sgx_misc_select_t,
+}
+
+impl SgxSealInfo {
+    ///
+    /// Get the SgxSealInfo of a key request.
+    ///
+    pub fn from_key_request(key_request: &sgx_key_request_t) -> Self {
+        SgxSealInfo {
+            key_policy: key_request.key_policy,
+            isv_svn: key_request.isv_svn,
+            cpu_svn: key_request.cpu_svn,
+            attribute_mask: key_request.attribute_mask,
+            misc_mask: key_request.misc_mask,
+        }
+    }
+
+    ///
+    /// Whether the seal key is bound to the enclave measurement (MRENCLAVE).
+    ///
+    pub fn is_mrenclave_policy(&self) -> bool {
+        (self.key_policy & SGX_KEYPOLICY_MRENCLAVE) != 0
+    }
+
+    ///
+    /// Whether the seal key is bound to the enclave signer (MRSIGNER).
+    ///
+    pub fn is_mrsigner_policy(&self) -> bool {
+        (self.key_policy & SGX_KEYPOLICY_MRSIGNER) != 0
+    }
+
+    ///
+    /// Whether the blob was sealed under an older ISV SVN or a different CPU SVN than the
+    /// calling enclave runs with, so resealing it binds it to the current SVNs.
+    ///
+    /// # Errors
+    ///
+    /// The error of creating the report of the calling enclave.
+    ///
+    pub fn is_outdated(&self) -> SgxResult<bool> {
+        let target_info = sgx_target_info_t::default();
+        let report_data = sgx_report_data_t::default();
+        let report = try!(rsgx_create_report(&target_info, &report_data));
+        Ok(self.isv_svn < report.body.isv_svn || self.cpu_svn.svn != report.body.cpu_svn.svn)
+    }
+}
 
 /// The structure about the unsealed data.
 pub struct SgxUnsealedData<'a, T: 'a + ?Sized> {
@@ -782,6 +838,46 @@ impl<'a, T: 'a + ?Sized> SgxSealedData<'a, T> {
         SgxSealedData::default()
     }
 
+    ///
+    /// Serialize SgxSealedData into an owned buffer in the layout of sgx_sealed_data_t.
+    ///
+    /// The buffer holds the sgx_sealed_data_t header followed by the payload, and can be stored or sent
+    /// as is. Use `from_bytes` to restore it.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    ///
+    /// Deserialize SgxSealedData from a buffer in the layout of sgx_sealed_data_t.
+    ///
+    /// The payload is copied, so the returned SgxSealedData does not borrow from `bytes`.
+    ///
+    /// # Parameters
+    ///
+    /// **bytes**
+    ///
+    /// The buffer produced by `to_bytes` or holding a raw sgx_sealed_data_t.
+    ///
+    /// # Return value
+    ///
+    /// The SgxSealedData restored from the buffer.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The buffer is shorter than the sgx_sealed_data_t header, the length fields of the header are not
+    /// consistent, or the buffer size does not match the size the header describes.
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> SgxResult<Self> {
+        SgxInternalSealedData::from_bytes(bytes).map(|x| {
+            SgxSealedData {
+                inner: x,
+                marker: PhantomData}
+        })
+    }
+
     ///
     /// Get the size of payload in SgxSealedData.
     ///
@@ -803,6 +899,62 @@ impl<'a, T: 'a + ?Sized> SgxSealedData<'a, T> {
         self.inner.get_key_request()
     }
 
+    ///
+    /// Get the key derivation parameters SgxSealedData was sealed with.
+    ///
+    pub fn get_seal_info(&self) -> SgxSealInfo {
+        SgxSealInfo::from_key_request(self.inner.get_key_request())
+    }
+
+    ///
+    /// Unseal SgxSealedData and seal the data again with the default key policy under the
+    /// current SVNs.
+    ///
+    /// This is the same as calling `reseal_data_ex` with the parameters `seal_data` uses.
+    ///
+    pub fn reseal_data(&self) -> SgxResult<Self> {
+        let attribute_mask = sgx_attributes_t{flags: TSEAL_DEFAULT_FLAGSMASK, xfrm: 0};
+        self.reseal_data_ex(SGX_KEYPOLICY_MRSIGNER,
+                            attribute_mask,
+                            TSEAL_DEFAULT_MISCMASK)
+    }
+
+    ///
+    /// Unseal SgxSealedData and seal the data again under the current SVNs.
+    ///
+    /// # Description
+    ///
+    /// The data is unsealed with the key request embedded in SgxSealedData, so data sealed
+    /// under an older ISV SVN or CPU SVN can still be unsealed after an enclave or microcode
+    /// update. It is then sealed with a fresh seal key derived from the current SVNs and the
+    /// given key policy, which may differ from the original one, for example to move from
+    /// MRENCLAVE to MRSIGNER. The additional text is kept.
+    ///
+    /// # Parameters
+    ///
+    /// **key_policy**, **attribute_mask**, **misc_mask**
+    ///
+    /// The same as the parameters of `seal_data_ex`.
+    ///
+    /// # Return value
+    ///
+    /// The resealed data.
+    ///
+    /// # Errors
+    ///
+    /// The errors of `unseal_data` and of `seal_data_ex`.
+    ///
+    pub fn reseal_data_ex(&self,
+                          key_policy: u16,
+                          attribute_mask: sgx_attributes_t,
+                          misc_mask: sgx_misc_select_t) -> SgxResult<Self> {
+        self.inner.reseal_data_ex(key_policy, attribute_mask, misc_mask).map(|x| {
+            SgxSealedData {
+                inner: x,
+                marker: PhantomData}
+        })
+    }
+
     ///
     /// Get a slice of encrypt text in SgxSealedData.
     ///
@@ -838,3 +990,18 @@ impl<'a, T: 'a + ?Sized> SgxSealedData<'a, T> {
         self.inner.get_encrypt_txt_len()
     }
 }
+
+#[cfg(feature = "serialize")]
+impl<'a, T: 'a + ?Sized> ::sgx_serialize::Serializable for SgxSealedData<'a, T> {
+    fn encode<S: ::sgx_serialize::Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        ::sgx_serialize::Serializable::encode(&self.to_bytes(), s)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<'a, T: 'a + ?Sized> ::sgx_serialize::DeSerializable for SgxSealedData<'a, T> {
+    fn decode<D: ::sgx_serialize::Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        let bytes: Vec<u8> = try!(::sgx_serialize::DeSerializable::decode(d));
+        SgxSealedData::from_bytes(&bytes).map_err(|_| d.error("invalid sealed data"))
+    }
+}
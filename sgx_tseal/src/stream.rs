@@ -0,0 +1,438 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//!
+//! Streaming sealing and unsealing.
+//!
+//! `SgxSealedData` seals a single buffer, so the whole plaintext and ciphertext have to fit in
+//! enclave memory at once. `SealWriter` and `UnsealReader` seal a stream of any length instead.
+//! One seal key is derived for the stream, and the data is encrypted in chunks of a fixed size
+//! with AES-GCM.
+//!
+//! The stream starts with a header holding the chunk size and the key request. Every chunk is
+//! followed by its MAC, and its index and whether it is the last chunk are authenticated along
+//! with it. Reordered, dropped or truncated chunks make `UnsealReader` fail.
+//!
+use sgx_types::*;
+use sgx_trts::trts::rsgx_lfence;
+use sgx_tcrypto::*;
+use sgx_tse::*;
+use sgx_tstd::io::{self, Read, Write, ErrorKind};
+use internal::*;
+use core::cmp;
+use core::mem;
+use core::ptr;
+use alloc::vec::Vec;
+
+/// The chunk size used by `SealWriter::new`.
+pub const SEAL_STREAM_DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+/// The largest chunk size accepted by `SealWriter` and `UnsealReader`.
+pub const SEAL_STREAM_MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+const STREAM_MAGIC: &'static [u8] = b"SGXSEALS";
+const STREAM_VERSION: u32 = 1;
+const FINAL_CHUNK_FLAG: u32 = 0x8000_0000;
+
+fn header_size() -> usize {
+    STREAM_MAGIC.len() + 8 + mem::size_of::<sgx_key_request_t>()
+}
+
+fn encode_header(chunk_size: u32, key_request: &sgx_key_request_t) -> Vec<u8> {
+
+    let key_request_size = mem::size_of::<sgx_key_request_t>();
+    let mut header: Vec<u8> = Vec::with_capacity(header_size());
+    header.extend_from_slice(STREAM_MAGIC);
+    put_u32(&mut header, STREAM_VERSION);
+    put_u32(&mut header, chunk_size);
+    header.extend_from_slice(unsafe {
+        ::core::slice::from_raw_parts(key_request as * const sgx_key_request_t as * const u8, key_request_size)
+    });
+    header
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    for i in 0..4 {
+        buf.push((v >> (i * 8)) as u8);
+    }
+}
+
+fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    for i in 0..8 {
+        buf.push((v >> (i * 8)) as u8);
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes[..4].iter().rev().fold(0, |v, &b| (v << 8) | b as u32)
+}
+
+fn chunk_iv(index: u64) -> [u8; SGX_SEAL_IV_SIZE] {
+    let mut iv = [0_u8; SGX_SEAL_IV_SIZE];
+    for i in 0..8 {
+        iv[i] = (index >> (i * 8)) as u8;
+    }
+    iv
+}
+
+fn chunk_aad(header: &[u8], index: u64, word: u32) -> Vec<u8> {
+    let mut aad: Vec<u8> = Vec::with_capacity(header.len() + 12);
+    aad.extend_from_slice(header);
+    put_u64(&mut aad, index);
+    put_u32(&mut aad, word);
+    aad
+}
+
+/// Seals everything written to it into the underlying writer.
+///
+/// `finish` must be called once all data has been written. It seals the last chunk, which
+/// `UnsealReader` requires to detect truncation. A writer dropped without `finish` leaves a
+/// stream that fails to unseal.
+pub struct SealWriter<W: Write> {
+    inner: Option<W>,
+    key: sgx_key_128bit_t,
+    header: Vec<u8>,
+    chunk_size: usize,
+    buf: Vec<u8>,
+    index: u64,
+}
+
+impl<W: Write> SealWriter<W> {
+
+    ///
+    /// Create a SealWriter with the default chunk size, sealing to the enclave signer.
+    ///
+    /// The stream header is written to `inner` immediately.
+    ///
+    pub fn new(inner: W) -> io::Result<Self> {
+        Self::with_chunk_size(inner, SEAL_STREAM_DEFAULT_CHUNK_SIZE)
+    }
+
+    ///
+    /// Create a SealWriter with the given chunk size, sealing to the enclave signer.
+    ///
+    /// A chunk of plaintext is held in enclave memory until it is sealed.
+    ///
+    pub fn with_chunk_size(inner: W, chunk_size: usize) -> io::Result<Self> {
+        let attribute_mask = sgx_attributes_t{flags: TSEAL_DEFAULT_FLAGSMASK, xfrm: 0};
+        Self::new_ex(inner,
+                     chunk_size,
+                     SGX_KEYPOLICY_MRSIGNER,
+                     attribute_mask,
+                     TSEAL_DEFAULT_MISCMASK)
+    }
+
+    ///
+    /// Create a SealWriter with the given chunk size and key derivation parameters.
+    ///
+    /// # Parameters
+    ///
+    /// **inner**
+    ///
+    /// The writer the sealed stream is written to.
+    ///
+    /// **chunk_size**
+    ///
+    /// The size of a plaintext chunk, up to SEAL_STREAM_MAX_CHUNK_SIZE.
+    ///
+    /// **key_policy**, **attribute_mask**, **misc_mask**
+    ///
+    /// The same as the parameters of `SgxSealedData::seal_data_ex`.
+    ///
+    /// # Errors
+    ///
+    /// **InvalidInput**
+    ///
+    /// The chunk size is zero or too large.
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The key policy or the attribute mask is not valid.
+    ///
+    /// Otherwise, the error of deriving the seal key or of writing the header.
+    ///
+    pub fn new_ex(mut inner: W,
+                  chunk_size: usize,
+                  key_policy: u16,
+                  attribute_mask: sgx_attributes_t,
+                  misc_mask: sgx_misc_select_t) -> io::Result<Self> {
+
+        if chunk_size == 0 || chunk_size > SEAL_STREAM_MAX_CHUNK_SIZE {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "invalid chunk size"));
+        }
+
+        let key_request = try!(seal_key_request(key_policy, attribute_mask, misc_mask));
+        let key = try!(rsgx_get_key(&key_request).map_err(|ret| {
+            if ret != sgx_status_t::SGX_ERROR_OUT_OF_MEMORY {
+                sgx_status_t::SGX_ERROR_UNEXPECTED
+            } else {
+                ret
+            }
+        }));
+
+        let mut writer = SealWriter {
+            inner: None,
+            key: key,
+            header: encode_header(chunk_size as u32, &key_request),
+            chunk_size: chunk_size,
+            buf: Vec::with_capacity(chunk_size),
+            index: 0,
+        };
+        try!(inner.write_all(&writer.header));
+        writer.inner = Some(inner);
+        Ok(writer)
+    }
+
+    ///
+    /// Get a reference to the underlying writer.
+    ///
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    ///
+    /// Seal the buffered data as the last chunk and return the underlying writer.
+    ///
+    pub fn finish(mut self) -> io::Result<W> {
+        try!(self.seal_chunk(true));
+        let mut inner = self.inner.take().unwrap();
+        try!(inner.flush());
+        Ok(inner)
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> io::Result<()> {
+
+        let len = self.buf.len();
+        let word = if last { len as u32 | FINAL_CHUNK_FLAG } else { len as u32 };
+        let iv = chunk_iv(self.index);
+        let aad = chunk_aad(&self.header, self.index, word);
+
+        let mut encrypt = vec![0_u8; len];
+        let mut tag = [0_u8; SGX_SEAL_TAG_SIZE];
+        try!(rsgx_rijndael128GCM_encrypt(&self.key, &self.buf, &iv, &aad, &mut encrypt, &mut tag));
+
+        let mut record: Vec<u8> = Vec::with_capacity(4);
+        put_u32(&mut record, word);
+        let inner = self.inner.as_mut().unwrap();
+        try!(inner.write_all(&record));
+        try!(inner.write_all(&encrypt));
+        try!(inner.write_all(&tag));
+
+        self.buf.clear();
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SealWriter<W> {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() == self.chunk_size {
+            try!(self.seal_chunk(false));
+        }
+        let n = cmp::min(buf.len(), self.chunk_size - self.buf.len());
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    ///
+    /// Seal the buffered data as a chunk and flush the underlying writer.
+    ///
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.len() > 0 {
+            try!(self.seal_chunk(false));
+        }
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for SealWriter<W> {
+    fn drop(&mut self) {
+        self.key = sgx_key_128bit_t::default();
+    }
+}
+
+/// Unseals a stream written by `SealWriter` from the underlying reader.
+///
+/// Reading returns an error if a chunk fails to unseal, or if the stream ends before its last
+/// chunk. Data after the last chunk is not read.
+pub struct UnsealReader<R: Read> {
+    inner: R,
+    key: sgx_key_128bit_t,
+    header: Vec<u8>,
+    chunk_size: usize,
+    buf: Vec<u8>,
+    pos: usize,
+    index: u64,
+    done: bool,
+}
+
+impl<R: Read> UnsealReader<R> {
+
+    ///
+    /// Create an UnsealReader, reading the stream header from `inner`.
+    ///
+    /// # Errors
+    ///
+    /// **InvalidData**
+    ///
+    /// The header is not the header of a sealed stream.
+    ///
+    /// **SGX_ERROR_INVALID_CPUSVN**, **SGX_ERROR_INVALID_ISVSVN**
+    ///
+    /// The stream was sealed on a newer platform or by a newer enclave.
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The seal key cannot be derived.
+    ///
+    pub fn new(mut inner: R) -> io::Result<Self> {
+
+        let mut header = vec![0_u8; header_size()];
+        try!(inner.read_exact(&mut header));
+
+        if &header[..STREAM_MAGIC.len()] != STREAM_MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a sealed stream"));
+        }
+        let mut offset = STREAM_MAGIC.len();
+        if read_u32(&header[offset..]) != STREAM_VERSION {
+            return Err(io::Error::new(ErrorKind::InvalidData, "unsupported sealed stream version"));
+        }
+        offset += 4;
+        let chunk_size = read_u32(&header[offset..]) as usize;
+        if chunk_size == 0 || chunk_size > SEAL_STREAM_MAX_CHUNK_SIZE {
+            return Err(io::Error::new(ErrorKind::InvalidData, "invalid chunk size"));
+        }
+        offset += 4;
+
+        let key_request = unsafe {
+            ptr::read_unaligned(header[offset..].as_ptr() as * const sgx_key_request_t)
+        };
+        if key_request.key_name != SGX_KEYSELECT_SEAL {
+            return Err(io::Error::new(ErrorKind::InvalidData, "invalid key request"));
+        }
+
+        let key = try!(rsgx_get_key(&key_request).map_err(|ret| {
+            if (ret == sgx_status_t::SGX_ERROR_INVALID_CPUSVN) ||
+               (ret == sgx_status_t::SGX_ERROR_INVALID_ISVSVN) ||
+               (ret == sgx_status_t::SGX_ERROR_OUT_OF_MEMORY) {
+                ret
+            } else {
+                sgx_status_t::SGX_ERROR_MAC_MISMATCH
+            }
+        }));
+
+        Ok(UnsealReader {
+            inner: inner,
+            key: key,
+            header: header,
+            chunk_size: chunk_size,
+            buf: Vec::new(),
+            pos: 0,
+            index: 0,
+            done: false,
+        })
+    }
+
+    ///
+    /// Get a reference to the underlying reader.
+    ///
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    fn unseal_chunk(&mut self) -> io::Result<()> {
+
+        let mut word = [0_u8; 4];
+        try!(self.read_record(&mut word));
+        let word = read_u32(&word);
+        let len = (word & !FINAL_CHUNK_FLAG) as usize;
+        if len > self.chunk_size {
+            return Err(io::Error::new(ErrorKind::InvalidData, "invalid chunk length"));
+        }
+
+        let mut encrypt = vec![0_u8; len];
+        let mut tag = [0_u8; SGX_SEAL_TAG_SIZE];
+        try!(self.read_record(&mut encrypt));
+        try!(self.read_record(&mut tag));
+
+        // the chunk length came from outside the enclave
+        rsgx_lfence();
+
+        let iv = chunk_iv(self.index);
+        let aad = chunk_aad(&self.header, self.index, word);
+        self.buf.clear();
+        self.buf.resize(len, 0);
+        self.pos = 0;
+        let ret = rsgx_rijndael128GCM_decrypt(&self.key, &encrypt, &iv, &aad, &tag, &mut self.buf);
+        if ret.is_err() {
+            self.buf.clear();
+            return Err(io::Error::from(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+        }
+
+        self.index += 1;
+        self.done = (word & FINAL_CHUNK_FLAG) != 0;
+        Ok(())
+    }
+
+    fn read_record(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf).map_err(|e| {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                io::Error::new(ErrorKind::UnexpectedEof, "sealed stream is truncated")
+            } else {
+                e
+            }
+        })
+    }
+}
+
+impl<R: Read> Read for UnsealReader<R> {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+        loop {
+            if self.pos < self.buf.len() {
+                let n = cmp::min(buf.len(), self.buf.len() - self.pos);
+                buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+                self.pos += n;
+                return Ok(n);
+            }
+            if self.done {
+                return Ok(0);
+            }
+            try!(self.unseal_chunk());
+        }
+    }
+}
+
+impl<R: Read> Drop for UnsealReader<R> {
+    fn drop(&mut self) {
+        self.key = sgx_key_128bit_t::default();
+    }
+}
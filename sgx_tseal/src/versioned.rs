@@ -0,0 +1,517 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Rollback-protected sealed state.
+//!
+//! A sealed blob is only bound to the enclave, so the host can hand back any
+//! blob the enclave ever sealed. `VersionedSealedState` binds each blob to a
+//! monotonic counter: the UUID and value of the counter go into the additional
+//! text of the blob, the counter is incremented on every seal, and a blob is
+//! only unsealed while its value is the current value of the counter.
+//!
+//! The counters come from a `CounterProvider`: the Platform Services Enclave
+//! (`sgx_tservice::sgxcounter::PseCounterProvider`), files on the host for
+//! tests (`FileCounterProvider`), or a remote counter service reached through
+//! a `RemoteCounterService` (`RemoteCounterProvider`).
+
+use sgx_types::*;
+use sgx_types::marker::ContiguousMemory;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_trts::memeq::ConsttimeMemEq;
+use sgx_tcrypto::rsgx_rijndael128_cmac_slice;
+use sgx_tservice::sgxcounter::CounterProvider;
+use sgx_tstd::untrusted::fs;
+use sgx_tstd::io::ErrorKind;
+use sgx_tstd::path::{Path, PathBuf};
+use sgx_tstd::sync::SgxMutex;
+use seal::SgxSealedData;
+use core::fmt;
+use core::marker::PhantomData;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const STATE_AAD_MAGIC: &'static [u8; 4] = b"VSS1";
+const UUID_SIZE: usize = SGX_MC_UUID_COUNTER_ID_SIZE + SGX_MC_UUID_NONCE_SIZE;
+const STATE_AAD_SIZE: usize = 4 + UUID_SIZE + 4;
+
+fn uuid_to_bytes(uuid: &sgx_mc_uuid_t) -> [u8; UUID_SIZE] {
+    let mut bytes = [0_u8; UUID_SIZE];
+    bytes[..SGX_MC_UUID_COUNTER_ID_SIZE].copy_from_slice(&uuid.counter_id);
+    bytes[SGX_MC_UUID_COUNTER_ID_SIZE..].copy_from_slice(&uuid.nonce);
+    bytes
+}
+
+fn uuid_from_bytes(bytes: &[u8]) -> sgx_mc_uuid_t {
+    let mut uuid = sgx_mc_uuid_t::default();
+    uuid.counter_id.copy_from_slice(&bytes[..SGX_MC_UUID_COUNTER_ID_SIZE]);
+    uuid.nonce.copy_from_slice(&bytes[SGX_MC_UUID_COUNTER_ID_SIZE..UUID_SIZE]);
+    uuid
+}
+
+fn put_u32(buf: &mut [u8], value: u32) {
+    for i in 0..4 {
+        buf[i] = (value >> (8 * i)) as u8;
+    }
+}
+
+fn get_u32(buf: &[u8]) -> u32 {
+    (0..4).fold(0_u32, |v, i| v | (buf[i] as u32) << (8 * i))
+}
+
+/// Why a versioned blob was refused.
+#[derive(Copy, Clone, Debug)]
+pub enum VersionedStateError {
+    /// The blob was sealed at counter value `sealed`, but the counter is at `current`.
+    Stale { sealed: u32, current: u32 },
+    /// The blob is bound to another counter.
+    CounterMismatch,
+    SgxStatus(sgx_status_t),
+}
+
+impl VersionedStateError {
+    pub fn __description(&self) -> &str {
+        match *self {
+            VersionedStateError::Stale { .. } => "sealed state is not the latest version",
+            VersionedStateError::CounterMismatch => "sealed state belongs to another counter",
+            VersionedStateError::SgxStatus(ref status) => status.__description(),
+        }
+    }
+}
+
+impl fmt::Display for VersionedStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VersionedStateError::Stale { sealed, current } => {
+                write!(f, "sealed state has version {}, the counter is at {}", sealed, current)
+            },
+            VersionedStateError::CounterMismatch => write!(f, "sealed state belongs to another counter"),
+            VersionedStateError::SgxStatus(status) => status.fmt(f),
+        }
+    }
+}
+
+impl From<sgx_status_t> for VersionedStateError {
+    fn from(status: sgx_status_t) -> VersionedStateError {
+        VersionedStateError::SgxStatus(status)
+    }
+}
+
+///
+/// Sealed state of type `T` which cannot be rolled back.
+///
+/// The state owns one counter of `P`. The application keeps the UUID of the counter, which
+/// identifies the state, and stores the latest blob returned by `seal`; `unseal` only accepts
+/// that blob. Sealing to the MRSIGNER policy, as SgxSealedData::seal_data.
+///
+/// `seal` seals the data at the next value of the counter before incrementing it, so a failed
+/// seal leaves the previous blob valid. Once the counter is incremented, the previous blob is
+/// stale: if the new blob is lost before it is stored, the state is lost too. Applications that
+/// cannot afford this should keep the new blob durable before sealing again.
+///
+/// Seals through one state are serialized, so threads sharing it each get their own version.
+/// Two states opened on the same counter are not: when their seals overlap, the one whose
+/// version was taken by the other fails with `VersionedStateError::Stale`.
+///
+pub struct VersionedSealedState<T: ?Sized, P: CounterProvider> {
+    provider: P,
+    counter_uuid: sgx_mc_uuid_t,
+    seal_lock: SgxMutex<()>,
+    marker: PhantomData<T>,
+}
+
+impl<T: ?Sized, P: CounterProvider> VersionedSealedState<T, P> {
+
+    ///
+    /// Creates a counter for a new piece of state.
+    ///
+    /// Nothing is sealed yet, so `unseal` fails until the first `seal`.
+    ///
+    pub fn create(provider: P) -> SgxResult<Self> {
+        let (counter_uuid, _) = try!(provider.create());
+        Ok(VersionedSealedState::open(provider, &counter_uuid))
+    }
+
+    ///
+    /// Opens the state whose counter is `counter_uuid`.
+    ///
+    pub fn open(provider: P, counter_uuid: &sgx_mc_uuid_t) -> Self {
+        VersionedSealedState {
+            provider: provider,
+            counter_uuid: *counter_uuid,
+            seal_lock: SgxMutex::new(()),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn get_counter_uuid(&self) -> &sgx_mc_uuid_t {
+        &self.counter_uuid
+    }
+
+    ///
+    /// Returns the current value of the counter, i.e. the version of the latest blob.
+    ///
+    pub fn version(&self) -> SgxResult<u32> {
+        self.provider.read(&self.counter_uuid)
+    }
+
+    ///
+    /// Destroys the counter. No blob of the state can be unsealed anymore.
+    ///
+    pub fn destroy(self) -> SgxError {
+        self.provider.destroy(&self.counter_uuid)
+    }
+
+    fn additional_text(&self, version: u32) -> [u8; STATE_AAD_SIZE] {
+        let mut aad = [0_u8; STATE_AAD_SIZE];
+        aad[..4].copy_from_slice(STATE_AAD_MAGIC);
+        aad[4..4 + UUID_SIZE].copy_from_slice(&uuid_to_bytes(&self.counter_uuid));
+        put_u32(&mut aad[4 + UUID_SIZE..], version);
+        aad
+    }
+
+    fn next_version(&self) -> Result<u32, VersionedStateError> {
+        let current = try!(self.provider.read(&self.counter_uuid));
+        current.checked_add(1).ok_or(VersionedStateError::SgxStatus(sgx_status_t::SGX_ERROR_MC_USED_UP))
+    }
+
+    fn commit(&self, version: u32) -> Result<(), VersionedStateError> {
+        let current = try!(self.provider.increment(&self.counter_uuid));
+        if current != version {
+            // Another writer incremented the counter in between.
+            return Err(VersionedStateError::Stale { sealed: version, current: current });
+        }
+        Ok(())
+    }
+
+    // Seals with `seal` at the next version and commits that version, holding the seal lock
+    // from reading the counter to incrementing it.
+    fn seal_next<F>(&self, seal: F) -> Result<Vec<u8>, VersionedStateError>
+        where F: FnOnce(&[u8]) -> SgxResult<Vec<u8>> {
+
+        // Nothing is guarded but the order of the seals, so a poisoned lock is still good.
+        let _guard = match self.seal_lock.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let version = try!(self.next_version());
+        let blob = try!(seal(&self.additional_text(version)));
+        try!(self.commit(version));
+        Ok(blob)
+    }
+
+    // Checks the authenticated additional text of an unsealed blob.
+    fn check(&self, aad: &[u8]) -> Result<(), VersionedStateError> {
+        if aad.len() != STATE_AAD_SIZE || &aad[..4] != &STATE_AAD_MAGIC[..] {
+            return Err(VersionedStateError::SgxStatus(sgx_status_t::SGX_ERROR_INVALID_PARAMETER));
+        }
+        if aad[4..4 + UUID_SIZE] != uuid_to_bytes(&self.counter_uuid) {
+            return Err(VersionedStateError::CounterMismatch);
+        }
+        let sealed = get_u32(&aad[4 + UUID_SIZE..]);
+        let current = try!(self.provider.read(&self.counter_uuid));
+        if sealed != current {
+            return Err(VersionedStateError::Stale { sealed: sealed, current: current });
+        }
+        Ok(())
+    }
+}
+
+impl<T: Copy + ContiguousMemory, P: CounterProvider> VersionedSealedState<T, P> {
+
+    ///
+    /// Seals `data` as the next version of the state and increments the counter.
+    ///
+    /// Returns the blob in the layout of sgx_sealed_data_t.
+    ///
+    pub fn seal(&self, data: &T) -> Result<Vec<u8>, VersionedStateError> {
+        self.seal_next(|aad| SgxSealedData::<T>::seal_data(aad, data).map(|sealed| sealed.to_bytes()))
+    }
+
+    ///
+    /// Unseals `blob`, if it is the latest version of the state.
+    ///
+    pub fn unseal(&self, blob: &[u8]) -> Result<T, VersionedStateError> {
+        let sealed = try!(SgxSealedData::<T>::from_bytes(blob));
+        let unsealed = try!(sealed.unseal_data());
+        try!(self.check(unsealed.get_additional_txt()));
+        Ok(*unsealed.get_decrypt_txt())
+    }
+}
+
+impl<T: Copy + ContiguousMemory, P: CounterProvider> VersionedSealedState<[T], P> {
+
+    ///
+    /// Seals `data` as the next version of the state and increments the counter.
+    ///
+    /// Returns the blob in the layout of sgx_sealed_data_t.
+    ///
+    pub fn seal(&self, data: &[T]) -> Result<Vec<u8>, VersionedStateError> {
+        self.seal_next(|aad| SgxSealedData::<[T]>::seal_data(aad, data).map(|sealed| sealed.to_bytes()))
+    }
+
+    ///
+    /// Unseals `blob`, if it is the latest version of the state.
+    ///
+    pub fn unseal(&self, blob: &[u8]) -> Result<Vec<T>, VersionedStateError> {
+        let sealed = try!(SgxSealedData::<[T]>::from_bytes(blob));
+        let unsealed = try!(sealed.unseal_data());
+        try!(self.check(unsealed.get_additional_txt()));
+        Ok(unsealed.get_decrypt_txt().to_vec())
+    }
+}
+
+///
+/// Counters kept in files of a host directory, one file per counter.
+///
+/// The files are neither encrypted nor authenticated, so the host can roll them back along with
+/// the blobs: this stands in for a real counter in tests and in simulation mode only.
+///
+pub struct FileCounterProvider {
+    dir: PathBuf,
+}
+
+impl FileCounterProvider {
+    ///
+    /// Keeps the counters in `dir`, which must exist.
+    ///
+    pub fn new<P: AsRef<Path>>(dir: P) -> FileCounterProvider {
+        FileCounterProvider { dir: dir.as_ref().to_path_buf() }
+    }
+
+    fn path(&self, counter_uuid: &sgx_mc_uuid_t) -> PathBuf {
+        let name: String = uuid_to_bytes(counter_uuid).iter().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(name)
+    }
+
+    fn store(&self, counter_uuid: &sgx_mc_uuid_t, value: u32) -> SgxError {
+        let mut bytes = [0_u8; 4];
+        put_u32(&mut bytes, value);
+        fs::write(self.path(counter_uuid), &bytes).map_err(|_| sgx_status_t::SGX_ERROR_UNEXPECTED)
+    }
+}
+
+impl CounterProvider for FileCounterProvider {
+    fn create(&self) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+        let mut bytes = [0_u8; UUID_SIZE];
+        try!(rsgx_read_rand(&mut bytes));
+        let counter_uuid = uuid_from_bytes(&bytes);
+        try!(self.store(&counter_uuid, 0));
+        Ok((counter_uuid, 0))
+    }
+
+    fn destroy(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxError {
+        fs::remove_file(self.path(counter_uuid)).map_err(|err| match err.kind() {
+            ErrorKind::NotFound => sgx_status_t::SGX_ERROR_MC_NOT_FOUND,
+            _ => sgx_status_t::SGX_ERROR_UNEXPECTED,
+        })
+    }
+
+    fn increment(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+        let value = try!(try!(self.read(counter_uuid)).checked_add(1).ok_or(sgx_status_t::SGX_ERROR_MC_USED_UP));
+        try!(self.store(counter_uuid, value));
+        Ok(value)
+    }
+
+    fn read(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+        let bytes = try!(fs::read(self.path(counter_uuid)).map_err(|err| match err.kind() {
+            ErrorKind::NotFound => sgx_status_t::SGX_ERROR_MC_NOT_FOUND,
+            _ => sgx_status_t::SGX_ERROR_UNEXPECTED,
+        }));
+        if bytes.len() != 4 {
+            return Err(sgx_status_t::SGX_ERROR_UNEXPECTED);
+        }
+        Ok(get_u32(&bytes))
+    }
+}
+
+///
+/// The transport to a counter service outside the enclave, e.g. a counter enclave on another
+/// machine, which serves requests with `RemoteCounterServer`.
+///
+pub trait RemoteCounterService {
+    /// Sends `request` to the service and returns its response.
+    fn call(&self, request: &[u8]) -> SgxResult<Vec<u8>>;
+}
+
+const REMOTE_REQUEST_TAG: u8 = 0x51;
+const REMOTE_RESPONSE_TAG: u8 = 0x52;
+const REMOTE_NONCE_SIZE: usize = 16;
+const REMOTE_MAC_SIZE: usize = SGX_CMAC_MAC_SIZE;
+
+// tag, op, uuid, nonce, mac
+const REMOTE_REQUEST_BODY_SIZE: usize = 2 + UUID_SIZE + REMOTE_NONCE_SIZE;
+const REMOTE_REQUEST_SIZE: usize = REMOTE_REQUEST_BODY_SIZE + REMOTE_MAC_SIZE;
+// tag, op, uuid, value, status, nonce, mac
+const REMOTE_RESPONSE_BODY_SIZE: usize = 2 + UUID_SIZE + 4 + 4 + REMOTE_NONCE_SIZE;
+const REMOTE_RESPONSE_SIZE: usize = REMOTE_RESPONSE_BODY_SIZE + REMOTE_MAC_SIZE;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum RemoteOp {
+    Create = 1,
+    Destroy = 2,
+    Increment = 3,
+    Read = 4,
+}
+
+impl RemoteOp {
+    fn from_u8(op: u8) -> Option<RemoteOp> {
+        match op {
+            1 => Some(RemoteOp::Create),
+            2 => Some(RemoteOp::Destroy),
+            3 => Some(RemoteOp::Increment),
+            4 => Some(RemoteOp::Read),
+            _ => None,
+        }
+    }
+}
+
+fn mac_matches(key: &sgx_cmac_128bit_key_t, body: &[u8], mac: &[u8]) -> bool {
+    match rsgx_rijndael128_cmac_slice(key, body) {
+        Ok(expected) => expected[..].consttime_memeq(mac),
+        Err(_) => false,
+    }
+}
+
+///
+/// The counters of a remote counter service.
+///
+/// Requests and responses are authenticated with AES-CMAC under `key`, which the enclave shares
+/// with the service, e.g. through a remote attestation session; each response must echo the
+/// random nonce of its request, so old responses cannot be replayed.
+///
+pub struct RemoteCounterProvider<S: RemoteCounterService> {
+    service: S,
+    key: sgx_cmac_128bit_key_t,
+}
+
+impl<S: RemoteCounterService> RemoteCounterProvider<S> {
+    pub fn new(service: S, key: &sgx_cmac_128bit_key_t) -> RemoteCounterProvider<S> {
+        RemoteCounterProvider { service: service, key: *key }
+    }
+
+    fn call(&self, op: RemoteOp, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+        let mut request = [0_u8; REMOTE_REQUEST_SIZE];
+        request[0] = REMOTE_REQUEST_TAG;
+        request[1] = op as u8;
+        request[2..2 + UUID_SIZE].copy_from_slice(&uuid_to_bytes(counter_uuid));
+        try!(rsgx_read_rand(&mut request[2 + UUID_SIZE..REMOTE_REQUEST_BODY_SIZE]));
+        let mac = try!(rsgx_rijndael128_cmac_slice(&self.key, &request[..REMOTE_REQUEST_BODY_SIZE]));
+        request[REMOTE_REQUEST_BODY_SIZE..].copy_from_slice(&mac);
+
+        let response = try!(self.service.call(&request));
+        if response.len() != REMOTE_RESPONSE_SIZE ||
+           !mac_matches(&self.key, &response[..REMOTE_RESPONSE_BODY_SIZE], &response[REMOTE_RESPONSE_BODY_SIZE..]) ||
+           response[0] != REMOTE_RESPONSE_TAG ||
+           response[1] != op as u8 ||
+           response[2 + UUID_SIZE + 8..REMOTE_RESPONSE_BODY_SIZE] != request[2 + UUID_SIZE..REMOTE_REQUEST_BODY_SIZE] {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        let uuid = uuid_from_bytes(&response[2..]);
+        if op != RemoteOp::Create && response[2..2 + UUID_SIZE] != request[2..2 + UUID_SIZE] {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        let value = get_u32(&response[2 + UUID_SIZE..]);
+        match get_u32(&response[2 + UUID_SIZE + 4..]) {
+            0 => Ok((uuid, value)),
+            status => Err(sgx_status_t::from_repr(status).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED)),
+        }
+    }
+}
+
+impl<S: RemoteCounterService> CounterProvider for RemoteCounterProvider<S> {
+    fn create(&self) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+        self.call(RemoteOp::Create, &sgx_mc_uuid_t::default())
+    }
+
+    fn destroy(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxError {
+        self.call(RemoteOp::Destroy, counter_uuid).map(|_| ())
+    }
+
+    fn increment(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+        self.call(RemoteOp::Increment, counter_uuid).map(|(_, value)| value)
+    }
+
+    fn read(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+        self.call(RemoteOp::Read, counter_uuid).map(|(_, value)| value)
+    }
+}
+
+///
+/// The service side of `RemoteCounterProvider`: serves authenticated requests from the counters
+/// of another provider, e.g. the PSE counters of a counter enclave.
+///
+pub struct RemoteCounterServer<P: CounterProvider> {
+    provider: P,
+    key: sgx_cmac_128bit_key_t,
+}
+
+impl<P: CounterProvider> RemoteCounterServer<P> {
+    pub fn new(provider: P, key: &sgx_cmac_128bit_key_t) -> RemoteCounterServer<P> {
+        RemoteCounterServer { provider: provider, key: *key }
+    }
+
+    ///
+    /// Handles one request and returns the response to send back.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The request is malformed or not authenticated with the shared key. No response is due.
+    ///
+    pub fn handle(&self, request: &[u8]) -> SgxResult<Vec<u8>> {
+        if request.len() != REMOTE_REQUEST_SIZE ||
+           !mac_matches(&self.key, &request[..REMOTE_REQUEST_BODY_SIZE], &request[REMOTE_REQUEST_BODY_SIZE..]) ||
+           request[0] != REMOTE_REQUEST_TAG {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        let op = try!(RemoteOp::from_u8(request[1]).ok_or(sgx_status_t::SGX_ERROR_MAC_MISMATCH));
+        let counter_uuid = uuid_from_bytes(&request[2..]);
+        let result = match op {
+            RemoteOp::Create => self.provider.create(),
+            RemoteOp::Destroy => self.provider.destroy(&counter_uuid).map(|_| (counter_uuid, 0)),
+            RemoteOp::Increment => self.provider.increment(&counter_uuid).map(|value| (counter_uuid, value)),
+            RemoteOp::Read => self.provider.read(&counter_uuid).map(|value| (counter_uuid, value)),
+        };
+        let (uuid, value, status) = match result {
+            Ok((uuid, value)) => (uuid, value, 0),
+            Err(status) => (counter_uuid, 0, status as u32),
+        };
+
+        let mut response = vec![0_u8; REMOTE_RESPONSE_SIZE];
+        response[0] = REMOTE_RESPONSE_TAG;
+        response[1] = op as u8;
+        response[2..2 + UUID_SIZE].copy_from_slice(&uuid_to_bytes(&uuid));
+        put_u32(&mut response[2 + UUID_SIZE..], value);
+        put_u32(&mut response[2 + UUID_SIZE + 4..], status);
+        response[2 + UUID_SIZE + 8..REMOTE_RESPONSE_BODY_SIZE].copy_from_slice(&request[2 + UUID_SIZE..REMOTE_REQUEST_BODY_SIZE]);
+        let mac = try!(rsgx_rijndael128_cmac_slice(&self.key, &response[..REMOTE_RESPONSE_BODY_SIZE]));
+        response[REMOTE_RESPONSE_BODY_SIZE..].copy_from_slice(&mac);
+        Ok(response)
+    }
+}
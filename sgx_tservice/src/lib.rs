@@ -39,6 +39,10 @@
 #![allow(non_camel_case_types)]
 
 extern crate sgx_types;
+extern crate sgx_trts;
+extern crate sgx_tcrypto;
+
+mod lock;
 
 pub mod sgxtime;
 pub mod sgxcounter;
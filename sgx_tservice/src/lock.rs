@@ -0,0 +1,58 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::*;
+use core::cell::UnsafeCell;
+
+// A value behind a tRTS spinlock, for the providers which keep state.
+pub struct SpinLocked<T> {
+    lock: UnsafeCell<sgx_spinlock_t>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinLocked<T> {}
+unsafe impl<T: Send> Send for SpinLocked<T> {}
+
+impl<T> SpinLocked<T> {
+    pub fn new(data: T) -> SpinLocked<T> {
+        SpinLocked {
+            lock: UnsafeCell::new(SGX_SPINLOCK_INITIALIZER),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    // f must not panic, the lock would be left held.
+    pub fn with<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        unsafe {
+            sgx_spin_lock(self.lock.get());
+            let result = f(&mut *self.data.get());
+            sgx_spin_unlock(self.lock.get());
+            result
+        }
+    }
+}
@@ -27,12 +27,17 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use sgx_types::*;
+use lock::SpinLocked;
 use core::cell::Cell;
 
 /// Monotonic counter ID
+///
+/// The counter comes from the provider set with set_counter_provider when it is created, or from
+/// the Platform Services Enclave if none is set.
 pub struct SgxMonotonicCounter {
     counter_uuid: sgx_mc_uuid_t,
     initflag: Cell<bool>,
+    provider: &'static (CounterProvider + Sync),
 }
 
 impl SgxMonotonicCounter {
@@ -123,16 +128,14 @@ impl SgxMonotonicCounter {
     ///
     pub fn new(counter_value: &mut u32) -> SgxResult<Self> {
 
-        let mut counter_uuid = sgx_mc_uuid_t::default();
-        let ret = rsgx_create_monotonic_counter(&mut counter_uuid, counter_value);
-
-        match ret {
-            sgx_status_t::SGX_SUCCESS => Ok(SgxMonotonicCounter{
-                                            counter_uuid: counter_uuid,
-                                            initflag: Cell::new(true),
-                                         }),
-            _ => Err(ret),
-        }
+        let provider = counter_provider();
+        let (counter_uuid, value) = try!(provider.create());
+        *counter_value = value;
+        Ok(SgxMonotonicCounter{
+            counter_uuid: counter_uuid,
+            initflag: Cell::new(true),
+            provider: provider,
+        })
     }
 
     ///
@@ -233,16 +236,14 @@ impl SgxMonotonicCounter {
     ///
     pub fn new_ex(owner_policy: u16, owner_attribute_mask: &sgx_attributes_t, counter_value: &mut u32) -> SgxResult<Self> {
 
-        let mut counter_uuid = sgx_mc_uuid_t::default();
-        let ret = rsgx_create_monotonic_counter_ex(owner_policy, owner_attribute_mask, &mut counter_uuid, counter_value);
-
-        match ret {
-            sgx_status_t::SGX_SUCCESS => Ok(SgxMonotonicCounter{
-                                            counter_uuid: counter_uuid,
-                                            initflag: Cell::new(true),
-                                         }),
-            _ => Err(ret),
-        }
+        let provider = counter_provider();
+        let (counter_uuid, value) = try!(provider.create_ex(owner_policy, owner_attribute_mask));
+        *counter_value = value;
+        Ok(SgxMonotonicCounter{
+            counter_uuid: counter_uuid,
+            initflag: Cell::new(true),
+            provider: provider,
+        })
     }
 
     ///
@@ -321,13 +322,9 @@ impl SgxMonotonicCounter {
             return Err(sgx_status_t::SGX_ERROR_MC_NOT_FOUND);
         }
 
-        let ret = rsgx_destroy_monotonic_counter(&self.counter_uuid);
-        if ret == sgx_status_t::SGX_SUCCESS {
-            self.initflag.set(false);
-            Ok(())
-        } else {
-            Err(ret)
-        }
+        try!(self.provider.destroy(&self.counter_uuid));
+        self.initflag.set(false);
+        Ok(())
     }
 
     ///
@@ -406,12 +403,7 @@ impl SgxMonotonicCounter {
             return Err(sgx_status_t::SGX_ERROR_MC_NOT_FOUND);
         }
 
-        let mut counter_value: u32 = 0;
-        let ret = rsgx_increment_monotonic_counter(&self.counter_uuid, &mut counter_value);
-         match ret {
-            sgx_status_t::SGX_SUCCESS => Ok(counter_value),
-            _ => Err(ret),
-        }
+        self.provider.increment(&self.counter_uuid)
     }
 
     ///
@@ -481,12 +473,7 @@ impl SgxMonotonicCounter {
             return Err(sgx_status_t::SGX_ERROR_MC_NOT_FOUND);
         }
 
-        let mut counter_value: u32 = 0;
-        let ret = rsgx_read_monotonic_counter(&self.counter_uuid, &mut counter_value);
-        match ret {
-            sgx_status_t::SGX_SUCCESS => Ok(counter_value),
-            _ => Err(ret),
-        }
+        self.provider.read(&self.counter_uuid)
     }
 }
 
@@ -499,6 +486,268 @@ impl Drop for SgxMonotonicCounter {
     }
 }
 
+///
+/// A source of monotonic counters, addressed by UUID.
+///
+/// Unlike SgxMonotonicCounter, a provider does not destroy the counters it creates, so a counter
+/// can outlive the enclave and be opened again from its UUID.
+///
+pub trait CounterProvider {
+    /// Creates a counter, and returns its UUID and initial value.
+    fn create(&self) -> SgxResult<(sgx_mc_uuid_t, u32)>;
+
+    ///
+    /// Creates a counter with the given owner policy and owner attribute mask, as
+    /// SgxMonotonicCounter::new_ex.
+    ///
+    /// Providers without owner policies create a counter as `create` does.
+    ///
+    fn create_ex(&self, owner_policy: u16, owner_attribute_mask: &sgx_attributes_t) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+        let _ = (owner_policy, owner_attribute_mask);
+        self.create()
+    }
+
+    fn destroy(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxError;
+
+    /// Increments the counter by 1, and returns the new value.
+    fn increment(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32>;
+
+    fn read(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32>;
+}
+
+impl<'a, P: CounterProvider + ?Sized> CounterProvider for &'a P {
+    fn create(&self) -> SgxResult<(sgx_mc_uuid_t, u32)> { (**self).create() }
+    fn create_ex(&self, owner_policy: u16, owner_attribute_mask: &sgx_attributes_t) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+        (**self).create_ex(owner_policy, owner_attribute_mask)
+    }
+    fn destroy(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxError { (**self).destroy(counter_uuid) }
+    fn increment(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> { (**self).increment(counter_uuid) }
+    fn read(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> { (**self).read(counter_uuid) }
+}
+
+///
+/// The monotonic counters of the Platform Services Enclave.
+///
+/// The caller should call rsgx_create_pse_session to establish a session with the platform service enclave
+/// before using the provider. The errors are those of the matching SgxMonotonicCounter methods.
+///
+#[derive(Copy, Clone, Default)]
+pub struct PseCounterProvider {
+    owner: Option<(u16, sgx_attributes_t)>,
+}
+
+impl PseCounterProvider {
+    ///
+    /// Creates counters with the default owner policy and owner attribute mask, as SgxMonotonicCounter::new.
+    ///
+    pub fn new() -> PseCounterProvider {
+        PseCounterProvider { owner: None }
+    }
+
+    ///
+    /// Creates counters with the given owner policy and owner attribute mask, as SgxMonotonicCounter::new_ex.
+    ///
+    pub fn new_ex(owner_policy: u16, owner_attribute_mask: &sgx_attributes_t) -> PseCounterProvider {
+        PseCounterProvider { owner: Some((owner_policy, *owner_attribute_mask)) }
+    }
+}
+
+impl CounterProvider for PseCounterProvider {
+    fn create(&self) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+
+        let mut counter_uuid = sgx_mc_uuid_t::default();
+        let mut counter_value: u32 = 0;
+        let ret = match self.owner {
+            Some((owner_policy, ref owner_attribute_mask)) => {
+                rsgx_create_monotonic_counter_ex(owner_policy, owner_attribute_mask, &mut counter_uuid, &mut counter_value)
+            },
+            None => rsgx_create_monotonic_counter(&mut counter_uuid, &mut counter_value),
+        };
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok((counter_uuid, counter_value)),
+            _ => Err(ret),
+        }
+    }
+
+    fn create_ex(&self, owner_policy: u16, owner_attribute_mask: &sgx_attributes_t) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+
+        let mut counter_uuid = sgx_mc_uuid_t::default();
+        let mut counter_value: u32 = 0;
+        let ret = rsgx_create_monotonic_counter_ex(owner_policy, owner_attribute_mask, &mut counter_uuid, &mut counter_value);
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok((counter_uuid, counter_value)),
+            _ => Err(ret),
+        }
+    }
+
+    fn destroy(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxError {
+
+        match rsgx_destroy_monotonic_counter(counter_uuid) {
+            sgx_status_t::SGX_SUCCESS => Ok(()),
+            ret => Err(ret),
+        }
+    }
+
+    fn increment(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+
+        let mut counter_value: u32 = 0;
+        match rsgx_increment_monotonic_counter(counter_uuid, &mut counter_value) {
+            sgx_status_t::SGX_SUCCESS => Ok(counter_value),
+            ret => Err(ret),
+        }
+    }
+
+    fn read(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+
+        let mut counter_value: u32 = 0;
+        match rsgx_read_monotonic_counter(counter_uuid, &mut counter_value) {
+            sgx_status_t::SGX_SUCCESS => Ok(counter_value),
+            ret => Err(ret),
+        }
+    }
+}
+
+static PSE_COUNTER_PROVIDER: PseCounterProvider = PseCounterProvider { owner: None };
+static mut COUNTER_PROVIDER_LOCK: sgx_spinlock_t = SGX_SPINLOCK_INITIALIZER;
+static mut COUNTER_PROVIDER: Option<&'static (CounterProvider + Sync)> = None;
+
+///
+/// Makes SgxMonotonicCounter create its counters with `provider`.
+///
+/// Counters created before keep their provider. A provider built at run time can be made
+/// `'static` with `Box::leak`.
+///
+pub fn set_counter_provider(provider: &'static (CounterProvider + Sync)) {
+    unsafe {
+        sgx_spin_lock(&mut COUNTER_PROVIDER_LOCK);
+        COUNTER_PROVIDER = Some(provider);
+        sgx_spin_unlock(&mut COUNTER_PROVIDER_LOCK);
+    }
+}
+
+///
+/// Makes SgxMonotonicCounter create its counters with the Platform Services Enclave again.
+///
+pub fn clear_counter_provider() {
+    unsafe {
+        sgx_spin_lock(&mut COUNTER_PROVIDER_LOCK);
+        COUNTER_PROVIDER = None;
+        sgx_spin_unlock(&mut COUNTER_PROVIDER_LOCK);
+    }
+}
+
+fn counter_provider() -> &'static (CounterProvider + Sync) {
+    unsafe {
+        sgx_spin_lock(&mut COUNTER_PROVIDER_LOCK);
+        let provider = COUNTER_PROVIDER;
+        sgx_spin_unlock(&mut COUNTER_PROVIDER_LOCK);
+        match provider {
+            Some(provider) => provider,
+            None => &PSE_COUNTER_PROVIDER,
+        }
+    }
+}
+
+/// The number of counters a MockCounterProvider holds, as the quota of an enclave on the PSE.
+pub const MOCK_COUNTER_QUOTA: usize = 256;
+
+struct MockCounters {
+    serial: u64,
+    // the serial number of the counter in each slot, 0 if the slot is free, and its value
+    slots: [(u64, u32); MOCK_COUNTER_QUOTA],
+}
+
+///
+/// Counters kept in enclave memory, for tests.
+///
+/// The counters are lost with the enclave. The UUID of a counter holds its slot and a serial
+/// number, so the UUID of a destroyed counter does not find the counter which reuses the slot.
+///
+pub struct MockCounterProvider {
+    counters: SpinLocked<MockCounters>,
+}
+
+impl MockCounterProvider {
+    pub fn new() -> MockCounterProvider {
+        MockCounterProvider {
+            counters: SpinLocked::new(MockCounters {
+                serial: 0,
+                slots: [(0, 0); MOCK_COUNTER_QUOTA],
+            }),
+        }
+    }
+}
+
+impl Default for MockCounterProvider {
+    fn default() -> MockCounterProvider {
+        MockCounterProvider::new()
+    }
+}
+
+impl MockCounters {
+    // The slot of the counter.
+    fn find(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<usize> {
+
+        let mut slot: usize = 0;
+        for i in 0..SGX_MC_UUID_COUNTER_ID_SIZE {
+            slot |= (counter_uuid.counter_id[i] as usize) << (8 * i);
+        }
+        let mut serial: u64 = 0;
+        for i in 0..8 {
+            serial |= (counter_uuid.nonce[i] as u64) << (8 * i);
+        }
+        if slot >= MOCK_COUNTER_QUOTA || serial == 0 || self.slots[slot].0 != serial ||
+           counter_uuid.nonce[8..].iter().any(|&b| b != 0) {
+            return Err(sgx_status_t::SGX_ERROR_MC_NOT_FOUND);
+        }
+        Ok(slot)
+    }
+}
+
+impl CounterProvider for MockCounterProvider {
+    fn create(&self) -> SgxResult<(sgx_mc_uuid_t, u32)> {
+        self.counters.with(|counters| {
+            let slot = try!(counters.slots.iter().position(|&(serial, _)| serial == 0)
+                                              .ok_or(sgx_status_t::SGX_ERROR_MC_OVER_QUOTA));
+            counters.serial += 1;
+            counters.slots[slot] = (counters.serial, 0);
+
+            let mut counter_uuid = sgx_mc_uuid_t::default();
+            for i in 0..SGX_MC_UUID_COUNTER_ID_SIZE {
+                counter_uuid.counter_id[i] = (slot >> (8 * i)) as u8;
+            }
+            for i in 0..8 {
+                counter_uuid.nonce[i] = (counters.serial >> (8 * i)) as u8;
+            }
+            Ok((counter_uuid, 0))
+        })
+    }
+
+    fn destroy(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxError {
+        self.counters.with(|counters| {
+            let slot = try!(counters.find(counter_uuid));
+            counters.slots[slot] = (0, 0);
+            Ok(())
+        })
+    }
+
+    fn increment(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+        self.counters.with(|counters| {
+            let slot = try!(counters.find(counter_uuid));
+            let value = try!(counters.slots[slot].1.checked_add(1).ok_or(sgx_status_t::SGX_ERROR_MC_USED_UP));
+            counters.slots[slot].1 = value;
+            Ok(value)
+        })
+    }
+
+    fn read(&self, counter_uuid: &sgx_mc_uuid_t) -> SgxResult<u32> {
+        self.counters.with(|counters| {
+            let slot = try!(counters.find(counter_uuid));
+            Ok(counters.slots[slot].1)
+        })
+    }
+}
+
 fn rsgx_create_monotonic_counter_ex(owner_policy: u16,
                                     owner_attribute_mask: &sgx_attributes_t,
                                     counter_uuid: &mut sgx_mc_uuid_t,
@@ -27,6 +27,9 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use sgx_types::*;
+use sgx_trts::trts::rsgx_read_rand;
+use sgx_tcrypto::SgxEccHandle;
+use lock::SpinLocked;
 use core::cmp::Ordering;
 use core::fmt;
 
@@ -87,18 +90,18 @@ impl PartialOrd for SgxTime {
 
 impl SgxTime {
 
+    ///
+    /// Reads the time of the provider set with set_time_provider, or the trusted time of the
+    /// Platform Services Enclave if none is set.
+    ///
     pub fn now() -> Result<SgxTime, SgxTimeError> {
 
-        let mut timestamp: sgx_time_t = 0;
-        let mut source_nonce: sgx_time_source_nonce_t = Default::default();
-
-        let ret = rsgx_get_trusted_time(&mut timestamp, &mut source_nonce);
-        match ret {
-            sgx_status_t::SGX_SUCCESS => Ok(SgxTime{
-                                            timestamp: timestamp,
-                                            source_nonce: source_nonce
-                                         }),
-            _ => Err(SgxTimeError::SgxStatus(ret)),
+        match time_provider().now() {
+            Ok((timestamp, source_nonce)) => Ok(SgxTime{
+                                                timestamp: timestamp,
+                                                source_nonce: source_nonce
+                                             }),
+            Err(ret) => Err(SgxTimeError::SgxStatus(ret)),
         }
     }
 
@@ -140,6 +143,231 @@ impl SgxTime {
     pub fn get_source_nonce(&self) -> sgx_time_source_nonce_t { self.source_nonce }
 }
 
+///
+/// A source of trusted time for SgxTime.
+///
+/// The Platform Services Enclave is missing on most servers and in simulation mode, so the time
+/// can come from a mock clock in tests, or from signed time tokens of a time server instead.
+///
+pub trait TimeProvider {
+    /// Returns the time in seconds and the nonce of the time source, as rsgx_get_trusted_time.
+    fn now(&self) -> SgxResult<(sgx_time_t, sgx_time_source_nonce_t)>;
+}
+
+///
+/// The trusted time of the Platform Services Enclave, the default provider.
+///
+/// The caller should call rsgx_create_pse_session to establish a session with the platform service enclave
+/// before reading the time.
+///
+#[derive(Copy, Clone, Default)]
+pub struct PseTimeProvider;
+
+impl TimeProvider for PseTimeProvider {
+    fn now(&self) -> SgxResult<(sgx_time_t, sgx_time_source_nonce_t)> {
+
+        let mut timestamp: sgx_time_t = 0;
+        let mut source_nonce: sgx_time_source_nonce_t = Default::default();
+
+        let ret = rsgx_get_trusted_time(&mut timestamp, &mut source_nonce);
+        match ret {
+            sgx_status_t::SGX_SUCCESS => Ok((timestamp, source_nonce)),
+            _ => Err(ret),
+        }
+    }
+}
+
+static PSE_TIME_PROVIDER: PseTimeProvider = PseTimeProvider;
+static mut TIME_PROVIDER_LOCK: sgx_spinlock_t = SGX_SPINLOCK_INITIALIZER;
+static mut TIME_PROVIDER: Option<&'static (TimeProvider + Sync)> = None;
+
+///
+/// Makes SgxTime read the time of `provider`.
+///
+/// A provider built at run time can be made `'static` with `Box::leak`.
+///
+pub fn set_time_provider(provider: &'static (TimeProvider + Sync)) {
+    unsafe {
+        sgx_spin_lock(&mut TIME_PROVIDER_LOCK);
+        TIME_PROVIDER = Some(provider);
+        sgx_spin_unlock(&mut TIME_PROVIDER_LOCK);
+    }
+}
+
+///
+/// Makes SgxTime read the trusted time of the Platform Services Enclave again.
+///
+pub fn clear_time_provider() {
+    unsafe {
+        sgx_spin_lock(&mut TIME_PROVIDER_LOCK);
+        TIME_PROVIDER = None;
+        sgx_spin_unlock(&mut TIME_PROVIDER_LOCK);
+    }
+}
+
+fn time_provider() -> &'static (TimeProvider + Sync) {
+    unsafe {
+        sgx_spin_lock(&mut TIME_PROVIDER_LOCK);
+        let provider = TIME_PROVIDER;
+        sgx_spin_unlock(&mut TIME_PROVIDER_LOCK);
+        match provider {
+            Some(provider) => provider,
+            None => &PSE_TIME_PROVIDER,
+        }
+    }
+}
+
+///
+/// A clock which only moves when told to, for tests.
+///
+pub struct MockTimeProvider {
+    time: SpinLocked<(sgx_time_t, sgx_time_source_nonce_t)>,
+}
+
+impl MockTimeProvider {
+    pub fn new(secs: sgx_time_t, source_nonce: &sgx_time_source_nonce_t) -> MockTimeProvider {
+        MockTimeProvider { time: SpinLocked::new((secs, *source_nonce)) }
+    }
+
+    pub fn set_secs(&self, secs: sgx_time_t) {
+        self.time.with(|time| time.0 = secs);
+    }
+
+    pub fn advance(&self, secs: Duration) {
+        self.time.with(|time| time.0 = time.0.saturating_add(secs));
+    }
+
+    ///
+    /// Changes the time source, as a reset of the platform service would.
+    ///
+    pub fn set_source_nonce(&self, source_nonce: &sgx_time_source_nonce_t) {
+        self.time.with(|time| time.1 = *source_nonce);
+    }
+}
+
+impl TimeProvider for MockTimeProvider {
+    fn now(&self) -> SgxResult<(sgx_time_t, sgx_time_source_nonce_t)> {
+        Ok(self.time.with(|time| *time))
+    }
+}
+
+pub const SGX_TIME_TOKEN_NONCE_SIZE: usize = 16;
+
+const TIME_TOKEN_TAG: &'static [u8; 8] = b"SGXTIME1";
+const TIME_TOKEN_SIGNED_SIZE: usize = 8 + 8 + 32 + SGX_TIME_TOKEN_NONCE_SIZE;
+
+///
+/// A time stamp issued by a time server, signed with ECDSA over the P-256 curve.
+///
+/// `nonce` is the nonce of the request the token answers, so an old token cannot be replayed.
+///
+#[derive(Copy, Clone, Default)]
+pub struct SgxTimeToken {
+    pub timestamp: sgx_time_t,
+    pub source_nonce: sgx_time_source_nonce_t,
+    pub nonce: [u8; SGX_TIME_TOKEN_NONCE_SIZE],
+    pub signature: sgx_ec256_signature_t,
+}
+
+impl SgxTimeToken {
+    // "SGXTIME1" || timestamp (little endian) || source nonce || request nonce
+    fn signed_bytes(&self) -> [u8; TIME_TOKEN_SIGNED_SIZE] {
+        let mut bytes = [0_u8; TIME_TOKEN_SIGNED_SIZE];
+        bytes[..8].copy_from_slice(TIME_TOKEN_TAG);
+        for i in 0..8 {
+            bytes[8 + i] = (self.timestamp >> (8 * i)) as u8;
+        }
+        bytes[16..48].copy_from_slice(&self.source_nonce);
+        bytes[48..].copy_from_slice(&self.nonce);
+        bytes
+    }
+}
+
+///
+/// The transport to a time server, usually an OCALL.
+///
+pub trait TimeTokenSource {
+    /// Asks the time server for a token answering `nonce`.
+    fn get_token(&self, nonce: &[u8; SGX_TIME_TOKEN_NONCE_SIZE]) -> SgxResult<SgxTimeToken>;
+}
+
+///
+/// The time of a time server, trusted through its signing key.
+///
+/// Each reading sends a fresh random nonce to the server, and accepts only a token which answers
+/// it and carries a valid signature of the server.
+///
+pub struct SignedTimeProvider<S: TimeTokenSource> {
+    source: S,
+    public_key: sgx_ec256_public_t,
+}
+
+impl<S: TimeTokenSource> SignedTimeProvider<S> {
+    pub fn new(source: S, public_key: &sgx_ec256_public_t) -> SignedTimeProvider<S> {
+        SignedTimeProvider { source: source, public_key: *public_key }
+    }
+}
+
+impl<S: TimeTokenSource> TimeProvider for SignedTimeProvider<S> {
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_MAC_MISMATCH**
+    ///
+    /// The token does not answer the request, or its signature is invalid.
+    ///
+    fn now(&self) -> SgxResult<(sgx_time_t, sgx_time_source_nonce_t)> {
+
+        let mut nonce = [0_u8; SGX_TIME_TOKEN_NONCE_SIZE];
+        try!(rsgx_read_rand(&mut nonce));
+        let token = try!(self.source.get_token(&nonce));
+        if token.nonce != nonce {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+
+        let ecc_handle = SgxEccHandle::new();
+        try!(ecc_handle.open());
+        if !try!(ecc_handle.ecdsa_verify_slice(&token.signed_bytes(), &self.public_key, &token.signature)) {
+            return Err(sgx_status_t::SGX_ERROR_MAC_MISMATCH);
+        }
+        Ok((token.timestamp, token.source_nonce))
+    }
+}
+
+///
+/// A stand-in for a trusted time server, which signs the time stamps it is given.
+///
+/// It lets tests and simulation runs exercise SignedTimeProvider; a real deployment runs the
+/// server out of reach of the host.
+///
+pub struct TimeServer {
+    private_key: sgx_ec256_private_t,
+    source_nonce: sgx_time_source_nonce_t,
+}
+
+impl TimeServer {
+    pub fn new(private_key: &sgx_ec256_private_t, source_nonce: &sgx_time_source_nonce_t) -> TimeServer {
+        TimeServer { private_key: *private_key, source_nonce: *source_nonce }
+    }
+
+    ///
+    /// Issues a token for `timestamp`, answering `nonce`.
+    ///
+    pub fn issue(&self, timestamp: sgx_time_t, nonce: &[u8; SGX_TIME_TOKEN_NONCE_SIZE]) -> SgxResult<SgxTimeToken> {
+
+        let mut token = SgxTimeToken {
+            timestamp: timestamp,
+            source_nonce: self.source_nonce,
+            nonce: *nonce,
+            signature: sgx_ec256_signature_t::default(),
+        };
+        let ecc_handle = SgxEccHandle::new();
+        try!(ecc_handle.open());
+        token.signature = try!(ecc_handle.ecdsa_sign_slice(&token.signed_bytes(), &self.private_key));
+        Ok(token)
+    }
+}
+
 ///
 /// rsgx_get_trusted_time gets trusted time from the AE service.
 ///
@@ -37,6 +37,8 @@ use alloc::slice;
 use io;
 use net::{ntoh, hton, IpAddr, Ipv4Addr, Ipv6Addr};
 use sys_common::{FromInner, AsInner, IntoInner};
+#[cfg(feature = "net")]
+use sys_common::net as net_imp;
 
 /// An internet socket address, either IPv4 or IPv6.
 ///
@@ -426,6 +428,12 @@ impl hash::Hash for SocketAddrV6 {
 /// Addresses returned by the operating system that are not IP addresses are
 /// silently ignored.
 ///
+/// Host names are only resolved when the `net` feature is enabled. The lookup
+/// is an OCALL to `getaddrinfo` of the untrusted side, so the addresses it
+/// returns are **untrusted**: they are bounds-checked before use, but the
+/// host can answer with any address it likes. Without the `net` feature a
+/// host name yields an [`InvalidInput`] error.
+///
 pub trait ToSocketAddrs {
     /// Returned iterator over socket addresses which this type may correspond
     /// to.
@@ -489,6 +497,19 @@ impl ToSocketAddrs for (Ipv6Addr, u16) {
     }
 }
 
+#[cfg(feature = "net")]
+fn resolve_socket_addr(s: &str, p: u16) -> io::Result<vec::IntoIter<SocketAddr>> {
+    let ips = net_imp::lookup_host(s)?;
+    let v: Vec<_> = ips.map(|mut a| { a.set_port(p); a }).collect();
+    Ok(v.into_iter())
+}
+
+// Without the `net` feature there is no resolver OCALL to ask.
+#[cfg(not(feature = "net"))]
+fn resolve_socket_addr(_s: &str, _p: u16) -> io::Result<vec::IntoIter<SocketAddr>> {
+    Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address"))
+}
+
 impl<'a> ToSocketAddrs for (&'a str, u16) {
     type Iter = vec::IntoIter<SocketAddr>;
     fn to_socket_addrs(&self) -> io::Result<vec::IntoIter<SocketAddr>> {
@@ -504,13 +525,11 @@ impl<'a> ToSocketAddrs for (&'a str, u16) {
             return Ok(vec![SocketAddr::V6(addr)].into_iter())
         }
 
-        Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address"))
-        //resolve_socket_addr(host, port)
+        resolve_socket_addr(host, port)
     }
 }
 
 // accepts strings like 'localhost:12345'
-#[allow(unused_variables)]
 impl ToSocketAddrs for str {
     type Iter = vec::IntoIter<SocketAddr>;
     fn to_socket_addrs(&self) -> io::Result<vec::IntoIter<SocketAddr>> {
@@ -534,8 +553,7 @@ impl ToSocketAddrs for str {
         let port_str = try_opt!(parts_iter.next(), "invalid socket address");
         let host = try_opt!(parts_iter.next(), "invalid socket address");
         let port: u16 = try_opt!(port_str.parse().ok(), "invalid port value");
-        Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address"))
-        //resolve_socket_addr(host, port)
+        resolve_socket_addr(host, port)
     }
 }
 
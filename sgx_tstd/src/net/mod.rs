@@ -39,7 +39,7 @@ use io::{self, Error, ErrorKind};
 pub use self::ip::{IpAddr, Ipv4Addr, Ipv6Addr, Ipv6MulticastScope};
 pub use self::addr::{SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
 #[cfg(feature = "net")]
-pub use self::tcp::TcpStream;
+pub use self::tcp::{TcpStream, TcpListener, Incoming};
 #[cfg(feature = "net")]
 pub use self::udp::UdpSocket;
 pub use self::parser::AddrParseError;
@@ -39,7 +39,7 @@ use alloc::boxed::Box;
 use alloc::rc::Rc;
 use alloc::sync::Arc;
 
-pub use panicking::set_panic_handler;
+pub use panicking::{set_hook, take_hook, set_panic_handler};
 pub use core::panic::{PanicInfo, Location};
 /// A marker trait which represents "panic safe" types in Rust.
 ///
@@ -37,27 +37,126 @@ use core::panic::{PanicInfo, Location};
 use core::any::Any;
 use core::ptr;
 use core::raw;
-use core::sync::atomic::{AtomicPtr, Ordering};
 use core::panic::BoxMeUp;
 use alloc::boxed::Box;
 use alloc::string::String;
+use sync::SgxThreadRwLock;
 
-static PANIC_HANDLER: AtomicPtr<()> = AtomicPtr::new(default_panic_handler as * mut ());
+#[derive(Copy, Clone)]
+enum Hook {
+    Default,
+    Custom(*mut (Fn(&PanicInfo) + 'static + Sync + Send)),
+}
+
+static HOOK_LOCK: SgxThreadRwLock = SgxThreadRwLock::new();
+static mut HOOK: Hook = Hook::Default;
+
+/// Registers a custom panic hook, replacing any that was previously registered.
+///
+/// The panic hook is invoked when a thread panics, but before the panic runtime
+/// is invoked. As such, the hook will run with both the aborting and unwinding
+/// runtimes. The default hook prints a message to standard error through the
+/// stderr OCALL and, with the `backtrace` feature, logs a backtrace.
+///
+/// The [`PanicInfo`] passed to the hook carries the payload of the panic and
+/// its [`Location`]. The hook runs on the panicking thread, so
+/// [`thread::rsgx_thread_self`] identifies it.
+///
+/// The panic hook is a global resource.
+///
+/// [`PanicInfo`]: struct.PanicInfo.html
+/// [`Location`]: struct.Location.html
+/// [`thread::rsgx_thread_self`]: ../thread/fn.rsgx_thread_self.html
+///
+/// # Panics
+///
+/// Panics if called from a panicking thread.
+///
+pub fn set_hook(hook: Box<Fn(&PanicInfo) + 'static + Sync + Send>) {
+    if thread::panicking() {
+        panic!("cannot modify the panic hook from a panicking thread");
+    }
+
+    unsafe {
+        HOOK_LOCK.write();
+        let old_hook = HOOK;
+        HOOK = Hook::Custom(Box::into_raw(hook));
+        HOOK_LOCK.write_unlock();
+
+        if let Hook::Custom(ptr) = old_hook {
+            Box::from_raw(ptr);
+        }
+    }
+}
+
+/// Unregisters the current panic hook, returning it.
+///
+/// If no custom hook is registered, the default hook will be returned.
+///
+/// # Panics
+///
+/// Panics if called from a panicking thread.
+///
+pub fn take_hook() -> Box<Fn(&PanicInfo) + 'static + Sync + Send> {
+    if thread::panicking() {
+        panic!("cannot modify the panic hook from a panicking thread");
+    }
+
+    unsafe {
+        HOOK_LOCK.write();
+        let hook = HOOK;
+        HOOK = Hook::Default;
+        HOOK_LOCK.write_unlock();
+
+        match hook {
+            Hook::Default => Box::new(default_hook),
+            Hook::Custom(ptr) => Box::from_raw(ptr),
+        }
+    }
+}
+
+/// Registers a custom panic handler, replacing any that was previously registered.
+///
+/// The handler receives the message, file, line and column of the panic. It is
+/// installed as the panic hook; see [`set_hook`] for a hook which receives the
+/// whole [`PanicInfo`].
+///
+/// [`set_hook`]: fn.set_hook.html
+/// [`PanicInfo`]: struct.PanicInfo.html
+pub fn set_panic_handler(handler: fn(&str, &str, u32, u32)) {
+    set_hook(Box::new(move |info: &PanicInfo| {
+        let location = info.location().unwrap();  // The current implementation always returns Some
+        handler(payload_as_str(info), location.file(), location.line(), location.column());
+    }));
+}
+
+fn payload_as_str<'a>(info: &'a PanicInfo) -> &'a str {
+    match info.payload().downcast_ref::<&'static str>() {
+        Some(s) => *s,
+        None => match info.payload().downcast_ref::<String>() {
+            Some(s) => &s[..],
+            None => "Box<Any>",
+        }
+    }
+}
 
 #[cfg(not(feature = "stdio"))]
 #[allow(unused_variables)]
-fn default_panic_handler(msg: &str, file: &str, line: u32, col: u32) {
+fn default_hook(info: &PanicInfo) {
 
 }
 
 #[cfg(feature = "stdio")]
-fn default_panic_handler(msg: &str, file: &str, line: u32, col: u32) {
+fn default_hook(info: &PanicInfo) {
 
     use sys::stdio::Stderr;
+    use sys_common::thread_info;
 
     #[cfg(feature = "backtrace")]
     use sys_common::backtrace;
 
+    // If this is a double panic, make sure that we print a backtrace
+    // for this panic. Otherwise only print it if logging is enabled.
     #[cfg(feature = "backtrace")]
     let log_backtrace = {
         let panics = update_panic_count(0);
@@ -69,10 +168,16 @@ fn default_panic_handler(msg: &str, file: &str, line: u32, col: u32) {
         }
     };
 
+    let location = info.location().unwrap();  // The current implementation always returns Some
+    let msg = payload_as_str(info);
+    let thread = thread_info::current_thread();
+    let name = thread.as_ref().and_then(|t| t.name()).unwrap_or("<unnamed>");
+    let id = thread::rsgx_thread_self();
+
     let mut err = Stderr::new().ok();
     let write = |err: &mut ::io::Write| {
-        let _ = writeln!(err, "thread panicked at '{}', {}:{}:{}",
-                        msg, file, line, col);
+        let _ = writeln!(err, "thread '{}' ({:#x}) panicked at '{}', {}:{}:{}",
+                         name, id, msg, location.file(), location.line(), location.column());
 
         #[cfg(feature = "backtrace")]
         {
@@ -83,7 +188,6 @@ fn default_panic_handler(msg: &str, file: &str, line: u32, col: u32) {
             if let Some(format) = log_backtrace {
                 let _ = backtrace::print(err, format);
             } else if FIRST_PANIC.compare_and_swap(true, false, Ordering::SeqCst) {
-                //let _ = writeln!(err, "note: Compile with `RUST_BACKTRACE=1` for a backtrace.");
                 let _ = writeln!(err, "note: Call backtrace::enable_backtrace with 'PrintFormat::Short' for a backtrace.");
             }
         }
@@ -95,35 +199,6 @@ fn default_panic_handler(msg: &str, file: &str, line: u32, col: u32) {
     }
 }
 
-/// Registers a custom panic handler, replacing any that was previously registered.
-pub fn set_panic_handler(handler: fn(&str, &str, u32, u32)) {
-    if thread::panicking() {
-        panic!("cannot modify the panic hook from a panicking thread");
-    }
-    PANIC_HANDLER.store(handler as * mut (), Ordering::SeqCst);
-}
-
-fn panic_handler<'a>(info: &'a PanicInfo) {
-
-    let location = info.location().unwrap();  // The current implementation always returns Some
-    let file = location.file();
-    let line = location.line();
-    let col = location.column();
-
-    let msg: &str = match info.payload().downcast_ref::<&'static str>() {
-        Some(s) => *s,
-        None => match info.payload().downcast_ref::<String>() {
-            Some(s) => &s[..],
-            None => "Box<Any>",
-        }
-    };
-
-    let value = PANIC_HANDLER.load(Ordering::SeqCst);
-    let handler: fn(&str, &str, u32, u32) = unsafe{ mem::transmute(value) };
-    handler(msg, file, line, col);
-}
-
-
 #[allow(improper_ctypes)]
 extern {
     fn __rust_maybe_catch_panic(f: fn(*mut u8),
@@ -396,12 +471,18 @@ fn rust_panic_with_hook(payload: &mut BoxMeUp,
         rsgx_abort()
     }
 
-    {
-        let info = PanicInfo::internal_constructor(
+    unsafe {
+        let mut info = PanicInfo::internal_constructor(
             message,
-			Location::internal_constructor(file, line, col),
+            Location::internal_constructor(file, line, col),
         );
-        panic_handler(&info);
+        info.set_payload(payload.get());
+        HOOK_LOCK.read();
+        match HOOK {
+            Hook::Default => default_hook(&info),
+            Hook::Custom(ptr) => (*ptr)(&info),
+        }
+        HOOK_LOCK.read_unlock();
     }
 
 
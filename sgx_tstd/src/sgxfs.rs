@@ -34,6 +34,10 @@ use path::Path;
 use sys::sgxfs as fs_imp;
 use sys_common::{AsInnerMut, FromInner, AsInner, IntoInner};
 
+pub use sgx_tprotected_fs::pfs::{ProtectedStorage, StorageFile, RecoveryFile, HostStorage, MemoryStorage};
+#[cfg(feature = "kvstore")]
+pub use sgx_tprotected_fs::pfs::KvStorage;
+
 /// A reference to an open file on the filesystem.
 ///
 /// An instance of a `File` can be read and/or written depending on what options
@@ -55,6 +59,14 @@ pub struct SgxFile {
 #[derive(Clone, Debug)]
 pub struct OpenOptions(fs_imp::OpenOptions);
 
+/// Metadata information about a protected file.
+///
+/// This structure is returned from the [`metadata`] function or method. Unlike
+/// the metadata of the host file, the length is that of the plain text.
+///
+#[derive(Clone, Copy, Debug)]
+pub struct Metadata(fs_imp::FileAttr);
+
 /// Read the entire contents of a file into a bytes vector.
 ///
 /// This is a convenience function for using SgxFile::open and read_to_end
@@ -148,6 +160,25 @@ impl SgxFile {
     pub fn clear_cache(&self) -> io::Result<()> {
         self.inner.clear_cache()
     }
+
+    /// Queries metadata about the underlying file.
+    ///
+    /// The cursor of the file is left where it was.
+    ///
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.inner.file_attr().map(Metadata)
+    }
+}
+
+impl Metadata {
+    /// Returns the size of the plain text of the file, in bytes.
+    pub fn len(&self) -> u64 {
+        self.0.size()
+    }
+}
+
+impl AsInner<fs_imp::FileAttr> for Metadata {
+    fn as_inner(&self) -> &fs_imp::FileAttr { &self.0 }
 }
 
 impl AsInner<fs_imp::SgxFile> for SgxFile {
@@ -284,6 +315,35 @@ impl OpenOptions {
         self._open_ex(path.as_ref(), key)
     }
 
+    /// Opens a file at `path` of `storage` with the options specified by
+    /// `self`, protected with the auto key.
+    ///
+    /// Files opened with [`open`] live on the host file system and go
+    /// through Intel's library. This goes through the Rust implementation
+    /// of the same format instead, on any [`ProtectedStorage`]: the host file
+    /// system ([`HostStorage`], whose files [`open`] can read back), enclave
+    /// memory ([`MemoryStorage`]), or, with the `kvstore` feature, the
+    /// key/value store of the application (`KvStorage`).
+    ///
+    /// [`open`]: #method.open
+    /// [`ProtectedStorage`]: trait.ProtectedStorage.html
+    /// [`HostStorage`]: struct.HostStorage.html
+    /// [`MemoryStorage`]: struct.MemoryStorage.html
+    pub fn open_in<S, P>(&self, storage: S, path: P) -> io::Result<SgxFile>
+        where S: ProtectedStorage + 'static, P: AsRef<Path> {
+        let inner = fs_imp::SgxFile::open_in(storage, path.as_ref(), &self.0, None)?;
+        Ok(SgxFile { inner: inner })
+    }
+
+    /// Like [`open_in`], for a file protected with `key`.
+    ///
+    /// [`open_in`]: #method.open_in
+    pub fn open_in_ex<S, P>(&self, storage: S, path: P, key: &sgx_key_128bit_t) -> io::Result<SgxFile>
+        where S: ProtectedStorage + 'static, P: AsRef<Path> {
+        let inner = fs_imp::SgxFile::open_in(storage, path.as_ref(), &self.0, Some(key))?;
+        Ok(SgxFile { inner: inner })
+    }
+
     fn _open(&self, path: &Path) -> io::Result<SgxFile> {
         let inner = fs_imp::SgxFile::open(path, &self.0)?;
         Ok(SgxFile { inner: inner })
@@ -303,6 +363,11 @@ pub fn remove<P: AsRef<Path>>(path: P) -> io::Result<()> {
     fs_imp::remove(path.as_ref())
 }
 
+/// Removes the protected file at `path` of `storage`.
+pub fn remove_in<S: ProtectedStorage, P: AsRef<Path>>(storage: &S, path: P) -> io::Result<()> {
+    fs_imp::remove_in(storage, path.as_ref())
+}
+
 pub fn export_auto_key<P: AsRef<Path>>(path: P) -> io::Result<sgx_key_128bit_t> {
     fs_imp::export_auto_key(path.as_ref())
 }
@@ -322,3 +387,78 @@ pub fn import_auto_key<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::R
 pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<u64> {
     fs_imp::copy(from.as_ref(), to.as_ref())
 }
+
+/// Given a path, query the file system to get information about a protected
+/// file opened with the auto key.
+///
+/// # Errors
+///
+/// This function will return an error if `path` does not exist or cannot be
+/// opened as a protected file.
+///
+pub fn metadata<P: AsRef<Path>>(path: P) -> io::Result<Metadata> {
+    fs_imp::stat(path.as_ref(), None).map(Metadata)
+}
+
+/// Like [`metadata`], for a file protected with `key`.
+pub fn metadata_ex<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t) -> io::Result<Metadata> {
+    fs_imp::stat(path.as_ref(), Some(key)).map(Metadata)
+}
+
+/// Truncates or extends the plain text of a protected file opened with the
+/// auto key.
+///
+/// If `size` is greater than the current length, the file is extended with
+/// zeros in place. If it is less, the file is rewritten: the protected format
+/// has no way to drop trailing data, so the first `size` bytes are copied to a
+/// temporary file next to it (the name with a `.sgxtmp` suffix), which is then
+/// moved over the original with [`rename`]. If the enclave stops half way, the
+/// file keeps either its old or its new content.
+///
+/// The file must not be open.
+///
+pub fn set_len<P: AsRef<Path>>(path: P, size: u64) -> io::Result<()> {
+    fs_imp::set_len(path.as_ref(), None, size)
+}
+
+/// Like [`set_len`], for a file protected with `key`.
+pub fn set_len_ex<P: AsRef<Path>>(path: P, key: &sgx_key_128bit_t, size: u64) -> io::Result<()> {
+    fs_imp::set_len(path.as_ref(), Some(key), size)
+}
+
+/// Renames a protected file opened with the auto key, replacing `to` if it
+/// already exists.
+///
+/// The name of a protected file is part of its authenticated metadata, so a
+/// file renamed on the host file system fails to open with
+/// `SGX_ERROR_FILE_NAME_MISMATCH`. This function first re-encrypts the
+/// metadata with the new name, then renames the file on the host. Only the
+/// metadata node is written, whatever the size of the file. If the enclave
+/// stops between the two steps, `from` no longer opens; calling `rename`
+/// again completes the move.
+///
+/// Paths which name the same file, such as `a` and `./a`, leave the file as
+/// it is.
+///
+/// The file must not be open.
+///
+pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    fs_imp::rename(from.as_ref(), to.as_ref(), None)
+}
+
+/// Like [`rename`], for a file protected with `key`.
+pub fn rename_ex<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q, key: &sgx_key_128bit_t) -> io::Result<()> {
+    fs_imp::rename(from.as_ref(), to.as_ref(), Some(key))
+}
+
+/// Re-encrypts a protected file from `old_key` to `new_key`.
+///
+/// The file keeps its name; like a shrinking [`set_len`], its content goes
+/// through a temporary file, which is protected with `new_key` and then moved
+/// over the original.
+///
+/// The file must not be open.
+///
+pub fn rekey<P: AsRef<Path>>(path: P, old_key: &sgx_key_128bit_t, new_key: &sgx_key_128bit_t) -> io::Result<()> {
+    fs_imp::rekey(path.as_ref(), Some(old_key), Some(new_key))
+}
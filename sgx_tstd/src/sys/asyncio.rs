@@ -0,0 +1,224 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_trts::libc::{c_int, pollfd, epoll_event, nfds_t};
+use core::cmp;
+use io::{self, Error, ErrorKind};
+use sys::fd::FileDesc;
+use sys::cvt;
+use sys_common::{AsInner, FromInner, IntoInner};
+use time::Duration;
+
+pub fn poll(fds: &mut [pollfd], timeout: Option<Duration>) -> io::Result<usize> {
+    // The untrusted side gets its own copy of the array and may write
+    // anything back, so only the `revents` fields are taken from it.
+    let mut ufds = fds.to_vec();
+    let n = cvt(unsafe {
+        libc::poll(ufds.as_mut_ptr(), ufds.len() as nfds_t, timeout_ms(timeout))
+    })? as usize;
+    if n > fds.len() {
+        return Err(Error::new(ErrorKind::InvalidData, "invalid number of ready descriptors"));
+    }
+    for (fd, ufd) in fds.iter_mut().zip(ufds.iter()) {
+        fd.revents = ufd.revents;
+    }
+    Ok(n)
+}
+
+pub struct Epoll(FileDesc);
+
+impl Epoll {
+    pub fn new() -> io::Result<Epoll> {
+        let fd = cvt(unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) })?;
+        Ok(Epoll(FileDesc::new(fd)))
+    }
+
+    pub fn ctl(&self, op: c_int, fd: c_int, events: u32, token: u64) -> io::Result<()> {
+        let mut event = epoll_event { events: events, u64: token };
+        cvt(unsafe { libc::epoll_ctl(self.0.raw(), op, fd, &mut event) })?;
+        Ok(())
+    }
+
+    pub fn wait(&self, events: &mut [epoll_event], timeout: Option<Duration>) -> io::Result<usize> {
+        let maxevents = cmp::min(events.len(), c_int::max_value() as usize) as c_int;
+        let n = cvt(unsafe {
+            libc::epoll_wait(self.0.raw(), events.as_mut_ptr(), maxevents, timeout_ms(timeout))
+        })? as usize;
+        if n > maxevents as usize {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid number of ready events"));
+        }
+        Ok(n)
+    }
+}
+
+impl AsInner<c_int> for Epoll {
+    fn as_inner(&self) -> &c_int { self.0.as_inner() }
+}
+
+impl FromInner<c_int> for Epoll {
+    fn from_inner(fd: c_int) -> Epoll {
+        Epoll(FileDesc::new(fd))
+    }
+}
+
+impl IntoInner<c_int> for Epoll {
+    fn into_inner(self) -> c_int { self.0.into_raw() }
+}
+
+// Converts a timeout to milliseconds, rounding up so that a short non-zero
+// timeout does not turn into a busy poll. `None` waits indefinitely.
+fn timeout_ms(timeout: Option<Duration>) -> c_int {
+    match timeout {
+        None => -1,
+        Some(dur) => {
+            let ms = dur.as_secs()
+                .saturating_mul(1000)
+                .saturating_add((dur.subsec_nanos() as u64 + 999_999) / 1_000_000);
+            cmp::min(ms, c_int::max_value() as u64) as c_int
+        }
+    }
+}
+
+mod libc {
+    use sgx_types::sgx_status_t;
+    use io;
+    pub use sgx_trts::libc::*;
+
+    extern "C" {
+        pub fn u_poll_ocall(result: * mut c_int,
+                            errno: * mut c_int,
+                            fds: * mut pollfd,
+                            nfds: nfds_t,
+                            timeout: c_int) -> sgx_status_t;
+
+        pub fn u_epoll_create1_ocall(result: * mut c_int,
+                                     errno: * mut c_int,
+                                     flags: c_int) -> sgx_status_t;
+
+        pub fn u_epoll_ctl_ocall(result: * mut c_int,
+                                 errno: * mut c_int,
+                                 epfd: c_int,
+                                 op: c_int,
+                                 fd: c_int,
+                                 event: * mut epoll_event) -> sgx_status_t;
+
+        pub fn u_epoll_wait_ocall(result: * mut c_int,
+                                  errno: * mut c_int,
+                                  epfd: c_int,
+                                  events: * mut epoll_event,
+                                  maxevents: c_int,
+                                  timeout: c_int) -> sgx_status_t;
+    }
+
+    pub unsafe fn poll(fds: * mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_poll_ocall(&mut result as * mut c_int,
+                                  &mut error as * mut c_int,
+                                  fds,
+                                  nfds,
+                                  timeout);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn epoll_create1(flags: c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_epoll_create1_ocall(&mut result as * mut c_int,
+                                           &mut error as * mut c_int,
+                                           flags);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn epoll_ctl(epfd: c_int, op: c_int, fd: c_int, event: * mut epoll_event) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_epoll_ctl_ocall(&mut result as * mut c_int,
+                                       &mut error as * mut c_int,
+                                       epfd,
+                                       op,
+                                       fd,
+                                       event);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn epoll_wait(epfd: c_int,
+                             events: * mut epoll_event,
+                             maxevents: c_int,
+                             timeout: c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_epoll_wait_ocall(&mut result as * mut c_int,
+                                        &mut error as * mut c_int,
+                                        epfd,
+                                        events,
+                                        maxevents,
+                                        timeout);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+}
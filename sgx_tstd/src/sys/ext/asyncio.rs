@@ -0,0 +1,152 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Readiness polling for file descriptors.
+//!
+//! These are thin wrappers over `poll(2)` and `epoll(7)`, which are run
+//! outside of the enclave through OCALLs. They let a single enclave thread
+//! wait on many sockets at once, for example to drive an event loop over
+//! non-blocking [`TcpStream`]s.
+//!
+//! The readiness reported here comes from the untrusted side. It can only
+//! be used as a hint: a descriptor reported as readable may still return
+//! `WouldBlock`, and the data read from it must be checked as usual.
+//!
+//! [`TcpStream`]: ../../../net/struct.TcpStream.html
+
+use sgx_trts::libc;
+use core::fmt;
+use io;
+use sys;
+use os::unix::io::{RawFd, AsRawFd, FromRawFd, IntoRawFd};
+use sys_common::{AsInner, FromInner, IntoInner};
+use time::Duration;
+
+pub use sgx_trts::libc::{pollfd, epoll_event};
+
+/// Waits for one of a set of file descriptors to become ready to perform
+/// I/O.
+///
+/// The `events` of each entry in `fds` select the conditions to wait for,
+/// and on return the `revents` are filled in with the conditions which
+/// occurred. Only the `revents` fields are written.
+///
+/// A `timeout` of `None` blocks until a descriptor is ready. A timeout which
+/// is not a whole number of milliseconds is rounded up.
+///
+/// Returns the number of entries with a non-zero `revents`, which is `0` if
+/// the call timed out.
+///
+/// # Platform-specific behavior
+///
+/// This function currently corresponds to the `poll` function on Unix.
+///
+pub fn poll(fds: &mut [pollfd], timeout: Option<Duration>) -> io::Result<usize> {
+    sys::asyncio::poll(fds, timeout)
+}
+
+/// An epoll instance.
+///
+/// The instance lives outside of the enclave and is closed when the value
+/// is dropped. Each registered descriptor carries a `u64` token which is
+/// handed back by [`wait`] when it becomes ready.
+///
+/// [`wait`]: #method.wait
+pub struct Epoll(sys::asyncio::Epoll);
+
+impl Epoll {
+    /// Creates a new epoll instance.
+    ///
+    /// # Platform-specific behavior
+    ///
+    /// This function currently corresponds to the `epoll_create1` function on
+    /// Unix, with `EPOLL_CLOEXEC` set.
+    ///
+    pub fn new() -> io::Result<Epoll> {
+        sys::asyncio::Epoll::new().map(Epoll)
+    }
+
+    /// Registers `fd` with this instance.
+    ///
+    /// `events` is a mask of `EPOLL*` flags from `sgx_trts::libc`, and
+    /// `token` is returned with every event reported for `fd`.
+    ///
+    pub fn add(&self, fd: RawFd, events: u32, token: u64) -> io::Result<()> {
+        self.0.ctl(libc::EPOLL_CTL_ADD, fd, events, token)
+    }
+
+    /// Changes the events and the token associated with a registered `fd`.
+    ///
+    pub fn modify(&self, fd: RawFd, events: u32, token: u64) -> io::Result<()> {
+        self.0.ctl(libc::EPOLL_CTL_MOD, fd, events, token)
+    }
+
+    /// Removes `fd` from this instance.
+    ///
+    pub fn delete(&self, fd: RawFd) -> io::Result<()> {
+        self.0.ctl(libc::EPOLL_CTL_DEL, fd, 0, 0)
+    }
+
+    /// Waits for events on the registered descriptors.
+    ///
+    /// At most `events.len()` events are stored at the front of `events`,
+    /// and their number is returned. It is `0` if the call timed out.
+    ///
+    /// A `timeout` of `None` blocks until an event is available. A timeout
+    /// which is not a whole number of milliseconds is rounded up.
+    ///
+    /// The events, including their tokens, are produced by the untrusted
+    /// side. A token should be looked up rather than trusted to name a live
+    /// registration.
+    ///
+    pub fn wait(&self, events: &mut [epoll_event], timeout: Option<Duration>) -> io::Result<usize> {
+        self.0.wait(events, timeout)
+    }
+}
+
+impl fmt::Debug for Epoll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Epoll")
+            .field("fd", self.0.as_inner())
+            .finish()
+    }
+}
+
+impl AsRawFd for Epoll {
+    fn as_raw_fd(&self) -> RawFd { *self.0.as_inner() }
+}
+
+impl FromRawFd for Epoll {
+    unsafe fn from_raw_fd(fd: RawFd) -> Epoll {
+        Epoll(sys::asyncio::Epoll::from_inner(fd))
+    }
+}
+
+impl IntoRawFd for Epoll {
+    fn into_raw_fd(self) -> RawFd { self.0.into_inner() }
+}
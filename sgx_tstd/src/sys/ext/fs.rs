@@ -28,9 +28,9 @@
 
 use sgx_trts::libc;
 #[cfg(feature = "untrusted_fs")]
-use fs::{self, Permissions, OpenOptions};
+use fs::{self, Permissions, OpenOptions, DirBuilder};
 #[cfg(not(feature = "untrusted_fs"))]
-use untrusted::fs::{self, Permissions, OpenOptions};
+use untrusted::fs::{self, Permissions, OpenOptions, DirBuilder};
 use io;
 use path::Path;
 use sys;
@@ -244,6 +244,18 @@ impl FileTypeExt for fs::FileType {
     fn is_socket(&self) -> bool { self.as_inner().is(libc::S_IFSOCK) }
 }
 
+/// Unix-specific extension methods for `fs::DirEntry`
+pub trait DirEntryExt {
+    /// Returns the underlying `d_ino` field in the contained `dirent`
+    /// structure.
+    ///
+    fn ino(&self) -> u64;
+}
+
+impl DirEntryExt for fs::DirEntry {
+    fn ino(&self) -> u64 { self.as_inner().ino() }
+}
+
 /// Creates a new symbolic link on the filesystem.
 ///
 /// The `dst` path will be a symbolic link pointing to the `src` path.
@@ -260,4 +272,18 @@ impl FileTypeExt for fs::FileType {
 pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()>
 {
     sys::fs::symlink(src.as_ref(), dst.as_ref())
-}
\ No newline at end of file
+}
+/// An extension trait for `fs::DirBuilder` for unix-specific options.
+pub trait DirBuilderExt {
+    /// Sets the mode to create new directories with. This option defaults to
+    /// 0o777.
+    ///
+    fn mode(&mut self, mode: u32) -> &mut Self;
+}
+
+impl DirBuilderExt for DirBuilder {
+    fn mode(&mut self, mode: u32) -> &mut DirBuilder {
+        self.as_inner_mut().set_mode(mode);
+        self
+    }
+}
@@ -31,6 +31,8 @@ pub mod ffi;
 pub mod fs;
 #[cfg(feature = "net")]
 pub mod net;
+#[cfg(feature = "net")]
+pub mod asyncio;
 
 /// A prelude for conveniently writing platform-specific code.
 ///
@@ -40,4 +42,5 @@ pub mod prelude {
     pub use super::ffi::{OsStrExt, OsStringExt};
     pub use super::fs::{PermissionsExt, OpenOptionsExt, MetadataExt, FileTypeExt};
     pub use super::fs::FileExt;
+    pub use super::fs::{DirEntryExt, DirBuilderExt};
 }
@@ -26,9 +26,9 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
-use sgx_trts::libc::{c_int, mode_t, time_t, stat64, off64_t};
+use sgx_trts::libc::{c_int, mode_t, time_t, stat64, off64_t, dirent64, DIR};
 use os::unix::prelude::*;
-use ffi::{CString, CStr, OsString};
+use ffi::{CString, CStr, OsStr, OsString};
 use io::{self, Error, ErrorKind, SeekFrom};
 use path::{Path, PathBuf};
 use sys::fd::FileDesc;
@@ -37,6 +37,7 @@ use sys::{cvt, cvt_r};
 use sys_common::{AsInner, FromInner};
 use core::fmt;
 use core::mem;
+use alloc::sync::Arc;
 
 pub struct File(FileDesc);
 
@@ -45,6 +46,25 @@ pub struct FileAttr {
     stat: stat64,
 }
 
+pub struct ReadDir {
+    dirp: Dir,
+    root: Arc<PathBuf>,
+    end_of_stream: bool,
+}
+
+struct Dir(*mut DIR);
+
+unsafe impl Send for Dir {}
+unsafe impl Sync for Dir {}
+
+pub struct DirEntry {
+    entry: dirent64,
+    root: Arc<PathBuf>,
+    // The name of the entry, checked to be non-empty and free of NUL and
+    // '/' bytes, since the dirent64 is filled in by the untrusted side.
+    name: Box<[u8]>,
+}
+
 #[derive(Clone, Debug)]
 pub struct OpenOptions {
     // generic
@@ -65,6 +85,9 @@ pub struct FilePermissions { mode: mode_t }
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct FileType { mode: mode_t }
 
+#[derive(Debug)]
+pub struct DirBuilder { mode: mode_t }
+
 impl FileAttr {
     pub fn size(&self) -> u64 { self.stat.st_size as u64 }
     pub fn perm(&self) -> FilePermissions {
@@ -128,6 +151,132 @@ impl FileType {
     pub fn is(&self, mode: mode_t) -> bool { self.mode & libc::S_IFMT == mode }
 }
 
+impl fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // This will only be called from std::fs::ReadDir, which will add a "ReadDir()" frame.
+        // Thus the result will be e g 'ReadDir("/home")'
+        fmt::Debug::fmt(&*self.root, f)
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<io::Result<DirEntry>> {
+        if self.end_of_stream {
+            return None;
+        }
+
+        unsafe {
+            loop {
+                let mut entry: dirent64 = mem::zeroed();
+                let mut eods: c_int = 0;
+                if libc::readdir64(self.dirp.0, &mut entry, &mut eods) == -1 {
+                    self.end_of_stream = true;
+                    return Some(Err(Error::last_os_error()))
+                }
+                if eods != 0 {
+                    self.end_of_stream = true;
+                    return None
+                }
+
+                let name = match entry_name(&entry) {
+                    Some(name) => name,
+                    None => {
+                        self.end_of_stream = true;
+                        return Some(Err(Error::new(ErrorKind::InvalidData,
+                                                   "invalid directory entry name")))
+                    }
+                };
+                if name == b"." || name == b".." {
+                    continue
+                }
+
+                let ret = DirEntry {
+                    entry: entry,
+                    root: self.root.clone(),
+                    name: name.to_vec().into_boxed_slice(),
+                };
+                return Some(Ok(ret))
+            }
+        }
+    }
+}
+
+// Checks the name of an entry which came from outside the enclave: it must
+// be terminated within `d_name`, and must not be empty or hold a separator,
+// otherwise joining it to the directory path could point anywhere.
+fn entry_name(entry: &dirent64) -> Option<&[u8]> {
+    let bytes = unsafe {
+        &*(&entry.d_name[..] as *const [libc::c_char] as *const [u8])
+    };
+    let len = bytes.iter().position(|&b| b == 0)?;
+    let name = &bytes[..len];
+    if name.is_empty() || name.contains(&b'/') {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let r = unsafe { libc::closedir(self.0) };
+        debug_assert_eq!(r, 0);
+    }
+}
+
+impl DirEntry {
+    pub fn path(&self) -> PathBuf {
+        self.root.join(OsStr::from_bytes(self.name_bytes()))
+    }
+
+    pub fn file_name(&self) -> OsString {
+        OsStr::from_bytes(self.name_bytes()).to_os_string()
+    }
+
+    pub fn metadata(&self) -> io::Result<FileAttr> {
+        lstat(&self.path())
+    }
+
+    pub fn file_type(&self) -> io::Result<FileType> {
+        match self.entry.d_type {
+            libc::DT_CHR => Ok(FileType { mode: libc::S_IFCHR }),
+            libc::DT_FIFO => Ok(FileType { mode: libc::S_IFIFO }),
+            libc::DT_LNK => Ok(FileType { mode: libc::S_IFLNK }),
+            libc::DT_REG => Ok(FileType { mode: libc::S_IFREG }),
+            libc::DT_SOCK => Ok(FileType { mode: libc::S_IFSOCK }),
+            libc::DT_DIR => Ok(FileType { mode: libc::S_IFDIR }),
+            libc::DT_BLK => Ok(FileType { mode: libc::S_IFBLK }),
+            _ => lstat(&self.path()).map(|m| m.file_type()),
+        }
+    }
+
+    pub fn ino(&self) -> u64 {
+        self.entry.d_ino as u64
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        &*self.name
+    }
+}
+
+impl DirBuilder {
+    pub fn new() -> DirBuilder {
+        DirBuilder { mode: 0o777 }
+    }
+
+    pub fn mkdir(&self, p: &Path) -> io::Result<()> {
+        let p = cstr(p)?;
+        cvt(unsafe { libc::mkdir(p.as_ptr(), self.mode) })?;
+        Ok(())
+    }
+
+    pub fn set_mode(&mut self, mode: u32) {
+        self.mode = mode as mode_t;
+    }
+}
+
 impl FromInner<u32> for FilePermissions {
     fn from_inner(mode: u32) -> FilePermissions {
         FilePermissions { mode: mode as mode_t }
@@ -339,6 +488,19 @@ impl fmt::Debug for File {
     }
 }
 
+pub fn readdir(p: &Path) -> io::Result<ReadDir> {
+    let root = Arc::new(p.to_path_buf());
+    let p = cstr(p)?;
+    unsafe {
+        let ptr = libc::opendir(p.as_ptr());
+        if ptr.is_null() {
+            Err(Error::last_os_error())
+        } else {
+            Ok(ReadDir { dirp: Dir(ptr), root: root, end_of_stream: false })
+        }
+    }
+}
+
 pub fn unlink(p: &Path) -> io::Result<()> {
     let p = cstr(p)?;
     cvt(unsafe { libc::unlink(p.as_ptr()) })?;
@@ -352,6 +514,33 @@ pub fn rename(old: &Path, new: &Path) -> io::Result<()> {
     Ok(())
 }
 
+pub fn rmdir(p: &Path) -> io::Result<()> {
+    let p = cstr(p)?;
+    cvt(unsafe { libc::rmdir(p.as_ptr()) })?;
+    Ok(())
+}
+
+pub fn remove_dir_all(path: &Path) -> io::Result<()> {
+    let filetype = lstat(path)?.file_type();
+    if filetype.is_symlink() {
+        unlink(path)
+    } else {
+        remove_dir_all_recursive(path)
+    }
+}
+
+fn remove_dir_all_recursive(path: &Path) -> io::Result<()> {
+    for child in readdir(path)? {
+        let child = child?;
+        if child.file_type()?.is_dir() {
+            remove_dir_all_recursive(&child.path())?;
+        } else {
+            unlink(&child.path())?;
+        }
+    }
+    rmdir(path)
+}
+
 pub fn set_perm(p: &Path, perm: FilePermissions) -> io::Result<()> {
     let p = cstr(p)?;
     cvt_r(|| unsafe { libc::chmod(p.as_ptr(), perm.mode) })?;
@@ -548,6 +737,29 @@ mod libc {
                                    pathname: * const c_char) -> sgx_status_t;
 
         pub fn u_fs_free_ocall(p: * mut c_void) -> sgx_status_t;
+
+        pub fn u_fs_mkdir_ocall(result: * mut c_int,
+                                error: * mut c_int,
+                                pathname: * const c_char,
+                                mode: mode_t) -> sgx_status_t;
+
+        pub fn u_fs_rmdir_ocall(result: * mut c_int,
+                                error: * mut c_int,
+                                pathname: * const c_char) -> sgx_status_t;
+
+        pub fn u_fs_opendir_ocall(result: * mut * mut DIR,
+                                  error: * mut c_int,
+                                  pathname: * const c_char) -> sgx_status_t;
+
+        pub fn u_fs_readdir64_ocall(result: * mut c_int,
+                                    error: * mut c_int,
+                                    dirp: * mut DIR,
+                                    entry: * mut dirent64,
+                                    eods: * mut c_int) -> sgx_status_t;
+
+        pub fn u_fs_closedir_ocall(result: * mut c_int,
+                                   error: * mut c_int,
+                                   dirp: * mut DIR) -> sgx_status_t;
     }
 
     pub unsafe fn open64(path: * const c_char, oflag: c_int, mode: c_int) -> c_int {
@@ -893,4 +1105,102 @@ mod libc {
 
         let _ = u_fs_free_ocall(p);
     }
+
+    pub unsafe fn mkdir(pathname: * const c_char, mode: mode_t) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_fs_mkdir_ocall(&mut result as * mut c_int,
+                                      &mut error as * mut c_int,
+                                      pathname,
+                                      mode);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn rmdir(pathname: * const c_char) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_fs_rmdir_ocall(&mut result as * mut c_int,
+                                      &mut error as * mut c_int,
+                                      pathname);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn opendir(pathname: * const c_char) -> * mut DIR {
+
+        let mut result: * mut DIR = ptr::null_mut();
+        let mut error: c_int = 0;
+        let status = u_fs_opendir_ocall(&mut result as * mut * mut DIR,
+                                        &mut error as * mut c_int,
+                                        pathname);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result.is_null() {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = ptr::null_mut();
+        }
+        result
+    }
+
+    pub unsafe fn readdir64(dirp: * mut DIR, entry: * mut dirent64, eods: * mut c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_fs_readdir64_ocall(&mut result as * mut c_int,
+                                          &mut error as * mut c_int,
+                                          dirp,
+                                          entry,
+                                          eods);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn closedir(dirp: * mut DIR) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_fs_closedir_ocall(&mut result as * mut c_int,
+                                         &mut error as * mut c_int,
+                                         dirp);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
 }
\ No newline at end of file
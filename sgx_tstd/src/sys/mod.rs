@@ -36,6 +36,8 @@ pub mod fs;
 pub mod sgxfs;
 #[cfg(feature = "net")]
 pub mod net;
+#[cfg(feature = "net")]
+pub mod asyncio;
 pub mod os_str;
 pub mod path;
 pub mod ext;
@@ -46,6 +48,8 @@ pub mod stdio;
 #[cfg(feature = "backtrace")]
 pub mod backtrace;
 pub mod time;
+#[cfg(feature = "thread")]
+pub mod thread;
 pub mod memchr;
 pub mod cmath;
 pub mod env;
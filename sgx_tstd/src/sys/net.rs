@@ -38,11 +38,35 @@ use sys_common::net::{getsockopt, setsockopt, sockaddr_to_addr};
 use time::Duration;
 
 pub use sys::{cvt, cvt_r};
+pub use self::libc::getaddrinfo;
 
 pub type wrlen_t = size_t;
 
 pub struct Socket(FileDesc);
 
+pub fn cvt_gai(err: c_int) -> io::Result<()> {
+    if err == 0 {
+        return Ok(())
+    }
+    if err == libc::EAI_SYSTEM {
+        return Err(io::Error::last_os_error())
+    }
+
+    // gai_strerror lives outside of the enclave, so keep our own table of
+    // the common codes instead of asking the untrusted side for a string.
+    let detail = match err {
+        libc::EAI_NONAME => "name or service not known",
+        libc::EAI_AGAIN => "temporary failure in name resolution",
+        libc::EAI_FAIL => "non-recoverable failure in name resolution",
+        libc::EAI_FAMILY => "address family not supported",
+        libc::EAI_SOCKTYPE => "socket type not supported",
+        libc::EAI_MEMORY => "memory allocation failure",
+        _ => "unknown error",
+    };
+    Err(io::Error::new(io::ErrorKind::Other,
+                       format!("failed to lookup address information: {}", detail)))
+}
+
 impl Socket {
 
     pub fn new(sockfd: c_int) -> io::Result<Socket> {
@@ -51,6 +75,62 @@ impl Socket {
         Ok(Socket(fd))
     }
 
+    pub fn new_with_addr(addr: &SocketAddr, ty: c_int) -> io::Result<Socket> {
+        let fam = match *addr {
+            SocketAddr::V4(..) => libc::AF_INET,
+            SocketAddr::V6(..) => libc::AF_INET6,
+        };
+        Socket::new_raw(fam, ty)
+    }
+
+    pub fn new_raw(fam: c_int, ty: c_int) -> io::Result<Socket> {
+        let fd = cvt(unsafe { libc::socket(fam, ty | libc::SOCK_CLOEXEC, 0) })?;
+        Ok(Socket(FileDesc::new(fd)))
+    }
+
+    pub fn accept(&self, storage: *mut libc::sockaddr, len: *mut libc::socklen_t)
+                  -> io::Result<Socket> {
+        let in_len = unsafe { *len };
+        let fd = cvt_r(|| unsafe {
+            *len = in_len;
+            libc::accept4(self.0.raw(), storage, len, libc::SOCK_CLOEXEC)
+        })?;
+        let sock = Socket(FileDesc::new(fd));
+
+        // The address length comes from the untrusted side, it must never
+        // claim more bytes than the buffer we handed out.
+        if unsafe { *len } > in_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "invalid address length returned by accept"));
+        }
+        Ok(sock)
+    }
+
+    pub fn connect_timeout(&self, addr: &SocketAddr, timeout: Duration) -> io::Result<()> {
+        if timeout.as_secs() == 0 && timeout.subsec_nanos() == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "cannot set a 0 duration timeout"));
+        }
+
+        // A blocking connect on Linux gives up after SO_SNDTIMEO and reports
+        // EINPROGRESS, which saves a poll OCALL per connection attempt.
+        let old = self.timeout(libc::SO_SNDTIMEO)?;
+        self.set_timeout(Some(timeout), libc::SO_SNDTIMEO)?;
+
+        let (addrp, len) = addr.into_inner();
+        let ret = cvt(unsafe { libc::connect(self.0.raw(), addrp, len) });
+        self.set_timeout(old, libc::SO_SNDTIMEO)?;
+
+        match ret {
+            Ok(_) => Ok(()),
+            Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) ||
+                          e.kind() == io::ErrorKind::WouldBlock => {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "connection timed out"))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn raw(&self) -> c_int { self.0.raw() }
 
     pub fn into_raw(self) -> c_int { self.0.into_raw() }
@@ -197,9 +277,34 @@ impl IntoInner<c_int> for Socket {
 mod libc {
     use sgx_types::sgx_status_t;
     use io;
+    use core::mem;
     pub use sgx_trts::libc::*;
+    pub use sys_common::net::c::connect;
 
     extern "C" {
+        pub fn u_net_socket_ocall(result: * mut c_int,
+                                  errno: * mut c_int,
+                                  domain: c_int,
+                                  ty: c_int,
+                                  protocol: c_int) -> sgx_status_t;
+
+        pub fn u_net_accept4_ocall(result: * mut c_int,
+                                   errno: * mut c_int,
+                                   sockfd: c_int,
+                                   addr: * mut sockaddr,
+                                   _in_addrlen: socklen_t,
+                                   addrlen: * mut socklen_t,
+                                   flags: c_int) -> sgx_status_t;
+
+        pub fn u_net_getaddrinfo_ocall(result: * mut c_int,
+                                       errno: * mut c_int,
+                                       node: * const c_char,
+                                       family: c_int,
+                                       socktype: c_int,
+                                       addrs: * mut c_void,
+                                       _in_len: size_t,
+                                       count: * mut size_t) -> sgx_status_t;
+
         pub fn u_net_recv_ocall(result: * mut ssize_t,
                                 errno: * mut c_int,
                                 sockfd: c_int,
@@ -230,6 +335,88 @@ mod libc {
 
     }
 
+    pub unsafe fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_net_socket_ocall(&mut result as * mut c_int,
+                                        &mut error as * mut c_int,
+                                        domain,
+                                        ty,
+                                        protocol);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn accept4(sockfd: c_int,
+                          addr: * mut sockaddr,
+                          addrlen: * mut socklen_t,
+                          flags: c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let in_addrlen: socklen_t = if !addrlen.is_null() {
+            *addrlen
+        } else {
+            0
+        };
+
+        let status = u_net_accept4_ocall(&mut result as * mut c_int,
+                                         &mut error as * mut c_int,
+                                         sockfd,
+                                         addr,
+                                         in_addrlen,
+                                         addrlen,
+                                         flags);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn getaddrinfo(node: * const c_char,
+                              family: c_int,
+                              socktype: c_int,
+                              addrs: * mut sockaddr_storage,
+                              len: size_t,
+                              count: * mut size_t) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_net_getaddrinfo_ocall(&mut result as * mut c_int,
+                                             &mut error as * mut c_int,
+                                             node,
+                                             family,
+                                             socktype,
+                                             addrs as * mut c_void,
+                                             len * mem::size_of::<sockaddr_storage>(),
+                                             count);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == EAI_SYSTEM {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = EAI_SYSTEM;
+        }
+        result
+    }
+
     pub unsafe fn recv(sockfd: c_int, buf: * mut c_void, len: size_t, flags: c_int) -> ssize_t {
 
         let mut result: ssize_t = 0;
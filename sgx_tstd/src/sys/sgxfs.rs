@@ -26,16 +26,60 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
-use sgx_types::{sgx_status_t, sgx_key_128bit_t};
+use sgx_types::{sgx_status_t, sgx_key_128bit_t, SysResult, SysError};
 use sgx_trts::libc;
 use sgx_tprotected_fs::{self, SgxFileStream};
+use sgx_tprotected_fs::pfs::{self, ProtectedFile, ProtectedStorage};
 use os::unix::prelude::*;
-use ffi::{CString, CStr};
+use ffi::{CString, CStr, OsString};
 use io::{self, Error, ErrorKind, SeekFrom};
-use path::Path;
+use path::{Path, PathBuf};
+use sys::fs as host_fs;
 use sys_common::FromInner;
+use core::cell::RefCell;
+use core::cmp;
+use core::u64;
 
-pub struct SgxFile(SgxFileStream);
+pub struct SgxFile(Inner);
+
+enum Inner {
+    Stream(SgxFileStream),
+    Storage(RefCell<Box<StorageStream>>),
+}
+
+// A `ProtectedFile` on any backend, with the error codes of `SgxFileStream`.
+trait StorageStream {
+    fn read(&mut self, buf: &mut [u8]) -> SysResult<usize>;
+    fn write(&mut self, buf: &[u8]) -> SysResult<usize>;
+    fn tell(&mut self) -> SysResult<i64>;
+    fn seek(&mut self, offset: i64, origin: sgx_tprotected_fs::SeekFrom) -> SysError;
+    fn flush(&mut self) -> SysError;
+    fn is_eof(&self) -> bool;
+    fn clearerr(&mut self);
+    fn clear_cache(&mut self) -> SysError;
+}
+
+impl<S: ProtectedStorage> StorageStream for ProtectedFile<S> {
+    fn read(&mut self, buf: &mut [u8]) -> SysResult<usize> { ProtectedFile::read(self, buf) }
+    fn write(&mut self, buf: &[u8]) -> SysResult<usize> { ProtectedFile::write(self, buf) }
+    fn tell(&mut self) -> SysResult<i64> { ProtectedFile::tell(self) }
+    fn seek(&mut self, offset: i64, origin: sgx_tprotected_fs::SeekFrom) -> SysError {
+        ProtectedFile::seek(self, offset, origin)
+    }
+    fn flush(&mut self) -> SysError { ProtectedFile::flush(self) }
+    fn is_eof(&self) -> bool { ProtectedFile::is_eof(self) }
+    fn clearerr(&mut self) { ProtectedFile::clearerr(self) }
+    fn clear_cache(&mut self) -> SysError { ProtectedFile::clear_cache(self) }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FileAttr {
+    size: u64,
+}
+
+impl FileAttr {
+    pub fn size(&self) -> u64 { self.size }
+}
 
 #[derive(Clone, Debug)]
 pub struct OpenOptions {
@@ -106,25 +150,30 @@ impl SgxFile {
             SgxFileStream::open(path, opts, key)
         };
 
-        file.map(|stream| SgxFile(stream))
-            .map_err(|err| {
-                match err {
-                    1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
-                    2 => Error::from_raw_os_error(libc::ENOENT),
-                    3 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY),
-                    4 | 5 => Error::from_raw_os_error(err),
-                    r if r > 4096 => {
-                        let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
-                        Error::from_sgx_error(status)
-                    },
-                    _ => Error::from_raw_os_error(err),
-                }
-            })
+        file.map(|stream| SgxFile(Inner::Stream(stream))).map_err(file_error)
+    }
+
+    pub fn open_in<S>(storage: S, path: &Path, opts: &OpenOptions, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxFile>
+        where S: ProtectedStorage + 'static {
+
+        let path = cstr(path)?;
+        let mode = opts.get_access_mode()?;
+        let opts = CString::new(mode.as_bytes())?;
+        let file = match key {
+            Some(key) => ProtectedFile::open_in(storage, &path, &opts, key),
+            None => ProtectedFile::open_auto_key_in(storage, &path, &opts),
+        };
+
+        file.map(|file| SgxFile(Inner::Storage(RefCell::new(Box::new(file))))).map_err(file_error)
     }
 
     pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
 
-        self.0.read(buf).map_err(|err| {
+        let result = match self.0 {
+            Inner::Stream(ref stream) => stream.read(buf),
+            Inner::Storage(ref file) => file.borrow_mut().read(buf),
+        };
+        result.map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -141,7 +190,11 @@ impl SgxFile {
 
     pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
 
-        self.0.write(buf).map_err(|err| {
+        let result = match self.0 {
+            Inner::Stream(ref stream) => stream.write(buf),
+            Inner::Storage(ref file) => file.borrow_mut().write(buf),
+        };
+        result.map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -158,7 +211,11 @@ impl SgxFile {
 
     pub fn tell(&self) -> io::Result<u64> {
 
-        self.0.tell().map_err(|err| {
+        let result = match self.0 {
+            Inner::Stream(ref stream) => stream.tell(),
+            Inner::Storage(ref file) => file.borrow_mut().tell(),
+        };
+        result.map_err(|err| {
             match err {
                 r if r > 4096 => {
                     let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
@@ -178,7 +235,11 @@ impl SgxFile {
             SeekFrom::Current(off) => (sgx_tprotected_fs::SeekFrom::Current, off),
         };
 
-        try!(self.0.seek(offset, whence).map_err(|err| {
+        let result = match self.0 {
+            Inner::Stream(ref stream) => stream.seek(offset, whence),
+            Inner::Storage(ref file) => file.borrow_mut().seek(offset, whence),
+        };
+        try!(result.map_err(|err| {
             match err {
                 r if r > 4096 => {
                     let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
@@ -194,7 +255,11 @@ impl SgxFile {
 
     pub fn flush(&self) -> io::Result<()> {
 
-        self.0.flush().map_err(|err| {
+        let result = match self.0 {
+            Inner::Stream(ref stream) => stream.flush(),
+            Inner::Storage(ref file) => file.borrow_mut().flush(),
+        };
+        result.map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -209,17 +274,35 @@ impl SgxFile {
         })
     }
 
+    pub fn file_attr(&self) -> io::Result<FileAttr> {
+
+        let pos = self.tell()?;
+        let size = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(FileAttr { size: size })
+    }
+
     pub fn is_eof(&self) -> bool {
-        self.0.is_eof()
+        match self.0 {
+            Inner::Stream(ref stream) => stream.is_eof(),
+            Inner::Storage(ref file) => file.borrow().is_eof(),
+        }
     }
 
     pub fn clearerr(&self) {
-        self.0.clearerr()
+        match self.0 {
+            Inner::Stream(ref stream) => stream.clearerr(),
+            Inner::Storage(ref file) => file.borrow_mut().clearerr(),
+        }
     }
 
     pub fn clear_cache(&self) -> io::Result<()> {
 
-        self.0.clear_cache().map_err(|err| {
+        let result = match self.0 {
+            Inner::Stream(ref stream) => stream.clear_cache(),
+            Inner::Storage(ref file) => file.borrow_mut().clear_cache(),
+        };
+        result.map_err(|err| {
             match err {
                 1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
                 2 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_INVALID_PARAMETER),
@@ -238,55 +321,40 @@ impl SgxFile {
 pub fn remove(path: &Path) -> io::Result<()> {
 
     let path = cstr(path)?;
-    sgx_tprotected_fs::remove(&path).map_err(|err| {
-        match err {
-            1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
-            2 => Error::from_raw_os_error(libc::ENOENT),
-            3 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY),
-            4 | 5 => Error::from_raw_os_error(err),
-            r if r > 4096 => {
-                let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
-                Error::from_sgx_error(status)
-            },
-            _ => Error::from_raw_os_error(err),
-        }
-    })
+    sgx_tprotected_fs::remove(&path).map_err(file_error)
+}
+
+pub fn remove_in<S: ProtectedStorage>(storage: &S, path: &Path) -> io::Result<()> {
+
+    let path = cstr(path)?;
+    sgx_tprotected_fs::pfs::remove_in(storage, &path).map_err(file_error)
 }
 
 pub fn export_auto_key(path: &Path) -> io::Result<sgx_key_128bit_t> {
 
     let path = cstr(path)?;
-    sgx_tprotected_fs::export_auto_key(&path).map_err(|err| {
-        match err {
-            1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
-            2 => Error::from_raw_os_error(libc::ENOENT),
-            3 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY),
-            4 | 5 => Error::from_raw_os_error(err),
-            r if r > 4096 => {
-                let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
-                Error::from_sgx_error(status)
-            },
-            _ => Error::from_raw_os_error(err),
-        }
-    })
+    sgx_tprotected_fs::export_auto_key(&path).map_err(file_error)
 }
 
 pub fn import_auto_key(path: &Path, key: &sgx_key_128bit_t) -> io::Result<()> {
 
     let path = cstr(path)?;
-    sgx_tprotected_fs::import_auto_key(&path, key).map_err(|err| {
-        match err {
-            1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
-            2 => Error::from_raw_os_error(libc::ENOENT),
-            3 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY),
-            4 | 5 => Error::from_raw_os_error(err),
-            r if r > 4096 => {
-                let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
-                Error::from_sgx_error(status)
-            },
-            _ => Error::from_raw_os_error(err),
-        }
-    })
+    sgx_tprotected_fs::import_auto_key(&path, key).map_err(file_error)
+}
+
+// Maps the error codes of opening or removing a file.
+fn file_error(err: i32) -> Error {
+    match err {
+        1 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_UNEXPECTED),
+        2 => Error::from_raw_os_error(libc::ENOENT),
+        3 => Error::from_sgx_error(sgx_status_t::SGX_ERROR_OUT_OF_MEMORY),
+        4 | 5 => Error::from_raw_os_error(err),
+        r if r > 4096 => {
+            let status = sgx_status_t::from_repr(r as u32).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
+            Error::from_sgx_error(status)
+        },
+        _ => Error::from_raw_os_error(err),
+    }
 }
 
 fn cstr(path: &Path) -> io::Result<CString> {
@@ -295,7 +363,7 @@ fn cstr(path: &Path) -> io::Result<CString> {
 
 impl FromInner<SgxFileStream> for SgxFile {
     fn from_inner(stream: SgxFileStream) -> SgxFile {
-        SgxFile(stream)
+        SgxFile(Inner::Stream(stream))
     }
 }
 
@@ -324,4 +392,144 @@ pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
     let ret = io::copy(&mut reader, &mut writer)?;
     fs::set_permissions(to, perm)?;
     Ok(ret)
-}
\ No newline at end of file
+}
+
+const COPY_BUF_SIZE: usize = 4096;
+
+fn open_with_key(path: &Path, opts: &OpenOptions, key: Option<&sgx_key_128bit_t>) -> io::Result<SgxFile> {
+    match key {
+        Some(key) => SgxFile::open_ex(path, opts, key),
+        None => SgxFile::open(path, opts),
+    }
+}
+
+fn read_options() -> OpenOptions {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    opts
+}
+
+fn write_options() -> OpenOptions {
+    let mut opts = OpenOptions::new();
+    opts.write(true);
+    opts
+}
+
+fn write_all(file: &SgxFile, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match file.write(buf)? {
+            0 => return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+            n => buf = &buf[n..],
+        }
+    }
+    Ok(())
+}
+
+// Copies at most `limit` bytes from the current position of `reader` to `writer`.
+fn copy_stream(reader: &SgxFile, writer: &SgxFile, limit: u64) -> io::Result<u64> {
+    let mut buf = [0_u8; COPY_BUF_SIZE];
+    let mut copied: u64 = 0;
+    while copied < limit {
+        let len = cmp::min(buf.len() as u64, limit - copied) as usize;
+        let n = reader.read(&mut buf[..len])?;
+        if n == 0 {
+            break;
+        }
+        write_all(writer, &buf[..n])?;
+        copied += n as u64;
+    }
+    Ok(copied)
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".sgxtmp");
+    PathBuf::from(name)
+}
+
+pub fn stat(path: &Path, key: Option<&sgx_key_128bit_t>) -> io::Result<FileAttr> {
+
+    open_with_key(path, &read_options(), key)?.file_attr()
+}
+
+pub fn set_len(path: &Path, key: Option<&sgx_key_128bit_t>, size: u64) -> io::Result<()> {
+
+    let len = stat(path, key)?.size();
+    if size >= len {
+        let mut opts = read_options();
+        opts.update(true);
+        let file = open_with_key(path, &opts, key)?;
+        file.seek(SeekFrom::End(0))?;
+        let zeros = [0_u8; COPY_BUF_SIZE];
+        let mut remaining = size - len;
+        while remaining > 0 {
+            let n = cmp::min(remaining, zeros.len() as u64) as usize;
+            write_all(&file, &zeros[..n])?;
+            remaining -= n as u64;
+        }
+        file.flush()
+    } else {
+        rewrite(path, key, key, size)
+    }
+}
+
+// Whether `from` and `to` name the same file on the host, `to` possibly not existing yet.
+fn same_file(from: &Path, to: &Path) -> io::Result<bool> {
+
+    let from = host_fs::canonicalize(from)?;
+    let to = match host_fs::canonicalize(to) {
+        Ok(to) => to,
+        Err(_) => {
+            let name = match to.file_name() {
+                Some(name) => name,
+                None => return Ok(false),
+            };
+            let parent = match to.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            match host_fs::canonicalize(parent) {
+                Ok(parent) => parent.join(name),
+                Err(_) => return Ok(false),
+            }
+        },
+    };
+    Ok(from == to)
+}
+
+// The name is changed in the meta data node first, then on the host. The host rename replaces
+// `to` atomically; if it does not happen, renaming again finishes the job.
+pub fn rename(from: &Path, to: &Path, key: Option<&sgx_key_128bit_t>) -> io::Result<()> {
+
+    if same_file(from, to)? {
+        stat(from, key)?;
+        return Ok(());
+    }
+    let from_name = cstr(from)?;
+    let to_name = cstr(to)?;
+    pfs::rename_meta(&from_name, &to_name, key).map_err(file_error)?;
+    host_fs::rename(from, to)
+}
+
+pub fn rekey(path: &Path, old_key: Option<&sgx_key_128bit_t>, new_key: Option<&sgx_key_128bit_t>) -> io::Result<()> {
+
+    rewrite(path, old_key, new_key, u64::MAX)
+}
+
+// The first `limit` bytes go to a temporary file under `new_key`, which is then renamed over the
+// original. The original is untouched until the host replaces it in one step, so a failure or a
+// crash at any point leaves either the old or the new content under `path`.
+fn rewrite(path: &Path, old_key: Option<&sgx_key_128bit_t>, new_key: Option<&sgx_key_128bit_t>, limit: u64) -> io::Result<()> {
+
+    let temp = temp_path(path);
+    let result = open_with_key(path, &read_options(), old_key).and_then(|reader| {
+        let writer = open_with_key(&temp, &write_options(), new_key)?;
+        copy_stream(&reader, &writer, limit)?;
+        writer.flush()
+    }).and_then(|_| rename(&temp, path, new_key));
+    if let Err(err) = result {
+        let _ = remove(&temp);
+        return Err(err);
+    }
+    Ok(())
+}
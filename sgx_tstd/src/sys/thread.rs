@@ -0,0 +1,149 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use sgx_types::sgx_status_t;
+use sync::SgxThreadSpinlock;
+use collections::BTreeMap;
+use alloc::boxed::{Box, FnBox};
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+use enclave;
+use io;
+
+type Table = BTreeMap<u64, Box<FnBox()>>;
+
+// Closures waiting for their thread to enter the enclave. The untrusted side
+// only ever sees the key, so a forged or replayed `t_thread_main_ecall` can
+// not make the enclave jump to an arbitrary address or run a closure twice.
+static LOCK: SgxThreadSpinlock = SgxThreadSpinlock::new();
+static mut PENDING: *mut Table = ptr::null_mut();
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+unsafe fn insert(id: u64, main: Box<FnBox()>) {
+    LOCK.lock();
+    if PENDING.is_null() {
+        let table: Box<Table> = Box::new(BTreeMap::new());
+        PENDING = Box::into_raw(table);
+    }
+    (*PENDING).insert(id, main);
+    LOCK.unlock();
+}
+
+unsafe fn remove(id: u64) -> Option<Box<FnBox()>> {
+    LOCK.lock();
+    let main = if PENDING.is_null() {
+        None
+    } else {
+        (*PENDING).remove(&id)
+    };
+    LOCK.unlock();
+    main
+}
+
+// Runs `p` on a new thread, which enters the enclave through
+// t_thread_main_ecall.
+pub unsafe fn spawn(p: Box<FnBox()>) -> io::Result<()> {
+
+    let eid = enclave::get_enclave_id();
+    if eid == 0 {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  "thread::spawn need the enclave id, enable global_init of sgx_urts"));
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    insert(id, p);
+
+    let mut status = sgx_status_t::SGX_SUCCESS;
+    let ret = libc::thread_spawn(eid, id, &mut status);
+    if ret != 0 {
+        // The new thread never entered the enclave, so nobody else can
+        // claim the closure; drop it here.
+        drop(remove(id));
+        return Err(match status {
+            sgx_status_t::SGX_SUCCESS => io::Error::last_os_error(),
+            _ => io::Error::from_sgx_error(status),
+        });
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn t_thread_main_ecall(id: u64) {
+
+    if let Some(main) = unsafe { remove(id) } {
+        // Lets u_thread_spawn_ocall return, the spawn has succeeded.
+        unsafe { libc::thread_started(); }
+        main();
+    }
+}
+
+mod libc {
+    use sgx_types::{sgx_status_t, sgx_enclave_id_t};
+    use io;
+    pub use sgx_trts::libc::*;
+
+    extern "C" {
+        pub fn u_thread_spawn_ocall(result: * mut c_int,
+                                    error: * mut c_int,
+                                    ecall_status: * mut uint32_t,
+                                    eid: uint64_t,
+                                    id: uint64_t) -> sgx_status_t;
+
+        pub fn u_thread_started_ocall() -> sgx_status_t;
+    }
+
+    // Returns -1 if the thread was not created, or if it could not enter the
+    // enclave; the status of its ECALL is then in `ecall_status`.
+    pub unsafe fn thread_spawn(eid: sgx_enclave_id_t, id: u64, ecall_status: &mut sgx_status_t) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let mut status_raw: uint32_t = 0;
+        let status = u_thread_spawn_ocall(&mut result as * mut c_int,
+                                          &mut error as * mut c_int,
+                                          &mut status_raw as * mut uint32_t,
+                                          eid as uint64_t,
+                                          id as uint64_t);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+                *ecall_status = sgx_status_t::from_repr(status_raw).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
+    pub unsafe fn thread_started() {
+
+        let _ = u_thread_started_ocall();
+    }
+}
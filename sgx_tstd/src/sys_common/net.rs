@@ -28,13 +28,15 @@
 
 #![allow(dead_code)]
 
-use sgx_trts::libc::{c_int, c_uint, c_void};
+use sgx_trts::libc::{c_int, c_uint, c_void, size_t};
 use core::cmp;
 use core::fmt;
 use core::mem;
+use alloc::vec;
 use io::{self, Error, ErrorKind};
 use net::{SocketAddr, Shutdown, Ipv4Addr, Ipv6Addr};
-use sys::net::{cvt, cvt_r, Socket, wrlen_t};
+use sys::net::{cvt, cvt_r, cvt_gai, Socket, wrlen_t};
+use ffi::CString;
 use sys_common::{AsInner, FromInner, IntoInner};
 use time::Duration;
 
@@ -80,13 +82,17 @@ pub fn sockaddr_to_addr(storage: &c::sockaddr_storage,
                     len: usize) -> io::Result<SocketAddr> {
     match storage.ss_family as c_int {
         c::AF_INET => {
-            assert!(len as usize >= mem::size_of::<c::sockaddr_in>());
+            if len < mem::size_of::<c::sockaddr_in>() {
+                return Err(Error::new(ErrorKind::InvalidInput, "invalid argument"));
+            }
             Ok(SocketAddr::V4(FromInner::from_inner(unsafe {
                 *(storage as *const _ as *const c::sockaddr_in)
             })))
         }
         c::AF_INET6 => {
-            assert!(len as usize >= mem::size_of::<c::sockaddr_in6>());
+            if len < mem::size_of::<c::sockaddr_in6>() {
+                return Err(Error::new(ErrorKind::InvalidInput, "invalid argument"));
+            }
             Ok(SocketAddr::V6(FromInner::from_inner(unsafe {
                 *(storage as *const _ as *const c::sockaddr_in6)
             })))
@@ -97,6 +103,62 @@ pub fn sockaddr_to_addr(storage: &c::sockaddr_storage,
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// get_host_addresses
+////////////////////////////////////////////////////////////////////////////////
+
+// The most addresses a single lookup hands back to the enclave.
+const LOOKUP_HOST_MAX_ADDRS: usize = 32;
+
+/// An iterator over the addresses of a host, as answered by the resolver of
+/// the untrusted side.
+pub struct LookupHost {
+    addrs: vec::IntoIter<SocketAddr>,
+}
+
+impl Iterator for LookupHost {
+    type Item = SocketAddr;
+    fn next(&mut self) -> Option<SocketAddr> {
+        self.addrs.next()
+    }
+}
+
+impl fmt::Debug for LookupHost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("LookupHost { .. }")
+    }
+}
+
+pub fn lookup_host(host: &str) -> io::Result<LookupHost> {
+    let c_host = CString::new(host)?;
+    let mut storage: Vec<c::sockaddr_storage> = (0..LOOKUP_HOST_MAX_ADDRS)
+        .map(|_| unsafe { mem::zeroed() })
+        .collect();
+    let mut count: size_t = 0;
+
+    cvt_gai(unsafe {
+        c::getaddrinfo(c_host.as_ptr(),
+                       c::AF_UNSPEC,
+                       c::SOCK_STREAM,
+                       storage.as_mut_ptr(),
+                       storage.len(),
+                       &mut count)
+    })?;
+
+    if count > storage.len() {
+        return Err(Error::new(ErrorKind::InvalidData,
+                              "invalid address count returned by getaddrinfo"));
+    }
+
+    // Every entry spans a whole sockaddr_storage, so a bogus family or
+    // length can not make us read past it; anything that is not an IP
+    // address is skipped.
+    let addrs: Vec<SocketAddr> = storage[..count].iter()
+        .filter_map(|s| sockaddr_to_addr(s, mem::size_of::<c::sockaddr_storage>()).ok())
+        .collect();
+    Ok(LookupHost { addrs: addrs.into_iter() })
+}
+
 fn to_ipv6mr_interface(value: u32) -> c_uint {
     value as c_uint
 }
@@ -116,6 +178,20 @@ impl TcpStream {
         Ok(TcpStream { inner: sock })
     }
 
+    pub fn connect(addr: &SocketAddr) -> io::Result<TcpStream> {
+        let sock = Socket::new_with_addr(addr, c::SOCK_STREAM)?;
+
+        let (addrp, len) = addr.into_inner();
+        cvt_r(|| unsafe { c::connect(*sock.as_inner(), addrp, len) })?;
+        Ok(TcpStream { inner: sock })
+    }
+
+    pub fn connect_timeout(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        let sock = Socket::new_with_addr(addr, c::SOCK_STREAM)?;
+        sock.connect_timeout(addr, timeout)?;
+        Ok(TcpStream { inner: sock })
+    }
+
     pub fn raw(&self) -> c_int { self.inner.raw() }
 
     pub fn into_raw(self) -> c_int { self.inner.into_raw() }
@@ -238,6 +314,107 @@ impl fmt::Debug for TcpStream {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// TCP listeners
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct TcpListener {
+    inner: Socket,
+}
+
+impl TcpListener {
+
+    pub fn bind(addr: &SocketAddr) -> io::Result<TcpListener> {
+        let sock = Socket::new_with_addr(addr, c::SOCK_STREAM)?;
+
+        // On platforms with Berkeley-derived sockets, this allows
+        // to quickly rebind a socket, without needing to wait for
+        // the OS to clean up the previous one.
+        setsockopt(&sock, c::SOL_SOCKET, c::SO_REUSEADDR, 1 as c_int)?;
+
+        // Bind our new socket
+        let (addrp, len) = addr.into_inner();
+        cvt(unsafe { c::bind(*sock.as_inner(), addrp, len as _) })?;
+
+        // Start listening
+        cvt(unsafe { c::listen(*sock.as_inner(), 128) })?;
+        Ok(TcpListener { inner: sock })
+    }
+
+    pub fn raw(&self) -> c_int { self.inner.raw() }
+
+    pub fn into_raw(self) -> c_int { self.inner.into_raw() }
+
+    pub fn socket(&self) -> &Socket { &self.inner }
+
+    pub fn into_socket(self) -> Socket { self.inner }
+
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        sockname(|buf, len| unsafe {
+            c::getsockname(*self.inner.as_inner(), buf, len)
+        })
+    }
+
+    pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let mut storage: c::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut len = mem::size_of_val(&storage) as c::socklen_t;
+        let sock = self.inner.accept(&mut storage as *mut _ as *mut _,
+                                     &mut len)?;
+        let addr = sockaddr_to_addr(&storage, len as usize)?;
+        Ok((TcpStream { inner: sock, }, addr))
+    }
+
+    pub fn duplicate(&self) -> io::Result<TcpListener> {
+        self.inner.duplicate().map(|s| TcpListener { inner: s })
+    }
+
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IP, c::IP_TTL, ttl as c_int)
+    }
+
+    pub fn ttl(&self) -> io::Result<u32> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IP, c::IP_TTL)?;
+        Ok(raw as u32)
+    }
+
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        setsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_V6ONLY, only_v6 as c_int)
+    }
+
+    pub fn only_v6(&self) -> io::Result<bool> {
+        let raw: c_int = getsockopt(&self.inner, c::IPPROTO_IPV6, c::IPV6_V6ONLY)?;
+        Ok(raw != 0)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+}
+
+impl FromInner<Socket> for TcpListener {
+    fn from_inner(socket: Socket) -> TcpListener {
+        TcpListener { inner: socket }
+    }
+}
+
+impl fmt::Debug for TcpListener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut res = f.debug_struct("TcpListener");
+
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+
+        let name = if cfg!(windows) {"socket"} else {"fd"};
+        res.field(name, &self.inner.as_inner())
+            .finish()
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // UDP
 ////////////////////////////////////////////////////////////////////////////////
@@ -445,10 +622,11 @@ impl fmt::Debug for UdpSocket {
     }
 }
 
-mod c {
+pub mod c {
     use sgx_types::sgx_status_t;
     use io;
     pub use sgx_trts::libc::*;
+    pub use sys::net::getaddrinfo;
 
     extern "C" {
 
@@ -464,6 +642,11 @@ mod c {
                                    address: * const sockaddr,
                                    addrlen: socklen_t) -> sgx_status_t;
 
+        pub fn u_net_listen_ocall(result: * mut c_int,
+                                  errno: * mut c_int,
+                                  sockfd: c_int,
+                                  backlog: c_int) -> sgx_status_t;
+
         pub fn u_net_setsockopt_ocall(result: * mut c_int,
                                       errno: * mut c_int,
                                       sockfd: c_int,
@@ -554,6 +737,26 @@ mod c {
         result
     }
 
+    pub unsafe fn listen(sockfd: c_int, backlog: c_int) -> c_int {
+
+        let mut result: c_int = 0;
+        let mut error: c_int = 0;
+        let status = u_net_listen_ocall(&mut result as * mut c_int,
+                                        &mut error as * mut c_int,
+                                        sockfd,
+                                        backlog);
+
+        if status == sgx_status_t::SGX_SUCCESS {
+            if result == -1 {
+                io::set_errno(error);
+            }
+        } else {
+            io::set_errno(ESGX);
+            result = -1;
+        }
+        result
+    }
+
     pub unsafe fn setsockopt(sockfd: c_int,
                              level: c_int,
                              optname: c_int,
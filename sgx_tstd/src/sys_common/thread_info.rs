@@ -58,4 +58,11 @@ pub fn set(thread: SgxThread) {
     THREAD_INFO.with(move |c| *c.borrow_mut() = Some(SgxThreadInfo{
         thread,
     }));
+}
+
+// The thread locals of a bound TCS outlive the untrusted thread, so a spawned
+// thread leaves the slot empty for the next thread bound to its TCS.
+#[cfg(feature = "thread")]
+pub fn reset() {
+    let _ = THREAD_INFO.try_with(|c| *c.borrow_mut() = None);
 }
\ No newline at end of file
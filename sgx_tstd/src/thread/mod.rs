@@ -35,6 +35,18 @@ use sync::{SgxMutex, SgxCondvar};
 use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::SeqCst;
 use alloc::sync::Arc;
+#[cfg(feature = "thread")]
+use any::Any;
+use ffi::{CStr, CString};
+#[cfg(feature = "thread")]
+use panic;
+#[cfg(feature = "thread")]
+use sys::thread as imp;
+#[cfg(feature = "thread")]
+use enclave::{get_thread_policy, SgxThreadPolicy};
+#[cfg(feature = "thread")]
+use io;
+use str;
 
 #[macro_use] mod local;
 pub use self::local::{LocalKey, LocalKeyInner, AccessError};
@@ -83,6 +95,129 @@ pub fn rsgx_thread_equal(a: sgx_thread_t, b: sgx_thread_t) -> bool {
     a == b
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Builder
+////////////////////////////////////////////////////////////////////////////////
+
+/// Thread factory, which can be used in order to configure the properties of
+/// a new thread.
+///
+/// Methods can be chained on it in order to configure it.
+///
+/// The only configuration available is the name of the thread. The stack size
+/// of every enclave thread is fixed by `StackMaxSize` in the enclave
+/// configuration file and can not be changed at runtime.
+///
+/// A new thread is started by an OCALL which creates an untrusted thread,
+/// and that thread enters the enclave through `t_thread_main_ecall`. So the
+/// enclave must import `sgx_thread.edl`, the `thread` feature of `sgx_urts`
+/// must be enabled, and `TCSNum` must leave a free TCS for every thread
+/// running at the same time.
+#[cfg(feature = "thread")]
+#[derive(Debug)]
+pub struct Builder {
+    // A name for the thread-to-be, for identification in panic messages
+    name: Option<String>,
+}
+
+#[cfg(feature = "thread")]
+impl Builder {
+    /// Generates the base configuration for spawning a thread, from which
+    /// configuration methods can be chained.
+    pub fn new() -> Builder {
+        Builder {
+            name: None,
+        }
+    }
+
+    /// Names the thread-to-be. The name can be read back through
+    /// [`SgxThread::name`] on the new thread.
+    pub fn name(mut self, name: String) -> Builder {
+        self.name = Some(name);
+        self
+    }
+
+    /// Spawns a new thread by taking ownership of the `Builder`, and returns an
+    /// [`io::Result`] to its [`JoinHandle`].
+    ///
+    /// The spawned thread may outlive the caller (unless the caller thread
+    /// is the main thread; the whole process is terminated when the main
+    /// thread finishes). The join handle can be used to block on
+    /// termination of the child thread, including recovering its panics.
+    ///
+    /// # Errors
+    ///
+    /// Unlike the [`spawn`] free function, this method yields an
+    /// [`io::Result`] to capture any failure to create the thread at
+    /// the OS level. It also fails if the TCS policy of the enclave is not
+    /// `Bound`, or if the enclave ID was not set by `t_global_init_ecall`.
+    pub fn spawn<F, T>(self, f: F) -> io::Result<JoinHandle<T>> where
+        F: FnOnce() -> T, F: Send + 'static, T: Send + 'static
+    {
+        let Builder { name } = self;
+
+        match get_thread_policy() {
+            SgxThreadPolicy::Bound => {},
+            _ => return Err(io::Error::new(io::ErrorKind::Other,
+                                           "thread::spawn need TCS policy is Bound")),
+        }
+
+        let name = name.map(|name| {
+            CString::new(name).expect("thread name may not contain interior null bytes")
+        });
+        let their_packet = Arc::new(Packet {
+            result: SgxMutex::new(None),
+            cvar: SgxCondvar::new(),
+        });
+        let my_packet = their_packet.clone();
+
+        let main = move || {
+            // The thread handle has to be created on the new thread, since its
+            // identifier is the trusted thread data of the TCS it is bound to.
+            thread_info::set(SgxThread::new_with_name(name));
+            let try_result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+            thread_info::reset();
+            let mut result = their_packet.result.lock().unwrap();
+            *result = Some(try_result);
+            their_packet.cvar.broadcast();
+        };
+
+        unsafe { imp::spawn(Box::new(main))? };
+        Ok(JoinHandle(JoinInner {
+            packet: my_packet,
+        }))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Free functions
+////////////////////////////////////////////////////////////////////////////////
+
+/// Spawns a new thread, returning a [`JoinHandle`] for it.
+///
+/// The join handle will implicitly *detach* the child thread upon being
+/// dropped. In this case, the child thread may outlive the parent.
+/// Additionally, the join handle provides a [`join`] method that can be used
+/// to join the child thread. If the child thread panics, [`join`] will return
+/// an [`Err`] containing the argument given to [`panic`].
+///
+/// This will create a thread using default parameters of [`Builder`], if you
+/// want to specify the thread name use this API instead.
+///
+/// # Panics
+///
+/// Panics if the OCALL fails to create a thread, or if the enclave does not
+/// satisfy the requirements listed in [`Builder`]; use [`Builder::spawn`]
+/// to recover from such errors.
+///
+/// [`join`]: struct.JoinHandle.html#method.join
+#[cfg(feature = "thread")]
+pub fn spawn<F, T>(f: F) -> JoinHandle<T> where
+    F: FnOnce() -> T, F: Send + 'static, T: Send + 'static
+{
+    Builder::new().spawn(f).unwrap()
+}
+
 /// Gets a handle to the thread that invokes it.
 pub fn current() -> SgxThread {
     thread_info::current_thread().expect("use of thread::current() need TCS policy is Bound")
@@ -179,7 +314,7 @@ pub fn park() {
 /// A `ThreadId` is an opaque object that has a unique value for each thread
 /// that creates one. `ThreadId`s are not guaranteed to correspond to a thread's
 /// system-designated identifier.
-#[derive(Eq, PartialEq, Copy, Clone)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub struct SgxThreadId {
     id: sgx_thread_t,
 }
@@ -196,6 +331,7 @@ impl SgxThreadId {
 /// The internal representation of a `Thread` handle
 struct Inner {
     id: SgxThreadId,
+    name: Option<CString>,     // Guaranteed to be UTF-8
 
     // state for thread park/unpark
     state: AtomicUsize,
@@ -214,9 +350,15 @@ impl SgxThread {
 
     /// Used only internally to construct a thread object without spawning
     pub(crate) fn new() -> Self {
+        SgxThread::new_with_name(None)
+    }
+
+    /// Used only internally to construct the handle of a spawned thread
+    pub(crate) fn new_with_name(name: Option<CString>) -> Self {
         SgxThread {
             inner: Arc::new(Inner {
                 id: SgxThreadId::new(),
+                name: name,
                 state: AtomicUsize::new(EMPTY),
                 lock: SgxMutex::new(()),
                 cvar: SgxCondvar::new(),
@@ -229,6 +371,17 @@ impl SgxThread {
         self.inner.id
     }
 
+    /// Gets the thread's name.
+    ///
+    /// Only threads spawned by a [`Builder`] with a name have one.
+    pub fn name(&self) -> Option<&str> {
+        self.cname().map(|s| unsafe { str::from_utf8_unchecked(s.to_bytes()) } )
+    }
+
+    fn cname(&self) -> Option<&CStr> {
+        self.inner.name.as_ref().map(|s| &**s)
+    }
+
     /// Atomically makes the handle's token available if it is not already.
     ///
     /// Every thread is equipped with some basic low-level blocking support, via
@@ -255,3 +408,76 @@ impl SgxThread {
         }
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// JoinHandle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A specialized [`Result`] type for threads.
+///
+/// Indicates the manner in which a thread exited.
+///
+/// A thread that completes without panicking is considered to exit successfully.
+#[cfg(feature = "thread")]
+pub type Result<T> = ::result::Result<T, Box<Any + Send + 'static>>;
+
+// The result of a spawned thread, filled in by the thread itself before it
+// leaves the enclave and waited on by `join`.
+#[cfg(feature = "thread")]
+struct Packet<T> {
+    result: SgxMutex<Option<Result<T>>>,
+    cvar: SgxCondvar,
+}
+
+/// Inner representation for JoinHandle
+#[cfg(feature = "thread")]
+struct JoinInner<T> {
+    packet: Arc<Packet<T>>,
+}
+
+#[cfg(feature = "thread")]
+impl<T> JoinInner<T> {
+    fn join(&mut self) -> Result<T> {
+        let mut result = self.packet.result.lock().unwrap();
+        loop {
+            match result.take() {
+                Some(r) => return r,
+                None => result = self.packet.cvar.wait(result).unwrap(),
+            }
+        }
+    }
+}
+
+/// An owned permission to join on a thread (block on its termination).
+///
+/// A `JoinHandle` *detaches* the associated thread when it is dropped, which
+/// means that there is no longer any handle to thread and no way to `join`
+/// on it.
+///
+/// This `struct` is created by the [`thread::spawn`] function and the
+/// [`thread::Builder::spawn`] method.
+///
+/// [`thread::spawn`]: fn.spawn.html
+/// [`thread::Builder::spawn`]: struct.Builder.html#method.spawn
+#[cfg(feature = "thread")]
+pub struct JoinHandle<T>(JoinInner<T>);
+
+#[cfg(feature = "thread")]
+unsafe impl<T> Send for JoinHandle<T> {}
+#[cfg(feature = "thread")]
+unsafe impl<T> Sync for JoinHandle<T> {}
+
+#[cfg(feature = "thread")]
+impl<T> JoinHandle<T> {
+    /// Waits for the associated thread to finish.
+    ///
+    /// If the child thread panics, [`Err`] is returned with the parameter given
+    /// to [`panic`].
+    ///
+    /// The result is published right before the child thread returns from
+    /// its ECALL, so its TCS may still be busy for a short while after
+    /// `join` returns.
+    pub fn join(mut self) -> Result<T> {
+        self.0.join()
+    }
+}
@@ -29,3 +29,5 @@
 pub mod fs;
 pub mod path;
 pub mod time;
+#[cfg(feature = "net")]
+pub mod net;
@@ -78,6 +78,21 @@
 //! In this way, `vec[0]` would panic. But `should_panic!` catches it. Thus
 //! `foo_panic` would pass the unit test.
 //!
+//! For test runs driven by scripts, `rsgx_test_suite!` runs the tests which
+//! match a name filter, supports `#[ignore]` and `#[should_panic]` markers,
+//! times each test and returns a [`TestReport`]. The report can be rendered
+//! as JSON, TAP or JUnit XML and handed back to the application:
+//!
+//! ```
+//! let report = rsgx_test_suite!(filter; foo, #[should_panic] foo_panic, #[ignore] bar);
+//! let xml = report.to_junit_xml("enclave");
+//! ```
+//!
+//! Tests are timed with the untrusted clock, so an enclave using
+//! `rsgx_test_suite!` must import `sgx_time.edl`.
+//!
+//! [`TestReport`]: struct.TestReport.html
+//!
 
 #![cfg_attr(not(target_env = "sgx"), no_std)]
 #![cfg_attr(target_env = "sgx", feature(rustc_private))]
@@ -89,6 +104,9 @@ extern crate sgx_tstd as std;
 
 use std::string::String;
 use std::vec::Vec;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use std::untrusted::time::InstantEx;
 
 /// This macro implements the fail test.
 ///
@@ -231,3 +249,283 @@ pub fn rsgx_unit_test(ncases: &mut u64, failurecases: &mut Vec<String>, f:&'stat
     }
 }
 
+/// This macro runs a test suite and returns a `TestReport`.
+///
+/// It takes an `Option<&str>` name filter, then a list of test case
+/// functions. Only the tests whose name contains the filter are run. A test
+/// function may be preceded by `#[ignore]`, to report it without running it,
+/// or by `#[should_panic]`, to make it pass only if it panics.
+///
+/// ```
+/// #[no_mangle]
+/// pub extern "C"
+/// fn test_ecall(filter: *const c_char) -> sgx_status_t {
+///     let filter = unsafe { CStr::from_ptr(filter) }.to_str().ok();
+///     let report = rsgx_test_suite!(filter; foo, #[should_panic] bar, #[ignore] zoo);
+///     if report.is_ok() {
+///         sgx_status_t::SGX_SUCCESS
+///     } else {
+///         sgx_status_t::SGX_ERROR_UNEXPECTED
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! rsgx_test_suite {
+    (
+        $filter:expr; $($(#[$attr:ident])* $f:ident),* $(,)*
+    ) => {
+        $crate::rsgx_run_tests(&[$($crate::TestCase::new(stringify!($f), $f)$(.$attr())*),*],
+                               $filter)
+    }
+}
+
+/// A test case run by `rsgx_run_tests`.
+#[derive(Clone, Copy)]
+pub struct TestCase {
+    name: &'static str,
+    func: fn(),
+    ignore: bool,
+    should_panic: bool,
+}
+
+impl TestCase {
+    /// Creates a test case which passes if `func` returns.
+    pub fn new(name: &'static str, func: fn()) -> TestCase {
+        TestCase {
+            name: name,
+            func: func,
+            ignore: false,
+            should_panic: false,
+        }
+    }
+
+    /// Reports the test as ignored instead of running it.
+    pub fn ignore(mut self) -> TestCase {
+        self.ignore = true;
+        self
+    }
+
+    /// Makes the test pass only if it panics.
+    pub fn should_panic(mut self) -> TestCase {
+        self.should_panic = true;
+        self
+    }
+
+    /// Returns the name of the test.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// The outcome of one test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+impl TestOutcome {
+    /// Returns the outcome in lower case, as used in the reports.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TestOutcome::Passed => "passed",
+            TestOutcome::Failed => "failed",
+            TestOutcome::Ignored => "ignored",
+        }
+    }
+}
+
+/// The result of one test.
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub outcome: TestOutcome,
+    /// The time the test took, measured with the untrusted clock. It is
+    /// zero for an ignored test.
+    pub duration: Duration,
+}
+
+/// The results of a test suite run by `rsgx_run_tests`.
+#[derive(Clone, Debug, Default)]
+pub struct TestReport {
+    /// The results of the tests which matched the filter, in the order they
+    /// were given.
+    pub results: Vec<TestResult>,
+    /// The number of tests which did not match the filter.
+    pub filtered_out: usize,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> usize {
+        self.count(TestOutcome::Passed)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(TestOutcome::Failed)
+    }
+
+    pub fn ignored(&self) -> usize {
+        self.count(TestOutcome::Ignored)
+    }
+
+    /// Returns true if no test failed.
+    pub fn is_ok(&self) -> bool {
+        self.failed() == 0
+    }
+
+    /// Renders the report as a JSON object:
+    ///
+    /// ```text
+    /// {"passed":1,"failed":1,"ignored":0,"filtered_out":0,
+    ///  "tests":[{"name":"foo","outcome":"passed","duration_ns":1200}, ...]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(out, "{{\"passed\":{},\"failed\":{},\"ignored\":{},\"filtered_out\":{},\"tests\":[",
+                       self.passed(), self.failed(), self.ignored(), self.filtered_out);
+        for (i, result) in self.results.iter().enumerate() {
+            if i != 0 {
+                out.push(',');
+            }
+            out.push_str("{\"name\":\"");
+            escape_json(&mut out, &result.name);
+            let _ = write!(out, "\",\"outcome\":\"{}\",\"duration_ns\":{}}}",
+                           result.outcome.as_str(), duration_ns(result.duration));
+        }
+        out.push_str("]}");
+        out
+    }
+
+    /// Renders the report in the Test Anything Protocol, version 13.
+    pub fn to_tap(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(out, "TAP version 13\n1..{}\n", self.results.len());
+        for (i, result) in self.results.iter().enumerate() {
+            let name = result.name.replace('#', "\\#");
+            let _ = match result.outcome {
+                TestOutcome::Passed => write!(out, "ok {} - {}\n", i + 1, name),
+                TestOutcome::Failed => write!(out, "not ok {} - {}\n", i + 1, name),
+                TestOutcome::Ignored => write!(out, "ok {} - {} # SKIP ignored\n", i + 1, name),
+            };
+        }
+        out
+    }
+
+    /// Renders the report as a JUnit XML `testsuite` named `suite`.
+    pub fn to_junit_xml(&self, suite: &str) -> String {
+        let total = self.results.iter().fold(Duration::new(0, 0), |acc, r| acc + r.duration);
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"");
+        escape_xml(&mut out, suite);
+        let _ = write!(out, "\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+                       self.results.len(), self.failed(), self.ignored(), secs(total));
+        for result in &self.results {
+            out.push_str("  <testcase name=\"");
+            escape_xml(&mut out, &result.name);
+            let _ = write!(out, "\" time=\"{}\"", secs(result.duration));
+            match result.outcome {
+                TestOutcome::Passed => out.push_str("/>\n"),
+                TestOutcome::Failed => out.push_str("><failure message=\"test failed\"/></testcase>\n"),
+                TestOutcome::Ignored => out.push_str("><skipped/></testcase>\n"),
+            }
+        }
+        out.push_str("</testsuite>\n");
+        out
+    }
+
+    fn count(&self, outcome: TestOutcome) -> usize {
+        self.results.iter().filter(|r| r.outcome == outcome).count()
+    }
+}
+
+/// Runs a test suite and returns its report.
+///
+/// This is the function behind `rsgx_test_suite!`. The tests whose name
+/// contains `filter` are run in order; with no filter, all of them are. The
+/// progress and the statistics are printed the same way as for
+/// `rsgx_unit_tests!`.
+pub fn rsgx_run_tests(cases: &[TestCase], filter: Option<&str>) -> TestReport {
+    rsgx_unit_test_start();
+
+    let mut report = TestReport::default();
+    for case in cases {
+        match filter {
+            Some(filter) if !case.name.contains(filter) => {
+                report.filtered_out += 1;
+                continue;
+            },
+            _ => {},
+        }
+
+        if case.ignore {
+            println!("{} {} ... {}", "testing", case.name, "\x1B[1;33mignored\x1B[0m");
+            report.results.push(TestResult {
+                name: String::from(case.name),
+                outcome: TestOutcome::Ignored,
+                duration: Duration::new(0, 0),
+            });
+            continue;
+        }
+
+        let func = case.func;
+        let start = <Instant as InstantEx>::now();
+        let panicked = std::panic::catch_unwind(|| { func(); }).is_err();
+        let duration = InstantEx::elapsed(&start);
+
+        let outcome = if panicked == case.should_panic {
+            println!("{} {} ... {}!", "testing", case.name, "\x1B[1;32mok\x1B[0m");
+            TestOutcome::Passed
+        } else {
+            println!("{} {} ... {}!", "testing", case.name, "\x1B[1;31mfailed\x1B[0m");
+            TestOutcome::Failed
+        };
+        report.results.push(TestResult {
+            name: String::from(case.name),
+            outcome: outcome,
+            duration: duration,
+        });
+    }
+
+    let failures = report.results.iter()
+                                 .filter(|r| r.outcome == TestOutcome::Failed)
+                                 .map(|r| r.name.clone())
+                                 .collect();
+    rsgx_unit_test_end((report.results.len() - report.ignored()) as u64, failures);
+    report
+}
+
+fn duration_ns(dur: Duration) -> u64 {
+    dur.as_secs().saturating_mul(1_000_000_000).saturating_add(dur.subsec_nanos() as u64)
+}
+
+fn secs(dur: Duration) -> String {
+    format!("{}.{:09}", dur.as_secs(), dur.subsec_nanos())
+}
+
+fn escape_json(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c),
+        }
+    }
+}
+
+fn escape_xml(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -91,4 +91,7 @@ pub use self::error::*;
 mod function;
 pub use self::function::*;
 
+mod quote;
+pub use self::quote::*;
+
 pub mod marker;
\ No newline at end of file
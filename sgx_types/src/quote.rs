@@ -0,0 +1,333 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use core::fmt;
+use core::ptr;
+use types::*;
+use error::*;
+
+/// The size of `sgx_quote_t` up to the signature.
+pub const SGX_QUOTE_HEADER_SIZE: ::size_t = 436;
+/// The size of the quote body as returned by IAS in `isvEnclaveQuoteBody`, which stops before
+/// `signature_len`.
+pub const SGX_QUOTE_BODY_SIZE: ::size_t = 432;
+
+///
+/// A quote parsed from its byte representation, with its variable-length signature.
+///
+#[derive(Clone, Copy)]
+pub struct Quote<'a> {
+    header: sgx_quote_t,
+    report_body: sgx_report_body_t,
+    signature: &'a [u8],
+}
+
+impl<'a> Quote<'a> {
+
+    ///
+    /// Parses a quote as returned by `sgx_get_quote`.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The quote is shorter than its header, or its length does not match `signature_len`.
+    ///
+    pub fn parse(bytes: &'a [u8]) -> SgxResult<Quote<'a>> {
+        if bytes.len() < SGX_QUOTE_HEADER_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let header = unsafe { ptr::read_unaligned(bytes.as_ptr() as * const sgx_quote_t) };
+        let signature = &bytes[SGX_QUOTE_HEADER_SIZE..];
+        if signature.len() as u64 != header.signature_len as u64 {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        Ok(Quote {
+            header: header,
+            report_body: header.report_body,
+            signature: signature,
+        })
+    }
+
+    ///
+    /// Parses a quote body without signature, as found in the `isvEnclaveQuoteBody` field of
+    /// an IAS attestation verification report.
+    ///
+    /// # Errors
+    ///
+    /// **SGX_ERROR_INVALID_PARAMETER**
+    ///
+    /// The body is not exactly `SGX_QUOTE_BODY_SIZE` bytes.
+    ///
+    pub fn parse_body(bytes: &'a [u8]) -> SgxResult<Quote<'a>> {
+        if bytes.len() != SGX_QUOTE_BODY_SIZE {
+            return Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER);
+        }
+        let mut header = sgx_quote_t::default();
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(),
+                                     &mut header as * mut sgx_quote_t as * mut u8,
+                                     SGX_QUOTE_BODY_SIZE);
+        }
+        Ok(Quote {
+            header: header,
+            report_body: header.report_body,
+            signature: &bytes[SGX_QUOTE_BODY_SIZE..],
+        })
+    }
+
+    pub fn version(&self) -> u16 {
+        self.header.version
+    }
+
+    ///
+    /// Returns the signature type, `sgx_quote_sign_type_t` as a number.
+    ///
+    pub fn sign_type(&self) -> u16 {
+        self.header.sign_type
+    }
+
+    pub fn epid_group_id(&self) -> sgx_epid_group_id_t {
+        self.header.epid_group_id
+    }
+
+    pub fn qe_svn(&self) -> sgx_isv_svn_t {
+        self.header.qe_svn
+    }
+
+    pub fn pce_svn(&self) -> sgx_isv_svn_t {
+        self.header.pce_svn
+    }
+
+    pub fn xeid(&self) -> u32 {
+        self.header.xeid
+    }
+
+    pub fn basename(&self) -> sgx_basename_t {
+        self.header.basename
+    }
+
+    pub fn report_body(&self) -> &sgx_report_body_t {
+        &self.report_body
+    }
+
+    ///
+    /// Returns the signature, empty for a quote parsed with `parse_body`.
+    ///
+    pub fn signature(&self) -> &'a [u8] {
+        self.signature
+    }
+
+    pub fn mr_enclave(&self) -> &sgx_measurement_t {
+        &self.report_body.mr_enclave
+    }
+
+    pub fn mr_signer(&self) -> &sgx_measurement_t {
+        &self.report_body.mr_signer
+    }
+
+    pub fn isv_prod_id(&self) -> sgx_prod_id_t {
+        self.report_body.isv_prod_id
+    }
+
+    pub fn isv_svn(&self) -> sgx_isv_svn_t {
+        self.report_body.isv_svn
+    }
+
+    pub fn attributes(&self) -> &sgx_attributes_t {
+        &self.report_body.attributes
+    }
+
+    pub fn flags(&self) -> u64 {
+        self.report_body.attributes.flags
+    }
+
+    pub fn xfrm(&self) -> u64 {
+        self.report_body.attributes.xfrm
+    }
+
+    ///
+    /// Returns true if the enclave was launched in debug mode, so its memory is open to
+    /// inspection.
+    ///
+    pub fn is_debug(&self) -> bool {
+        self.report_body.attributes.flags & SGX_FLAGS_DEBUG != 0
+    }
+
+    pub fn report_data(&self) -> &sgx_report_data_t {
+        &self.report_body.report_data
+    }
+}
+
+///
+/// The reason an `AttestationPolicy` rejected a report body.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PolicyRejection {
+    /// MRENCLAVE is not in the allowed list; holds the value found.
+    MrEnclaveNotAllowed([u8; SGX_HASH_SIZE]),
+    /// MRSIGNER is not in the allowed list; holds the value found.
+    MrSignerNotAllowed([u8; SGX_HASH_SIZE]),
+    IsvProdIdMismatch { expected: sgx_prod_id_t, found: sgx_prod_id_t },
+    IsvSvnTooLow { min: sgx_isv_svn_t, found: sgx_isv_svn_t },
+    DebugNotAllowed,
+    /// `found & mask` is not `expected`.
+    XfrmMismatch { mask: u64, expected: u64, found: u64 },
+}
+
+impl fmt::Display for PolicyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PolicyRejection::MrEnclaveNotAllowed(_) => write!(f, "MRENCLAVE is not allowed"),
+            PolicyRejection::MrSignerNotAllowed(_) => write!(f, "MRSIGNER is not allowed"),
+            PolicyRejection::IsvProdIdMismatch { expected, found } =>
+                write!(f, "ISVPRODID is {}, expected {}", found, expected),
+            PolicyRejection::IsvSvnTooLow { min, found } =>
+                write!(f, "ISVSVN is {}, below the minimum of {}", found, min),
+            PolicyRejection::DebugNotAllowed => write!(f, "debug enclaves are not allowed"),
+            PolicyRejection::XfrmMismatch { mask, expected, found } =>
+                write!(f, "XFRM is {:#x}, expected {:#x} under mask {:#x}", found, expected, mask),
+        }
+    }
+}
+
+///
+/// A policy on the identity and attributes of an attested enclave.
+///
+/// An empty measurement list allows any value. Debug enclaves are rejected unless
+/// `allow_debug` is set, and no XFRM bits are checked unless `xfrm` is set.
+///
+/// ```ignore
+/// const SIGNERS: &'static [sgx_measurement_t] = &[sgx_measurement_t { m: [0x5a; 32] }];
+///
+/// let policy = AttestationPolicy::new()
+///     .mr_signers(SIGNERS)
+///     .isv_prod_id(1)
+///     .min_isv_svn(2);
+/// policy.check_quote(&Quote::parse(&quote)?)?;
+/// ```
+///
+#[derive(Clone, Copy, Default)]
+pub struct AttestationPolicy<'a> {
+    mr_enclaves: &'a [sgx_measurement_t],
+    mr_signers: &'a [sgx_measurement_t],
+    isv_prod_id: Option<sgx_prod_id_t>,
+    min_isv_svn: sgx_isv_svn_t,
+    allow_debug: bool,
+    xfrm_mask: u64,
+    xfrm: u64,
+}
+
+impl<'a> AttestationPolicy<'a> {
+
+    ///
+    /// Creates a policy that accepts any production enclave.
+    ///
+    pub fn new() -> AttestationPolicy<'a> {
+        AttestationPolicy::default()
+    }
+
+    pub fn mr_enclaves(mut self, allowed: &'a [sgx_measurement_t]) -> AttestationPolicy<'a> {
+        self.mr_enclaves = allowed;
+        self
+    }
+
+    pub fn mr_signers(mut self, allowed: &'a [sgx_measurement_t]) -> AttestationPolicy<'a> {
+        self.mr_signers = allowed;
+        self
+    }
+
+    pub fn isv_prod_id(mut self, isv_prod_id: sgx_prod_id_t) -> AttestationPolicy<'a> {
+        self.isv_prod_id = Some(isv_prod_id);
+        self
+    }
+
+    pub fn min_isv_svn(mut self, min_isv_svn: sgx_isv_svn_t) -> AttestationPolicy<'a> {
+        self.min_isv_svn = min_isv_svn;
+        self
+    }
+
+    pub fn allow_debug(mut self, allow_debug: bool) -> AttestationPolicy<'a> {
+        self.allow_debug = allow_debug;
+        self
+    }
+
+    ///
+    /// Requires `xfrm & mask == expected`, for example to insist on AVX with
+    /// `xfrm(SGX_XFRM_AVX, SGX_XFRM_AVX)`.
+    ///
+    pub fn xfrm(mut self, mask: u64, expected: u64) -> AttestationPolicy<'a> {
+        self.xfrm_mask = mask;
+        self.xfrm = expected & mask;
+        self
+    }
+
+    ///
+    /// Checks a report body against the policy.
+    ///
+    /// The checks are made in the order MRENCLAVE, MRSIGNER, ISVPRODID, ISVSVN, debug and XFRM,
+    /// and the first one that fails is returned.
+    ///
+    pub fn check(&self, body: &sgx_report_body_t) -> Result<(), PolicyRejection> {
+        if !self.mr_enclaves.is_empty() &&
+           !self.mr_enclaves.iter().any(|m| m.m == body.mr_enclave.m) {
+            return Err(PolicyRejection::MrEnclaveNotAllowed(body.mr_enclave.m));
+        }
+        if !self.mr_signers.is_empty() &&
+           !self.mr_signers.iter().any(|m| m.m == body.mr_signer.m) {
+            return Err(PolicyRejection::MrSignerNotAllowed(body.mr_signer.m));
+        }
+        if let Some(expected) = self.isv_prod_id {
+            if body.isv_prod_id != expected {
+                return Err(PolicyRejection::IsvProdIdMismatch { expected: expected, found: body.isv_prod_id });
+            }
+        }
+        if body.isv_svn < self.min_isv_svn {
+            return Err(PolicyRejection::IsvSvnTooLow { min: self.min_isv_svn, found: body.isv_svn });
+        }
+        if !self.allow_debug && body.attributes.flags & SGX_FLAGS_DEBUG != 0 {
+            return Err(PolicyRejection::DebugNotAllowed);
+        }
+        if body.attributes.xfrm & self.xfrm_mask != self.xfrm {
+            return Err(PolicyRejection::XfrmMismatch {
+                mask: self.xfrm_mask,
+                expected: self.xfrm,
+                found: body.attributes.xfrm,
+            });
+        }
+        Ok(())
+    }
+
+    ///
+    /// Checks the report body of a quote against the policy.
+    ///
+    pub fn check_quote(&self, quote: &Quote) -> Result<(), PolicyRejection> {
+        self.check(quote.report_body())
+    }
+}
+
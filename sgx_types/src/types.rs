@@ -522,6 +522,8 @@ pub const SGX_AESGCM_MAC_SIZE: ::size_t        = 16;
 pub const SGX_CMAC_KEY_SIZE: ::size_t          = 16;
 pub const SGX_CMAC_MAC_SIZE: ::size_t          = 16;
 pub const SGX_AESCTR_KEY_SIZE: ::size_t        = 16;
+pub const SGX_HMAC256_KEY_SIZE: ::size_t       = 32;
+pub const SGX_HMAC256_MAC_SIZE: ::size_t       = 32;
 pub const SGX_RSA3072_KEY_SIZE: ::size_t       = 384;
 pub const SGX_RSA3072_PRI_EXP_SIZE: ::size_t   = 384;
 pub const SGX_RSA3072_PUB_EXP_SIZE: ::size_t   = 4;
@@ -606,6 +608,8 @@ pub type sgx_aes_gcm_128bit_tag_t   = [::uint8_t; SGX_AESGCM_MAC_SIZE];
 pub type sgx_cmac_128bit_key_t      = [::uint8_t; SGX_CMAC_KEY_SIZE];
 pub type sgx_cmac_128bit_tag_t      = [::uint8_t; SGX_CMAC_MAC_SIZE];
 pub type sgx_aes_ctr_128bit_key_t   = [::uint8_t; SGX_AESCTR_KEY_SIZE];
+pub type sgx_hmac_256bit_key_t      = [::uint8_t; SGX_HMAC256_KEY_SIZE];
+pub type sgx_hmac_256bit_tag_t      = [::uint8_t; SGX_HMAC256_MAC_SIZE];
 
 impl_enum! {
     #[repr(u32)]
@@ -971,8 +975,31 @@ pub const SGX_CREATE_ENCLAVE_EX_PCL_BIT_IDX: ::size_t = 0;
 pub const SGX_CREATE_ENCLAVE_EX_PCL: ::uint32_t = (1 << SGX_CREATE_ENCLAVE_EX_PCL_BIT_IDX as ::uint32_t);
 pub const SGX_CREATE_ENCLAVE_EX_SWITCHLESS_BIT_IDX: ::size_t = 1;
 pub const SGX_CREATE_ENCLAVE_EX_SWITCHLESS: ::uint32_t = (1 << SGX_CREATE_ENCLAVE_EX_SWITCHLESS_BIT_IDX as ::uint32_t);
-pub const _SGX_LAST_EX_FEATURE_IDX_: ::uint32_t = SGX_CREATE_ENCLAVE_EX_SWITCHLESS_BIT_IDX as ::uint32_t;
+pub const SGX_CREATE_ENCLAVE_EX_KSS_BIT_IDX: ::size_t = 2;
+pub const SGX_CREATE_ENCLAVE_EX_KSS: ::uint32_t = (1 << SGX_CREATE_ENCLAVE_EX_KSS_BIT_IDX as ::uint32_t);
+pub const _SGX_LAST_EX_FEATURE_IDX_: ::uint32_t = SGX_CREATE_ENCLAVE_EX_KSS_BIT_IDX as ::uint32_t;
 pub const _SGX_EX_FEATURES_MASK_: ::uint32_t = (0xFFFFFFFF_u32 >> (MAX_EX_FEATURES_COUNT as ::uint32_t - 1 - _SGX_LAST_EX_FEATURE_IDX_));
+
+pub const SGX_CONFIGID_SIZE: ::size_t = 64;
+
+pub type sgx_config_svn_t = ::uint16_t;
+pub type sgx_config_id_t = [::uint8_t; SGX_CONFIGID_SIZE];
+
+impl_copy_clone! {
+
+    pub struct sgx_kss_config_t {
+        pub config_id: sgx_config_id_t,
+        pub config_svn: sgx_config_svn_t,
+    }
+}
+
+impl_struct_default! {
+    sgx_kss_config_t, 66;
+}
+
+impl_struct_ContiguousMemory! {
+    sgx_kss_config_t;
+}
 //
 // trts.pic.h
 //
@@ -1071,6 +1098,7 @@ pub const SL_MAX_TASKS_MAX_QWORDS: ::uint32_t = 8;
 
 pub const _SGX_USWITCHLESS_WORKER_EVENT_NUM: ::size_t = 4;
 
+#[repr(C)]
 pub struct sgx_uswitchless_config_t {
     pub switchless_calls_pool_size_qwords: ::uint32_t,
     pub num_uworkers: ::uint32_t,
@@ -27,10 +27,13 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use sgx_types::*;
+use switchless::SwitchlessConfig;
 use std::path::{Path, PathBuf};
 use std::ffi::{CString, CStr};
-use std::io;
+use std::fs::File;
+use std::io::{self, Read, Write};
 use std::os::unix::ffi::OsStrExt;
+use std::ptr;
 
 ///
 /// Loads the enclave using its file name and initializes it using a launch token.
@@ -225,6 +228,144 @@ pub fn rsgx_create_enclave(file_name: &CStr,
     }
 }
 
+///
+/// Loads an encrypted enclave using its file name and the sealed key which
+/// decrypts it.
+///
+/// # Description
+///
+/// The rsgx_create_encrypted_enclave function behaves like rsgx_create_enclave,
+/// but loads an enclave whose code was encrypted with the Intel(R) SGX
+/// Protected Code Loader. The sealed decryption key is handed to the
+/// enclave during initialization.
+///
+/// # Parameters
+///
+/// **sealed_key**
+///
+/// The sealed blob holding the key used to decrypt the enclave, as produced
+/// by the key provisioning enclave.
+///
+/// The other parameters are the same as those of rsgx_create_enclave.
+///
+/// # Requirements
+///
+/// Header: sgx_urts.h
+///
+/// Library: libsgx_urts.a
+///
+/// # Return value
+///
+/// The sgx_enclave_id_t returned.
+///
+/// # Errors
+///
+/// **SGX_ERROR_PCL_NOT_ENCRYPTED**
+///
+/// The enclave is not encrypted.
+///
+/// Otherwise, see rsgx_create_enclave.
+///
+pub fn rsgx_create_encrypted_enclave(file_name: &CStr,
+                                     debug: i32,
+                                     launch_token: &mut sgx_launch_token_t,
+                                     launch_token_updated: &mut i32,
+                                     misc_attr: &mut sgx_misc_attribute_t,
+                                     sealed_key: &[u8]) -> SgxResult<sgx_enclave_id_t> {
+
+    let mut enclave_id: sgx_enclave_id_t = 0;
+    let ret = unsafe {
+        sgx_create_encrypted_enclave(file_name.as_ptr() as * const c_schar,
+                                     debug as int32_t,
+                                     launch_token as * mut sgx_launch_token_t,
+                                     launch_token_updated as * mut int32_t,
+                                     &mut enclave_id as * mut sgx_enclave_id_t,
+                                     misc_attr as * mut sgx_misc_attribute_t,
+                                     sealed_key.as_ptr())
+    };
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(enclave_id),
+        _ => Err(ret),
+    }
+}
+
+///
+/// Loads the enclave with extended features enabled.
+///
+/// # Description
+///
+/// The rsgx_create_enclave_ex function behaves like rsgx_create_enclave, and
+/// additionally enables the features selected in ex_features.
+///
+/// # Parameters
+///
+/// **ex_features**
+///
+/// A bit mask of SGX_CREATE_ENCLAVE_EX_* flags. Bits which are not defined
+/// must be 0.
+///
+/// **ex_features_p**
+///
+/// The configuration of each selected feature, indexed by its bit index.
+/// SGX_CREATE_ENCLAVE_EX_PCL takes a pointer to the sealed key,
+/// SGX_CREATE_ENCLAVE_EX_SWITCHLESS a pointer to an sgx_uswitchless_config_t
+/// and SGX_CREATE_ENCLAVE_EX_KSS a pointer to an sgx_kss_config_t. Entries
+/// of features which are not selected must be NULL.
+///
+/// The other parameters are the same as those of rsgx_create_enclave.
+///
+/// # Safety
+///
+/// Each entry of ex_features_p for a selected feature must point to a valid
+/// configuration of the type that feature takes, for the duration of the call.
+///
+/// # Requirements
+///
+/// Header: sgx_urts.h
+///
+/// Library: libsgx_urts.a
+///
+/// # Return value
+///
+/// The sgx_enclave_id_t returned.
+///
+/// # Errors
+///
+/// **SGX_ERROR_INVALID_PARAMETER**
+///
+/// An undefined bit is set in ex_features, or the configuration of a selected
+/// feature is NULL.
+///
+/// **SGX_ERROR_FEATURE_NOT_SUPPORTED**
+///
+/// A selected feature is not supported by the platform or by this version of
+/// the uRTS.
+///
+/// Otherwise, see rsgx_create_enclave.
+///
+pub unsafe fn rsgx_create_enclave_ex(file_name: &CStr,
+                                     debug: i32,
+                                     launch_token: &mut sgx_launch_token_t,
+                                     launch_token_updated: &mut i32,
+                                     misc_attr: &mut sgx_misc_attribute_t,
+                                     ex_features: u32,
+                                     ex_features_p: &[* const c_void; 32]) -> SgxResult<sgx_enclave_id_t> {
+
+    let mut enclave_id: sgx_enclave_id_t = 0;
+    let ret = sgx_create_enclave_ex(file_name.as_ptr() as * const c_schar,
+                                    debug as int32_t,
+                                    launch_token as * mut sgx_launch_token_t,
+                                    launch_token_updated as * mut int32_t,
+                                    &mut enclave_id as * mut sgx_enclave_id_t,
+                                    misc_attr as * mut sgx_misc_attribute_t,
+                                    ex_features as uint32_t,
+                                    *ex_features_p);
+    match ret {
+        sgx_status_t::SGX_SUCCESS => Ok(enclave_id),
+        _ => Err(ret),
+    }
+}
+
 ///
 /// The function destroys an enclave and frees its associated resources.
 ///
@@ -349,4 +490,176 @@ impl Drop for SgxEnclave {
         self.exit();
         let _ = rsgx_destroy_enclave(self.id);
     }
-}
\ No newline at end of file
+}
+
+/// A builder for loading an enclave with extended options.
+///
+/// It covers what `SgxEnclave::create` does, and in addition can enable
+/// switchless calls and Key Separation and Sharing (KSS), load an encrypted
+/// enclave, and keep the launch token in a file between runs.
+#[derive(Clone)]
+pub struct SgxEnclaveBuilder {
+    path: PathBuf,
+    debug: i32,
+    token_file: Option<PathBuf>,
+    switchless: Option<SwitchlessConfig>,
+    kss: Option<sgx_kss_config_t>,
+    sealed_key: Option<Vec<u8>>,
+}
+
+impl SgxEnclaveBuilder {
+
+    /// Starts a builder for the enclave image at `file_name`.
+    ///
+    /// The enclave is created in non-debug mode, without a token file and
+    /// with no extended feature.
+    pub fn new<P: AsRef<Path>>(file_name: P) -> SgxEnclaveBuilder {
+        SgxEnclaveBuilder {
+            path: file_name.as_ref().to_owned(),
+            debug: 0,
+            token_file: None,
+            switchless: None,
+            kss: None,
+            sealed_key: None,
+        }
+    }
+
+    /// Sets the debug flag, 0 or 1, as for `rsgx_create_enclave`.
+    pub fn debug(&mut self, debug: i32) -> &mut SgxEnclaveBuilder {
+        self.debug = debug;
+        self
+    }
+
+    /// Keeps the launch token in the file at `path`.
+    ///
+    /// The token is read from the file before the enclave is created, and
+    /// written back if it was updated. A missing or short file is treated
+    /// as an empty token, and failing to write it back is not an error.
+    pub fn launch_token_file<P: AsRef<Path>>(&mut self, path: P) -> &mut SgxEnclaveBuilder {
+        self.token_file = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Enables switchless calls with the given configuration.
+    ///
+    /// The closures of the configuration are installed once the enclave is
+    /// created, not by this call; see `SwitchlessConfig::on_event`.
+    pub fn switchless(&mut self, config: &SwitchlessConfig) -> &mut SgxEnclaveBuilder {
+        self.switchless = Some(config.clone());
+        self
+    }
+
+    /// Enables Key Separation and Sharing with the given CONFIGID and
+    /// CONFIGSVN.
+    pub fn kss(&mut self, config: sgx_kss_config_t) -> &mut SgxEnclaveBuilder {
+        self.kss = Some(config);
+        self
+    }
+
+    /// Loads the enclave as an encrypted enclave, decrypted with the given
+    /// sealed key.
+    pub fn sealed_key(&mut self, sealed_key: &[u8]) -> &mut SgxEnclaveBuilder {
+        self.sealed_key = Some(sealed_key.to_vec());
+        self
+    }
+
+    /// Loads and initializes the enclave.
+    pub fn create(&self) -> SgxResult<SgxEnclave> {
+        let mut misc_attr = sgx_misc_attribute_t {
+            secs_attr: sgx_attributes_t { flags: 0, xfrm: 0 },
+            misc_select: 0,
+        };
+        self.create_with_misc_attr(&mut misc_attr)
+    }
+
+    /// Loads and initializes the enclave, and stores the misc select and
+    /// attributes of the enclave in `misc_attr`.
+    pub fn create_with_misc_attr(&self, misc_attr: &mut sgx_misc_attribute_t) -> SgxResult<SgxEnclave> {
+
+        let path: CString = cstr(self.path.as_path())
+                                .map_err(|_| sgx_status_t::SGX_ERROR_INVALID_ENCLAVE)?;
+
+        let mut launch_token: sgx_launch_token_t = [0; 1024];
+        let mut launch_token_updated: i32 = 0;
+        if let Some(ref token_file) = self.token_file {
+            read_launch_token(token_file, &mut launch_token);
+        }
+
+        let switchless = self.switchless.as_ref().map(|config| config.config());
+        let mut ex_features: u32 = 0;
+        let mut ex_features_p: [* const c_void; 32] = [ptr::null(); 32];
+        if let Some(ref config) = switchless {
+            ex_features |= SGX_CREATE_ENCLAVE_EX_SWITCHLESS;
+            ex_features_p[SGX_CREATE_ENCLAVE_EX_SWITCHLESS_BIT_IDX] =
+                config as * const sgx_uswitchless_config_t as * const c_void;
+        }
+        if let Some(ref config) = self.kss {
+            ex_features |= SGX_CREATE_ENCLAVE_EX_KSS;
+            ex_features_p[SGX_CREATE_ENCLAVE_EX_KSS_BIT_IDX] =
+                config as * const sgx_kss_config_t as * const c_void;
+        }
+
+        let eid = match (self.sealed_key.as_ref(), ex_features) {
+            (None, 0) => rsgx_create_enclave(path.as_c_str(),
+                                             self.debug,
+                                             &mut launch_token,
+                                             &mut launch_token_updated,
+                                             misc_attr)?,
+            (Some(key), 0) => rsgx_create_encrypted_enclave(path.as_c_str(),
+                                                            self.debug,
+                                                            &mut launch_token,
+                                                            &mut launch_token_updated,
+                                                            misc_attr,
+                                                            key)?,
+            (key, _) => {
+                if let Some(key) = key {
+                    ex_features |= SGX_CREATE_ENCLAVE_EX_PCL;
+                    ex_features_p[SGX_CREATE_ENCLAVE_EX_PCL_BIT_IDX] = key.as_ptr() as * const c_void;
+                }
+                // The configurations pointed to by ex_features_p are
+                // borrowed from self or switchless, and outlive the call.
+                unsafe {
+                    rsgx_create_enclave_ex(path.as_c_str(),
+                                           self.debug,
+                                           &mut launch_token,
+                                           &mut launch_token_updated,
+                                           misc_attr,
+                                           ex_features,
+                                           &ex_features_p)?
+                }
+            },
+        };
+
+        if let Some(ref config) = self.switchless {
+            config.install();
+        }
+
+        let enclave = SgxEnclave {
+            id: eid,
+            debug: self.debug,
+            path: self.path.clone(),
+        };
+
+        if launch_token_updated != 0 {
+            if let Some(ref token_file) = self.token_file {
+                let _ = write_launch_token(token_file, &launch_token);
+            }
+        }
+
+        enclave.init();
+        Ok(enclave)
+    }
+}
+
+fn read_launch_token(path: &Path, token: &mut sgx_launch_token_t) {
+    let valid = File::open(path)
+                    .and_then(|mut f| f.read_exact(&mut token[..]))
+                    .is_ok();
+    if !valid {
+        *token = [0; 1024];
+    }
+}
+
+fn write_launch_token(path: &Path, token: &sgx_launch_token_t) -> io::Result<()> {
+    File::create(path)?.write_all(&token[..])
+}
@@ -27,8 +27,9 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use std::ptr;
+use std::cmp;
 use std::io::Error;
-use libc::{self, c_int, c_char, c_void, size_t, ssize_t, off64_t, c_ulong, mode_t, stat64};
+use libc::{self, c_int, c_char, c_void, size_t, ssize_t, off64_t, c_ulong, mode_t, stat64, dirent64, DIR};
 
 #[no_mangle]
 pub extern "C" fn u_fs_open64_ocall(error: * mut c_int,
@@ -420,6 +421,110 @@ pub extern "C" fn u_fs_realpath_ocall(error: * mut c_int, pathname: * const c_ch
     ret
 }
 
+#[no_mangle]
+pub extern "C" fn u_fs_mkdir_ocall(error: * mut c_int, pathname: * const c_char, mode: mode_t) -> c_int {
+
+    let mut errno = 0;
+    let ret = unsafe { libc::mkdir(pathname, mode) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_fs_rmdir_ocall(error: * mut c_int, pathname: * const c_char) -> c_int {
+
+    let mut errno = 0;
+    let ret = unsafe { libc::rmdir(pathname) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_fs_opendir_ocall(error: * mut c_int, pathname: * const c_char) -> * mut DIR {
+
+    let mut errno = 0;
+    let ret = unsafe { libc::opendir(pathname) };
+    if ret.is_null() {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_fs_readdir64_ocall(error: * mut c_int,
+                                       dirp: * mut DIR,
+                                       entry: * mut dirent64,
+                                       eods: * mut c_int) -> c_int {
+    let mut errno = 0;
+    let mut end = 0;
+    let mut ret = 0;
+    unsafe {
+        // readdir64 reports both the end of the stream and an error with
+        // NULL, only errno tells them apart.
+        *libc::__errno_location() = 0;
+        let p = libc::readdir64(dirp);
+        if p.is_null() {
+            errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+            if errno == 0 {
+                end = 1;
+            } else {
+                ret = -1;
+            }
+        } else if !entry.is_null() {
+            // d_name may be shorter than the 256 bytes of dirent64, so only
+            // the d_reclen bytes of the record are read.
+            let name_offset = (*p).d_name.as_ptr() as usize - p as usize;
+            let reclen = (*p).d_reclen as usize;
+            if reclen < name_offset {
+                errno = libc::EIO;
+                ret = -1;
+            } else {
+                let len = cmp::min(reclen - name_offset, (*entry).d_name.len() - 1);
+                (*entry).d_ino = (*p).d_ino;
+                (*entry).d_off = (*p).d_off;
+                (*entry).d_reclen = (*p).d_reclen;
+                (*entry).d_type = (*p).d_type;
+                ptr::copy_nonoverlapping((*p).d_name.as_ptr(), (*entry).d_name.as_mut_ptr(), len);
+                (*entry).d_name[len] = 0;
+            }
+        }
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    if !eods.is_null() {
+        unsafe { *eods = end; }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_fs_closedir_ocall(error: * mut c_int, dirp: * mut DIR) -> c_int {
+
+    let mut errno = 0;
+    let ret = unsafe { libc::closedir(dirp) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
+
 #[no_mangle]
 pub extern "C" fn u_fs_free_ocall(p: * mut c_void) {
 
@@ -0,0 +1,284 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Key/value store for protected files.
+//!
+//! `sgx_tprotected_fs::pfs::KvStorage` keeps the encrypted nodes of protected
+//! files as objects of the store installed with [`set_store`], reached through
+//! the OCALLs of `edl/sgx_kvstore.edl`. Everything the enclave stores is
+//! encrypted and authenticated, so the store need not be trusted; it only has
+//! to make each `put` atomic and durable by the time it returns.
+//!
+//! [`MemoryKvStore`] stands in for an object store, and [`DirKvStore`] keeps
+//! one file per object in a directory, e.g. on a tmpfs.
+//!
+//! [`set_store`]: fn.set_store.html
+//! [`MemoryKvStore`]: struct.MemoryKvStore.html
+//! [`DirKvStore`]: struct.DirKvStore.html
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+use std::sync::{Arc, Mutex, Once, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use libc::{self, c_int, size_t};
+
+/// A store of byte string values under byte string keys.
+pub trait KvStore: Send + Sync {
+    /// Returns the value of `key`, or `None` if there is none.
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
+
+    /// Sets the value of `key`, replacing any previous value as a whole.
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()>;
+
+    /// Deletes `key`, failing with `ErrorKind::NotFound` if there is none.
+    fn delete(&self, key: &[u8]) -> io::Result<()>;
+}
+
+type StoreSlot = RwLock<Option<Arc<dyn KvStore>>>;
+
+static INIT: Once = Once::new();
+static mut STORE: * const StoreSlot = ptr::null();
+
+fn slot() -> &'static StoreSlot {
+    unsafe {
+        INIT.call_once(|| {
+            STORE = Box::into_raw(Box::new(RwLock::new(None)));
+        });
+        &*STORE
+    }
+}
+
+/// Serves the `sgx_kvstore.edl` OCALLs of every enclave from `store`.
+///
+/// Until a store is set, the OCALLs fail with `ENODEV`.
+pub fn set_store(store: Arc<dyn KvStore>) {
+    if let Ok(mut slot) = slot().write() {
+        *slot = Some(store);
+    }
+}
+
+/// Removes the store set with [`set_store`].
+///
+/// [`set_store`]: fn.set_store.html
+pub fn clear_store() {
+    if let Ok(mut slot) = slot().write() {
+        *slot = None;
+    }
+}
+
+/// Objects kept in a `HashMap` in the memory of the application.
+#[derive(Default)]
+pub struct MemoryKvStore {
+    objects: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryKvStore {
+    pub fn new() -> MemoryKvStore {
+        MemoryKvStore::default()
+    }
+
+    /// Returns the keys of all the objects, in no particular order.
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        match self.objects.lock() {
+            Ok(objects) => objects.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+fn poisoned() -> io::Error {
+    io::Error::from_raw_os_error(libc::EIO)
+}
+
+impl KvStore for MemoryKvStore {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let objects = self.objects.lock().map_err(|_| poisoned())?;
+        Ok(objects.get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let mut objects = self.objects.lock().map_err(|_| poisoned())?;
+        objects.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> io::Result<()> {
+        let mut objects = self.objects.lock().map_err(|_| poisoned())?;
+        match objects.remove(key) {
+            Some(_) => Ok(()),
+            None => Err(ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+/// Objects kept as files of a directory, named after the hex encoding of
+/// their key.
+///
+/// A `put` writes a temporary file and renames it over the object, so it is
+/// atomic; it is durable if the file system is. Keys are limited to half the
+/// longest file name of the file system, less a few bytes for temporary files.
+pub struct DirKvStore {
+    dir: PathBuf,
+    temp_id: AtomicUsize,
+}
+
+impl DirKvStore {
+    /// Uses the directory `dir`, creating it if needed.
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<DirKvStore> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(DirKvStore { dir: dir.as_ref().to_path_buf(), temp_id: AtomicUsize::new(0) })
+    }
+
+    fn object_name(key: &[u8]) -> String {
+        key.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn path(&self, key: &[u8]) -> PathBuf {
+        // An empty key would name the directory itself.
+        let name = DirKvStore::object_name(key);
+        self.dir.join(if name.is_empty() { "-".to_string() } else { name })
+    }
+}
+
+impl KvStore for DirKvStore {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let id = self.temp_id.fetch_add(1, Ordering::Relaxed);
+        let temp = self.dir.join(format!("{}.{}.tmp", DirKvStore::object_name(key), id));
+        let result = File::create(&temp).and_then(|mut file| {
+            file.write_all(value)?;
+            file.sync_all()
+        }).and_then(|_| fs::rename(&temp, self.path(key)));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+
+    fn delete(&self, key: &[u8]) -> io::Result<()> {
+        fs::remove_file(self.path(key))
+    }
+}
+
+// Runs `f` on the installed store and returns the OCALL result, with `*error` set.
+fn with_store<F>(error: * mut c_int, f: F) -> c_int
+    where F: FnOnce(&dyn KvStore) -> io::Result<()> {
+
+    let store = match slot().read() {
+        Ok(slot) => slot.clone(),
+        Err(_) => None,
+    };
+    let result = match store {
+        Some(store) => {
+            // Unwinding into the enclave is undefined behavior.
+            match panic::catch_unwind(AssertUnwindSafe(|| f(&*store))) {
+                Ok(Ok(())) => 0,
+                Ok(Err(err)) => err.raw_os_error().unwrap_or(match err.kind() {
+                    ErrorKind::NotFound => libc::ENOENT,
+                    _ => libc::EIO,
+                }),
+                Err(_) => libc::EIO,
+            }
+        },
+        None => libc::ENODEV,
+    };
+    if !error.is_null() {
+        unsafe { *error = result; }
+    }
+    if result == 0 { 0 } else { -1 }
+}
+
+unsafe fn as_slice<'a>(data: * const u8, len: size_t) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+fn invalid() -> io::Error {
+    io::Error::from_raw_os_error(libc::EINVAL)
+}
+
+#[no_mangle]
+pub extern "C" fn u_kvstore_get_ocall(error: * mut c_int,
+                                      key: * const u8,
+                                      key_len: size_t,
+                                      buf: * mut u8,
+                                      buf_len: size_t,
+                                      value_len: * mut size_t) -> c_int {
+    with_store(error, |store| {
+        let key = unsafe { as_slice(key, key_len) }.ok_or_else(invalid)?;
+        if (buf.is_null() && buf_len != 0) || value_len.is_null() {
+            return Err(invalid());
+        }
+        let value = store.get(key)?.ok_or_else(|| io::Error::from_raw_os_error(libc::ENOENT))?;
+        unsafe {
+            ptr::copy_nonoverlapping(value.as_ptr(), buf, value.len().min(buf_len));
+            *value_len = value.len();
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn u_kvstore_put_ocall(error: * mut c_int,
+                                      key: * const u8,
+                                      key_len: size_t,
+                                      value: * const u8,
+                                      value_len: size_t) -> c_int {
+    with_store(error, |store| {
+        let key = unsafe { as_slice(key, key_len) }.ok_or_else(invalid)?;
+        let value = unsafe { as_slice(value, value_len) }.ok_or_else(invalid)?;
+        store.put(key, value)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn u_kvstore_delete_ocall(error: * mut c_int,
+                                         key: * const u8,
+                                         key_len: size_t) -> c_int {
+    with_store(error, |store| {
+        let key = unsafe { as_slice(key, key_len) }.ok_or_else(invalid)?;
+        store.delete(key)
+    })
+}
@@ -35,7 +35,12 @@ pub mod backtrace;
 pub mod time;
 pub mod fs;
 pub mod net;
+pub mod asyncio;
+pub mod kvstore;
+pub mod switchless;
 pub mod env;
+#[cfg(feature = "thread")]
+pub mod thread;
 
 pub use enclave::*;
 
@@ -27,7 +27,22 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 use std::io::Error;
-use libc::{self, c_int, c_void, size_t, ssize_t, c_ulong, sockaddr, socklen_t};
+use std::{mem, ptr};
+use libc::{self, c_char, c_int, c_void, size_t, ssize_t, c_ulong, sockaddr, socklen_t, sockaddr_storage};
+
+#[no_mangle]
+pub extern "C" fn u_net_socket_ocall(error: * mut c_int, domain: c_int, ty: c_int, protocol: c_int) -> c_int {
+
+    let mut errno = 0;
+    let ret = unsafe { libc::socket(domain, ty, protocol) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
 
 #[no_mangle]
 pub extern "C" fn u_net_bind_ocall(error: * mut c_int,
@@ -61,6 +76,38 @@ pub extern "C" fn u_net_connect_ocall(error: * mut c_int,
     ret
 }
 
+#[no_mangle]
+pub extern "C" fn u_net_listen_ocall(error: * mut c_int, sockfd: c_int, backlog: c_int) -> c_int {
+
+    let mut errno = 0;
+    let ret = unsafe { libc::listen(sockfd, backlog) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
+
+#[no_mangle]
+pub extern "C" fn u_net_accept4_ocall(error: * mut c_int,
+                                      sockfd: c_int,
+                                      address: * mut sockaddr,
+                                      _in_addrlen: socklen_t,
+                                      addrlen: * mut socklen_t,
+                                      flags: c_int) -> c_int {
+    let mut errno = 0;
+    let ret = unsafe { libc::accept4(sockfd, address, addrlen, flags) };
+    if ret < 0 {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    ret
+}
+
 #[no_mangle]
 pub extern "C" fn u_net_recv_ocall(error: * mut c_int,
                                    sockfd: c_int,
@@ -231,4 +278,51 @@ pub extern "C" fn u_net_ioctl_ocall(error: * mut c_int, fd: c_int, request: c_in
         unsafe { *error = errno; }
     }
     ret
-}
\ No newline at end of file
+}
+
+#[no_mangle]
+pub extern "C" fn u_net_getaddrinfo_ocall(error: * mut c_int,
+                                          node: * const c_char,
+                                          family: c_int,
+                                          socktype: c_int,
+                                          addrs: * mut c_void,
+                                          _in_len: size_t,
+                                          count: * mut size_t) -> c_int {
+    let mut errno = 0;
+    let mut n: size_t = 0;
+    let mut hints: libc::addrinfo = unsafe { mem::zeroed() };
+    hints.ai_family = family;
+    hints.ai_socktype = socktype;
+
+    let mut res: * mut libc::addrinfo = ptr::null_mut();
+    let ret = unsafe { libc::getaddrinfo(node, ptr::null(), &hints, &mut res) };
+    if ret == 0 {
+        // Flatten the list into the caller's array of sockaddr_storage,
+        // dropping whatever does not fit.
+        let storage_len = mem::size_of::<sockaddr_storage>();
+        let cap = _in_len / storage_len;
+        let mut cur = res;
+        while !cur.is_null() && n < cap {
+            let ai = unsafe { &*cur };
+            if !ai.ai_addr.is_null() && ai.ai_addrlen as usize <= storage_len {
+                unsafe {
+                    let dst = (addrs as * mut sockaddr_storage).add(n) as * mut u8;
+                    ptr::write_bytes(dst, 0, storage_len);
+                    ptr::copy_nonoverlapping(ai.ai_addr as * const u8, dst, ai.ai_addrlen as usize);
+                }
+                n += 1;
+            }
+            cur = ai.ai_next;
+        }
+        unsafe { libc::freeaddrinfo(res); }
+    } else if ret == libc::EAI_SYSTEM {
+        errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+    }
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    if !count.is_null() {
+        unsafe { *count = n; }
+    }
+    ret
+}
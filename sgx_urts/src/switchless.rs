@@ -0,0 +1,207 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Switchless call configuration.
+//!
+//! A [`SwitchlessConfig`] describes the worker threads which serve switchless
+//! ECALLs and OCALLs, and can route the worker events reported by the
+//! runtime to Rust closures. Pass it to `SgxEnclaveBuilder::switchless`.
+//!
+//! Only the calls marked `transition_using_threads` in the EDL are switchless.
+//! `edl/sgx_fs_switchless.edl` and `edl/sgx_net_switchless.edl` are variants
+//! of the `sgx_tstd` OCALLs marked this way. A switchless call which finds
+//! no free worker falls back to an ordinary call after
+//! [`SwitchlessConfig::retries_before_fallback`] retries.
+//!
+//! [`SwitchlessConfig`]: struct.SwitchlessConfig.html
+//! [`SwitchlessConfig::retries_before_fallback`]: struct.SwitchlessConfig.html#method.retries_before_fallback
+
+use sgx_types::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Arc, Once, RwLock};
+
+/// A closure called on a switchless worker event.
+///
+/// The runtime does not tell which enclave a worker belongs to, so there is
+/// one set of closures for the whole process, one closure per event.
+pub type WorkerCallback = dyn Fn(sgx_uswitchless_worker_type_t,
+                                 sgx_uswitchless_worker_event_t,
+                                 &sgx_uswitchless_worker_stats_t) + Send + Sync;
+
+type Callbacks = [Option<Arc<WorkerCallback>>; _SGX_USWITCHLESS_WORKER_EVENT_NUM];
+type CallbackTable = RwLock<Callbacks>;
+
+static INIT: Once = Once::new();
+static mut CALLBACKS: * const CallbackTable = ptr::null();
+
+fn callbacks() -> &'static CallbackTable {
+    unsafe {
+        INIT.call_once(|| {
+            CALLBACKS = Box::into_raw(Box::new(RwLock::new(Default::default())));
+        });
+        &*CALLBACKS
+    }
+}
+
+extern "C" fn dispatch(worker_type: sgx_uswitchless_worker_type_t,
+                       worker_event: sgx_uswitchless_worker_event_t,
+                       worker_stats: * const sgx_uswitchless_worker_stats_t) {
+
+    let idx = worker_event as usize;
+    if idx >= _SGX_USWITCHLESS_WORKER_EVENT_NUM || worker_stats.is_null() {
+        return;
+    }
+    let callback = match callbacks().read() {
+        Ok(table) => table[idx].clone(),
+        Err(_) => None,
+    };
+    if let Some(callback) = callback {
+        let stats = unsafe { &*worker_stats };
+        // Unwinding into the runtime's worker thread is undefined behavior.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| callback(worker_type, worker_event, stats)));
+    }
+}
+
+/// A builder for `sgx_uswitchless_config_t` and the closures called on
+/// worker events.
+#[derive(Clone)]
+pub struct SwitchlessConfig {
+    pool_size_qwords: u32,
+    num_uworkers: u32,
+    num_tworkers: u32,
+    retries_before_fallback: u32,
+    retries_before_sleep: u32,
+    callbacks: Callbacks,
+}
+
+impl SwitchlessConfig {
+
+    /// Starts from the runtime defaults: one untrusted and one trusted
+    /// worker, and default pool size and retry counts.
+    pub fn new() -> SwitchlessConfig {
+        SwitchlessConfig {
+            pool_size_qwords: 0,
+            num_uworkers: 1,
+            num_tworkers: 1,
+            retries_before_fallback: 0,
+            retries_before_sleep: 0,
+            callbacks: Default::default(),
+        }
+    }
+
+    /// Sets the number of untrusted workers, which serve switchless OCALLs.
+    pub fn untrusted_workers(&mut self, num: u32) -> &mut SwitchlessConfig {
+        self.num_uworkers = num;
+        self
+    }
+
+    /// Sets the number of trusted workers, which serve switchless ECALLs.
+    pub fn trusted_workers(&mut self, num: u32) -> &mut SwitchlessConfig {
+        self.num_tworkers = num;
+        self
+    }
+
+    /// Sets the size of the pool of pending switchless calls, in 64-call
+    /// units. 0 selects the default.
+    pub fn pool_size_qwords(&mut self, qwords: u32) -> &mut SwitchlessConfig {
+        self.pool_size_qwords = qwords;
+        self
+    }
+
+    /// Sets how many times a caller retries before falling back to an
+    /// ordinary call. 0 selects the default.
+    pub fn retries_before_fallback(&mut self, retries: u32) -> &mut SwitchlessConfig {
+        self.retries_before_fallback = retries;
+        self
+    }
+
+    /// Sets how many times an idle worker retries before it sleeps. 0
+    /// selects the default.
+    pub fn retries_before_sleep(&mut self, retries: u32) -> &mut SwitchlessConfig {
+        self.retries_before_sleep = retries;
+        self
+    }
+
+    /// Calls `callback` whenever a worker reports `event`.
+    ///
+    /// The closure is kept in the configuration, and installed in the
+    /// process-wide set once an enclave has been created with it. From then
+    /// on it serves `event` for every enclave whose configuration routes it,
+    /// including enclaves created before with another closure for `event`.
+    /// Events reported while the enclave is being created go to the closure
+    /// installed before, if any; a failed creation installs nothing.
+    pub fn on_event<F>(&mut self, event: sgx_uswitchless_worker_event_t, callback: F) -> &mut SwitchlessConfig
+        where F: Fn(sgx_uswitchless_worker_type_t,
+                    sgx_uswitchless_worker_event_t,
+                    &sgx_uswitchless_worker_stats_t) + Send + Sync + 'static {
+
+        let idx = event as usize;
+        if idx < _SGX_USWITCHLESS_WORKER_EVENT_NUM {
+            self.callbacks[idx] = Some(Arc::new(callback));
+        }
+        self
+    }
+
+    /// Returns the configuration to hand to the runtime, which routes the
+    /// events with a closure to `dispatch`.
+    pub(crate) fn config(&self) -> sgx_uswitchless_config_t {
+        let mut config = sgx_uswitchless_config_t {
+            switchless_calls_pool_size_qwords: self.pool_size_qwords,
+            num_uworkers: self.num_uworkers,
+            num_tworkers: self.num_tworkers,
+            retries_before_fallback: self.retries_before_fallback,
+            retries_before_sleep: self.retries_before_sleep,
+            ..Default::default()
+        };
+        for (func, callback) in config.callback_func.iter_mut().zip(self.callbacks.iter()) {
+            if callback.is_some() {
+                *func = dispatch;
+            }
+        }
+        config
+    }
+
+    /// Makes the closures of this configuration serve their events. Called
+    /// once an enclave has been created with it.
+    pub(crate) fn install(&self) {
+        if let Ok(mut table) = callbacks().write() {
+            for (slot, callback) in table.iter_mut().zip(self.callbacks.iter()) {
+                if callback.is_some() {
+                    *slot = callback.clone();
+                }
+            }
+        }
+    }
+}
+
+impl Default for SwitchlessConfig {
+    fn default() -> SwitchlessConfig {
+        SwitchlessConfig::new()
+    }
+}
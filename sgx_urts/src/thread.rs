@@ -0,0 +1,128 @@
+// Copyright (C) 2017-2018 Baidu, Inc. All Rights Reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+//
+//  * Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+//  * Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in
+//    the documentation and/or other materials provided with the
+//    distribution.
+//  * Neither the name of Baidu, Inc., nor the names of its
+//    contributors may be used to endorse or promote products derived
+//    from this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::thread;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex, Condvar};
+use sgx_types::*;
+use libc::{self, c_int};
+
+extern "C" {
+    fn t_thread_main_ecall(eid: sgx_enclave_id_t, id: uint64_t) -> sgx_status_t;
+}
+
+// The outcome of entering the enclave on a new thread: success once the
+// enclave has claimed the closure, or the status of the failed ECALL.
+struct Start {
+    status: Mutex<Option<sgx_status_t>>,
+    cvar: Condvar,
+}
+
+impl Start {
+    fn finish(&self, status: sgx_status_t) {
+        let mut current = self.status.lock().unwrap();
+        if current.is_none() {
+            *current = Some(status);
+            self.cvar.notify_one();
+        }
+    }
+
+    fn wait(&self) -> sgx_status_t {
+        let mut current = self.status.lock().unwrap();
+        loop {
+            match *current {
+                Some(status) => return status,
+                None => current = self.cvar.wait(current).unwrap(),
+            }
+        }
+    }
+}
+
+thread_local! { static STARTING: RefCell<Option<Arc<Start>>> = RefCell::new(None) }
+
+///
+/// Creates a thread which enters the enclave through `t_thread_main_ecall`.
+///
+/// Returns once the new thread has entered the enclave, or failed to, so the
+/// enclave learns of a failed ECALL (e.g. SGX_ERROR_OUT_OF_TCS) through
+/// `status` and can drop the closure itself.
+///
+#[no_mangle]
+pub extern "C" fn u_thread_spawn_ocall(error: * mut c_int, status: * mut uint32_t, eid: uint64_t, id: uint64_t) -> c_int {
+
+    let start = Arc::new(Start {
+        status: Mutex::new(None),
+        cvar: Condvar::new(),
+    });
+    let their_start = start.clone();
+
+    let mut errno = 0;
+    let mut ecall_status = sgx_status_t::SGX_SUCCESS;
+    let ret = match thread::Builder::new().spawn(move || {
+        STARTING.with(|s| *s.borrow_mut() = Some(their_start.clone()));
+        let ret = unsafe { t_thread_main_ecall(eid as sgx_enclave_id_t, id) };
+        // Only reached first if the enclave never claimed the closure.
+        their_start.finish(match ret {
+            sgx_status_t::SGX_SUCCESS => sgx_status_t::SGX_ERROR_UNEXPECTED,
+            _ => ret,
+        });
+        STARTING.with(|s| *s.borrow_mut() = None);
+    }) {
+        Ok(_) => {
+            ecall_status = start.wait();
+            match ecall_status {
+                sgx_status_t::SGX_SUCCESS => 0,
+                _ => -1,
+            }
+        },
+        Err(e) => {
+            errno = e.raw_os_error().unwrap_or(libc::EAGAIN);
+            -1
+        },
+    };
+    if !error.is_null() {
+        unsafe { *error = errno; }
+    }
+    if !status.is_null() {
+        unsafe { *status = ecall_status as uint32_t; }
+    }
+    ret
+}
+
+///
+/// Called by `t_thread_main_ecall` once it has claimed the closure.
+///
+#[no_mangle]
+pub extern "C" fn u_thread_started_ocall() {
+
+    STARTING.with(|s| {
+        if let Some(start) = s.borrow_mut().take() {
+            start.finish(sgx_status_t::SGX_SUCCESS);
+        }
+    });
+}